@@ -0,0 +1,233 @@
+/**
+ * ============================================================================
+ * ARCHIVO: ./services/engine-rust/src/admin.rs
+ * MÓDULO: Rust Engine
+ * ============================================================================
+ *
+ * 📥 ENTRADA:
+ *   - Requests HTTP de un operador (`GET /status`, `GET /workers`,
+ *     `POST /workers/{name}/{pause|resume|cancel}`, `POST /shutdown`)
+ *
+ * 🔄 TRANSFORMACIÓN:
+ *   FUNCIONES: AdminServerWorker::work, route_request
+ *
+ * 📤 SALIDA:
+ *   - JSON con el status del motor o la lista de workers; 202 al aceptar un
+ *     shutdown; 404 si el worker referenciado no existe
+ *
+ * 🔗 DEPENDENCIAS:
+ *   - worker (BackgroundRunner, a través de RustArbitrageEngine)
+ *
+ * ============================================================================
+ */
+
+//! Servidor de control remoto del motor: antes de esto, pausar/reanudar un
+//! worker o inspeccionar `get_status()` solo era posible dentro del mismo
+//! proceso (o matándolo con Ctrl-C). `AdminServerWorker` expone ese mismo
+//! control por HTTP, reusando los canales de comando de
+//! `BackgroundRunner`/`Worker` que ya existen para cada job de larga
+//! duración, en vez de agregar un mecanismo de control nuevo.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use log::{error, info};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::worker::{Worker, WorkerState, WorkerStatus};
+use crate::RustArbitrageEngine;
+
+/// Respuesta HTTP ya armada: código, razón y body.
+struct HttpResponse {
+    status_line: &'static str,
+    body: String,
+}
+
+impl HttpResponse {
+    fn json(status_line: &'static str, body: serde_json::Value) -> Self {
+        Self {
+            status_line,
+            body: body.to_string(),
+        }
+    }
+
+    fn ok(body: serde_json::Value) -> Self {
+        Self::json("200 OK", body)
+    }
+
+    fn accepted(body: serde_json::Value) -> Self {
+        Self::json("202 Accepted", body)
+    }
+
+    fn not_found(body: serde_json::Value) -> Self {
+        Self::json("404 Not Found", body)
+    }
+
+    fn bad_request(body: serde_json::Value) -> Self {
+        Self::json("400 Bad Request", body)
+    }
+}
+
+/// Sirve la API de administración del motor por HTTP. Al igual que
+/// `MetricsServerWorker`, parsea el request a mano en vez de traer un
+/// framework HTTP: acá además hace falta distinguir método + path, así que
+/// `route_request` hace ese (pequeño) trabajo.
+pub struct AdminServerWorker {
+    addr: String,
+    listener: Option<TcpListener>,
+    engine: Arc<RustArbitrageEngine>,
+    last_error: Option<String>,
+}
+
+impl AdminServerWorker {
+    pub fn new(addr: String, engine: Arc<RustArbitrageEngine>) -> Self {
+        Self {
+            addr,
+            listener: None,
+            engine,
+            last_error: None,
+        }
+    }
+
+    /// Lee la request line (`MÉTODO PATH HTTP/1.1`) de la conexión entrante.
+    /// No necesita parsear headers ni body: todos los endpoints actúan
+    /// únicamente sobre el path.
+    async fn read_request_line(stream: &mut TcpStream) -> std::io::Result<String> {
+        let mut buf = [0u8; 2048];
+        let n = stream.read(&mut buf).await?;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        Ok(request.lines().next().unwrap_or("").to_string())
+    }
+
+    fn route_request(&self, request_line: &str) -> HttpResponse {
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("");
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        match (method, segments.as_slice()) {
+            ("GET", ["status"]) => HttpResponse::ok(serde_json::json!(self.engine.get_status())),
+            ("GET", ["workers"]) => {
+                HttpResponse::ok(serde_json::json!(worker_statuses_json(self.engine.list_workers())))
+            }
+            ("POST", ["workers", name, action @ ("pause" | "resume" | "cancel")]) => {
+                self.dispatch_worker_command(name, action)
+            }
+            ("POST", ["shutdown"]) => {
+                let engine = Arc::clone(&self.engine);
+                tokio::spawn(async move {
+                    engine.stop().await;
+                });
+                HttpResponse::accepted(serde_json::json!({ "shutting_down": true }))
+            }
+            _ => HttpResponse::not_found(serde_json::json!({ "error": "not found" })),
+        }
+    }
+
+    fn dispatch_worker_command(&self, name: &str, action: &str) -> HttpResponse {
+        let known = self.engine.list_workers().iter().any(|w| w.name == name);
+        if !known {
+            return HttpResponse::not_found(serde_json::json!({ "error": format!("unknown worker '{name}'") }));
+        }
+
+        match action {
+            "pause" => self.engine.pause_worker(name),
+            "resume" => self.engine.resume_worker(name),
+            "cancel" => self.engine.cancel_worker(name),
+            _ => return HttpResponse::bad_request(serde_json::json!({ "error": "unknown action" })),
+        }
+
+        HttpResponse::ok(serde_json::json!({ "worker": name, "action": action }))
+    }
+
+    async fn write_response(stream: &mut TcpStream, response: HttpResponse) -> std::io::Result<()> {
+        let raw = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status_line,
+            response.body.len(),
+            response.body
+        );
+        stream.write_all(raw.as_bytes()).await?;
+        stream.flush().await
+    }
+}
+
+/// Serializa `WorkerStatus` (que no deriva `Serialize` porque vive en un
+/// módulo sin dependencia de `serde` más allá de esto) a JSON.
+fn worker_statuses_json(statuses: Vec<WorkerStatus>) -> Vec<serde_json::Value> {
+    statuses
+        .into_iter()
+        .map(|status| {
+            let (state, last_error_from_state) = match status.state {
+                WorkerState::Active => ("active".to_string(), None),
+                WorkerState::Idle => ("idle".to_string(), None),
+                WorkerState::Done => ("done".to_string(), None),
+                WorkerState::Dead(reason) => ("dead".to_string(), Some(reason)),
+            };
+
+            serde_json::json!({
+                "name": status.name,
+                "state": state,
+                "progress": status.progress,
+                "last_error": status.last_error.or(last_error_from_state),
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Worker for AdminServerWorker {
+    fn name(&self) -> &str {
+        "admin_server"
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        if self.listener.is_none() {
+            match TcpListener::bind(&self.addr).await {
+                Ok(listener) => {
+                    info!("🛠️ API de administración disponible en http://{}", self.addr);
+                    self.listener = Some(listener);
+                }
+                Err(e) => {
+                    error!("❌ No se pudo bindear la API de administración en {}: {}", self.addr, e);
+                    self.last_error = Some(e.to_string());
+                    return WorkerState::Dead(e.to_string());
+                }
+            }
+        }
+
+        let listener = self.listener.as_ref().unwrap();
+        let (mut stream, _peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                self.last_error = Some(e.to_string());
+                return WorkerState::Active;
+            }
+        };
+
+        let request_line = match Self::read_request_line(&mut stream).await {
+            Ok(line) => line,
+            Err(e) => {
+                self.last_error = Some(e.to_string());
+                return WorkerState::Active;
+            }
+        };
+
+        let response = self.route_request(&request_line);
+        if let Err(e) = Self::write_response(&mut stream, response).await {
+            self.last_error = Some(e.to_string());
+        }
+
+        WorkerState::Active
+    }
+
+    fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            name: self.name().to_string(),
+            state: WorkerState::Active,
+            progress: Some(format!("escuchando en {}", self.addr)),
+            last_error: self.last_error.clone(),
+        }
+    }
+}
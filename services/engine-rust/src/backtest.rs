@@ -0,0 +1,342 @@
+/**
+ * ============================================================================
+ * ARCHIVO: ./services/engine-rust/src/backtest.rs
+ * MÓDULO: Rust Engine
+ * ============================================================================
+ *
+ * 📥 ENTRADA:
+ *   - Archivo de workload JSON: ciclos ordenados en el tiempo con pools,
+ *     precios y gas price grabados de mercado real
+ *
+ * 🔄 TRANSFORMACIÓN:
+ *   FUNCIONES: run_backtest, publish_report
+ *
+ * 📤 SALIDA:
+ *   - `BenchmarkReport`: rutas encontradas, profit neto simulado, success
+ *     rate y wall-clock por ciclo, a stdout como JSON o vía POST
+ *
+ * 🔗 DEPENDENCIAS:
+ *   - pathfinding (ArbitragePathfinder)
+ *
+ * ============================================================================
+ */
+
+//! Harness de backtesting/benchmark: reproduce de forma determinista un
+//! workload grabado (pools, precios y gas de un momento real del mercado)
+//! contra el pipeline de generación de rutas, sin tocar Sheets ni RPCs en
+//! vivo. Da una forma reproducible de tunear parámetros y detectar
+//! regresiones de profit/latencia entre versiones del motor, y alimenta con
+//! estadísticas medidas (en vez de una tabla vacía) a
+//! `analyze_historical_patterns` en el arranque en frío.
+
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::pathfinding::types::Blockchain;
+use crate::pathfinding::{ArbitragePathfinder, PoolInfo};
+
+/// Un ciclo de mercado grabado: el estado de los pools y el gas price
+/// vigente en ese instante, listo para re-alimentar el pathfinder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkloadCycle {
+    pub timestamp_unix: u64,
+    pub input_token: String,
+    pub input_amount: f64,
+    pub gas_price_gwei: f64,
+    pub pools: Vec<PoolInfo>,
+}
+
+/// Archivo de workload completo: metadata + ciclos ordenados en el tiempo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workload {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub cycles: Vec<WorkloadCycle>,
+}
+
+impl Workload {
+    /// Carga un workload desde un archivo JSON en disco.
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read workload file {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse workload file {} as JSON", path.display()))
+    }
+}
+
+/// Resultado del replay de un ciclo individual.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CycleResult {
+    pub timestamp_unix: u64,
+    pub routes_found: usize,
+    pub simulated_net_profit_usd: f64,
+    pub wall_clock_ms: f64,
+}
+
+/// Reporte estructurado de un benchmark completo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub workload_name: String,
+    pub cycles_replayed: usize,
+    pub total_routes_found: usize,
+    pub total_simulated_net_profit_usd: f64,
+    /// Fracción de ciclos en los que se encontró al menos una ruta rentable.
+    pub success_rate: f64,
+    pub avg_wall_clock_ms: f64,
+    pub cycles: Vec<CycleResult>,
+}
+
+/// Umbrales del pathfinder usados durante el replay, iguales a los de
+/// producción para que el benchmark mida lo que el motor realmente corre.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchmarkConfig {
+    pub min_profit_threshold: f64,
+    pub max_slippage_tolerance: f64,
+    pub max_results_per_cycle: usize,
+    /// Precio USD del token nativo de la chain grabada, para convertir el
+    /// `gas_price_gwei` de cada ciclo a un costo en USD restado de
+    /// `net_profit` (igual que en producción, vía `gas_cost_usd`).
+    pub native_token_price_usd: f64,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            min_profit_threshold: 10.0,
+            max_slippage_tolerance: 0.02,
+            max_results_per_cycle: 10,
+            native_token_price_usd: 2000.0,
+        }
+    }
+}
+
+/// Chain "legacy" (sin EIP-1559) usada para el replay: cada ciclo grabado ya
+/// trae su propio `gas_price_gwei`, que se empuja al pathfinder vía
+/// `set_gas_price_gwei` antes de cada ciclo en vez de derivarlo de un
+/// `base_fee` que el workload no graba.
+fn backtest_blockchain() -> Blockchain {
+    Blockchain {
+        blockchain_id: "backtest".to_string(),
+        name: "Backtest Chain".to_string(),
+        chain_id: 0,
+        is_active: true,
+        native_token: "ETH".to_string(),
+        rpc_url_1: String::new(),
+        rpc_url_2: None,
+        rpc_url_3: None,
+        wss_url: None,
+        explorer_url: String::new(),
+        block_time_ms: 0,
+        gas_price_gwei: 0.0,
+        max_gas_price: 0.0,
+        min_gas_price: 0.0,
+        eip1559_supported: false,
+        base_fee: None,
+        priority_fee: None,
+        gas_limit: 0,
+        multicall_address: None,
+        weth_address: String::new(),
+        usdc_address: None,
+        usdt_address: None,
+        dai_address: None,
+        extra_fields: HashMap::new(),
+    }
+}
+
+/// Reproduce todos los ciclos de un workload, en orden, contra un
+/// `ArbitragePathfinder` nuevo y devuelve el reporte agregado.
+///
+/// Determinista: el mismo workload con la misma `BenchmarkConfig` siempre
+/// produce el mismo reporte (salvo `wall_clock_ms`, que es de por sí una
+/// medición), a diferencia del ciclo en vivo donde el timing de red
+/// introduce variación en qué pools llegan a tiempo a cada ciclo.
+pub fn run_backtest(workload: &Workload, config: &BenchmarkConfig) -> BenchmarkReport {
+    let mut pathfinder = ArbitragePathfinder::new(
+        config.min_profit_threshold,
+        config.max_slippage_tolerance,
+        backtest_blockchain(),
+        config.native_token_price_usd,
+    );
+
+    let cycles: Vec<CycleResult> = workload
+        .cycles
+        .iter()
+        .map(|cycle| {
+            pathfinder.set_gas_price_gwei(cycle.gas_price_gwei);
+
+            let start = Instant::now();
+            let routes = pathfinder.find_best_routes(
+                &cycle.pools,
+                &cycle.input_token,
+                cycle.input_amount,
+                config.max_results_per_cycle,
+            );
+            let wall_clock_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+            CycleResult {
+                timestamp_unix: cycle.timestamp_unix,
+                routes_found: routes.len(),
+                simulated_net_profit_usd: routes.iter().map(|r| r.net_profit).sum(),
+                wall_clock_ms,
+            }
+        })
+        .collect();
+
+    summarize(workload.name.clone(), cycles)
+}
+
+fn summarize(workload_name: String, cycles: Vec<CycleResult>) -> BenchmarkReport {
+    let cycles_replayed = cycles.len();
+    let total_routes_found: usize = cycles.iter().map(|c| c.routes_found).sum();
+    let total_simulated_net_profit_usd: f64 =
+        cycles.iter().map(|c| c.simulated_net_profit_usd).sum();
+    let successful_cycles = cycles.iter().filter(|c| c.routes_found > 0).count();
+
+    let success_rate = if cycles_replayed == 0 {
+        0.0
+    } else {
+        successful_cycles as f64 / cycles_replayed as f64
+    };
+
+    let avg_wall_clock_ms = if cycles_replayed == 0 {
+        0.0
+    } else {
+        cycles.iter().map(|c| c.wall_clock_ms).sum::<f64>() / cycles_replayed as f64
+    };
+
+    BenchmarkReport {
+        workload_name,
+        cycles_replayed,
+        total_routes_found,
+        total_simulated_net_profit_usd,
+        success_rate,
+        avg_wall_clock_ms,
+        cycles,
+    }
+}
+
+/// Emite el reporte: a stdout como JSON si no se configuró
+/// `report_endpoint`, o vía POST a un servidor de recolección (p.ej. un
+/// dashboard de CI que trackea regresiones de profit/latencia entre
+/// versiones del motor).
+pub async fn publish_report(report: &BenchmarkReport, report_endpoint: Option<&str>) -> Result<()> {
+    match report_endpoint {
+        Some(url) => {
+            reqwest::Client::new()
+                .post(url)
+                .json(report)
+                .send()
+                .await
+                .with_context(|| format!("Failed to POST benchmark report to {url}"))?
+                .error_for_status()
+                .context("Benchmark collection server returned an error status")?;
+        }
+        None => {
+            println!("{}", serde_json::to_string_pretty(report)?);
+        }
+    }
+
+    Ok(())
+}
+
+// ==================================================================================
+// TESTS
+// ==================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_pool(pool_id: &str, price_a_to_b: f64) -> PoolInfo {
+        PoolInfo {
+            pool_id: pool_id.to_string(),
+            dex_name: "TEST_DEX".to_string(),
+            token_a: "USDC".to_string(),
+            token_b: "WETH".to_string(),
+            price_a_to_b,
+            price_b_to_a: 1.0 / price_a_to_b,
+            liquidity_usd: 1_000_000.0,
+            volume_24h: 500_000.0,
+            fee_rate: 0.003,
+            last_updated: 0,
+            reserve_a: None,
+            reserve_b: None,
+            pool_kind: Default::default(),
+            reserve_a_units: None,
+            reserve_b_units: None,
+        }
+    }
+
+    #[test]
+    fn test_workload_roundtrip_json() {
+        let workload = Workload {
+            name: "smoke".to_string(),
+            description: "single cycle".to_string(),
+            cycles: vec![WorkloadCycle {
+                timestamp_unix: 1,
+                input_token: "USDC".to_string(),
+                input_amount: 1000.0,
+                gas_price_gwei: 20.0,
+                pools: vec![sample_pool("p1", 1.0)],
+            }],
+        };
+
+        let json = serde_json::to_string(&workload).unwrap();
+        let parsed: Workload = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.name, "smoke");
+        assert_eq!(parsed.cycles.len(), 1);
+    }
+
+    #[test]
+    fn test_run_backtest_empty_workload() {
+        let workload = Workload {
+            name: "empty".to_string(),
+            description: String::new(),
+            cycles: Vec::new(),
+        };
+
+        let report = run_backtest(&workload, &BenchmarkConfig::default());
+
+        assert_eq!(report.cycles_replayed, 0);
+        assert_eq!(report.success_rate, 0.0);
+        assert_eq!(report.avg_wall_clock_ms, 0.0);
+    }
+
+    #[test]
+    fn test_run_backtest_counts_cycles() {
+        let workload = Workload {
+            name: "two_cycles".to_string(),
+            description: String::new(),
+            cycles: vec![
+                WorkloadCycle {
+                    timestamp_unix: 1,
+                    input_token: "USDC".to_string(),
+                    input_amount: 1000.0,
+                    gas_price_gwei: 20.0,
+                    pools: vec![sample_pool("p1", 1.0)],
+                },
+                WorkloadCycle {
+                    timestamp_unix: 2,
+                    input_token: "USDC".to_string(),
+                    input_amount: 1000.0,
+                    gas_price_gwei: 25.0,
+                    pools: vec![sample_pool("p2", 1.02)],
+                },
+            ],
+        };
+
+        let report = run_backtest(&workload, &BenchmarkConfig::default());
+
+        assert_eq!(report.cycles_replayed, 2);
+        assert_eq!(report.cycles.len(), 2);
+        assert_eq!(report.cycles[0].timestamp_unix, 1);
+        assert_eq!(report.cycles[1].timestamp_unix, 2);
+    }
+}
@@ -0,0 +1,291 @@
+//! Conector a un agregador DEX externo (0x-style `/quote?sellToken=&buyToken=&sellAmount=`).
+//!
+//! Hoy todo `DexClient` deriva precios de `PoolInfo` local, que diverge de la
+//! realidad entre ticks de WebSocket. Este conector consulta el agregador en
+//! tiempo real y devuelve su cotización (`buyAmount`, `estimatedGas`,
+//! `sources` incluidas) como ground-truth, para cruzarla contra el output DP
+//! local antes de emitir una `ArbitrageRoute` ejecutable. `quote` nunca
+//! bloquea `find_best_routes`: si el agregador no responde dentro de
+//! `timeout`, cae de vuelta a `amm::constant_product_output` sobre el pool
+//! local provisto.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use primitive_types::U256;
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+
+use crate::pathfinding::{self, amm, DexClient, PoolInfo};
+use crate::utils::amounts::{HexOrDecimalU256, TokenAmount};
+
+/// Base URL + API key de un agregador 0x-style para una chain puntual (cada
+/// chain tiene su propio despliegue del agregador, con su propia key).
+#[derive(Debug, Clone)]
+pub struct AggregatorChainConfig {
+    pub chain_id: u64,
+    pub base_url: String,
+    pub api_key: Option<String>,
+}
+
+/// Respuesta cruda del endpoint `/quote`. Solo se parsean los campos que el
+/// pathfinder realmente consume; el resto de la respuesta 0x-style
+/// (`price`, `allowanceTarget`, `to`, `data`, ...) no hace falta aquí.
+#[derive(Debug, Deserialize)]
+struct AggregatorQuoteResponse {
+    #[serde(rename = "buyAmount")]
+    buy_amount: HexOrDecimalU256,
+    #[serde(rename = "estimatedGas")]
+    estimated_gas: u64,
+    #[serde(default)]
+    sources: Vec<AggregatorSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AggregatorSource {
+    name: String,
+    #[allow(dead_code)]
+    proportion: String,
+}
+
+/// Cotización resuelta: `from_aggregator` distingue una respuesta real del
+/// agregador de la curva local usada cuando el agregador no respondió a
+/// tiempo, para que el caller sepa si de verdad cruzó contra ground-truth.
+#[derive(Debug, Clone)]
+pub struct AggregatorQuote {
+    pub buy_amount: TokenAmount,
+    pub estimated_gas: u64,
+    pub sources: Vec<String>,
+    pub from_aggregator: bool,
+}
+
+/// `DexClient` respaldado por un agregador externo en vez de pools locales.
+/// Los métodos síncronos del trait (`fetch_pools`/`estimate_slippage`) sirven
+/// sobre `local_pools` (último snapshot conocido); la cotización en tiempo
+/// real vive en `quote`, que es async y tiene su propio timeout/fallback.
+pub struct AggregatorDexClient {
+    name: String,
+    http: Client,
+    chains: HashMap<u64, AggregatorChainConfig>,
+    /// Acota cuántas requests concurrentes salen hacia el agregador: un
+    /// free tier 0x-style devuelve 429 mucho antes que el ritmo de escaneo
+    /// del pathfinder.
+    concurrency: Arc<Semaphore>,
+    timeout: Duration,
+    local_pools: Vec<PoolInfo>,
+}
+
+impl AggregatorDexClient {
+    pub fn new(
+        name: impl Into<String>,
+        chains: Vec<AggregatorChainConfig>,
+        max_concurrent_requests: usize,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            http: Client::new(),
+            chains: chains.into_iter().map(|c| (c.chain_id, c)).collect(),
+            concurrency: Arc::new(Semaphore::new(max_concurrent_requests.max(1))),
+            timeout,
+            local_pools: Vec::new(),
+        }
+    }
+
+    /// Reemplaza el snapshot local de pools usado por `fetch_pools` y por el
+    /// fallback de `quote` cuando el agregador no responde.
+    pub fn set_local_pools(&mut self, pools: Vec<PoolInfo>) {
+        self.local_pools = pools;
+    }
+
+    /// Cotiza `sell_amount` de `sell_token` -> `buy_token` en `chain_id`
+    /// contra el agregador configurado. Si la request no responde dentro de
+    /// `self.timeout`, no hay config para `chain_id`, o la respuesta no
+    /// parsea, cae de vuelta a `amm::constant_product_output` sobre
+    /// `fallback_pool` — nunca propaga el error al llamador, para que
+    /// `find_best_routes` no se bloquee esperando una red externa.
+    pub async fn quote(
+        &self,
+        chain_id: u64,
+        sell_token: &str,
+        buy_token: &str,
+        sell_amount: TokenAmount,
+        buy_token_decimals: u8,
+        fallback_pool: Option<&PoolInfo>,
+    ) -> AggregatorQuote {
+        match self
+            .quote_via_http(chain_id, sell_token, buy_token, sell_amount, buy_token_decimals)
+            .await
+        {
+            Ok(quote) => quote,
+            Err(_) => self.quote_via_fallback(sell_amount, buy_token_decimals, fallback_pool),
+        }
+    }
+
+    async fn quote_via_http(
+        &self,
+        chain_id: u64,
+        sell_token: &str,
+        buy_token: &str,
+        sell_amount: TokenAmount,
+        buy_token_decimals: u8,
+    ) -> Result<AggregatorQuote> {
+        let config = self
+            .chains
+            .get(&chain_id)
+            .context("no aggregator configured for this chain")?;
+
+        let _permit = self
+            .concurrency
+            .acquire()
+            .await
+            .context("aggregator concurrency semaphore closed")?;
+
+        let mut request = self
+            .http
+            .get(format!("{}/quote", config.base_url))
+            .query(&[
+                ("sellToken", sell_token),
+                ("buyToken", buy_token),
+                ("sellAmount", &sell_amount.raw().to_string()),
+            ]);
+
+        if let Some(api_key) = &config.api_key {
+            request = request.header("0x-api-key", api_key);
+        }
+
+        let response = tokio::time::timeout(self.timeout, request.send())
+            .await
+            .context("aggregator request timed out")??
+            .error_for_status()
+            .context("aggregator returned an error status")?
+            .json::<AggregatorQuoteResponse>()
+            .await
+            .context("failed to parse aggregator quote response")?;
+
+        Ok(AggregatorQuote {
+            buy_amount: TokenAmount::from_raw(response.buy_amount.0, buy_token_decimals),
+            estimated_gas: response.estimated_gas,
+            sources: response.sources.into_iter().map(|s| s.name).collect(),
+            from_aggregator: true,
+        })
+    }
+
+    /// Cotiza localmente vía `amm::constant_product_output` cuando el
+    /// agregador no respondió. `fallback_pool` debe ser el pool local del par
+    /// `sell_token`/`buy_token`; sin reservas (o sin pool) devuelve un
+    /// `buy_amount` de cero en vez de inventar un número.
+    fn quote_via_fallback(
+        &self,
+        sell_amount: TokenAmount,
+        buy_token_decimals: u8,
+        fallback_pool: Option<&PoolInfo>,
+    ) -> AggregatorQuote {
+        let buy_amount_human = fallback_pool
+            .and_then(|pool| {
+                let (reserve_in, reserve_out) = (pool.reserve_a?, pool.reserve_b?);
+                let output = amm::constant_product_output(
+                    sell_amount.as_f64_lossy(),
+                    reserve_in,
+                    reserve_out,
+                    pool.fee_rate,
+                );
+                (output > 0.0).then_some(output)
+            })
+            .unwrap_or(0.0);
+
+        let buy_amount = TokenAmount::from_f64(buy_amount_human, buy_token_decimals)
+            .unwrap_or(TokenAmount::from_raw(U256::zero(), buy_token_decimals));
+
+        AggregatorQuote {
+            buy_amount,
+            estimated_gas: 0,
+            sources: vec!["local_constant_product_fallback".to_string()],
+            from_aggregator: false,
+        }
+    }
+}
+
+impl DexClient for AggregatorDexClient {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn fetch_pools(&self) -> Vec<PoolInfo> {
+        self.local_pools.clone()
+    }
+
+    fn estimate_slippage(&self, pool: &PoolInfo, amount: f64) -> f64 {
+        pathfinding::estimate_slippage_impact(amount, pool)
+    }
+
+    fn calculate_gas_cost(&self, _operation_type: &str) -> u64 {
+        // Gas units "flat" hasta que una cotización real del agregador traiga
+        // `estimated_gas`; el costo EIP-1559-aware en USD se resuelve después
+        // vía `pathfinding::gas_cost_usd`, no acá.
+        150_000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_pool() -> PoolInfo {
+        PoolInfo {
+            pool_id: "test_pool".to_string(),
+            dex_name: "uniswap".to_string(),
+            token_a: "ETH".to_string(),
+            token_b: "USDT".to_string(),
+            price_a_to_b: 1800.0,
+            price_b_to_a: 0.000556,
+            liquidity_usd: 1_000_000.0,
+            volume_24h: 500_000.0,
+            fee_rate: 0.003,
+            last_updated: 1698000000,
+            reserve_a: Some(1_000.0),
+            reserve_b: Some(1_800_000.0),
+            pool_kind: Default::default(),
+            reserve_a_units: None,
+            reserve_b_units: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_falls_back_to_local_constant_product_when_no_chain_is_configured() {
+        let client = AggregatorDexClient::new("0x_aggregator", vec![], 4, Duration::from_secs(1));
+        let pool = sample_pool();
+
+        let quote = client
+            .quote(1, "ETH", "USDT", TokenAmount::from_f64(1.0, 18).unwrap(), 6, Some(&pool))
+            .await;
+
+        assert!(!quote.from_aggregator);
+        let expected = amm::constant_product_output(1.0, 1_000.0, 1_800_000.0, 0.003);
+        assert!((quote.buy_amount.as_f64_lossy() - expected).abs() < 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_quote_falls_back_to_zero_without_a_fallback_pool() {
+        let client = AggregatorDexClient::new("0x_aggregator", vec![], 4, Duration::from_secs(1));
+
+        let quote = client
+            .quote(1, "ETH", "USDT", TokenAmount::from_f64(1.0, 18).unwrap(), 6, None)
+            .await;
+
+        assert!(!quote.from_aggregator);
+        assert_eq!(quote.buy_amount.as_f64_lossy(), 0.0);
+    }
+
+    #[test]
+    fn test_fetch_pools_returns_the_cached_local_snapshot() {
+        let mut client = AggregatorDexClient::new("0x_aggregator", vec![], 4, Duration::from_secs(1));
+        client.set_local_pools(vec![sample_pool()]);
+
+        assert_eq!(client.fetch_pools().len(), 1);
+        assert_eq!(client.get_name(), "0x_aggregator");
+    }
+}
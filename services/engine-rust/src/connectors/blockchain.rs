@@ -9,9 +9,21 @@
 
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message;
+
+use primitive_types::U256;
+
+use crate::utils::amounts::HexOrDecimalU256;
+use super::merkle_proof::{erc20_balance_storage_key, verify_account_proof, verify_storage_proof};
+use super::middleware::{Middleware, Provider};
+use super::price_oracle::PriceOracle;
 
 // ==================================================================================
 // TYPES & STRUCTS
@@ -22,17 +34,97 @@ use anyhow::{Context, Result};
 pub struct ChainConfig {
     pub chain_id: u64,
     pub name: String,
-    pub rpc_url: String,
+    /// Endpoints RPC en orden de prioridad. `call_rpc` prueba el primero
+    /// sano; si todos fallan o están en cuarentena, recorre la lista hasta
+    /// agotarla en vez de tumbar la chain entera por un único proveedor
+    /// caído, que es el caso común con RPCs públicos/gratuitos.
+    pub rpc_urls: Vec<String>,
     pub explorer_url: String,
     pub native_token: String,
     pub is_active: bool,
+    /// Endpoint websocket (`wss://...`) usado para suscribirse a logs en
+    /// tiempo real. `None` desactiva el streaming de reservas en esta chain.
+    pub ws_url: Option<String>,
+}
+
+/// Salud observada de un endpoint RPC individual, usada para preferir
+/// endpoints sanos y poner en cuarentena temporal a los que vienen fallando.
+#[derive(Debug, Clone, Default)]
+struct EndpointHealth {
+    consecutive_failures: u32,
+    /// `None` si el endpoint nunca respondió mal, o si ya se re-probó y tuvo
+    /// éxito. Mientras `SystemTime::now() < quarantined_until`, `call_rpc`
+    /// solo lo intenta tras agotar el resto de endpoints sanos.
+    quarantined_until: Option<SystemTime>,
+}
+
+/// Cuántas veces `call_rpc` reintenta un mismo endpoint (con backoff) antes
+/// de marcarlo fallido y pasar al siguiente de la lista.
+const DEFAULT_MAX_RETRIES_PER_ENDPOINT: u32 = 3;
+const RETRY_BACKOFF_BASE: Duration = Duration::from_millis(200);
+/// Cuarentena base tras la primera falla consecutiva; se duplica por cada
+/// falla adicional (hasta `MAX_QUARANTINE_DOUBLINGS`) para no volver a probar
+/// un endpoint caído en cada ciclo.
+const QUARANTINE_BASE: Duration = Duration::from_secs(15);
+const MAX_QUARANTINE_DOUBLINGS: u32 = 5;
+
+/// Pool a monitorear en tiempo real vía suscripción a sus logs `Sync`.
+#[derive(Debug, Clone)]
+pub struct PoolSubscriptionTarget {
+    pub chain_id: u64,
+    pub pool_id: String,
+    pub pool_address: String,
+}
+
+/// Última reserva conocida de un pool, empujada por la suscripción websocket
+/// en vez de esperar al siguiente refresh del snapshot de Sheets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolReserveUpdate {
+    pub pool_id: String,
+    pub reserves_a: f64,
+    pub reserves_b: f64,
+    pub block_number: u64,
+    pub updated_at_unix: u64,
 }
 
+/// Topic Keccak-256 del evento `Sync(uint112,uint112)` (Uniswap V2 y forks).
+const SYNC_EVENT_TOPIC: &str =
+    "0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1";
+
+/// Cuántos gas prices recientes de swaps aterrizados se conservan por chain
+/// para estimar el percentil de la competencia actual.
+const MAX_GAS_PRICE_SAMPLES: usize = 50;
+
 /// Cliente de blockchain
 pub struct BlockchainConnector {
     chains: HashMap<u64, ChainConfig>,
     client: Client,
     request_timeout: Duration,
+    /// Cache de reservas en tiempo real, compartida con `PriceEngine` y
+    /// `PathFinder`: solo las tareas de suscripción escriben aquí.
+    reserve_cache: Arc<Mutex<HashMap<String, PoolReserveUpdate>>>,
+    /// Timestamp del último evento recibido por chain, para detectar sockets
+    /// "zombies" que siguen conectados pero dejaron de emitir logs.
+    last_event_received: Arc<Mutex<HashMap<u64, SystemTime>>>,
+    /// Gas prices efectivos (gwei) de los swaps recién aterrizados en cada
+    /// chain, usados para estimar contra quién se está compitiendo al pujar
+    /// priority fee. Ventana acotada a `MAX_GAS_PRICE_SAMPLES` por chain.
+    recent_gas_prices: Arc<Mutex<HashMap<u64, VecDeque<f64>>>>,
+    /// Salud observada por endpoint RPC (clave: la URL), compartida entre
+    /// todas las chains porque un mismo proveedor a veces sirve varias redes.
+    endpoint_health: Arc<Mutex<HashMap<String, EndpointHealth>>>,
+    /// Reintentos por endpoint antes de pasar al siguiente de la lista.
+    /// Configurable vía [`Self::set_max_retries_per_endpoint`].
+    max_retries_per_endpoint: u32,
+    /// Metadata ERC20 ya resuelta, cacheada por `(chain_id, token_address en
+    /// minúsculas)`. Ver [`Self::get_token_metadata`].
+    token_metadata_cache: Arc<Mutex<HashMap<(u64, String), TokenMetadata>>>,
+    /// Fuente del precio USD del token nativo de cada chain, usada por
+    /// `estimate_gas` para `estimated_cost_usd`. `None` hasta que el caller
+    /// la configure vía [`Self::set_price_oracle`]; sin ella, `estimate_gas`
+    /// no puede convertir el costo a USD y lo reporta en `0.0` en vez de
+    /// inventar un precio.
+    price_oracle: Option<Arc<dyn PriceOracle>>,
 }
 
 /// Información de balance
@@ -45,6 +137,27 @@ pub struct BalanceInfo {
     pub decimals: u8,
 }
 
+/// Metadata ERC20 de un token (`decimals`/`symbol`/`name`), cacheada por
+/// `(chain_id, token_address)` en [`BlockchainConnector`] porque nunca cambia
+/// una vez desplegado el contrato y consultarla on-chain en cada balance
+/// desperdiciaría una llamada `eth_call` por campo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+}
+
+/// Balance verificado criptográficamente contra el `stateRoot` de un bloque,
+/// en vez de tomado on-trust de la respuesta cruda de un RPC (ver
+/// [`BlockchainConnector::get_verified_native_balance`] /
+/// [`BlockchainConnector::get_verified_token_balance`]).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VerifiedBalance {
+    pub balance: HexOrDecimalU256,
+    pub block_number: u64,
+}
+
 /// Información de gas
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GasInfo {
@@ -52,8 +165,42 @@ pub struct GasInfo {
     pub gas_limit: u64,
     pub estimated_cost_eth: f64,
     pub estimated_cost_usd: f64,
+    /// Fees EIP-1559 (`eth_feeHistory`), cuando la chain los reporta. `None`
+    /// en chains pre-London, donde solo existe el `gas_price_gwei` legacy.
+    #[serde(default)]
+    pub max_fee_per_gas: Option<f64>,
+    #[serde(default)]
+    pub max_priority_fee_per_gas: Option<f64>,
+    /// Precio USD del token nativo usado para `estimated_cost_usd`, tal como
+    /// lo reportó el `PriceOracle` configurado (ver
+    /// [`BlockchainConnector::set_price_oracle`]). `0.0` si no hay oracle
+    /// configurado o la consulta falló, en cuyo caso `estimated_cost_usd`
+    /// tampoco es confiable.
+    #[serde(default)]
+    pub native_token_price_usd: f64,
+    /// Timestamp unix (segundos) en el que la fuente reportó
+    /// `native_token_price_usd`, no cuando se leyó de cache: permite a los
+    /// callers juzgar qué tan desactualizado está el precio. `0` si no hay
+    /// oracle configurado o la consulta falló.
+    #[serde(default)]
+    pub native_token_price_updated_at_unix: u64,
 }
 
+/// Fees EIP-1559 estimados a partir de `eth_feeHistory`: el `baseFeePerGas`
+/// del próximo bloque y un priority fee (tip) tomado del percentil pedido de
+/// los últimos bloques.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Eip1559FeeEstimate {
+    pub base_fee_gwei: f64,
+    pub max_priority_fee_per_gas_gwei: f64,
+    pub max_fee_per_gas_gwei: f64,
+}
+
+/// Percentiles de `reward` pedidos a `eth_feeHistory`. Cubren competencia
+/// baja/media/alta por bloque; `estimate_eip1559_fees` elige la columna más
+/// cercana al percentil pedido por el caller.
+const FEE_HISTORY_REWARD_PERCENTILES: [f64; 3] = [10.0, 50.0, 90.0];
+
 /// Parámetros de transacción
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionParams {
@@ -109,6 +256,37 @@ struct JsonRpcError {
     message: String,
 }
 
+/// Falla de un único intento de `try_rpc_call`, junto con si vale la pena
+/// reintentarla (mismo endpoint, con backoff) o si hay que abandonar ese
+/// endpoint ya mismo y pasar al siguiente.
+struct RpcCallError {
+    error: anyhow::Error,
+    retryable: bool,
+}
+
+impl RpcCallError {
+    fn retryable(error: anyhow::Error) -> Self {
+        RpcCallError { error, retryable: true }
+    }
+
+    fn non_retryable(error: anyhow::Error) -> Self {
+        RpcCallError { error, retryable: false }
+    }
+
+    fn into_error(self) -> anyhow::Error {
+        self.error
+    }
+}
+
+/// Códigos JSON-RPC de nodo (no de aplicación) que justifican reintentar en
+/// el mismo endpoint o, al agotar los reintentos, en el siguiente: límite de
+/// rate, nodo ocupado/no sincronizado, o error interno del proveedor. Otros
+/// códigos (p.ej. -32602 "invalid params") son errores del caller y
+/// reintentar no los arregla.
+fn is_retryable_rpc_error_code(code: i64) -> bool {
+    matches!(code, -32000 | -32001 | -32002 | -32003 | -32005 | -32603)
+}
+
 // ==================================================================================
 // BLOCKCHAIN CONNECTOR IMPLEMENTATION
 // ==================================================================================
@@ -125,14 +303,36 @@ impl BlockchainConnector {
             chains: HashMap::new(),
             client,
             request_timeout: Duration::from_secs(request_timeout_secs),
+            reserve_cache: Arc::new(Mutex::new(HashMap::new())),
+            last_event_received: Arc::new(Mutex::new(HashMap::new())),
+            recent_gas_prices: Arc::new(Mutex::new(HashMap::new())),
+            endpoint_health: Arc::new(Mutex::new(HashMap::new())),
+            max_retries_per_endpoint: DEFAULT_MAX_RETRIES_PER_ENDPOINT,
+            token_metadata_cache: Arc::new(Mutex::new(HashMap::new())),
+            price_oracle: None,
         }
     }
-    
+
+    /// Configura la fuente de precio USD del token nativo que `estimate_gas`
+    /// usa para `estimated_cost_usd`. Sin esta llamada, `estimate_gas`
+    /// reporta el costo en USD como `0.0` en vez de asumir un precio fijo.
+    pub fn set_price_oracle(&mut self, oracle: Arc<dyn PriceOracle>) {
+        self.price_oracle = Some(oracle);
+    }
+
+    /// Sobrescribe cuántas veces `call_rpc` reintenta un mismo endpoint (con
+    /// backoff) antes de pasar al siguiente de la lista. Útil para entornos
+    /// con RPCs conocidos como particularmente flaky (subir) o para tests que
+    /// no quieren esperar el backoff completo (bajar a 1).
+    pub fn set_max_retries_per_endpoint(&mut self, max_retries: u32) {
+        self.max_retries_per_endpoint = max_retries.max(1);
+    }
+
     /// Agregar configuración de chain
     pub fn add_chain(&mut self, config: ChainConfig) {
         self.chains.insert(config.chain_id, config);
     }
-    
+
     /// Cargar chains desde lista
     pub fn load_chains(&mut self, chains: Vec<ChainConfig>) {
         self.chains.clear();
@@ -142,17 +342,241 @@ impl BlockchainConnector {
             }
         }
     }
-    
+
     /// Obtener configuración de chain
     pub fn get_chain(&self, chain_id: u64) -> Option<&ChainConfig> {
         self.chains.get(&chain_id)
     }
-    
+
+    /// `true` si `rpc_url` está actualmente en cuarentena (falló lo
+    /// suficiente recientemente como para que `call_rpc` lo deprioritice).
+    /// Expuesto para monitoreo/tests; no usado en el hot path.
+    pub fn is_endpoint_quarantined(&self, rpc_url: &str) -> bool {
+        let health = self.endpoint_health.lock().unwrap();
+        health
+            .get(rpc_url)
+            .and_then(|h| h.quarantined_until)
+            .map(|until| SystemTime::now() < until)
+            .unwrap_or(false)
+    }
+
+    // ================================================================================
+    // REAL-TIME RESERVE STREAMING (websocket log subscriptions)
+    // ================================================================================
+
+    /// Última reserva conocida de un pool (leída por `PriceEngine`/`PathFinder`).
+    pub fn get_reserve(&self, pool_id: &str) -> Option<PoolReserveUpdate> {
+        self.reserve_cache.lock().unwrap().get(pool_id).cloned()
+    }
+
+    /// Todas las reservas conocidas, para consumo masivo por ciclo.
+    pub fn all_reserves(&self) -> HashMap<String, PoolReserveUpdate> {
+        self.reserve_cache.lock().unwrap().clone()
+    }
+
+    /// Timestamp del último evento de log recibido para una chain. `None`
+    /// significa que nunca llegó un evento (o no hay suscripción activa).
+    pub fn last_event_received(&self, chain_id: u64) -> Option<SystemTime> {
+        self.last_event_received.lock().unwrap().get(&chain_id).copied()
+    }
+
+    /// Timestamps de "último evento recibido" para todas las chains con
+    /// suscripción activa, pensado para exponerse vía `PerformanceMetrics`
+    /// y así hacer visibles los stalls de streaming.
+    pub fn all_last_event_timestamps(&self) -> HashMap<u64, SystemTime> {
+        self.last_event_received.lock().unwrap().clone()
+    }
+
+    /// Registra el gas price efectivo (gwei) de un swap recién aterrizado en
+    /// una chain, observado a través de la suscripción a logs. Alimenta
+    /// `gas_price_percentile`, que el cost model usa para pujar priority fee.
+    fn record_landed_gas_price(&self, chain_id: u64, gas_price_gwei: f64) {
+        let mut samples = self.recent_gas_prices.lock().unwrap();
+        let window = samples.entry(chain_id).or_insert_with(VecDeque::new);
+        window.push_back(gas_price_gwei);
+        if window.len() > MAX_GAS_PRICE_SAMPLES {
+            window.pop_front();
+        }
+    }
+
+    /// Percentil (0.0-1.0) de los gas prices recientemente observados en una
+    /// chain. `None` si todavía no se observó ningún swap aterrizado.
+    pub fn gas_price_percentile(&self, chain_id: u64, percentile: f64) -> Option<f64> {
+        let samples = self.recent_gas_prices.lock().unwrap();
+        let window = samples.get(&chain_id)?;
+        if window.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<f64> = window.iter().copied().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let idx = ((sorted.len() - 1) as f64 * percentile.clamp(0.0, 1.0)).round() as usize;
+        Some(sorted[idx])
+    }
+
+    /// Suscribe a los logs `Sync` de cada pool dado sobre el websocket RPC de
+    /// su chain y mantiene `reserve_cache` actualizado en tiempo real. Cada
+    /// chain corre su propia tarea y se reconecta con backoff exponencial si
+    /// el socket se cae.
+    pub fn subscribe_pool_reserves(self: &Arc<Self>, targets: Vec<PoolSubscriptionTarget>) {
+        let mut by_chain: HashMap<u64, Vec<PoolSubscriptionTarget>> = HashMap::new();
+        for target in targets {
+            by_chain.entry(target.chain_id).or_default().push(target);
+        }
+
+        for (chain_id, pools) in by_chain {
+            let connector = Arc::clone(self);
+            tokio::spawn(async move {
+                connector.run_reserve_subscription(chain_id, pools).await;
+            });
+        }
+    }
+
+    /// Bucle de reconexión con backoff exponencial para la suscripción de una
+    /// chain. Nunca retorna salvo que la chain no tenga `ws_url` configurado.
+    async fn run_reserve_subscription(&self, chain_id: u64, pools: Vec<PoolSubscriptionTarget>) {
+        const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+        const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+        let Some(ws_url) = self.chains.get(&chain_id).and_then(|c| c.ws_url.clone()) else {
+            log::warn!(
+                "Chain {} has no ws_url configured; skipping real-time reserve streaming",
+                chain_id
+            );
+            return;
+        };
+
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            match self.stream_reserve_updates(chain_id, &ws_url, &pools).await {
+                Ok(()) => {
+                    log::warn!("Reserve subscription for chain {} closed cleanly, reconnecting", chain_id);
+                    backoff = INITIAL_BACKOFF;
+                }
+                Err(e) => {
+                    log::error!(
+                        "Reserve subscription for chain {} failed: {}; retrying in {:?}",
+                        chain_id, e, backoff
+                    );
+                }
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Abre el websocket, se suscribe a los logs `Sync` de cada pool y
+    /// procesa eventos hasta que el socket se cierra o falla.
+    async fn stream_reserve_updates(
+        &self,
+        chain_id: u64,
+        ws_url: &str,
+        pools: &[PoolSubscriptionTarget],
+    ) -> Result<()> {
+        let (ws_stream, _) = tokio_tungstenite::connect_async(ws_url)
+            .await
+            .context("Failed to connect to chain websocket")?;
+        let (mut write, mut read) = ws_stream.split();
+
+        // Una suscripción `eth_subscribe` por pool, para poder mapear el id
+        // de suscripción devuelto de vuelta al pool_id.
+        let mut pending_subscriptions: HashMap<u64, String> = HashMap::new();
+        for (idx, pool) in pools.iter().enumerate() {
+            let request_id = idx as u64 + 1;
+            let subscribe_request = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request_id,
+                "method": "eth_subscribe",
+                "params": ["logs", { "address": pool.pool_address, "topics": [SYNC_EVENT_TOPIC] }]
+            });
+            write
+                .send(Message::Text(subscribe_request.to_string()))
+                .await
+                .context("Failed to send eth_subscribe")?;
+            pending_subscriptions.insert(request_id, pool.pool_id.clone());
+        }
+
+        // Mapea subscription_id (asignado por el nodo) -> pool_id, una vez
+        // que llega la confirmación de cada `eth_subscribe`.
+        let mut subscription_to_pool: HashMap<String, String> = HashMap::new();
+
+        while let Some(message) = read.next().await {
+            let message = message.context("Websocket error while streaming reserves")?;
+            let Message::Text(text) = message else {
+                continue;
+            };
+            let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) else {
+                continue;
+            };
+
+            // Confirmación de un eth_subscribe: {"id": N, "result": "0x..."}
+            if let Some(request_id) = value.get("id").and_then(|v| v.as_u64()) {
+                if let (Some(pool_id), Some(subscription_id)) = (
+                    pending_subscriptions.get(&request_id),
+                    value.get("result").and_then(|v| v.as_str()),
+                ) {
+                    subscription_to_pool.insert(subscription_id.to_string(), pool_id.clone());
+                }
+                continue;
+            }
+
+            // Notificación: {"method": "eth_subscription", "params": {"subscription": "0x..", "result": {...log}}}
+            if value.get("method").and_then(|v| v.as_str()) != Some("eth_subscription") {
+                continue;
+            }
+
+            let Some(params) = value.get("params") else {
+                continue;
+            };
+            let Some(subscription_id) = params.get("subscription").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let Some(pool_id) = subscription_to_pool.get(subscription_id) else {
+                continue;
+            };
+            let Some(log) = params.get("result") else {
+                continue;
+            };
+
+            if let Some(update) = decode_sync_log(pool_id, log) {
+                self.reserve_cache.lock().unwrap().insert(pool_id.clone(), update);
+            }
+
+            // El log trae el hash de la transacción que lo emitió: usarlo
+            // para leer el gas price efectivo que pagó ese swap aterrizado,
+            // y así saber contra qué competencia se está pujando en esta chain.
+            if let Some(tx_hash) = log.get("transactionHash").and_then(|v| v.as_str()) {
+                if let Ok(Some(receipt)) = self.get_transaction_receipt(chain_id, tx_hash).await {
+                    if let Some(gas_price_gwei) = receipt
+                        .effective_gas_price
+                        .as_deref()
+                        .and_then(|hex| u128::from_str_radix(hex.trim_start_matches("0x"), 16).ok())
+                        .map(|wei| wei as f64 / 1_000_000_000.0)
+                    {
+                        self.record_landed_gas_price(chain_id, gas_price_gwei);
+                    }
+                }
+            }
+
+            let now = SystemTime::now();
+            self.last_event_received.lock().unwrap().insert(chain_id, now);
+        }
+
+        Ok(())
+    }
+
     // ================================================================================
     // JSON-RPC METHODS
     // ================================================================================
     
-    /// Ejecutar llamada JSON-RPC genérica
+    /// Ejecutar llamada JSON-RPC genérica, con failover entre los endpoints
+    /// de `chain.rpc_urls`: reintenta cada endpoint con backoff exponencial
+    /// hasta `max_retries_per_endpoint` veces frente a errores transitorios
+    /// (timeouts, 5xx, 429, o códigos JSON-RPC de nodo sobrecargado/caído),
+    /// y pasa al siguiente endpoint si se agotan los reintentos. Endpoints
+    /// sanos se prueban antes que los que están en cuarentena por fallas
+    /// recientes (ver [`EndpointHealth`]).
     async fn call_rpc(
         &self,
         chain_id: u64,
@@ -161,43 +585,133 @@ impl BlockchainConnector {
     ) -> Result<serde_json::Value> {
         let chain = self.chains.get(&chain_id)
             .ok_or_else(|| anyhow::anyhow!("Chain {} not configured", chain_id))?;
-        
+        if chain.rpc_urls.is_empty() {
+            return Err(anyhow::anyhow!("Chain {} has no rpc_urls configured", chain_id));
+        }
+
+        let ordered_urls = self.order_endpoints_by_health(&chain.rpc_urls);
+        let mut last_error = None;
+
+        for url in &ordered_urls {
+            let mut backoff = RETRY_BACKOFF_BASE;
+
+            for attempt in 0..self.max_retries_per_endpoint {
+                if attempt > 0 {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+
+                match self.try_rpc_call(url, method, &params).await {
+                    Ok(value) => {
+                        self.record_endpoint_success(url);
+                        return Ok(value);
+                    }
+                    Err(err) => {
+                        let retryable = err.retryable;
+                        last_error = Some(err.into_error());
+                        if !retryable {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            self.record_endpoint_failure(url);
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            anyhow::anyhow!("All {} RPC endpoint(s) failed for chain {}", ordered_urls.len(), chain_id)
+        }))
+    }
+
+    /// Un único intento de llamada JSON-RPC contra `url`, sin retry ni
+    /// failover (eso vive en `call_rpc`). Clasifica la falla como
+    /// reintentable o no para que el loop de arriba decida si insistir con
+    /// este mismo endpoint o pasar directo al siguiente.
+    async fn try_rpc_call(
+        &self,
+        url: &str,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> std::result::Result<serde_json::Value, RpcCallError> {
         let request = JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
             method: method.to_string(),
-            params,
+            params: params.clone(),
             id: 1,
         };
-        
+
         let response = self.client
-            .post(&chain.rpc_url)
+            .post(url)
             .json(&request)
             .send()
             .await
-            .context("Failed to send RPC request")?;
-        
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!(
-                "RPC request failed with status: {}",
-                response.status()
-            ));
+            .map_err(|e| RpcCallError::retryable(anyhow::Error::new(e).context("Failed to send RPC request")))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let err = anyhow::anyhow!("RPC request failed with status: {}", status);
+            // 429 (rate-limited) y 5xx (nodo caído/sobrecargado) ameritan
+            // reintento; un 4xx "normal" (p.ej. 400 por params inválidos) no
+            // se arregla reintentando el mismo método.
+            let retryable = status.as_u16() == 429 || status.is_server_error();
+            return Err(RpcCallError { error: err, retryable });
         }
-        
+
         let rpc_response: JsonRpcResponse = response
             .json()
             .await
-            .context("Failed to parse RPC response")?;
-        
+            .map_err(|e| RpcCallError::retryable(anyhow::Error::new(e).context("Failed to parse RPC response")))?;
+
         if let Some(error) = rpc_response.error {
-            return Err(anyhow::anyhow!(
-                "RPC error {}: {}",
-                error.code,
-                error.message
-            ));
+            let retryable = is_retryable_rpc_error_code(error.code);
+            let err = anyhow::anyhow!("RPC error {}: {}", error.code, error.message);
+            return Err(RpcCallError { error: err, retryable });
         }
-        
+
         rpc_response.result
-            .ok_or_else(|| anyhow::anyhow!("No result in RPC response"))
+            .ok_or_else(|| RpcCallError::non_retryable(anyhow::anyhow!("No result in RPC response")))
+    }
+
+    /// Endpoints sanos primero (en su orden de prioridad original), seguidos
+    /// de los que siguen en cuarentena. Nunca descarta un endpoint: si todos
+    /// están en cuarentena, igual se prueban en orden, lo que actúa como el
+    /// "re-probing periódico" de endpoints caídos.
+    fn order_endpoints_by_health(&self, urls: &[String]) -> Vec<String> {
+        let now = SystemTime::now();
+        let health = self.endpoint_health.lock().unwrap();
+
+        let (mut healthy, mut quarantined): (Vec<String>, Vec<String>) = (Vec::new(), Vec::new());
+        for url in urls {
+            let is_quarantined = health
+                .get(url)
+                .and_then(|h| h.quarantined_until)
+                .map(|until| now < until)
+                .unwrap_or(false);
+            if is_quarantined {
+                quarantined.push(url.clone());
+            } else {
+                healthy.push(url.clone());
+            }
+        }
+
+        healthy.append(&mut quarantined);
+        healthy
+    }
+
+    fn record_endpoint_success(&self, url: &str) {
+        let mut health = self.endpoint_health.lock().unwrap();
+        health.insert(url.to_string(), EndpointHealth::default());
+    }
+
+    fn record_endpoint_failure(&self, url: &str) {
+        let mut health = self.endpoint_health.lock().unwrap();
+        let entry = health.entry(url.to_string()).or_default();
+        entry.consecutive_failures += 1;
+
+        let doublings = (entry.consecutive_failures - 1).min(MAX_QUARANTINE_DOUBLINGS);
+        let quarantine = QUARANTINE_BASE * 2u32.pow(doublings);
+        entry.quarantined_until = Some(SystemTime::now() + quarantine);
     }
     
     // ================================================================================
@@ -211,18 +725,10 @@ impl BlockchainConnector {
             "eth_blockNumber",
             serde_json::json!([]),
         ).await?;
-        
-        let block_hex = result.as_str()
-            .ok_or_else(|| anyhow::anyhow!("Invalid block number format"))?;
-        
-        let block_number = u64::from_str_radix(
-            block_hex.trim_start_matches("0x"),
-            16
-        ).context("Failed to parse block number")?;
-        
-        Ok(block_number)
+
+        parse_hex_u64_result(&result).context("Failed to parse block number")
     }
-    
+
     /// Obtener precio de gas actual
     pub async fn get_gas_price(&self, chain_id: u64) -> Result<f64> {
         let result = self.call_rpc(
@@ -230,21 +736,136 @@ impl BlockchainConnector {
             "eth_gasPrice",
             serde_json::json!([]),
         ).await?;
-        
-        let gas_price_hex = result.as_str()
-            .ok_or_else(|| anyhow::anyhow!("Invalid gas price format"))?;
-        
-        let gas_price_wei = u128::from_str_radix(
-            gas_price_hex.trim_start_matches("0x"),
-            16
-        ).context("Failed to parse gas price")?;
-        
-        // Convertir de wei a gwei
-        let gas_price_gwei = gas_price_wei as f64 / 1_000_000_000.0;
-        
-        Ok(gas_price_gwei)
+
+        parse_gas_price_gwei_result(&result).context("Failed to parse gas price")
+    }
+
+    /// Ejecuta varias llamadas JSON-RPC en un solo POST HTTP (un batch
+    /// JSON-RPC), demultiplexando la respuesta por `id` en vez de asumir que
+    /// vienen en el mismo orden que la request (el spec JSON-RPC no lo
+    /// garantiza). Pensado para reemplazar varios `call_rpc` secuenciales
+    /// (p.ej. `get_all_chains_status`) por un único round-trip de red.
+    ///
+    /// Una falla de un request individual dentro del batch (p.ej. un método
+    /// no soportado) no tumba el resto: cada entrada del Vec devuelto es su
+    /// propio `Result`. A diferencia de `call_rpc`, este método no hace
+    /// failover entre endpoints ni retry — usa el primer endpoint sano y
+    /// reporta la falla del batch completo si ese endpoint no responde,
+    /// porque reintentar todo el batch por la falla de un único request
+    /// desperdiciaría las respuestas que sí llegaron bien.
+    pub async fn call_rpc_batch(
+        &self,
+        chain_id: u64,
+        calls: Vec<(&str, serde_json::Value)>,
+    ) -> Result<Vec<Result<serde_json::Value>>> {
+        let chain = self.chains.get(&chain_id)
+            .ok_or_else(|| anyhow::anyhow!("Chain {} not configured", chain_id))?;
+        if chain.rpc_urls.is_empty() {
+            return Err(anyhow::anyhow!("Chain {} has no rpc_urls configured", chain_id));
+        }
+        let url = self.order_endpoints_by_health(&chain.rpc_urls)
+            .into_iter()
+            .next()
+            .expect("rpc_urls is non-empty");
+
+        let requests: Vec<JsonRpcRequest> = calls
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                params: params.clone(),
+                id: id as u64,
+            })
+            .collect();
+
+        let response = self.client
+            .post(&url)
+            .json(&requests)
+            .send()
+            .await
+            .context("Failed to send RPC batch request");
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                self.record_endpoint_failure(&url);
+                return Err(e);
+            }
+        };
+
+        if !response.status().is_success() {
+            self.record_endpoint_failure(&url);
+            return Err(anyhow::anyhow!("RPC batch request failed with status: {}", response.status()));
+        }
+
+        let responses: std::result::Result<Vec<JsonRpcResponse>, _> = response
+            .json()
+            .await
+            .context("Failed to parse RPC batch response");
+        let responses = match responses {
+            Ok(responses) => responses,
+            Err(e) => {
+                self.record_endpoint_failure(&url);
+                return Err(e);
+            }
+        };
+        self.record_endpoint_success(&url);
+
+        let mut by_id: HashMap<u64, JsonRpcResponse> = responses.into_iter().map(|r| (r.id, r)).collect();
+
+        Ok((0..calls.len() as u64)
+            .map(|id| match by_id.remove(&id) {
+                Some(resp) => match resp.error {
+                    Some(error) => Err(anyhow::anyhow!("RPC error {}: {}", error.code, error.message)),
+                    None => resp.result.ok_or_else(|| anyhow::anyhow!("No result in RPC response")),
+                },
+                None => Err(anyhow::anyhow!("No response for batch request id {}", id)),
+            })
+            .collect())
     }
     
+    /// Estima fees EIP-1559 vía `eth_feeHistory` sobre los últimos
+    /// `block_count` bloques: `baseFeePerGas` del próximo bloque más un
+    /// `maxPriorityFeePerGas` tomado del percentil más cercano a
+    /// `priority_fee_percentile` (0-100, p.ej. 50.0 para la mediana) entre
+    /// [`FEE_HISTORY_REWARD_PERCENTILES`]. `maxFeePerGas = baseFee * 2 +
+    /// priorityFee` deja margen para que el base fee suba un par de bloques
+    /// antes de que la tx entre. `None` si la chain no reporta
+    /// `baseFeePerGas` (pre-London): el caller debe caer al `gas_price`
+    /// legacy de `get_gas_price`.
+    pub async fn estimate_eip1559_fees(
+        &self,
+        chain_id: u64,
+        block_count: u64,
+        priority_fee_percentile: f64,
+    ) -> Result<Option<Eip1559FeeEstimate>> {
+        let percentile_index = FEE_HISTORY_REWARD_PERCENTILES
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                (*a - priority_fee_percentile)
+                    .abs()
+                    .partial_cmp(&(*b - priority_fee_percentile).abs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(index, _)| index)
+            .unwrap_or(1);
+
+        let result = self
+            .call_rpc(
+                chain_id,
+                "eth_feeHistory",
+                serde_json::json!([
+                    format!("0x{:x}", block_count),
+                    "pending",
+                    FEE_HISTORY_REWARD_PERCENTILES
+                ]),
+            )
+            .await?;
+
+        Ok(parse_eip1559_fee_history(&result, percentile_index))
+    }
+
     /// Obtener balance de ETH/token nativo
     pub async fn get_native_balance(
         &self,
@@ -285,12 +906,14 @@ impl BlockchainConnector {
         token_address: &str,
         wallet_address: &str,
     ) -> Result<BalanceInfo> {
+        let metadata = self.get_token_metadata(chain_id, token_address).await?;
+
         // Construir data para balanceOf(address)
         let data = format!(
             "0x70a08231000000000000000000000000{}",
             wallet_address.trim_start_matches("0x")
         );
-        
+
         let result = self.call_rpc(
             chain_id,
             "eth_call",
@@ -302,27 +925,196 @@ impl BlockchainConnector {
                 "latest"
             ]),
         ).await?;
-        
+
         let balance_hex = result.as_str()
             .ok_or_else(|| anyhow::anyhow!("Invalid balance format"))?;
-        
+
         let balance = u128::from_str_radix(
             balance_hex.trim_start_matches("0x"),
             16
         ).unwrap_or(0);
-        
-        // Asumir 18 decimales por defecto (debería obtenerse del token)
-        let balance_formatted = balance as f64 / 1e18;
-        
+
+        let balance_formatted = balance as f64 / 10f64.powi(metadata.decimals as i32);
+
         Ok(BalanceInfo {
             address: wallet_address.to_string(),
-            token: token_address.to_string(),
+            token: metadata.symbol,
             balance: balance.to_string(),
             balance_formatted,
-            decimals: 18,
+            decimals: metadata.decimals,
         })
     }
-    
+
+    /// Resuelve `decimals()`/`symbol()`/`name()` de un token ERC20 vía
+    /// `eth_call`, cacheando el resultado por `(chain_id, token_address)`:
+    /// estos valores son inmutables una vez desplegado el contrato, así que
+    /// no hay razón para repetir tres `eth_call` en cada consulta de balance.
+    /// Tolera tokens no estándar que devuelven `symbol`/`name` como
+    /// `bytes32` en vez de `string` (p.ej. MKR, SAI); si `symbol()`/`name()`
+    /// fallan o no se pueden decodificar, cae a `"UNKNOWN"`/el símbolo.
+    pub async fn get_token_metadata(&self, chain_id: u64, token_address: &str) -> Result<TokenMetadata> {
+        let cache_key = (chain_id, token_address.to_lowercase());
+        if let Some(cached) = self.token_metadata_cache.lock().unwrap().get(&cache_key) {
+            return Ok(cached.clone());
+        }
+
+        let decimals_bytes = self.eth_call_raw(chain_id, token_address, "0x313ce567").await?;
+        let decimals = decimals_bytes.last().copied().unwrap_or(18);
+
+        let symbol = self
+            .eth_call_raw(chain_id, token_address, "0x95d89b41")
+            .await
+            .ok()
+            .and_then(|bytes| decode_abi_string(&bytes))
+            .unwrap_or_else(|| "UNKNOWN".to_string());
+
+        let name = self
+            .eth_call_raw(chain_id, token_address, "0x06fdde03")
+            .await
+            .ok()
+            .and_then(|bytes| decode_abi_string(&bytes))
+            .unwrap_or_else(|| symbol.clone());
+
+        let metadata = TokenMetadata { symbol, name, decimals };
+        self.token_metadata_cache.lock().unwrap().insert(cache_key, metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Calienta el cache de metadata de varios tokens por adelantado (p.ej.
+    /// al cargar la config de assets), para que las consultas de balance que
+    /// vengan después no paguen la latencia de los tres `eth_call` la
+    /// primera vez que se necesita cada token.
+    pub async fn prefetch_metadata(&self, chain_id: u64, tokens: &[String]) -> Result<()> {
+        for token in tokens {
+            self.get_token_metadata(chain_id, token).await?;
+        }
+        Ok(())
+    }
+
+    /// `eth_call` crudo contra `token_address` con el selector dado (sin
+    /// argumentos), devolviendo los bytes de retorno ya decodificados de hex.
+    async fn eth_call_raw(&self, chain_id: u64, token_address: &str, selector: &str) -> Result<Vec<u8>> {
+        let result = self
+            .call_rpc(
+                chain_id,
+                "eth_call",
+                serde_json::json!([
+                    {
+                        "to": token_address,
+                        "data": selector
+                    },
+                    "latest"
+                ]),
+            )
+            .await?;
+
+        let hex_str = result.as_str().ok_or_else(|| anyhow::anyhow!("Invalid eth_call result format"))?;
+        hex::decode(hex_str.trim_start_matches("0x")).context("Failed to decode eth_call result hex")
+    }
+
+    /// Igual que [`Self::get_native_balance`], pero en vez de confiar
+    /// ciegamente en el campo `balance` que devuelve el RPC, verifica
+    /// criptográficamente el valor contra el `stateRoot` del bloque actual
+    /// vía `eth_getProof` (inspirado en clientes ligeros tipo Helios). Pensado
+    /// para RPCs públicos/gratuitos como el `eth.llamarpc.com` por defecto,
+    /// donde no hay garantía de que el endpoint no esté desactualizado o
+    /// mintiendo directamente.
+    pub async fn get_verified_native_balance(&self, chain_id: u64, address: &str) -> Result<VerifiedBalance> {
+        let (block_number, state_root) = self.fetch_latest_state_root(chain_id).await?;
+        let address_bytes = parse_address(address)?;
+
+        let proof = self
+            .call_rpc(
+                chain_id,
+                "eth_getProof",
+                serde_json::json!([address, [], format!("0x{:x}", block_number)]),
+            )
+            .await?;
+        let account_proof = decode_proof_nodes(&proof, "accountProof")?;
+
+        let account = verify_account_proof(state_root, &address_bytes, &account_proof)
+            .context("native balance proof verification failed")?;
+
+        Ok(VerifiedBalance {
+            balance: HexOrDecimalU256(account.balance),
+            block_number,
+        })
+    }
+
+    /// Igual que [`Self::get_verified_native_balance`] pero para el balance
+    /// ERC20 de `wallet_address` en `token_address`: además del
+    /// `accountProof` del token, verifica el `storageProof` del slot
+    /// `balanceOf(wallet_address)` contra el `storageHash` ya verificado de
+    /// esa cuenta. `balance_mapping_slot` es el slot del mapping `balanceOf`
+    /// en el storage layout del token (`0` para la mayoría de
+    /// implementaciones OpenZeppelin/Solmate estándar).
+    pub async fn get_verified_token_balance(
+        &self,
+        chain_id: u64,
+        token_address: &str,
+        wallet_address: &str,
+        balance_mapping_slot: U256,
+    ) -> Result<VerifiedBalance> {
+        let (block_number, state_root) = self.fetch_latest_state_root(chain_id).await?;
+        let token_bytes = parse_address(token_address)?;
+        let wallet_bytes = parse_address(wallet_address)?;
+
+        let storage_key = erc20_balance_storage_key(&wallet_bytes, balance_mapping_slot);
+        let storage_key_hex = format!("0x{}", hex::encode(storage_key));
+
+        let proof = self
+            .call_rpc(
+                chain_id,
+                "eth_getProof",
+                serde_json::json!([token_address, [storage_key_hex], format!("0x{:x}", block_number)]),
+            )
+            .await?;
+        let account_proof = decode_proof_nodes(&proof, "accountProof")?;
+        let account = verify_account_proof(state_root, &token_bytes, &account_proof)
+            .context("token account proof verification failed")?;
+
+        let storage_proof_entry = proof
+            .get("storageProof")
+            .and_then(|v| v.as_array())
+            .and_then(|entries| entries.first())
+            .ok_or_else(|| anyhow::anyhow!("eth_getProof response missing storageProof"))?;
+        let storage_nodes = decode_proof_nodes(storage_proof_entry, "proof")?;
+
+        let balance = verify_storage_proof(account.storage_hash, storage_key, &storage_nodes)
+            .context("token balance storage proof verification failed")?;
+
+        Ok(VerifiedBalance {
+            balance: HexOrDecimalU256(balance),
+            block_number,
+        })
+    }
+
+    /// Bloque actual y su `stateRoot`, tomado como raíz confiada contra la
+    /// que se verifican los proofs de `eth_getProof` pedidos para ese mismo
+    /// número de bloque.
+    async fn fetch_latest_state_root(&self, chain_id: u64) -> Result<(u64, [u8; 32])> {
+        let block_number = self.get_block_number(chain_id).await?;
+        let block = self
+            .call_rpc(
+                chain_id,
+                "eth_getBlockByNumber",
+                serde_json::json!([format!("0x{:x}", block_number), false]),
+            )
+            .await?;
+
+        let state_root_hex = block
+            .get("stateRoot")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("eth_getBlockByNumber response missing stateRoot"))?;
+        let state_root_bytes = hex::decode(state_root_hex.trim_start_matches("0x"))
+            .context("Failed to decode stateRoot hex")?;
+        let state_root: [u8; 32] = state_root_bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("stateRoot is not 32 bytes"))?;
+
+        Ok((block_number, state_root))
+    }
+
     /// Estimar gas para una transacción
     pub async fn estimate_gas(
         &self,
@@ -348,21 +1140,48 @@ impl BlockchainConnector {
             16
         ).context("Failed to parse gas estimate")?;
         
-        // Obtener precio de gas actual
-        let gas_price_gwei = self.get_gas_price(chain_id).await?;
-        
+        // Preferir fees EIP-1559 (maxFeePerGas/maxPriorityFeePerGas) cuando la
+        // chain los reporte; si no (pre-London) o si eth_feeHistory falla,
+        // caer al gas_price legacy.
+        let eip1559_fees = self.estimate_eip1559_fees(chain_id, 20, 50.0).await.unwrap_or(None);
+
+        let (gas_price_gwei, max_fee_per_gas, max_priority_fee_per_gas) = match eip1559_fees {
+            Some(fees) => (
+                fees.max_fee_per_gas_gwei,
+                Some(fees.max_fee_per_gas_gwei),
+                Some(fees.max_priority_fee_per_gas_gwei),
+            ),
+            None => (self.get_gas_price(chain_id).await?, None, None),
+        };
+
         // Calcular costo estimado
         let estimated_cost_eth = (gas_limit as f64) * gas_price_gwei / 1e9;
-        
-        // TODO: Obtener precio de ETH en USD para cálculo preciso
-        let eth_price_usd = 2000.0; // Placeholder
-        let estimated_cost_usd = estimated_cost_eth * eth_price_usd;
-        
+
+        let (native_token_price_usd, native_token_price_updated_at_unix) = match &self.price_oracle {
+            Some(oracle) => match oracle.get_price(chain_id).await {
+                Ok(point) => (point.price_usd, point.updated_at_unix),
+                Err(err) => {
+                    log::warn!(
+                        "price oracle lookup failed for chain {}: {:#}; estimated_cost_usd will be 0.0",
+                        chain_id,
+                        err
+                    );
+                    (0.0, 0)
+                }
+            },
+            None => (0.0, 0),
+        };
+        let estimated_cost_usd = estimated_cost_eth * native_token_price_usd;
+
         Ok(GasInfo {
             gas_price_gwei,
             gas_limit,
             estimated_cost_eth,
             estimated_cost_usd,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            native_token_price_usd,
+            native_token_price_updated_at_unix,
         })
     }
     
@@ -472,33 +1291,75 @@ impl BlockchainConnector {
     
     /// Obtener información de todas las chains
     pub async fn get_all_chains_status(&self) -> HashMap<u64, ChainStatus> {
-        let mut statuses = HashMap::new();
-        
-        for (chain_id, chain) in &self.chains {
-            let is_healthy = self.is_healthy(*chain_id).await;
-            
-            let block_number = if is_healthy {
-                self.get_block_number(*chain_id).await.ok()
-            } else {
-                None
-            };
-            
-            let gas_price = if is_healthy {
-                self.get_gas_price(*chain_id).await.ok()
-            } else {
-                None
-            };
-            
-            statuses.insert(*chain_id, ChainStatus {
-                chain_id: *chain_id,
-                name: chain.name.clone(),
-                is_healthy,
-                block_number,
-                gas_price_gwei: gas_price,
-            });
-        }
-        
-        statuses
+        let per_chain_status = self.chains.iter().map(|(chain_id, chain)| {
+            let chain_id = *chain_id;
+            let name = chain.name.clone();
+            async move {
+                // Un solo round-trip por chain en vez de block number + gas
+                // price secuenciales; todas las chains corren en paralelo.
+                let batch = self
+                    .call_rpc_batch(
+                        chain_id,
+                        vec![
+                            ("eth_blockNumber", serde_json::json!([])),
+                            ("eth_gasPrice", serde_json::json!([])),
+                        ],
+                    )
+                    .await;
+
+                let (block_number, gas_price_gwei) = match batch {
+                    Ok(mut results) if results.len() == 2 => {
+                        let gas_price_result = results.pop().unwrap();
+                        let block_number_result = results.pop().unwrap();
+                        (
+                            block_number_result.ok().and_then(|v| parse_hex_u64_result(&v).ok()),
+                            gas_price_result.ok().and_then(|v| parse_gas_price_gwei_result(&v).ok()),
+                        )
+                    }
+                    _ => (None, None),
+                };
+
+                (
+                    chain_id,
+                    ChainStatus {
+                        chain_id,
+                        name,
+                        is_healthy: block_number.is_some(),
+                        block_number,
+                        gas_price_gwei,
+                    },
+                )
+            }
+        });
+
+        join_all(per_chain_status).await.into_iter().collect()
+    }
+}
+
+/// `BlockchainConnector` es el `Provider` base de la pila de middleware: su
+/// único trabajo es la llamada RPC cruda, ya implementada como método
+/// inherente más arriba.
+#[async_trait]
+impl Provider for BlockchainConnector {
+    async fn call_rpc(&self, chain_id: u64, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+        self.call_rpc(chain_id, method, params).await
+    }
+}
+
+/// Caso base de la cadena de `Middleware`: no llena ningún campo por su
+/// cuenta y no puede enviar una transacción porque no tiene firmante.
+/// `GasOracle`/`NonceManager`/`SignerMiddleware` son las capas que agregan
+/// esas capacidades sin tocar este struct.
+#[async_trait]
+impl Middleware for BlockchainConnector {
+    async fn fill_transaction(&self, _chain_id: u64, params: TransactionParams) -> Result<TransactionParams> {
+        Ok(params)
+    }
+
+    async fn send_transaction(&self, _chain_id: u64, _params: TransactionParams) -> Result<String> {
+        Err(anyhow::anyhow!(
+            "BlockchainConnector has no signer attached; wrap it with SignerMiddleware to send transactions"
+        ))
     }
 }
 
@@ -512,6 +1373,151 @@ pub struct ChainStatus {
     pub gas_price_gwei: Option<f64>,
 }
 
+/// Parsea una respuesta cruda de `eth_feeHistory` en un `Eip1559FeeEstimate`.
+/// Toma el último `baseFeePerGas` (el del próximo bloque) y, de la columna
+/// `percentile_index` de `reward`, la mediana de los valores distintos de
+/// cero entre los bloques devueltos (los bloques vacíos reportan reward 0 y
+/// no dicen nada sobre la competencia real). `None` si la chain no reporta
+/// `baseFeePerGas` (pre-London).
+fn parse_eip1559_fee_history(response: &serde_json::Value, percentile_index: usize) -> Option<Eip1559FeeEstimate> {
+    let base_fee_hex = response
+        .get("baseFeePerGas")
+        .and_then(|v| v.as_array())
+        .and_then(|fees| fees.last())
+        .and_then(|v| v.as_str())?;
+    let base_fee_wei = u128::from_str_radix(base_fee_hex.trim_start_matches("0x"), 16).ok()?;
+    let base_fee_gwei = base_fee_wei as f64 / 1_000_000_000.0;
+
+    let mut priority_fee_samples: Vec<f64> = response
+        .get("reward")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|block_rewards| block_rewards.get(percentile_index))
+        .filter_map(|v| v.as_str())
+        .filter_map(|hex| u128::from_str_radix(hex.trim_start_matches("0x"), 16).ok())
+        .map(|wei| wei as f64 / 1_000_000_000.0)
+        .filter(|gwei| *gwei > 0.0)
+        .collect();
+    priority_fee_samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let max_priority_fee_per_gas_gwei = if priority_fee_samples.is_empty() {
+        0.0
+    } else {
+        priority_fee_samples[priority_fee_samples.len() / 2]
+    };
+
+    Some(Eip1559FeeEstimate {
+        base_fee_gwei,
+        max_priority_fee_per_gas_gwei,
+        max_fee_per_gas_gwei: base_fee_gwei * 2.0 + max_priority_fee_per_gas_gwei,
+    })
+}
+
+/// Parsea el resultado hex `0x...` de `eth_blockNumber` (o cualquier otro
+/// método que devuelva un `QUANTITY` entero) a `u64`.
+fn parse_hex_u64_result(result: &serde_json::Value) -> Result<u64> {
+    let hex_str = result.as_str().ok_or_else(|| anyhow::anyhow!("Invalid hex quantity format"))?;
+    u64::from_str_radix(hex_str.trim_start_matches("0x"), 16).map_err(Into::into)
+}
+
+/// Parsea el resultado hex `0x...` (wei) de `eth_gasPrice` a gwei.
+fn parse_gas_price_gwei_result(result: &serde_json::Value) -> Result<f64> {
+    let hex_str = result.as_str().ok_or_else(|| anyhow::anyhow!("Invalid gas price format"))?;
+    let gas_price_wei = u128::from_str_radix(hex_str.trim_start_matches("0x"), 16)?;
+    Ok(gas_price_wei as f64 / 1_000_000_000.0)
+}
+
+/// Decodifica el resultado crudo de un `eth_call` a `symbol()`/`name()` como
+/// un `string` ABI-encoded estándar (offset de 32 bytes + longitud + datos
+/// UTF-8 padded). Si eso falla pero la respuesta son exactamente 32 bytes,
+/// cae a interpretarla como `bytes32` (el formato no estándar que usan
+/// tokens viejos como MKR/SAI), recortando el padding de ceros.
+fn decode_abi_string(bytes: &[u8]) -> Option<String> {
+    if bytes.len() >= 64 {
+        let length = u64::from_be_bytes(bytes[56..64].try_into().ok()?) as usize;
+        if let Some(data) = bytes.get(64..64 + length) {
+            if let Ok(s) = std::str::from_utf8(data) {
+                let trimmed = s.trim_end_matches('\0');
+                if !trimmed.is_empty() {
+                    return Some(trimmed.to_string());
+                }
+            }
+        }
+    }
+
+    if bytes.len() == 32 {
+        let trimmed: Vec<u8> = bytes.iter().copied().take_while(|&b| b != 0).collect();
+        if let Ok(s) = std::str::from_utf8(&trimmed) {
+            if !s.is_empty() {
+                return Some(s.to_string());
+            }
+        }
+    }
+
+    None
+}
+
+/// Parsea una dirección `0x...` de 20 bytes para alimentarla a
+/// `merkle_proof::verify_account_proof`, que opera sobre bytes crudos.
+fn parse_address(address: &str) -> Result<[u8; 20]> {
+    let bytes = hex::decode(address.trim_start_matches("0x")).context("Failed to decode address hex")?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| anyhow::anyhow!("address is {} bytes, expected 20", bytes.len()))
+}
+
+/// Extrae y decodifica el array de nodos RLP (hex strings) del campo `field`
+/// de una respuesta `eth_getProof` (p.ej. `"accountProof"` o, dentro de una
+/// entrada de `storageProof`, `"proof"`).
+fn decode_proof_nodes(value: &serde_json::Value, field: &str) -> Result<Vec<Vec<u8>>> {
+    value
+        .get(field)
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow::anyhow!("eth_getProof response missing '{}'", field))?
+        .iter()
+        .map(|node| {
+            let hex_str = node
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("proof node in '{}' is not a string", field))?;
+            hex::decode(hex_str.trim_start_matches("0x")).context("Failed to decode proof node hex")
+        })
+        .collect()
+}
+
+/// Decodifica un log `Sync(uint112,uint112)` en un `PoolReserveUpdate`. El
+/// campo `data` del log son 64 bytes: dos `uint112` cada uno ocupando los
+/// últimos 16 bytes de su palabra de 32 bytes.
+fn decode_sync_log(pool_id: &str, log: &serde_json::Value) -> Option<PoolReserveUpdate> {
+    let data_hex = log.get("data")?.as_str()?;
+    let data = hex::decode(data_hex.trim_start_matches("0x")).ok()?;
+    if data.len() != 64 {
+        return None;
+    }
+
+    let reserves_a = u128::from_be_bytes(data[16..32].try_into().ok()?) as f64;
+    let reserves_b = u128::from_be_bytes(data[48..64].try_into().ok()?) as f64;
+
+    let block_number = log
+        .get("blockNumber")
+        .and_then(|v| v.as_str())
+        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+        .unwrap_or(0);
+
+    let updated_at_unix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    Some(PoolReserveUpdate {
+        pool_id: pool_id.to_string(),
+        reserves_a,
+        reserves_b,
+        block_number,
+        updated_at_unix,
+    })
+}
+
 // ==================================================================================
 // TESTS
 // ==================================================================================
@@ -533,15 +1539,196 @@ mod tests {
         let config = ChainConfig {
             chain_id: 1,
             name: "Ethereum".to_string(),
-            rpc_url: "https://eth.llamarpc.com".to_string(),
+            rpc_urls: vec!["https://eth.llamarpc.com".to_string()],
             explorer_url: "https://etherscan.io".to_string(),
             native_token: "ETH".to_string(),
             is_active: true,
+            ws_url: Some("wss://eth.llamarpc.com".to_string()),
         };
-        
+
         connector.add_chain(config);
         assert_eq!(connector.chains.len(), 1);
         assert!(connector.get_chain(1).is_some());
     }
+
+    #[test]
+    fn test_decode_sync_log() {
+        // reserve0 = 1000, reserve1 = 2000, cada uno en los últimos 16 bytes
+        // de su palabra de 32 bytes.
+        let mut data = vec![0u8; 64];
+        data[16..32].copy_from_slice(&1000u128.to_be_bytes());
+        data[48..64].copy_from_slice(&2000u128.to_be_bytes());
+
+        let log = serde_json::json!({
+            "data": format!("0x{}", hex::encode(&data)),
+            "blockNumber": "0x10",
+        });
+
+        let update = decode_sync_log("POOL_1", &log).unwrap();
+        assert_eq!(update.pool_id, "POOL_1");
+        assert_eq!(update.reserves_a, 1000.0);
+        assert_eq!(update.reserves_b, 2000.0);
+        assert_eq!(update.block_number, 16);
+    }
+
+    #[test]
+    fn test_parse_eip1559_fee_history_takes_the_last_base_fee_and_median_reward() {
+        let response = serde_json::json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x3b9aca00", "0x4190ab00", "0x47868c00"],
+            "reward": [
+                ["0x0", "0x3b9aca00", "0x77359400"],
+                ["0x0", "0x77359400", "0xee6b2800"],
+                ["0x0", "0x1dcd6500", "0x3b9aca00"]
+            ]
+        });
+
+        let estimate = parse_eip1559_fee_history(&response, 1).unwrap();
+
+        // baseFeePerGas: 0x47868c00 wei = 1.2 gwei.
+        assert!((estimate.base_fee_gwei - 1.2).abs() < 1e-6);
+        // Columna percentil 50 (índice 1): [1.0, 2.0, 0.5] gwei -> mediana 1.0.
+        assert!((estimate.max_priority_fee_per_gas_gwei - 1.0).abs() < 1e-6);
+        assert!((estimate.max_fee_per_gas_gwei - (1.2 * 2.0 + 1.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_eip1559_fee_history_skips_zero_reward_empty_blocks() {
+        let response = serde_json::json!({
+            "baseFeePerGas": ["0x3b9aca00"],
+            "reward": [["0x0"], ["0x0"], ["0x0"]]
+        });
+
+        let estimate = parse_eip1559_fee_history(&response, 0).unwrap();
+
+        assert_eq!(estimate.max_priority_fee_per_gas_gwei, 0.0);
+    }
+
+    #[test]
+    fn test_parse_eip1559_fee_history_returns_none_without_a_base_fee() {
+        let response = serde_json::json!({ "reward": [["0x0"]] });
+        assert!(parse_eip1559_fee_history(&response, 0).is_none());
+    }
+
+    #[test]
+    fn test_gas_price_percentile_no_samples() {
+        let connector = BlockchainConnector::new(30);
+        assert_eq!(connector.gas_price_percentile(1, 0.75), None);
+    }
+
+    #[test]
+    fn test_gas_price_percentile_tracks_recent_samples() {
+        let connector = BlockchainConnector::new(30);
+        for gwei in [10.0, 20.0, 30.0, 40.0, 50.0] {
+            connector.record_landed_gas_price(1, gwei);
+        }
+
+        // Percentil 50 de [10,20,30,40,50] es el del medio: 30.
+        assert_eq!(connector.gas_price_percentile(1, 0.5), Some(30.0));
+        // Percentil 100 es el máximo observado.
+        assert_eq!(connector.gas_price_percentile(1, 1.0), Some(50.0));
+    }
+
+    #[test]
+    fn test_parse_address_accepts_with_and_without_0x_prefix() {
+        let expected = [0x11u8; 20];
+        assert_eq!(parse_address("0x1111111111111111111111111111111111111111").unwrap(), expected);
+        assert_eq!(parse_address("1111111111111111111111111111111111111111").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_address_rejects_wrong_length() {
+        assert!(parse_address("0x1234").is_err());
+    }
+
+    #[test]
+    fn test_decode_proof_nodes_parses_hex_array() {
+        let response = serde_json::json!({
+            "accountProof": ["0xc0", "0x80"]
+        });
+        let nodes = decode_proof_nodes(&response, "accountProof").unwrap();
+        assert_eq!(nodes, vec![vec![0xc0u8], vec![0x80u8]]);
+    }
+
+    #[test]
+    fn test_decode_proof_nodes_errors_when_field_missing() {
+        let response = serde_json::json!({});
+        assert!(decode_proof_nodes(&response, "accountProof").is_err());
+    }
+
+    #[test]
+    fn test_order_endpoints_by_health_prefers_unquarantined_in_priority_order() {
+        let connector = BlockchainConnector::new(30);
+        let urls = vec!["https://a".to_string(), "https://b".to_string(), "https://c".to_string()];
+
+        // "b" acumula fallas hasta quedar en cuarentena; "a" y "c" quedan sanos.
+        connector.record_endpoint_failure("https://b");
+
+        let ordered = connector.order_endpoints_by_health(&urls);
+        assert_eq!(ordered, vec!["https://a", "https://c", "https://b"]);
+        assert!(connector.is_endpoint_quarantined("https://b"));
+        assert!(!connector.is_endpoint_quarantined("https://a"));
+    }
+
+    #[test]
+    fn test_record_endpoint_success_clears_quarantine() {
+        let connector = BlockchainConnector::new(30);
+        connector.record_endpoint_failure("https://flaky");
+        assert!(connector.is_endpoint_quarantined("https://flaky"));
+
+        connector.record_endpoint_success("https://flaky");
+        assert!(!connector.is_endpoint_quarantined("https://flaky"));
+    }
+
+    #[test]
+    fn test_is_retryable_rpc_error_code() {
+        // -32005 (rate limit) y -32603 (internal error) son del nodo: reintentables.
+        assert!(is_retryable_rpc_error_code(-32005));
+        assert!(is_retryable_rpc_error_code(-32603));
+        // -32602 (invalid params) es un error del caller: reintentar no ayuda.
+        assert!(!is_retryable_rpc_error_code(-32602));
+    }
+
+    #[test]
+    fn test_decode_abi_string_standard_dynamic_encoding() {
+        // offset=0x20, length=3, "USD" + padding hasta 32 bytes.
+        let mut bytes = vec![0u8; 32];
+        bytes[31] = 0x20;
+        let mut length_word = vec![0u8; 32];
+        length_word[31] = 3;
+        bytes.extend(length_word);
+        let mut data = b"USD".to_vec();
+        data.resize(32, 0);
+        bytes.extend(data);
+
+        assert_eq!(decode_abi_string(&bytes), Some("USD".to_string()));
+    }
+
+    #[test]
+    fn test_decode_abi_string_legacy_bytes32_fallback() {
+        // Tokens viejos (MKR, SAI) devuelven symbol()/name() como bytes32 crudo.
+        let mut bytes = b"MKR".to_vec();
+        bytes.resize(32, 0);
+        assert_eq!(decode_abi_string(&bytes), Some("MKR".to_string()));
+    }
+
+    #[test]
+    fn test_decode_abi_string_rejects_garbage() {
+        assert_eq!(decode_abi_string(&[]), None);
+        assert_eq!(decode_abi_string(&[0u8; 10]), None);
+    }
+
+    #[test]
+    fn test_parse_hex_u64_result() {
+        assert_eq!(parse_hex_u64_result(&serde_json::json!("0x10")).unwrap(), 16);
+        assert!(parse_hex_u64_result(&serde_json::json!(null)).is_err());
+    }
+
+    #[test]
+    fn test_parse_gas_price_gwei_result() {
+        // 1 gwei = 0x3b9aca00 wei.
+        let gwei = parse_gas_price_gwei_result(&serde_json::json!("0x3b9aca00")).unwrap();
+        assert!((gwei - 1.0).abs() < 1e-9);
+    }
 }
 
@@ -0,0 +1,271 @@
+/**
+ * ============================================================================
+ * ARCHIVO: ./services/engine-rust/src/connectors/config_source.rs
+ * MÓDULO: Rust Engine
+ * ============================================================================
+ *
+ * 📥 ENTRADA:
+ *   - Nombre de hoja/tabla lógica (BLOCKCHAINS, DEXES, ASSETS, POOLS, ...)
+ *
+ * 🔄 TRANSFORMACIÓN:
+ *   FUNCIONES: fetch, last_modified
+ *
+ * 📤 SALIDA:
+ *   - Filas como `HashMap<String, serde_json::Value>`, el mismo formato que
+ *     ya consumen los `parse_*_config` de `main.rs`
+ *
+ * 🔗 DEPENDENCIAS:
+ *   - sheets (SheetsConnector)
+ *
+ * ============================================================================
+ */
+
+//! `ConfigSource` abstrae de dónde viene la configuración dinámica
+//! (blockchains, DEXes, assets, pools) para que `RustArbitrageEngine` no esté
+//! atado a Google Sheets. Cualquier backend que implemente este trait puede
+//! conectarse sin tocar los `parse_*_config` existentes, que ya consumen la
+//! representación genérica de fila.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::sheets::SheetsConnector;
+
+/// Fuente de configuración dinámica. Implementaciones: Sheets (producción),
+/// un directorio local de TOML/JSON (desarrollo/offline), o Postgres.
+#[async_trait]
+pub trait ConfigSource: Send + Sync {
+    /// Trae todas las filas de una hoja/tabla lógica (p.ej. "BLOCKCHAINS").
+    async fn fetch(&self, sheet_name: &str) -> Result<Vec<HashMap<String, Value>>>;
+
+    /// Momento de la última modificación conocida de la fuente, usado por
+    /// `update_configuration()` para decidir si hay que recargar.
+    async fn last_modified(&self) -> Result<Instant>;
+}
+
+// ==================================================================================
+// SHEETS BACKEND
+// ==================================================================================
+
+/// Adapta `SheetsConnector` (que requiere `&mut self` por su cache interna)
+/// al trait `ConfigSource` (que se consume como `Arc<dyn ConfigSource>` y por
+/// lo tanto solo ofrece `&self`).
+pub struct SheetsConfigSource {
+    connector: tokio::sync::Mutex<SheetsConnector>,
+}
+
+impl SheetsConfigSource {
+    pub fn new(connector: SheetsConnector) -> Self {
+        Self {
+            connector: tokio::sync::Mutex::new(connector),
+        }
+    }
+}
+
+#[async_trait]
+impl ConfigSource for SheetsConfigSource {
+    async fn fetch(&self, sheet_name: &str) -> Result<Vec<HashMap<String, Value>>> {
+        let mut connector = self.connector.lock().await;
+        let rows = connector.get_sheet_data(sheet_name).await?;
+        Ok(rows_to_maps(&rows))
+    }
+
+    async fn last_modified(&self) -> Result<Instant> {
+        // SheetsConnector no trackea un timestamp de última modificación
+        // remota; `Instant::now()` fuerza un recargo en cada poll hasta que
+        // esa señal exista, lo cual es seguro (solo implica trabajo extra).
+        Ok(Instant::now())
+    }
+}
+
+/// Convierte filas crudas (`header_row` + `data_rows`) en mapas `columna ->
+/// valor`, igual que `SheetsConnector::get_blockchains_array`.
+fn rows_to_maps(rows: &[Vec<String>]) -> Vec<HashMap<String, Value>> {
+    if rows.is_empty() {
+        return Vec::new();
+    }
+
+    let headers = &rows[0];
+    rows[1..]
+        .iter()
+        .map(|row| {
+            let mut map = HashMap::new();
+            for (i, cell) in row.iter().enumerate() {
+                if let Some(header) = headers.get(i) {
+                    map.insert(header.clone(), Value::String(cell.clone()));
+                }
+            }
+            map
+        })
+        .collect()
+}
+
+// ==================================================================================
+// LOCAL DIRECTORY BACKEND (TOML/JSON)
+// ==================================================================================
+
+/// Lee la configuración desde un directorio local con un archivo por hoja
+/// (`BLOCKCHAINS.toml`, `BLOCKCHAINS.json`, etc.), para correr el motor
+/// offline o en tests sin depender de Google Sheets.
+pub struct LocalDirConfigSource {
+    dir: PathBuf,
+}
+
+impl LocalDirConfigSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn file_path(&self, sheet_name: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", sheet_name, extension))
+    }
+}
+
+#[async_trait]
+impl ConfigSource for LocalDirConfigSource {
+    async fn fetch(&self, sheet_name: &str) -> Result<Vec<HashMap<String, Value>>> {
+        let json_path = self.file_path(sheet_name, "json");
+        if json_path.exists() {
+            let contents = tokio::fs::read_to_string(&json_path)
+                .await
+                .with_context(|| format!("Failed to read {}", json_path.display()))?;
+            let rows: Vec<HashMap<String, Value>> = serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse {} as JSON rows", json_path.display()))?;
+            return Ok(rows);
+        }
+
+        let toml_path = self.file_path(sheet_name, "toml");
+        if toml_path.exists() {
+            let contents = tokio::fs::read_to_string(&toml_path)
+                .await
+                .with_context(|| format!("Failed to read {}", toml_path.display()))?;
+            let rows: Vec<HashMap<String, toml::Value>> = toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse {} as TOML rows", toml_path.display()))?;
+            return Ok(rows
+                .into_iter()
+                .map(|row| row.into_iter().map(|(k, v)| (k, toml_value_to_json(v))).collect())
+                .collect());
+        }
+
+        Ok(Vec::new())
+    }
+
+    async fn last_modified(&self) -> Result<Instant> {
+        // El sistema de archivos expone mtime como `SystemTime`, no
+        // `Instant`; como el resto del motor solo compara `Instant` contra
+        // `Instant::now()`, forzar un recargo en cada poll es la opción
+        // segura hasta que `update_configuration` migre a `SystemTime`.
+        Ok(Instant::now())
+    }
+}
+
+fn toml_value_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Number(i.into()),
+        toml::Value::Float(f) => serde_json::Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(arr) => Value::Array(arr.into_iter().map(toml_value_to_json).collect()),
+        toml::Value::Table(table) => {
+            Value::Object(table.into_iter().map(|(k, v)| (k, toml_value_to_json(v))).collect())
+        }
+    }
+}
+
+// ==================================================================================
+// POSTGRES BACKEND
+// ==================================================================================
+
+/// Lee la configuración desde una base Postgres donde cada hoja lógica es
+/// una tabla del mismo nombre (p.ej. `BLOCKCHAINS`), con una entrada de
+/// `HashMap<String, Value>` por columna y fila. Solo las columnas legibles
+/// como texto llegan como `Value::String`; las de otros tipos (o NULL) quedan
+/// como `Value::Null`.
+pub struct PostgresConfigSource {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresConfigSource {
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(5)
+            .connect(database_url)
+            .await
+            .context("Failed to connect to Postgres config database")?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl ConfigSource for PostgresConfigSource {
+    async fn fetch(&self, sheet_name: &str) -> Result<Vec<HashMap<String, Value>>> {
+        // El nombre de tabla viene de una lista fija de hojas conocidas, no
+        // de input externo, así que el `format!` aquí no es inyección SQL.
+        let query = format!("SELECT * FROM {}", sheet_name.to_lowercase());
+        let rows = sqlx::query(&query)
+            .fetch_all(&self.pool)
+            .await
+            .with_context(|| format!("Failed to query config table {}", sheet_name))?;
+
+        Ok(rows.iter().map(row_to_map).collect())
+    }
+
+    async fn last_modified(&self) -> Result<Instant> {
+        // Igual que los otros backends: sin una columna `updated_at` dedicada
+        // por tabla no hay forma barata de saber si cambió, así que se asume
+        // que sí y se deja que `update_configuration` decida la cadencia.
+        Ok(Instant::now())
+    }
+}
+
+fn row_to_map(row: &sqlx::postgres::PgRow) -> HashMap<String, Value> {
+    use sqlx::{Column, Row, TypeInfo, ValueRef};
+
+    let mut map = HashMap::new();
+    for column in row.columns() {
+        let name = column.name().to_string();
+        let value = row
+            .try_get_raw(column.ordinal())
+            .ok()
+            .filter(|raw| !raw.is_null())
+            .and_then(|_| row.try_get::<String, _>(column.ordinal()).ok())
+            .map(Value::String)
+            .unwrap_or(Value::Null);
+        let _ = column.type_info().name();
+        map.insert(name, value);
+    }
+    map
+}
+
+// ==================================================================================
+// TESTS
+// ==================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_to_maps() {
+        let rows = vec![
+            vec!["CHAIN_ID".to_string(), "CHAIN_NAME".to_string()],
+            vec!["1".to_string(), "Ethereum".to_string()],
+        ];
+
+        let maps = rows_to_maps(&rows);
+        assert_eq!(maps.len(), 1);
+        assert_eq!(maps[0].get("CHAIN_ID"), Some(&Value::String("1".to_string())));
+        assert_eq!(maps[0].get("CHAIN_NAME"), Some(&Value::String("Ethereum".to_string())));
+    }
+
+    #[test]
+    fn test_rows_to_maps_empty() {
+        assert!(rows_to_maps(&[]).is_empty());
+    }
+}
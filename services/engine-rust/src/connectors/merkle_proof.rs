@@ -0,0 +1,400 @@
+//! Verificación trustless de balances/storage vía pruebas Merkle-Patricia
+//! (`eth_getProof`), inspirado en clientes ligeros como Helios: en vez de
+//! confiar ciegamente en la respuesta de un RPC público, reconstruimos el
+//! hash chain desde el `stateRoot` confiado del header hasta la hoja de la
+//! cuenta (o del slot de storage) usando solo RLP + Keccak-256, sin
+//! dependencias externas de trie/rlp.
+//!
+//! Limitación conocida: los nodos "inline" (< 32 bytes, embebidos en su
+//! padre en vez de referenciados por hash) no están soportados — en la
+//! práctica solo aparecen en tries casi vacíos (testnets de juguete), nunca
+//! en la cuenta/storage de un contrato real en mainnet.
+
+use anyhow::{anyhow, bail, Context, Result};
+use primitive_types::U256;
+use sha3::{Digest, Keccak256};
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// Item RLP decodificado: una cadena de bytes crudos, o una lista de items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RlpItem {
+    String(Vec<u8>),
+    List(Vec<RlpItem>),
+}
+
+impl RlpItem {
+    fn as_bytes(&self) -> Result<&[u8]> {
+        match self {
+            RlpItem::String(bytes) => Ok(bytes),
+            RlpItem::List(_) => Err(anyhow!("expected an RLP string, got a list")),
+        }
+    }
+
+    fn as_list(&self) -> Result<&[RlpItem]> {
+        match self {
+            RlpItem::List(items) => Ok(items),
+            RlpItem::String(_) => Err(anyhow!("expected an RLP list, got a string")),
+        }
+    }
+}
+
+/// Decodifica un único item RLP al inicio de `input`, devolviéndolo junto al
+/// número de bytes consumidos. No exige que `input` se consuma por completo
+/// (un nodo de trie puede venir seguido de padding o de otros nodos).
+fn rlp_decode(input: &[u8]) -> Result<(RlpItem, usize)> {
+    let prefix = *input.first().ok_or_else(|| anyhow!("empty RLP input"))?;
+
+    if prefix < 0x80 {
+        return Ok((RlpItem::String(vec![prefix]), 1));
+    }
+
+    if prefix < 0xb8 {
+        let len = (prefix - 0x80) as usize;
+        let payload = input.get(1..1 + len).ok_or_else(|| anyhow!("truncated RLP short string"))?;
+        return Ok((RlpItem::String(payload.to_vec()), 1 + len));
+    }
+
+    if prefix < 0xc0 {
+        let len_of_len = (prefix - 0xb7) as usize;
+        let len_bytes = input.get(1..1 + len_of_len).ok_or_else(|| anyhow!("truncated RLP long string length"))?;
+        let len = be_bytes_to_usize(len_bytes)?;
+        let start = 1 + len_of_len;
+        let payload = input.get(start..start + len).ok_or_else(|| anyhow!("truncated RLP long string"))?;
+        return Ok((RlpItem::String(payload.to_vec()), start + len));
+    }
+
+    if prefix < 0xf8 {
+        let len = (prefix - 0xc0) as usize;
+        let payload = input.get(1..1 + len).ok_or_else(|| anyhow!("truncated RLP short list"))?;
+        return Ok((RlpItem::List(rlp_decode_list_payload(payload)?), 1 + len));
+    }
+
+    let len_of_len = (prefix - 0xf7) as usize;
+    let len_bytes = input.get(1..1 + len_of_len).ok_or_else(|| anyhow!("truncated RLP long list length"))?;
+    let len = be_bytes_to_usize(len_bytes)?;
+    let start = 1 + len_of_len;
+    let payload = input.get(start..start + len).ok_or_else(|| anyhow!("truncated RLP long list"))?;
+    Ok((RlpItem::List(rlp_decode_list_payload(payload)?), start + len))
+}
+
+fn rlp_decode_list_payload(mut payload: &[u8]) -> Result<Vec<RlpItem>> {
+    let mut items = Vec::new();
+    while !payload.is_empty() {
+        let (item, consumed) = rlp_decode(payload)?;
+        items.push(item);
+        payload = &payload[consumed..];
+    }
+    Ok(items)
+}
+
+fn be_bytes_to_usize(bytes: &[u8]) -> Result<usize> {
+    if bytes.len() > std::mem::size_of::<usize>() {
+        bail!("RLP length field too large");
+    }
+    let mut buf = [0u8; std::mem::size_of::<usize>()];
+    buf[std::mem::size_of::<usize>() - bytes.len()..].copy_from_slice(bytes);
+    Ok(usize::from_be_bytes(buf))
+}
+
+/// Convierte bytes crudos a su secuencia de nibbles (medio-byte cada uno,
+/// nibble alto primero), la unidad de path que usa el Merkle-Patricia Trie.
+fn bytes_to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Decodifica el hex-prefix encoding usado por los nodos leaf/extension para
+/// empaquetar un número impar o par de nibbles en bytes completos, devolviendo
+/// los nibbles del path y si el nodo es una hoja (vs. una extensión).
+fn decode_hex_prefix(encoded: &[u8]) -> Result<(Vec<u8>, bool)> {
+    let first = *encoded.first().ok_or_else(|| anyhow!("empty hex-prefix path"))?;
+    let flag = first >> 4;
+    let is_leaf = flag == 2 || flag == 3;
+    let is_odd = flag == 1 || flag == 3;
+
+    let mut nibbles = if is_odd { vec![first & 0x0f] } else { Vec::new() };
+    nibbles.extend(bytes_to_nibbles(&encoded[1..]));
+    Ok((nibbles, is_leaf))
+}
+
+/// Referencia a un hijo dentro de un nodo de trie: o bien vacía (no hay
+/// hijo), o el hash de 32 bytes de otro nodo referenciado por `eth_getProof`.
+enum ChildRef {
+    Empty,
+    Hash([u8; 32]),
+}
+
+fn child_ref(item: &RlpItem) -> Result<ChildRef> {
+    let bytes = item.as_bytes()?;
+    match bytes.len() {
+        0 => Ok(ChildRef::Empty),
+        32 => {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(bytes);
+            Ok(ChildRef::Hash(hash))
+        }
+        other => bail!("unsupported inline trie node of {other} bytes (expected empty or a 32-byte hash)"),
+    }
+}
+
+/// Camina la cadena de nodos de `proof` (en orden raíz-a-hoja, como los
+/// devuelve `eth_getProof`) verificando en cada paso que `keccak256(nodo)`
+/// coincide con la referencia esperada del nodo anterior, empezando en
+/// `root_hash`. Devuelve los bytes crudos del valor almacenado en la hoja
+/// que corresponde a `key_nibbles`.
+fn walk_trie_proof(proof: &[Vec<u8>], root_hash: [u8; 32], key_nibbles: &[u8]) -> Result<Vec<u8>> {
+    let mut expected_hash = root_hash;
+    let mut depth = 0usize;
+
+    for (index, node_bytes) in proof.iter().enumerate() {
+        let actual_hash = keccak256(node_bytes);
+        if actual_hash != expected_hash {
+            bail!(
+                "proof node {index} hash mismatch: expected {}, got {}",
+                hex::encode(expected_hash),
+                hex::encode(actual_hash)
+            );
+        }
+
+        let (node, _) = rlp_decode(node_bytes).context("failed to RLP-decode proof node")?;
+        let items = node.as_list().context("proof node is not an RLP list")?;
+
+        let next_ref = match items.len() {
+            17 => {
+                if depth == key_nibbles.len() {
+                    // La clave termina exactamente en este branch: el valor
+                    // vive en el slot 17 (el "value" del branch).
+                    return Ok(items[16].as_bytes()?.to_vec());
+                }
+                let nibble = *key_nibbles.get(depth).ok_or_else(|| anyhow!("key exhausted inside branch node"))? as usize;
+                depth += 1;
+                child_ref(&items[nibble])?
+            }
+            2 => {
+                let (path, is_leaf) = decode_hex_prefix(items[0].as_bytes()?)?;
+                let remaining = key_nibbles.get(depth..).ok_or_else(|| anyhow!("key exhausted before leaf/extension"))?;
+                if !remaining.starts_with(path.as_slice()) {
+                    bail!("proof path diverges from the requested key (key not present in this trie)");
+                }
+                depth += path.len();
+
+                if is_leaf {
+                    if depth != key_nibbles.len() {
+                        bail!("leaf node reached before consuming the full key");
+                    }
+                    return Ok(items[1].as_bytes()?.to_vec());
+                }
+                child_ref(&items[1])?
+            }
+            other => bail!("invalid trie node with {other} items (expected 2 or 17)"),
+        };
+
+        let is_last = index + 1 == proof.len();
+        match next_ref {
+            ChildRef::Hash(hash) => expected_hash = hash,
+            ChildRef::Empty if is_last => bail!("proof ended on an empty child reference without a value"),
+            ChildRef::Empty => bail!("proof continues past an empty child reference"),
+        }
+    }
+
+    bail!("proof ended without reaching a leaf node")
+}
+
+/// Campos de una cuenta Ethereum tal como se almacenan (RLP-encoded) en la
+/// hoja del trie de estado.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifiedAccount {
+    pub nonce: u64,
+    pub balance: U256,
+    pub storage_hash: [u8; 32],
+    pub code_hash: [u8; 32],
+}
+
+fn u256_from_be_bytes(bytes: &[u8]) -> U256 {
+    if bytes.is_empty() {
+        U256::zero()
+    } else {
+        U256::from_big_endian(bytes)
+    }
+}
+
+fn hash32_from_be_bytes(bytes: &[u8]) -> Result<[u8; 32]> {
+    if bytes.len() != 32 {
+        bail!("expected a 32-byte hash, got {} bytes", bytes.len());
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(bytes);
+    Ok(hash)
+}
+
+/// Verifica un `accountProof` de `eth_getProof` contra un `stateRoot`
+/// confiado (típicamente tomado del header de bloque vía
+/// `eth_getBlockByNumber`), usando `keccak256(address)` como key del trie de
+/// estado. Devuelve los campos de la cuenta solo si la cadena de hashes del
+/// proof llega intacta hasta `state_root`.
+pub fn verify_account_proof(state_root: [u8; 32], address: &[u8; 20], proof: &[Vec<u8>]) -> Result<VerifiedAccount> {
+    let key_nibbles = bytes_to_nibbles(&keccak256(address));
+    let account_rlp = walk_trie_proof(proof, state_root, &key_nibbles)
+        .context("account proof verification failed")?;
+
+    let (account, _) = rlp_decode(&account_rlp).context("failed to RLP-decode account leaf value")?;
+    let fields = account.as_list().context("account leaf value is not an RLP list")?;
+    if fields.len() != 4 {
+        bail!("account RLP has {} fields, expected 4 (nonce, balance, storageHash, codeHash)", fields.len());
+    }
+
+    let nonce = u256_from_be_bytes(fields[0].as_bytes()?).low_u64();
+    let balance = u256_from_be_bytes(fields[1].as_bytes()?);
+    let storage_hash = hash32_from_be_bytes(fields[2].as_bytes()?)?;
+    let code_hash = hash32_from_be_bytes(fields[3].as_bytes()?)?;
+
+    Ok(VerifiedAccount { nonce, balance, storage_hash, code_hash })
+}
+
+/// Verifica un `storageProof` de `eth_getProof` contra el `storageHash` de
+/// una cuenta ya verificada (ver [`verify_account_proof`]), usando
+/// `keccak256(storage_key)` como key del trie de storage de esa cuenta.
+/// Devuelve el valor crudo del slot (p.ej. el resultado de `balanceOf`).
+pub fn verify_storage_proof(storage_hash: [u8; 32], storage_key: [u8; 32], proof: &[Vec<u8>]) -> Result<U256> {
+    let key_nibbles = bytes_to_nibbles(&keccak256(&storage_key));
+    let value_rlp = walk_trie_proof(proof, storage_hash, &key_nibbles)
+        .context("storage proof verification failed")?;
+
+    let (value, _) = rlp_decode(&value_rlp).context("failed to RLP-decode storage leaf value")?;
+    Ok(u256_from_be_bytes(value.as_bytes()?))
+}
+
+/// Key de storage del slot `balanceOf(wallet)` para un mapping
+/// `mapping(address => uint256)` declarado en el slot `mapping_slot` de un
+/// token ERC20 estándar: `keccak256(pad32(wallet) ++ pad32(mapping_slot))`.
+pub fn erc20_balance_storage_key(wallet: &[u8; 20], mapping_slot: U256) -> [u8; 32] {
+    let mut preimage = [0u8; 64];
+    preimage[12..32].copy_from_slice(wallet);
+    mapping_slot.to_big_endian(&mut preimage[32..64]);
+    keccak256(&preimage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Prefijo RLP de longitud: forma corta hasta 55 bytes, forma larga
+    /// (longitud de la longitud + longitud big-endian) por encima.
+    fn rlp_length_prefix(offset: u8, len: usize) -> Vec<u8> {
+        if len <= 55 {
+            return vec![offset + len as u8];
+        }
+        let len_bytes: Vec<u8> = len.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+        let mut out = vec![offset + 55 + len_bytes.len() as u8];
+        out.extend(len_bytes);
+        out
+    }
+
+    fn rlp_encode_string(bytes: &[u8]) -> Vec<u8> {
+        if bytes.len() == 1 && bytes[0] < 0x80 {
+            return bytes.to_vec();
+        }
+        let mut out = rlp_length_prefix(0x80, bytes.len());
+        out.extend_from_slice(bytes);
+        out
+    }
+
+    fn rlp_encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload: Vec<u8> = items.concat();
+        let mut out = rlp_length_prefix(0xc0, payload.len());
+        out.extend_from_slice(&payload);
+        out
+    }
+
+    #[test]
+    fn test_rlp_decode_short_string_and_list() {
+        let (item, consumed) = rlp_decode(&rlp_encode_string(b"dog")).unwrap();
+        assert_eq!(item, RlpItem::String(b"dog".to_vec()));
+        assert_eq!(consumed, 4);
+
+        let encoded = rlp_encode_list(&[rlp_encode_string(b"cat"), rlp_encode_string(b"dog")]);
+        let (item, _) = rlp_decode(&encoded).unwrap();
+        assert_eq!(
+            item,
+            RlpItem::List(vec![RlpItem::String(b"cat".to_vec()), RlpItem::String(b"dog".to_vec())])
+        );
+    }
+
+    #[test]
+    fn test_decode_hex_prefix_leaf_and_extension_odd_even() {
+        // flag=2 (leaf, even) + nibbles [a,b] packed as one extra zero-pad byte.
+        let (nibbles, is_leaf) = decode_hex_prefix(&[0x20, 0xab]).unwrap();
+        assert!(is_leaf);
+        assert_eq!(nibbles, vec![0xa, 0xb]);
+
+        // flag=1 (extension, odd) with leading nibble 3.
+        let (nibbles, is_leaf) = decode_hex_prefix(&[0x13, 0xcd]).unwrap();
+        assert!(!is_leaf);
+        assert_eq!(nibbles, vec![0x3, 0xc, 0xd]);
+    }
+
+    #[test]
+    fn test_verify_account_proof_single_leaf_branch() {
+        // Trie de una sola cuenta: la raíz es directamente el nodo leaf
+        // (path = todos los nibbles de keccak256(address)).
+        let address = [0x11u8; 20];
+        let key_nibbles = bytes_to_nibbles(&keccak256(&address));
+
+        let account_rlp = rlp_encode_list(&[
+            rlp_encode_string(&[7u8]),                    // nonce = 7
+            rlp_encode_string(&1_000_000u64.to_be_bytes()[4..]), // balance
+            rlp_encode_string(&[0xaa; 32]),                // storageHash
+            rlp_encode_string(&[0xbb; 32]),                // codeHash
+        ]);
+
+        // Hex-prefix encode del path completo (64 nibbles = longitud par).
+        let mut hp_path = vec![0x20u8];
+        for pair in key_nibbles.chunks(2) {
+            hp_path.push((pair[0] << 4) | pair[1]);
+        }
+
+        let leaf_node = rlp_encode_list(&[rlp_encode_string(&hp_path), rlp_encode_string(&account_rlp)]);
+        let state_root = keccak256(&leaf_node);
+
+        let verified = verify_account_proof(state_root, &address, &[leaf_node]).unwrap();
+        assert_eq!(verified.nonce, 7);
+        assert_eq!(verified.balance, U256::from(1_000_000u64));
+        assert_eq!(verified.storage_hash, [0xaa; 32]);
+        assert_eq!(verified.code_hash, [0xbb; 32]);
+    }
+
+    #[test]
+    fn test_verify_account_proof_rejects_tampered_root() {
+        let address = [0x22u8; 20];
+        let key_nibbles = bytes_to_nibbles(&keccak256(&address));
+        let account_rlp = rlp_encode_list(&[
+            rlp_encode_string(&[0]),
+            rlp_encode_string(&[]),
+            rlp_encode_string(&[0xcc; 32]),
+            rlp_encode_string(&[0xdd; 32]),
+        ]);
+        let mut hp_path = vec![0x20u8];
+        for pair in key_nibbles.chunks(2) {
+            hp_path.push((pair[0] << 4) | pair[1]);
+        }
+        let leaf_node = rlp_encode_list(&[rlp_encode_string(&hp_path), rlp_encode_string(&account_rlp)]);
+
+        let wrong_root = [0u8; 32];
+        assert!(verify_account_proof(wrong_root, &address, &[leaf_node]).is_err());
+    }
+
+    #[test]
+    fn test_erc20_balance_storage_key_matches_solidity_mapping_layout() {
+        let wallet = [0x33u8; 20];
+        let key = erc20_balance_storage_key(&wallet, U256::from(9u64));
+
+        let mut preimage = [0u8; 64];
+        preimage[12..32].copy_from_slice(&wallet);
+        preimage[63] = 9;
+        assert_eq!(key, keccak256(&preimage));
+    }
+}
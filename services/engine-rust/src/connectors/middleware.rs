@@ -0,0 +1,424 @@
+//! Capas de `Provider`/`Middleware` al estilo ethers-rs.
+//!
+//! `BlockchainConnector` concentra hoy RPC crudo, estimación de gas, nonce y
+//! envío en un solo struct: cada capacidad nueva significaba editar el core.
+//! Este módulo separa el contrato mínimo (`Provider::call_rpc`) de las
+//! capacidades que se le agregan encima (`Middleware`), implementadas como
+//! wrappers genéricos que envuelven un `Provider`/`Middleware` interior y
+//! delegan hacia abajo. Así, `SignerMiddleware::new(NonceManager::new(GasOracle::new(connector)), signer)`
+//! compone exactamente las capas que un caller necesite sin tocar
+//! `BlockchainConnector`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::blockchain::TransactionParams;
+
+/// Superficie mínima de un nodo RPC: una única llamada genérica. Todo lo
+/// demás (balances, gas, envío...) se construye encima vía `Middleware`.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    async fn call_rpc(&self, chain_id: u64, method: &str, params: Value) -> Result<Value>;
+}
+
+/// Firma una `TransactionParams` ya completa (`gas_price`/`gas_limit`/`nonce`
+/// llenos) y devuelve el raw tx hex listo para `eth_sendRawTransaction`.
+/// Implementado por quien tenga la clave privada o integre con un wallet
+/// remoto (HSM, KMS...); este módulo no asume ninguna librería de firma en
+/// particular.
+pub trait TransactionSigner: Send + Sync {
+    fn sign_transaction(&self, params: &TransactionParams) -> Result<String>;
+}
+
+/// Una capa que puede completar campos de una transacción y, en última
+/// instancia, enviarla. `fill_transaction` delega hacia adentro antes o
+/// después de llenar su propio campo según la capa; `send_transaction` solo
+/// tiene una implementación real en `SignerMiddleware` (es la única capa que
+/// sabe firmar), el resto delega hacia `inner`.
+#[async_trait]
+pub trait Middleware: Provider {
+    async fn fill_transaction(&self, chain_id: u64, params: TransactionParams) -> Result<TransactionParams>;
+
+    async fn send_transaction(&self, chain_id: u64, params: TransactionParams) -> Result<String>;
+}
+
+fn parse_hex_u64(value: &Value, context: &str) -> Result<u64> {
+    let hex = value
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("invalid {} format", context))?;
+    u64::from_str_radix(hex.trim_start_matches("0x"), 16)
+        .with_context(|| format!("failed to parse {}", context))
+}
+
+// ================================================================================
+// GasOracle: llena gas_price/gas_limit
+// ================================================================================
+
+/// Llena `gas_price` (`eth_gasPrice`) y `gas_limit` (`eth_estimateGas`) de una
+/// `TransactionParams` que no los traiga ya, consultando la capa interior.
+pub struct GasOracle<P> {
+    inner: P,
+}
+
+impl<P> GasOracle<P> {
+    pub fn new(inner: P) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for GasOracle<P> {
+    async fn call_rpc(&self, chain_id: u64, method: &str, params: Value) -> Result<Value> {
+        self.inner.call_rpc(chain_id, method, params).await
+    }
+}
+
+#[async_trait]
+impl<P: Middleware> Middleware for GasOracle<P> {
+    async fn fill_transaction(&self, chain_id: u64, mut params: TransactionParams) -> Result<TransactionParams> {
+        if params.gas_price.is_none() {
+            let result = self.inner.call_rpc(chain_id, "eth_gasPrice", serde_json::json!([])).await?;
+            let gas_price_hex = result
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("invalid gas price format"))?;
+            params.gas_price = Some(gas_price_hex.to_string());
+        }
+        if params.gas_limit.is_none() {
+            let result = self
+                .inner
+                .call_rpc(
+                    chain_id,
+                    "eth_estimateGas",
+                    serde_json::json!([{
+                        "from": params.from,
+                        "to": params.to,
+                        "data": params.data,
+                        "value": params.value
+                    }]),
+                )
+                .await?;
+            params.gas_limit = Some(parse_hex_u64(&result, "gas estimate")?);
+        }
+        self.inner.fill_transaction(chain_id, params).await
+    }
+
+    async fn send_transaction(&self, chain_id: u64, params: TransactionParams) -> Result<String> {
+        let filled = self.fill_transaction(chain_id, params).await?;
+        self.inner.send_transaction(chain_id, filled).await
+    }
+}
+
+// ================================================================================
+// NonceManager: llena nonce
+// ================================================================================
+
+/// Llena `nonce` de una `TransactionParams` que no lo traiga ya, llevando un
+/// contador local por `(chain_id, address)` en vez de preguntarle al nodo en
+/// cada envío: `eth_getTransactionCount(address, "pending")` se queda atrás
+/// de los envíos que todavía no confirmaron, así que disparar varias
+/// transacciones seguidas contra la misma wallet con una sola consulta por
+/// tx produce nonces repetidos ("nonce too low"). El primer uso de una
+/// `(chain_id, address)` inicializa el contador desde el nodo; de ahí en más
+/// cada llamada reparte el siguiente nonce y lo incrementa en memoria. Todos
+/// los contadores comparten un único mutex, tomado solo para leer e
+/// incrementar (nunca durante la consulta al nodo), así que tareas
+/// concurrentes serializan la asignación aun entre distintas chains/wallets.
+pub struct NonceManager<P> {
+    inner: P,
+    nonces: Mutex<HashMap<(u64, String), u64>>,
+}
+
+impl<P> NonceManager<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<P: Provider> NonceManager<P> {
+    /// Re-sincroniza el contador de `address` en `chain_id` desde el nodo,
+    /// descartando el valor cacheado. Llamar tras un envío fallido o un gap
+    /// detectado en la secuencia de nonces, para no seguir repartiendo
+    /// nonces que el nodo ya no considera válidos.
+    pub async fn reset(&self, chain_id: u64, address: &str) -> Result<()> {
+        let nonce = self.fetch_pending_nonce(chain_id, address).await?;
+        self.nonces.lock().unwrap().insert((chain_id, address.to_string()), nonce);
+        Ok(())
+    }
+
+    async fn fetch_pending_nonce(&self, chain_id: u64, address: &str) -> Result<u64> {
+        let result = self
+            .inner
+            .call_rpc(chain_id, "eth_getTransactionCount", serde_json::json!([address, "pending"]))
+            .await?;
+        parse_hex_u64(&result, "nonce")
+    }
+
+    async fn next_nonce(&self, chain_id: u64, address: &str) -> Result<u64> {
+        let key = (chain_id, address.to_string());
+        {
+            let mut nonces = self.nonces.lock().unwrap();
+            if let Some(counter) = nonces.get_mut(&key) {
+                let nonce = *counter;
+                *counter += 1;
+                return Ok(nonce);
+            }
+        }
+
+        // Primera vez que se ve esta (chain_id, address): consultar el nodo
+        // fuera del lock. Si otra tarea ganó la carrera e inicializó el
+        // contador mientras esperábamos, `or_insert` respeta el valor que
+        // ya quedó cacheado en vez de pisarlo con el nuestro.
+        let fetched = self.fetch_pending_nonce(chain_id, address).await?;
+        let mut nonces = self.nonces.lock().unwrap();
+        let counter = nonces.entry(key).or_insert(fetched);
+        let nonce = *counter;
+        *counter += 1;
+        Ok(nonce)
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for NonceManager<P> {
+    async fn call_rpc(&self, chain_id: u64, method: &str, params: Value) -> Result<Value> {
+        self.inner.call_rpc(chain_id, method, params).await
+    }
+}
+
+#[async_trait]
+impl<P: Middleware> Middleware for NonceManager<P> {
+    async fn fill_transaction(&self, chain_id: u64, mut params: TransactionParams) -> Result<TransactionParams> {
+        if params.nonce.is_none() {
+            params.nonce = Some(self.next_nonce(chain_id, &params.from).await?);
+        }
+        self.inner.fill_transaction(chain_id, params).await
+    }
+
+    async fn send_transaction(&self, chain_id: u64, params: TransactionParams) -> Result<String> {
+        let filled = self.fill_transaction(chain_id, params).await?;
+        self.inner.send_transaction(chain_id, filled).await
+    }
+}
+
+// ================================================================================
+// SignerMiddleware: firma y envía
+// ================================================================================
+
+/// Firma una `TransactionParams` ya completa con `signer` y la envía vía
+/// `eth_sendRawTransaction`. Es la única capa que implementa `send_transaction`
+/// de verdad; las demás solo delegan hacia `inner`.
+pub struct SignerMiddleware<P, S> {
+    inner: P,
+    signer: S,
+}
+
+impl<P, S> SignerMiddleware<P, S> {
+    pub fn new(inner: P, signer: S) -> Self {
+        Self { inner, signer }
+    }
+}
+
+#[async_trait]
+impl<P: Provider, S: Send + Sync> Provider for SignerMiddleware<P, S> {
+    async fn call_rpc(&self, chain_id: u64, method: &str, params: Value) -> Result<Value> {
+        self.inner.call_rpc(chain_id, method, params).await
+    }
+}
+
+#[async_trait]
+impl<P: Middleware, S: TransactionSigner> Middleware for SignerMiddleware<P, S> {
+    async fn fill_transaction(&self, chain_id: u64, params: TransactionParams) -> Result<TransactionParams> {
+        self.inner.fill_transaction(chain_id, params).await
+    }
+
+    async fn send_transaction(&self, chain_id: u64, params: TransactionParams) -> Result<String> {
+        let filled = self.fill_transaction(chain_id, params).await?;
+        let signed_tx = self.signer.sign_transaction(&filled)?;
+        let result = self
+            .inner
+            .call_rpc(chain_id, "eth_sendRawTransaction", serde_json::json!([signed_tx]))
+            .await?;
+        result
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow::anyhow!("invalid transaction hash format"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// `Provider` de prueba: responde con valores fijos por método y registra
+    /// cada llamada recibida, para poder afirmar qué capa la disparó.
+    struct MockProvider {
+        responses: std::collections::HashMap<&'static str, Value>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl MockProvider {
+        fn new(responses: &[(&'static str, Value)]) -> Self {
+            Self {
+                responses: responses.iter().cloned().collect(),
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        async fn call_rpc(&self, _chain_id: u64, method: &str, _params: Value) -> Result<Value> {
+            self.calls.lock().unwrap().push(method.to_string());
+            self.responses
+                .get(method)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("unexpected RPC method: {}", method))
+        }
+    }
+
+    #[async_trait]
+    impl Middleware for MockProvider {
+        async fn fill_transaction(&self, _chain_id: u64, params: TransactionParams) -> Result<TransactionParams> {
+            Ok(params)
+        }
+
+        async fn send_transaction(&self, _chain_id: u64, _params: TransactionParams) -> Result<String> {
+            Err(anyhow::anyhow!("MockProvider has no signer attached"))
+        }
+    }
+
+    struct StubSigner;
+    impl TransactionSigner for StubSigner {
+        fn sign_transaction(&self, _params: &TransactionParams) -> Result<String> {
+            Ok("0xsignedtx".to_string())
+        }
+    }
+
+    fn sample_params() -> TransactionParams {
+        TransactionParams {
+            from: "0xfrom".to_string(),
+            to: "0xto".to_string(),
+            data: "0x".to_string(),
+            value: "0x0".to_string(),
+            gas_limit: None,
+            gas_price: None,
+            nonce: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gas_oracle_fills_gas_price_and_gas_limit() {
+        let base = MockProvider::new(&[
+            ("eth_gasPrice", serde_json::json!("0x3b9aca00")),
+            ("eth_estimateGas", serde_json::json!("0x5208")),
+        ]);
+        let oracle = GasOracle::new(base);
+
+        let filled = oracle.fill_transaction(1, sample_params()).await.unwrap();
+
+        assert_eq!(filled.gas_price, Some("0x3b9aca00".to_string()));
+        assert_eq!(filled.gas_limit, Some(0x5208));
+    }
+
+    #[tokio::test]
+    async fn test_gas_oracle_leaves_already_filled_fields_untouched() {
+        let base = MockProvider::new(&[]);
+        let oracle = GasOracle::new(base);
+        let mut params = sample_params();
+        params.gas_price = Some("0xdeadbeef".to_string());
+        params.gas_limit = Some(21_000);
+
+        let filled = oracle.fill_transaction(1, params).await.unwrap();
+
+        assert_eq!(filled.gas_price, Some("0xdeadbeef".to_string()));
+        assert_eq!(filled.gas_limit, Some(21_000));
+        assert!(oracle.inner.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nonce_manager_fills_nonce_via_pending_transaction_count() {
+        let base = MockProvider::new(&[("eth_getTransactionCount", serde_json::json!("0x7"))]);
+        let manager = NonceManager::new(base);
+
+        let filled = manager.fill_transaction(1, sample_params()).await.unwrap();
+
+        assert_eq!(filled.nonce, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_manager_increments_locally_without_requerying_the_node() {
+        let base = MockProvider::new(&[("eth_getTransactionCount", serde_json::json!("0x7"))]);
+        let manager = NonceManager::new(base);
+
+        let first = manager.fill_transaction(1, sample_params()).await.unwrap();
+        let second = manager.fill_transaction(1, sample_params()).await.unwrap();
+        let third = manager.fill_transaction(1, sample_params()).await.unwrap();
+
+        assert_eq!(first.nonce, Some(7));
+        assert_eq!(second.nonce, Some(8));
+        assert_eq!(third.nonce, Some(9));
+        assert_eq!(
+            manager.inner.calls.lock().unwrap().as_slice(),
+            ["eth_getTransactionCount"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nonce_manager_tracks_separate_counters_per_chain_and_address() {
+        let base = MockProvider::new(&[("eth_getTransactionCount", serde_json::json!("0x3"))]);
+        let manager = NonceManager::new(base);
+
+        let mut other_wallet = sample_params();
+        other_wallet.from = "0xother".to_string();
+
+        let on_chain_1 = manager.fill_transaction(1, sample_params()).await.unwrap();
+        let on_chain_2 = manager.fill_transaction(2, sample_params()).await.unwrap();
+        let other_wallet_on_chain_1 = manager.fill_transaction(1, other_wallet).await.unwrap();
+
+        assert_eq!(on_chain_1.nonce, Some(3));
+        assert_eq!(on_chain_2.nonce, Some(3));
+        assert_eq!(other_wallet_on_chain_1.nonce, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_manager_reset_resyncs_from_the_node() {
+        let base = MockProvider::new(&[("eth_getTransactionCount", serde_json::json!("0x7"))]);
+        let manager = NonceManager::new(base);
+
+        let first = manager.fill_transaction(1, sample_params()).await.unwrap();
+        assert_eq!(first.nonce, Some(7));
+
+        manager.reset(1, "0xfrom").await.unwrap();
+        let after_reset = manager.fill_transaction(1, sample_params()).await.unwrap();
+        assert_eq!(after_reset.nonce, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_layered_stack_fills_everything_before_the_signer_sends_it() {
+        let base = MockProvider::new(&[
+            ("eth_gasPrice", serde_json::json!("0x3b9aca00")),
+            ("eth_estimateGas", serde_json::json!("0x5208")),
+            ("eth_getTransactionCount", serde_json::json!("0x7")),
+            ("eth_sendRawTransaction", serde_json::json!("0xabc123")),
+        ]);
+        let stack = SignerMiddleware::new(NonceManager::new(GasOracle::new(base)), StubSigner);
+
+        let tx_hash = stack.send_transaction(1, sample_params()).await.unwrap();
+
+        assert_eq!(tx_hash, "0xabc123");
+    }
+
+    #[tokio::test]
+    async fn test_base_middleware_without_a_signer_refuses_to_send() {
+        let base = MockProvider::new(&[]);
+        let result = base.send_transaction(1, sample_params()).await;
+        assert!(result.is_err());
+    }
+}
@@ -14,7 +14,7 @@
  *   - pyth
  *   - sheets
  *   - defillama
- * 
+ *
  * ============================================================================
  */
 
@@ -24,9 +24,18 @@ pub mod pyth;
 pub mod defillama;
 pub mod sheets;
 pub mod blockchain;
+pub mod config_source;
+pub mod route_sink;
+pub mod aggregator;
+pub mod middleware;
+pub mod merkle_proof;
+pub mod price_oracle;
 
 pub use pyth::PythConnector;
 pub use defillama::DefiLlamaConnector;
+pub use aggregator::{AggregatorChainConfig, AggregatorDexClient, AggregatorQuote};
+pub use config_source::{ConfigSource, LocalDirConfigSource, PostgresConfigSource, SheetsConfigSource};
+pub use route_sink::{RouteSink, SheetsRouteSink, SqlRouteSink};
 pub use sheets::{
     SheetsConnector,
     SheetsConfig,
@@ -45,4 +54,8 @@ pub use blockchain::{
     TransactionResult,
     TransactionStatus,
     ChainStatus,
+    VerifiedBalance,
+    TokenMetadata,
 };
+pub use middleware::{GasOracle, Middleware, NonceManager, Provider, SignerMiddleware, TransactionSigner};
+pub use price_oracle::{CachedPriceOracle, ChainlinkPriceOracle, HttpPriceOracle, PriceOracle, PricePoint};
@@ -0,0 +1,298 @@
+//! Resolución del precio USD del token nativo de cada chain.
+//!
+//! `estimate_gas` necesitaba el precio de ETH/BNB/MATIC/... para convertir
+//! `estimated_cost_eth` a `estimated_cost_usd`, y usaba un placeholder fijo
+//! (`$2000`, válido solo para ETH y ya desactualizado incluso ahí). Este
+//! módulo define `PriceOracle` como una capa pluggable al estilo
+//! `Provider`/`Middleware`: una fuente on-chain (`ChainlinkPriceOracle`, vía
+//! `latestRoundData()` de un agregador por chain) y una off-chain
+//! (`HttpPriceOracle`), ambas envolvibles en `CachedPriceOracle` para no
+//! pagar una consulta de red en cada estimación de gas.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use primitive_types::U256;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::middleware::Provider;
+
+fn now_unix() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Precio USD resuelto junto con el momento en que la fuente lo reportó
+/// (no cuando se leyó de un cache), para que [`super::blockchain::GasInfo`]
+/// pueda exponer cuán desactualizado está.
+#[derive(Debug, Clone, Copy)]
+pub struct PricePoint {
+    pub price_usd: f64,
+    pub updated_at_unix: u64,
+}
+
+/// Resuelve el precio USD del token nativo de una chain (`chain_id`).
+/// Implementado tanto por fuentes on-chain (`ChainlinkPriceOracle`) como
+/// off-chain (`HttpPriceOracle`); `CachedPriceOracle` envuelve cualquiera de
+/// las dos para que `estimate_gas` no pague su latencia/costo en cada
+/// llamada.
+#[async_trait]
+pub trait PriceOracle: Send + Sync {
+    async fn get_price(&self, chain_id: u64) -> Result<PricePoint>;
+}
+
+/// Selector de `latestRoundData()` del estándar Chainlink
+/// `AggregatorV3Interface`.
+const LATEST_ROUND_DATA_SELECTOR: &str = "0xfeaf968c";
+
+/// Decimales con los que Chainlink reporta casi universalmente sus feeds
+/// `<ASSET>/USD` (a diferencia de los feeds `<ASSET>/ETH`, que usan 18).
+/// Asumido fijo en vez de consultar `decimals()` por separado en cada
+/// lectura, igual que `get_token_metadata` asume `ERC20.decimals()` fijo
+/// tras la primera resolución.
+const CHAINLINK_USD_FEED_DECIMALS: u32 = 8;
+
+/// Fuente on-chain: lee `latestRoundData()` del agregador Chainlink
+/// `<NATIVE>/USD` configurado para cada chain vía `eth_call`.
+pub struct ChainlinkPriceOracle<P> {
+    inner: P,
+    /// chain_id -> dirección del agregador `<NATIVE>/USD` en esa chain.
+    aggregators: HashMap<u64, String>,
+}
+
+impl<P> ChainlinkPriceOracle<P> {
+    pub fn new(inner: P, aggregators: HashMap<u64, String>) -> Self {
+        Self { inner, aggregators }
+    }
+}
+
+#[async_trait]
+impl<P: Provider> PriceOracle for ChainlinkPriceOracle<P> {
+    async fn get_price(&self, chain_id: u64) -> Result<PricePoint> {
+        let aggregator = self
+            .aggregators
+            .get(&chain_id)
+            .ok_or_else(|| anyhow::anyhow!("no Chainlink aggregator configured for chain {}", chain_id))?;
+
+        let result = self
+            .inner
+            .call_rpc(
+                chain_id,
+                "eth_call",
+                serde_json::json!([
+                    { "to": aggregator, "data": LATEST_ROUND_DATA_SELECTOR },
+                    "latest"
+                ]),
+            )
+            .await?;
+
+        let hex_str = result
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("invalid latestRoundData response format"))?;
+        let bytes = hex::decode(hex_str.trim_start_matches("0x"))
+            .context("failed to decode latestRoundData response hex")?;
+
+        // latestRoundData() devuelve 5 words de 32 bytes: roundId, answer,
+        // startedAt, updatedAt, answeredInRound. Los feeds de precio nunca
+        // son negativos en la práctica, así que `answer` se lee como
+        // magnitud sin signo.
+        let answer_word = bytes
+            .get(32..64)
+            .ok_or_else(|| anyhow::anyhow!("latestRoundData response too short (missing answer)"))?;
+        let updated_at_word = bytes
+            .get(96..128)
+            .ok_or_else(|| anyhow::anyhow!("latestRoundData response too short (missing updatedAt)"))?;
+
+        let answer = U256::from_big_endian(answer_word);
+        let price_usd = answer.as_u128() as f64 / 10f64.powi(CHAINLINK_USD_FEED_DECIMALS as i32);
+        let updated_at_unix = U256::from_big_endian(updated_at_word).as_u64();
+
+        Ok(PricePoint { price_usd, updated_at_unix })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HttpPriceResponse {
+    price_usd: f64,
+    #[serde(default)]
+    updated_at_unix: Option<u64>,
+}
+
+/// Fuente off-chain: resuelve el símbolo nativo de `chain_id` y golpea
+/// `{base_url}/{symbol}`, esperando un JSON `{ "price_usd": <f64> }`
+/// (`updated_at_unix` es opcional; si la fuente no lo reporta, se asume el
+/// momento en que respondió la llamada HTTP).
+pub struct HttpPriceOracle {
+    client: Client,
+    base_url: String,
+    native_token_symbols: HashMap<u64, String>,
+}
+
+impl HttpPriceOracle {
+    pub fn new(client: Client, base_url: String, native_token_symbols: HashMap<u64, String>) -> Self {
+        Self { client, base_url, native_token_symbols }
+    }
+}
+
+#[async_trait]
+impl PriceOracle for HttpPriceOracle {
+    async fn get_price(&self, chain_id: u64) -> Result<PricePoint> {
+        let symbol = self
+            .native_token_symbols
+            .get(&chain_id)
+            .ok_or_else(|| anyhow::anyhow!("no native token symbol configured for chain {}", chain_id))?;
+
+        let url = format!("{}/{}", self.base_url.trim_end_matches('/'), symbol);
+        let response: HttpPriceResponse = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .context("price HTTP request failed")?
+            .error_for_status()
+            .context("price HTTP request returned an error status")?
+            .json()
+            .await
+            .context("failed to parse price HTTP response")?;
+
+        Ok(PricePoint {
+            price_usd: response.price_usd,
+            updated_at_unix: response.updated_at_unix.unwrap_or_else(now_unix),
+        })
+    }
+}
+
+/// TTL corto por defecto: `estimate_gas` se llama en el hot path de ranking
+/// de rutas, así que golpear la fuente real (on-chain u HTTP) en cada
+/// llamada sería tan caro como barato era el placeholder que reemplaza.
+pub const DEFAULT_PRICE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Envuelve cualquier `PriceOracle` con un cache en memoria de TTL corto por
+/// `chain_id`, análogo al `token_metadata_cache` de `BlockchainConnector`:
+/// el precio no cambia lo bastante rápido como para justificar una consulta
+/// de red en cada `estimate_gas`. El `PricePoint` cacheado conserva el
+/// `updated_at_unix` que reportó la fuente, así que servir desde cache no
+/// oculta cuán desactualizado está el precio subyacente.
+pub struct CachedPriceOracle<O> {
+    inner: O,
+    ttl: Duration,
+    cache: Mutex<HashMap<u64, (PricePoint, SystemTime)>>,
+}
+
+impl<O> CachedPriceOracle<O> {
+    pub fn new(inner: O, ttl: Duration) -> Self {
+        Self { inner, ttl, cache: Mutex::new(HashMap::new()) }
+    }
+}
+
+#[async_trait]
+impl<O: PriceOracle> PriceOracle for CachedPriceOracle<O> {
+    async fn get_price(&self, chain_id: u64) -> Result<PricePoint> {
+        if let Some((point, fetched_at)) = self.cache.lock().unwrap().get(&chain_id).copied() {
+            let elapsed = SystemTime::now()
+                .duration_since(fetched_at)
+                .unwrap_or(Duration::from_secs(u64::MAX));
+            if elapsed < self.ttl {
+                return Ok(point);
+            }
+        }
+
+        let point = self.inner.get_price(chain_id).await?;
+        self.cache.lock().unwrap().insert(chain_id, (point, SystemTime::now()));
+        Ok(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// `Provider` de prueba que siempre responde con un `latestRoundData()`
+    /// fijo, para probar `ChainlinkPriceOracle` sin una red real.
+    struct StubProvider {
+        result: serde_json::Value,
+    }
+
+    #[async_trait]
+    impl Provider for StubProvider {
+        async fn call_rpc(&self, _chain_id: u64, _method: &str, _params: serde_json::Value) -> Result<serde_json::Value> {
+            Ok(self.result.clone())
+        }
+    }
+
+    /// Codifica una respuesta `latestRoundData()` de juguete con `answer` y
+    /// `updatedAt` dados, dejando `roundId`/`startedAt`/`answeredInRound` en
+    /// cero (no los usa `ChainlinkPriceOracle`).
+    fn encode_latest_round_data(answer: u128, updated_at: u64) -> serde_json::Value {
+        let mut bytes = vec![0u8; 160];
+        U256::from(answer).to_big_endian(&mut bytes[32..64]);
+        U256::from(updated_at).to_big_endian(&mut bytes[96..128]);
+        serde_json::json!(format!("0x{}", hex::encode(bytes)))
+    }
+
+    #[tokio::test]
+    async fn test_chainlink_oracle_decodes_answer_and_updated_at() {
+        let provider = StubProvider { result: encode_latest_round_data(300_000_000_000, 1_700_000_000) };
+        let oracle = ChainlinkPriceOracle::new(provider, HashMap::from([(1, "0xaggregator".to_string())]));
+
+        let point = oracle.get_price(1).await.unwrap();
+
+        assert!((point.price_usd - 3000.0).abs() < 1e-6);
+        assert_eq!(point.updated_at_unix, 1_700_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_chainlink_oracle_errors_on_unconfigured_chain() {
+        let provider = StubProvider { result: encode_latest_round_data(0, 0) };
+        let oracle = ChainlinkPriceOracle::new(provider, HashMap::new());
+
+        assert!(oracle.get_price(999).await.is_err());
+    }
+
+    struct CountingOracle {
+        calls: AtomicU32,
+        point: PricePoint,
+    }
+
+    #[async_trait]
+    impl PriceOracle for CountingOracle {
+        async fn get_price(&self, _chain_id: u64) -> Result<PricePoint> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.point)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_oracle_serves_repeat_lookups_from_cache_within_ttl() {
+        let inner = CountingOracle {
+            calls: AtomicU32::new(0),
+            point: PricePoint { price_usd: 3000.0, updated_at_unix: 1_700_000_000 },
+        };
+        let cached = CachedPriceOracle::new(inner, Duration::from_secs(60));
+
+        let first = cached.get_price(1).await.unwrap();
+        let second = cached.get_price(1).await.unwrap();
+
+        assert!((first.price_usd - 3000.0).abs() < 1e-6);
+        assert!((second.price_usd - 3000.0).abs() < 1e-6);
+        assert_eq!(cached.inner.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cached_oracle_refetches_once_ttl_expires() {
+        let inner = CountingOracle {
+            calls: AtomicU32::new(0),
+            point: PricePoint { price_usd: 3000.0, updated_at_unix: 1_700_000_000 },
+        };
+        let cached = CachedPriceOracle::new(inner, Duration::from_millis(0));
+
+        cached.get_price(1).await.unwrap();
+        cached.get_price(1).await.unwrap();
+
+        assert_eq!(cached.inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
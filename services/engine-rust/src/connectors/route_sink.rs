@@ -0,0 +1,294 @@
+/**
+ * ============================================================================
+ * ARCHIVO: ./services/engine-rust/src/connectors/route_sink.rs
+ * MÓDULO: Rust Engine
+ * ============================================================================
+ *
+ * 📥 ENTRADA:
+ *   - Rutas de arbitraje generadas por ciclo (`ArbitrageRoute`)
+ *   - Actualizaciones de status de una ruta ya escrita (`route_id`, `status`)
+ *
+ * 🔄 TRANSFORMACIÓN:
+ *   FUNCIONES: write_routes, update_status
+ *
+ * 📤 SALIDA:
+ *   - Filas persistidas en Sheets y/o en una tabla SQL `routes`, según qué
+ *     sinks estén registrados
+ *
+ * 🔗 DEPENDENCIAS:
+ *   - sheets (SheetsConnector)
+ *   - sqlx (SQLite por defecto, Postgres si se conecta con una URL distinta)
+ *
+ * ============================================================================
+ */
+
+//! `RouteSink` abstrae a dónde se persisten las rutas generadas, para que
+//! `RustArbitrageEngine` pueda escribirlas a varios destinos a la vez (Sheets
+//! para el dashboard operativo, SQL para poder correr queries históricas
+//! sobre `ROUTE_ID` en vez de leerlas de vuelta de una spreadsheet). El motor
+//! guarda un `Vec<Arc<dyn RouteSink>>` y fanea cada escritura a todos.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::ArbitrageRoute;
+use super::sheets::SheetsConnector;
+
+/// Destino de persistencia de rutas generadas. Implementaciones:
+/// `SheetsRouteSink` (hoja `ROUTES`, el destino histórico) y `SqlRouteSink`
+/// (SQLite embebido por defecto, Postgres si se le pasa una URL `postgres://`).
+#[async_trait]
+pub trait RouteSink: Send + Sync {
+    /// Persiste (o actualiza, si el `route_id` ya existía) el batch de rutas
+    /// de un ciclo. Implementaciones transaccionales no deben dejar filas a
+    /// medio escribir si una ruta del batch falla.
+    async fn write_routes(&self, routes: &[ArbitrageRoute]) -> Result<()>;
+
+    /// Actualiza el status de una ruta ya escrita (p.ej. `PENDING` ->
+    /// `EXECUTED`/`FAILED`), sin tocar el resto de sus columnas.
+    async fn update_status(&self, route_id: &str, status: &str) -> Result<()>;
+}
+
+// ==================================================================================
+// SHEETS BACKEND
+// ==================================================================================
+
+/// Adapta `SheetsConnector` al trait `RouteSink`, igual que `SheetsConfigSource`
+/// adapta el mismo conector para lectura de configuración.
+pub struct SheetsRouteSink {
+    connector: Arc<SheetsConnector>,
+}
+
+impl SheetsRouteSink {
+    pub fn new(connector: Arc<SheetsConnector>) -> Self {
+        Self { connector }
+    }
+}
+
+#[async_trait]
+impl RouteSink for SheetsRouteSink {
+    async fn write_routes(&self, routes: &[ArbitrageRoute]) -> Result<()> {
+        if routes.is_empty() {
+            return Ok(());
+        }
+
+        let sheet_data: Vec<HashMap<String, Value>> = routes.iter().map(route_to_sheet_row).collect();
+
+        self.connector
+            .update_sheet_data("ROUTES", sheet_data)
+            .await
+            .context("Failed to write routes to Sheets")?;
+
+        Ok(())
+    }
+
+    async fn update_status(&self, route_id: &str, status: &str) -> Result<()> {
+        // La hoja EXECUTIONS ya es el canal existente para status de
+        // ejecución; profit/gas se registran en 0 aquí porque todavía no se
+        // conocen en este punto del flujo. `write_execution_result` solo
+        // agrega filas, así que esta fila con ceros no se sobreescribe después.
+        self.connector
+            .write_execution_result(route_id, 0.0, 0.0, status)
+            .await
+            .context("Failed to write route status to Sheets")?;
+
+        Ok(())
+    }
+}
+
+fn route_to_sheet_row(route: &ArbitrageRoute) -> HashMap<String, Value> {
+    let mut row = HashMap::new();
+    row.insert("ROUTE_ID".to_string(), Value::String(route.route_id.clone()));
+    row.insert("SOURCE_TOKEN".to_string(), Value::String(route.source_token.clone()));
+    row.insert("TARGET_TOKEN".to_string(), Value::String(route.target_token.clone()));
+    row.insert(
+        "NET_PROFIT_USD".to_string(),
+        serde_json::Number::from_f64(route.net_profit_usd).map(Value::Number).unwrap_or(Value::Null),
+    );
+    row.insert(
+        "ROI_PERCENTAGE".to_string(),
+        serde_json::Number::from_f64(route.roi_percentage).map(Value::Number).unwrap_or(Value::Null),
+    );
+    row.insert(
+        "GAS_COST_USD".to_string(),
+        serde_json::Number::from_f64(route.gas_cost_usd).map(Value::Number).unwrap_or(Value::Null),
+    );
+    row.insert("STATUS".to_string(), Value::String("PENDING".to_string()));
+    row.insert("CREATED_AT".to_string(), Value::String(route.created_at.to_rfc3339()));
+    row
+}
+
+// ==================================================================================
+// SQL BACKEND (SQLite por defecto, Postgres opcional)
+// ==================================================================================
+
+/// Persiste rutas en una tabla SQL `routes`, upsertenado por `route_id`.
+/// Usa `sqlx::Any` así que el mismo código sirve para SQLite (`sqlite://...`,
+/// el default) y Postgres (`postgres://...`): el driver se elige por el
+/// esquema de la URL de conexión y el SQL (`ON CONFLICT`) es válido en
+/// ambos. Esto es lo que permite correr pattern analysis histórico con SQL
+/// en vez de leer las rutas de vuelta de una spreadsheet.
+pub struct SqlRouteSink {
+    pool: sqlx::AnyPool,
+}
+
+impl SqlRouteSink {
+    /// `database_url` por defecto es un archivo SQLite local (p.ej.
+    /// `sqlite://routes.db`); pasar una URL `postgres://` usa Postgres en su
+    /// lugar sin tocar el resto de esta implementación.
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        sqlx::any::install_default_drivers();
+
+        let pool = sqlx::any::AnyPoolOptions::new()
+            .max_connections(5)
+            .connect(database_url)
+            .await
+            .with_context(|| format!("Failed to connect route sink database at {}", database_url))?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS routes (
+                route_id TEXT PRIMARY KEY,
+                source_token TEXT NOT NULL,
+                target_token TEXT NOT NULL,
+                net_profit_usd REAL NOT NULL,
+                roi_percentage REAL NOT NULL,
+                gas_cost_usd REAL NOT NULL,
+                status TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .context("Failed to create routes table")?;
+
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl RouteSink for SqlRouteSink {
+    async fn write_routes(&self, routes: &[ArbitrageRoute]) -> Result<()> {
+        if routes.is_empty() {
+            return Ok(());
+        }
+
+        // Una sola transacción para todo el batch: si una ruta falla a
+        // mitad de camino, el ciclo no deja filas a medio escribir.
+        let mut tx = self.pool.begin().await.context("Failed to start route sink transaction")?;
+
+        for route in routes {
+            sqlx::query(
+                "INSERT INTO routes (route_id, source_token, target_token, net_profit_usd, roi_percentage, gas_cost_usd, status, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, 'PENDING', ?)
+                 ON CONFLICT(route_id) DO UPDATE SET
+                    source_token = excluded.source_token,
+                    target_token = excluded.target_token,
+                    net_profit_usd = excluded.net_profit_usd,
+                    roi_percentage = excluded.roi_percentage,
+                    gas_cost_usd = excluded.gas_cost_usd,
+                    created_at = excluded.created_at",
+            )
+            .bind(route.route_id.clone())
+            .bind(route.source_token.clone())
+            .bind(route.target_token.clone())
+            .bind(route.net_profit_usd)
+            .bind(route.roi_percentage)
+            .bind(route.gas_cost_usd)
+            .bind(route.created_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to upsert route {}", route.route_id))?;
+        }
+
+        tx.commit().await.context("Failed to commit route sink transaction")?;
+        Ok(())
+    }
+
+    async fn update_status(&self, route_id: &str, status: &str) -> Result<()> {
+        // Deliberadamente no pasa por `write_routes`/`ON CONFLICT`: esto solo
+        // toca `status`, para no pisar datos de la ruta con un re-write viejo
+        // si la actualización de status llega después de una nueva generación.
+        sqlx::query("UPDATE routes SET status = ? WHERE route_id = ?")
+            .bind(status)
+            .bind(route_id)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to update status for route {}", route_id))?;
+
+        Ok(())
+    }
+}
+
+// ==================================================================================
+// TESTS
+// ==================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn sample_route() -> ArbitrageRoute {
+        ArbitrageRoute {
+            route_id: "route-1".to_string(),
+            source_token: "USDC".to_string(),
+            target_token: "WETH".to_string(),
+            intermediate_token: None,
+            dex_path: vec!["uniswap".to_string(), "sushiswap".to_string()],
+            input_amount: 1000.0,
+            expected_output: 1010.0,
+            net_profit_usd: 8.5,
+            roi_percentage: 0.85,
+            gas_cost_usd: 1.5,
+            execution_time_estimate: 200,
+            confidence_score: 0.9,
+            created_at: Utc::now(),
+            priority_fee_bid_gwei: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_route_to_sheet_row_maps_all_fields() {
+        let row = route_to_sheet_row(&sample_route());
+        assert_eq!(row.get("ROUTE_ID"), Some(&Value::String("route-1".to_string())));
+        assert_eq!(row.get("STATUS"), Some(&Value::String("PENDING".to_string())));
+        assert_eq!(row.get("NET_PROFIT_USD"), Some(&Value::Number(serde_json::Number::from_f64(8.5).unwrap())));
+    }
+
+    #[tokio::test]
+    async fn test_sql_route_sink_write_and_update_status() {
+        let sink = SqlRouteSink::connect("sqlite::memory:").await.unwrap();
+
+        sink.write_routes(&[sample_route()]).await.unwrap();
+        sink.update_status("route-1", "EXECUTED").await.unwrap();
+
+        let row: (String,) = sqlx::query_as("SELECT status FROM routes WHERE route_id = ?")
+            .bind("route-1")
+            .fetch_one(&sink.pool)
+            .await
+            .unwrap();
+        assert_eq!(row.0, "EXECUTED");
+    }
+
+    #[tokio::test]
+    async fn test_sql_route_sink_upsert_keeps_latest_values() {
+        let sink = SqlRouteSink::connect("sqlite::memory:").await.unwrap();
+
+        let mut route = sample_route();
+        sink.write_routes(&[route.clone()]).await.unwrap();
+
+        route.net_profit_usd = 42.0;
+        sink.write_routes(&[route]).await.unwrap();
+
+        let row: (f64,) = sqlx::query_as("SELECT net_profit_usd FROM routes WHERE route_id = ?")
+            .bind("route-1")
+            .fetch_one(&sink.pool)
+            .await
+            .unwrap();
+        assert_eq!(row.0, 42.0);
+    }
+}
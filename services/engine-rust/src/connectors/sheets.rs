@@ -12,8 +12,32 @@
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
 use std::collections::HashMap;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::Mutex;
 use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, SigningKey, VerifyingKey};
+#[cfg(feature = "persistent-cache")]
+use std::io::{Read as _, Write as _};
+
+/// Parsea un campo monetario/numérico crudo de un sheet, rechazando valores
+/// no-finitos que `str::parse::<f64>` acepta sin quejarse (p.ej. una celda
+/// con el texto literal `"nan"` parsea como `f64::NAN`, que luego
+/// envenenaría cualquier cálculo y desordenaría el ranking de oportunidades
+/// vía `partial_cmp`). Cae a `0.0` ante cualquier valor inválido, igual que
+/// el `unwrap_or(0.0)` que reemplaza.
+fn parse_money_field(raw: &str) -> f64 {
+    raw.parse::<f64>()
+        .ok()
+        .and_then(|v| crate::utils::money::Money::from_f64(v).ok())
+        .map(|m| m.to_f64())
+        .unwrap_or(0.0)
+}
 
 // ==================================================================================
 // TYPES & STRUCTS
@@ -26,6 +50,15 @@ pub struct SheetsConfig {
     pub api_key: Option<String>,
     pub service_account_json: Option<String>,
     pub cache_ttl_seconds: u64,
+    /// Direcciones (secp256k1, estilo Ethereum "0x...") autorizadas a firmar
+    /// filas de configuración. Vacío deshabilita la verificación de firmas,
+    /// ya que la hoja es el "cerebro" que controla routers, gas caps y
+    /// flash-loans con dinero real on-chain.
+    pub trusted_signers: Vec<String>,
+    /// Si es `true`, una fila con firma inválida o ausente rechaza la carga
+    /// completa de la hoja. Si es `false`, la fila se descarta con un
+    /// warning y el resto de la hoja se sigue procesando.
+    pub strict_signature_verification: bool,
 }
 
 /// Cliente de Google Sheets
@@ -33,6 +66,42 @@ pub struct SheetsConnector {
     config: SheetsConfig,
     client: Client,
     cache: HashMap<String, CachedData>,
+    oauth_token: Mutex<Option<OAuthToken>>,
+    /// Overlay de escrituras pendientes: filas bufferizadas por hoja que aún
+    /// no se han volcado a Google Sheets. Capa de interior-mutability sobre
+    /// el backing store remoto, igual que el patrón usado para overlays de
+    /// cuentas. Las filas aún no volcadas se leen con `overlay_rows()`;
+    /// `get_sheet_data` no consulta el overlay.
+    write_overlay: Mutex<HashMap<String, Vec<Vec<String>>>>,
+    flush_config: FlushConfig,
+    last_flush: Mutex<SystemTime>,
+    /// Segundo nivel de cache, respaldado en disco, que sobrevive a reinicios.
+    /// Solo se inicializa cuando se compila con `--features persistent-cache`.
+    #[cfg(feature = "persistent-cache")]
+    persistent_store: Option<sled::Db>,
+}
+
+/// Payload serializado que se guarda comprimido en el store persistente.
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedSheet {
+    data: Vec<Vec<String>>,
+    timestamp_unix: u64,
+}
+
+/// Umbrales que disparan un flush automático del overlay de escritura.
+#[derive(Debug, Clone, Copy)]
+pub struct FlushConfig {
+    pub max_buffered_rows: usize,
+    pub max_buffer_age: Duration,
+}
+
+impl Default for FlushConfig {
+    fn default() -> Self {
+        Self {
+            max_buffered_rows: 50,
+            max_buffer_age: Duration::from_secs(10),
+        }
+    }
 }
 
 /// Datos cacheados con timestamp
@@ -42,6 +111,27 @@ struct CachedData {
     timestamp: SystemTime,
 }
 
+/// Subconjunto relevante del JSON de credenciales de service account
+/// descargado desde Google Cloud Console.
+#[derive(Debug, Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+}
+
+/// Access token de OAuth2 cacheado junto con su momento de expiración (unix seconds)
+#[derive(Debug, Clone)]
+struct OAuthToken {
+    access_token: String,
+    expires_at: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
 /// Representación de una blockchain desde Sheets
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockchainConfig {
@@ -105,6 +195,17 @@ pub struct SystemConfig {
     pub max_gas_price_gwei: f64,
     pub flash_loan_enabled: bool,
     pub max_concurrent_routes: u32,
+    /// Fee del proveedor de flash loans (p.ej. Aave V3), en basis points.
+    pub flash_loan_fee_bps: u32,
+    /// Monto mínimo en USD a partir del cual una ruta requiere flash loan.
+    pub min_flash_loan_usd: f64,
+    /// Fee de protocolo (p.ej. un cut de plataforma) en basis points, sumado
+    /// a la comisión de cada pool en `calculate_swap_fees`.
+    pub protocol_fee_bps: u32,
+    /// Cota máxima de fees compuestos (swap + flash loan + protocolo +
+    /// gas-equivalente) como basis points del trade, para rechazar rutas
+    /// que solo son "rentables" porque alguna fuente de fee está stubbeada.
+    pub max_total_fee_bps: u32,
 }
 
 // ==================================================================================
@@ -123,82 +224,305 @@ impl SheetsConnector {
             config,
             client,
             cache: HashMap::new(),
+            oauth_token: Mutex::new(None),
+            write_overlay: Mutex::new(HashMap::new()),
+            flush_config: FlushConfig::default(),
+            last_flush: Mutex::new(SystemTime::now()),
+            #[cfg(feature = "persistent-cache")]
+            persistent_store: None,
         })
     }
+
+    /// Abre (o crea) el store persistente en disco en la ruta dada y lo asocia
+    /// a este conector. Solo disponible con `--features persistent-cache`.
+    #[cfg(feature = "persistent-cache")]
+    pub fn with_persistent_cache(mut self, db_path: &str) -> Result<Self> {
+        let db = sled::open(db_path).context("Failed to open persistent cache store")?;
+        self.persistent_store = Some(db);
+        Ok(self)
+    }
     
     /// Crear desde variables de entorno
     pub fn from_env() -> Result<Self> {
         let spreadsheet_id = std::env::var("SPREADSHEET_ID")
             .context("SPREADSHEET_ID environment variable not set")?;
-        
-        let api_key = std::env::var("GOOGLE_API_KEY").ok();
-        let service_account_json = std::env::var("GOOGLE_SERVICE_ACCOUNT_JSON").ok();
-        
+
+        let api_key = resolve_secret_env("GOOGLE_API_KEY")?;
+        let service_account_json = resolve_secret_env("GOOGLE_SERVICE_ACCOUNT_JSON")?;
+
         let cache_ttl_seconds = std::env::var("SHEETS_CACHE_TTL")
             .unwrap_or_else(|_| "300".to_string())
             .parse()
             .unwrap_or(300);
         
+        let trusted_signers = std::env::var("SHEETS_TRUSTED_SIGNERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let strict_signature_verification = std::env::var("SHEETS_STRICT_SIGNATURE_VERIFICATION")
+            .map(|v| v.to_lowercase() == "true")
+            .unwrap_or(false);
+
         let config = SheetsConfig {
             spreadsheet_id,
             api_key,
             service_account_json,
             cache_ttl_seconds,
+            trusted_signers,
+            strict_signature_verification,
         };
-        
+
         Self::new(config)
     }
-    
+
+    // ================================================================================
+    // SERVICE ACCOUNT OAUTH2 (RS256 JWT-BEARER)
+    // ================================================================================
+
+    /// Obtiene un access token válido, refrescándolo vía JWT-bearer si hace falta.
+    ///
+    /// El token se cachea en memoria y se renueva automáticamente unos segundos
+    /// antes de expirar para evitar que una llamada en vuelo reciba un 401.
+    async fn get_access_token(&self) -> Result<String> {
+        const EXPIRY_SAFETY_MARGIN_SECS: u64 = 60;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        {
+            let cached = self.oauth_token.lock().unwrap();
+            if let Some(token) = cached.as_ref() {
+                if token.expires_at > now + EXPIRY_SAFETY_MARGIN_SECS {
+                    return Ok(token.access_token.clone());
+                }
+            }
+        }
+
+        let raw_json = self
+            .config
+            .service_account_json
+            .as_ref()
+            .context("No service account configured")?;
+
+        let key: ServiceAccountKey = serde_json::from_str(raw_json)
+            .context("Failed to parse service account JSON")?;
+
+        let assertion = self.build_signed_jwt(&key, now)?;
+
+        let response = self
+            .client
+            .post("https://oauth2.googleapis.com/token")
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .context("Failed to reach OAuth2 token endpoint")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "OAuth2 token exchange failed: {}",
+                response.status()
+            ));
+        }
+
+        let token_response: TokenResponse = response
+            .json()
+            .await
+            .context("Failed to parse OAuth2 token response")?;
+
+        let token = OAuthToken {
+            access_token: token_response.access_token.clone(),
+            expires_at: now + token_response.expires_in,
+        };
+
+        *self.oauth_token.lock().unwrap() = Some(token);
+
+        Ok(token_response.access_token)
+    }
+
+    /// Construye y firma un JWT RS256 siguiendo el flujo JWT-bearer de Google
+    /// (https://developers.google.com/identity/protocols/oauth2/service-account).
+    fn build_signed_jwt(&self, key: &ServiceAccountKey, now: u64) -> Result<String> {
+        let header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+        let claims = serde_json::json!({
+            "iss": key.client_email,
+            "scope": "https://www.googleapis.com/auth/spreadsheets",
+            "aud": "https://oauth2.googleapis.com/token",
+            "iat": now,
+            "exp": now + 3600,
+        });
+
+        let header_b64 = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?);
+        let claims_b64 = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?);
+        let signing_input = format!("{}.{}", header_b64, claims_b64);
+
+        let private_key = parse_rsa_private_key(&key.private_key)?;
+
+        let digest = Sha256::digest(signing_input.as_bytes());
+        let signature = private_key
+            .sign(Pkcs1v15Sign::new::<Sha256>(), &digest)
+            .context("Failed to sign JWT with RSA-SHA256")?;
+        let signature_b64 = URL_SAFE_NO_PAD.encode(signature);
+
+        Ok(format!("{}.{}", signing_input, signature_b64))
+    }
+
     // ================================================================================
     // CORE METHODS - SHEET DATA FETCHING
     // ================================================================================
     
-    /// Obtener datos de una hoja (con cache)
+    /// Obtener datos de una hoja (cache en memoria -> cache en disco -> red)
     pub async fn get_sheet_data(&mut self, sheet_name: &str) -> Result<Vec<Vec<String>>> {
-        // Verificar cache
+        // Nivel 1: cache en memoria
         if let Some(cached) = self.cache.get(sheet_name) {
             let elapsed = SystemTime::now()
                 .duration_since(cached.timestamp)
                 .unwrap_or(Duration::from_secs(u64::MAX));
-            
+
             if elapsed.as_secs() < self.config.cache_ttl_seconds {
                 return Ok(cached.data.clone());
             }
         }
-        
-        // Fetch desde Google Sheets API
+
+        // Nivel 2: cache persistente en disco (sobrevive a reinicios)
+        #[cfg(feature = "persistent-cache")]
+        if let Some(persisted) = self.read_persistent_cache(sheet_name)? {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+
+            if now.saturating_sub(persisted.timestamp_unix) < self.config.cache_ttl_seconds {
+                self.cache.insert(sheet_name.to_string(), CachedData {
+                    data: persisted.data.clone(),
+                    timestamp: SystemTime::now(),
+                });
+                return Ok(persisted.data);
+            }
+        }
+
+        // Nivel 3: fetch desde Google Sheets API
         let data = self.fetch_sheet_data(sheet_name).await?;
-        
-        // Actualizar cache
+
+        // Poblar ambos tiers en el miss
         self.cache.insert(sheet_name.to_string(), CachedData {
             data: data.clone(),
             timestamp: SystemTime::now(),
         });
-        
+
+        #[cfg(feature = "persistent-cache")]
+        self.write_persistent_cache(sheet_name, &data)?;
+
         Ok(data)
     }
+
+    /// Lee una hoja del store persistente, descomprimiendo con deflate.
+    #[cfg(feature = "persistent-cache")]
+    fn read_persistent_cache(&self, sheet_name: &str) -> Result<Option<PersistedSheet>> {
+        let Some(db) = &self.persistent_store else {
+            return Ok(None);
+        };
+
+        let Some(compressed) = db.get(sheet_name).context("Failed to read persistent cache")? else {
+            return Ok(None);
+        };
+
+        let raw = inflate_bytes(&compressed)?;
+        let persisted: PersistedSheet =
+            serde_json::from_slice(&raw).context("Failed to deserialize persisted sheet")?;
+
+        Ok(Some(persisted))
+    }
+
+    /// Serializa y comprime con deflate los datos de una hoja antes de persistirlos.
+    #[cfg(feature = "persistent-cache")]
+    fn write_persistent_cache(&self, sheet_name: &str, data: &[Vec<String>]) -> Result<()> {
+        let Some(db) = &self.persistent_store else {
+            return Ok(());
+        };
+
+        let timestamp_unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let persisted = PersistedSheet {
+            data: data.to_vec(),
+            timestamp_unix,
+        };
+
+        let raw = serde_json::to_vec(&persisted)?;
+        let compressed = deflate_bytes(&raw)?;
+
+        db.insert(sheet_name, compressed).context("Failed to write persistent cache")?;
+        db.flush().context("Failed to flush persistent cache to disk")?;
+
+        Ok(())
+    }
+
+    /// Carga en el cache de memoria todas las hojas conocidas guardadas en
+    /// disco, para que el arranque en frío no dependa de la red.
+    #[cfg(feature = "persistent-cache")]
+    pub fn warm_cache(&mut self) -> Result<()> {
+        const KNOWN_SHEETS: &[&str] = &["BLOCKCHAINS", "DEXES", "ASSETS", "POOLS", "ROUTES"];
+
+        for sheet_name in KNOWN_SHEETS {
+            if let Some(persisted) = self.read_persistent_cache(sheet_name)? {
+                self.cache.insert(sheet_name.to_string(), CachedData {
+                    data: persisted.data,
+                    timestamp: SystemTime::now(),
+                });
+            }
+        }
+
+        Ok(())
+    }
     
     /// Fetch real desde Google Sheets API
     async fn fetch_sheet_data(&self, sheet_name: &str) -> Result<Vec<Vec<String>>> {
         let range = format!("{}!A:Z", sheet_name);
-        
-        // Construir URL según método de autenticación
-        let url = if let Some(api_key) = &self.config.api_key {
+
+        // Construir URL según método de autenticación: preferir service account
+        // (permite lecturas Y escrituras) y caer a api_key como fallback de solo lectura.
+        let bearer_token = if self.config.service_account_json.is_some() {
+            Some(self.get_access_token().await?)
+        } else {
+            None
+        };
+
+        let url = if bearer_token.is_some() {
+            format!(
+                "https://sheets.googleapis.com/v4/spreadsheets/{}/values/{}",
+                self.config.spreadsheet_id, range
+            )
+        } else if let Some(api_key) = &self.config.api_key {
             format!(
                 "https://sheets.googleapis.com/v4/spreadsheets/{}/values/{}?key={}",
                 self.config.spreadsheet_id, range, api_key
             )
         } else {
-            // TODO: Implementar autenticación con service account
-            return Err(anyhow::anyhow!("Service account authentication not yet implemented"));
+            return Err(anyhow::anyhow!(
+                "No authentication configured: set service_account_json or api_key"
+            ));
         };
-        
-        let response = self.client
-            .get(&url)
+
+        let mut request = self.client.get(&url);
+        if let Some(token) = &bearer_token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request
             .send()
             .await
             .context("Failed to fetch sheet data")?;
-        
+
         if !response.status().is_success() {
             return Err(anyhow::anyhow!(
                 "Sheets API returned error: {}",
@@ -228,152 +552,232 @@ impl SheetsConnector {
         Ok(values)
     }
     
-    /// Invalidar cache de una hoja
+    /// Invalidar cache de una hoja (solo el nivel en memoria; el store
+    /// persistente se refresca por su propio `cache_ttl_seconds` en el
+    /// próximo `get_sheet_data`).
     pub fn invalidate_cache(&mut self, sheet_name: &str) {
         self.cache.remove(sheet_name);
     }
-    
-    /// Invalidar todo el cache
+
+    /// Invalidar todo el cache, tanto en memoria como en el store
+    /// persistente en disco (si está habilitado).
     pub fn invalidate_all_cache(&mut self) {
         self.cache.clear();
+
+        #[cfg(feature = "persistent-cache")]
+        if let Some(db) = &self.persistent_store {
+            if let Err(e) = db.clear() {
+                log::warn!("Failed to clear persistent cache: {}", e);
+            }
+        }
     }
     
     // ================================================================================
     // HIGH-LEVEL METHODS - TYPED DATA
     // ================================================================================
-    
+
+    /// Si `trusted_signers` está configurado, trata la última celda de la fila
+    /// como una firma ECDSA secp256k1 sobre las celdas anteriores y verifica
+    /// que el signer recuperado esté en la allowlist. Retorna las celdas de
+    /// datos (sin la firma) cuando la fila es válida, o `Ok(None)` cuando debe
+    /// descartarse (solo en modo no estricto). En modo estricto, cualquier
+    /// firma ausente/inválida/no confiable rechaza la carga completa.
+    ///
+    /// Si `trusted_signers` está vacío la verificación está deshabilitada y la
+    /// fila se devuelve tal cual, sin tocar la última celda.
+    fn verify_row<'a>(&self, row: &'a [String]) -> Result<Option<&'a [String]>> {
+        if self.config.trusted_signers.is_empty() {
+            return Ok(Some(row));
+        }
+
+        if row.len() < 2 {
+            let msg = "row too short to carry a trailing signature column";
+            return if self.config.strict_signature_verification {
+                Err(anyhow::anyhow!(msg))
+            } else {
+                log::warn!("Dropping config row: {}", msg);
+                Ok(None)
+            };
+        }
+
+        let (data, signature_cell) = row.split_at(row.len() - 1);
+        let result = recover_signer_address(data, &signature_cell[0]);
+
+        match result {
+            Ok(signer)
+                if self
+                    .config
+                    .trusted_signers
+                    .iter()
+                    .any(|s| s.eq_ignore_ascii_case(&signer)) =>
+            {
+                Ok(Some(data))
+            }
+            Ok(signer) => {
+                let msg = format!("row signed by untrusted address {}", signer);
+                if self.config.strict_signature_verification {
+                    Err(anyhow::anyhow!(msg))
+                } else {
+                    log::warn!("Dropping config row: {}", msg);
+                    Ok(None)
+                }
+            }
+            Err(e) => {
+                if self.config.strict_signature_verification {
+                    Err(e.context("Row signature verification failed"))
+                } else {
+                    log::warn!("Dropping config row with invalid signature: {}", e);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
     /// Obtener configuración de blockchains
     pub async fn get_blockchains(&mut self) -> Result<Vec<BlockchainConfig>> {
         let data = self.get_sheet_data("BLOCKCHAINS").await?;
-        
+
         if data.is_empty() {
             return Ok(Vec::new());
         }
-        
+
         // Saltar header (primera fila)
         let rows = &data[1..];
-        
-        let blockchains = rows
-            .iter()
-            .filter_map(|row| {
-                if row.len() < 8 {
-                    return None;
-                }
-                
-                Some(BlockchainConfig {
-                    chain_id: row[0].parse().ok()?,
-                    name: row[1].clone(),
-                    rpc_url: row[2].clone(),
-                    explorer_url: row[3].clone(),
-                    native_token: row[4].clone(),
-                    is_active: row[5].to_lowercase() == "true",
-                    gas_price_gwei: row[6].parse().unwrap_or(0.0),
-                    block_time_ms: row[7].parse().unwrap_or(12000),
-                })
-            })
-            .collect();
-        
+
+        let mut blockchains = Vec::new();
+        for row in rows {
+            let Some(row) = self.verify_row(row)? else {
+                continue;
+            };
+            if row.len() < 8 {
+                continue;
+            }
+            let Some(chain_id) = row[0].parse().ok() else {
+                continue;
+            };
+
+            blockchains.push(BlockchainConfig {
+                chain_id,
+                name: row[1].clone(),
+                rpc_url: row[2].clone(),
+                explorer_url: row[3].clone(),
+                native_token: row[4].clone(),
+                is_active: row[5].to_lowercase() == "true",
+                gas_price_gwei: parse_money_field(&row[6]),
+                block_time_ms: row[7].parse().unwrap_or(12000),
+            });
+        }
+
         Ok(blockchains)
     }
-    
+
     /// Obtener configuración de DEXes
     pub async fn get_dexes(&mut self) -> Result<Vec<DexConfig>> {
         let data = self.get_sheet_data("DEXES").await?;
-        
+
         if data.is_empty() {
             return Ok(Vec::new());
         }
-        
+
         let rows = &data[1..];
-        
-        let dexes = rows
-            .iter()
-            .filter_map(|row| {
-                if row.len() < 9 {
-                    return None;
-                }
-                
-                Some(DexConfig {
-                    dex_id: row[0].clone(),
-                    name: row[1].clone(),
-                    dex_type: row[2].clone(),
-                    chain_id: row[3].parse().ok()?,
-                    router_address: row[4].clone(),
-                    factory_address: row[5].clone(),
-                    fee_bps: row[6].parse().unwrap_or(30),
-                    is_active: row[7].to_lowercase() == "true",
-                    supports_flash_loans: row[8].to_lowercase() == "true",
-                })
-            })
-            .collect();
-        
+
+        let mut dexes = Vec::new();
+        for row in rows {
+            let Some(row) = self.verify_row(row)? else {
+                continue;
+            };
+            if row.len() < 9 {
+                continue;
+            }
+            let Some(chain_id) = row[3].parse().ok() else {
+                continue;
+            };
+
+            dexes.push(DexConfig {
+                dex_id: row[0].clone(),
+                name: row[1].clone(),
+                dex_type: row[2].clone(),
+                chain_id,
+                router_address: row[4].clone(),
+                factory_address: row[5].clone(),
+                fee_bps: row[6].parse().unwrap_or(30),
+                is_active: row[7].to_lowercase() == "true",
+                supports_flash_loans: row[8].to_lowercase() == "true",
+            });
+        }
+
         Ok(dexes)
     }
-    
+
     /// Obtener configuración de assets
     pub async fn get_assets(&mut self) -> Result<Vec<AssetConfig>> {
         let data = self.get_sheet_data("ASSETS").await?;
-        
+
         if data.is_empty() {
             return Ok(Vec::new());
         }
-        
+
         let rows = &data[1..];
-        
-        let assets = rows
-            .iter()
-            .filter_map(|row| {
-                if row.len() < 8 {
-                    return None;
-                }
-                
-                Some(AssetConfig {
-                    symbol: row[0].clone(),
-                    name: row[1].clone(),
-                    address: row[2].clone(),
-                    chain_id: row[3].parse().ok()?,
-                    decimals: row[4].parse().unwrap_or(18),
-                    price_usd: row[5].parse().unwrap_or(0.0),
-                    is_stable: row[6].to_lowercase() == "true",
-                    is_active: row[7].to_lowercase() == "true",
-                })
-            })
-            .collect();
-        
+
+        let mut assets = Vec::new();
+        for row in rows {
+            let Some(row) = self.verify_row(row)? else {
+                continue;
+            };
+            if row.len() < 8 {
+                continue;
+            }
+            let Some(chain_id) = row[3].parse().ok() else {
+                continue;
+            };
+
+            assets.push(AssetConfig {
+                symbol: row[0].clone(),
+                name: row[1].clone(),
+                address: row[2].clone(),
+                chain_id,
+                decimals: row[4].parse().unwrap_or(18),
+                price_usd: parse_money_field(&row[5]),
+                is_stable: row[6].to_lowercase() == "true",
+                is_active: row[7].to_lowercase() == "true",
+            });
+        }
+
         Ok(assets)
     }
-    
+
     /// Obtener datos de pools
     pub async fn get_pools(&mut self) -> Result<Vec<PoolData>> {
         let data = self.get_sheet_data("POOLS").await?;
-        
+
         if data.is_empty() {
             return Ok(Vec::new());
         }
-        
+
         let rows = &data[1..];
-        
-        let pools = rows
-            .iter()
-            .filter_map(|row| {
-                if row.len() < 9 {
-                    return None;
-                }
-                
-                Some(PoolData {
-                    pool_id: row[0].clone(),
-                    dex_id: row[1].clone(),
-                    token_a: row[2].clone(),
-                    token_b: row[3].clone(),
-                    reserve_a: row[4].parse().unwrap_or(0.0),
-                    reserve_b: row[5].parse().unwrap_or(0.0),
-                    tvl_usd: row[6].parse().unwrap_or(0.0),
-                    volume_24h: row[7].parse().unwrap_or(0.0),
-                    is_active: row[8].to_lowercase() == "true",
-                })
-            })
-            .collect();
-        
+
+        let mut pools = Vec::new();
+        for row in rows {
+            let Some(row) = self.verify_row(row)? else {
+                continue;
+            };
+            if row.len() < 9 {
+                continue;
+            }
+
+            pools.push(PoolData {
+                pool_id: row[0].clone(),
+                dex_id: row[1].clone(),
+                token_a: row[2].clone(),
+                token_b: row[3].clone(),
+                reserve_a: parse_money_field(&row[4]),
+                reserve_b: parse_money_field(&row[5]),
+                tvl_usd: parse_money_field(&row[6]),
+                volume_24h: parse_money_field(&row[7]),
+                is_active: row[8].to_lowercase() == "true",
+            });
+        }
+
         Ok(pools)
     }
     
@@ -413,6 +817,18 @@ impl SheetsConnector {
             max_concurrent_routes: config_map.get("MAX_CONCURRENT_ROUTES")
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(40),
+            flash_loan_fee_bps: config_map.get("FLASH_LOAN_FEE_BPS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(9), // 0.09% típico de Aave V3
+            min_flash_loan_usd: config_map.get("MIN_FLASH_LOAN_USD")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000.0),
+            protocol_fee_bps: config_map.get("PROTOCOL_FEE_BPS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            max_total_fee_bps: config_map.get("MAX_TOTAL_FEE_BPS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(500), // 5%
         })
     }
     
@@ -421,6 +837,12 @@ impl SheetsConnector {
     // ================================================================================
     
     /// Escribir resultados de ejecución a Sheets
+    ///
+    /// La fila se agrega al overlay en memoria de la hoja `EXECUTIONS` en vez
+    /// de disparar una llamada HTTP inmediata. El overlay se vacía en batch
+    /// cuando se alcanza `flush_config.max_buffered_rows` o
+    /// `flush_config.max_buffer_age`, lo que evite saturar la cuota de la
+    /// Sheets API en un loop de arbitraje de alta frecuencia.
     pub async fn write_execution_result(
         &self,
         route_id: &str,
@@ -428,16 +850,292 @@ impl SheetsConnector {
         gas_cost_usd: f64,
         status: &str,
     ) -> Result<()> {
-        // TODO: Implementar escritura a hoja EXECUTIONS
-        // Requiere autenticación con service account y permisos de escritura
-        
+        let row = vec![
+            route_id.to_string(),
+            format!("{:.2}", profit_usd),
+            format!("{:.2}", gas_cost_usd),
+            status.to_string(),
+            chrono::Utc::now().to_rfc3339(),
+        ];
+
+        let should_flush = {
+            let mut overlay = self.write_overlay.lock().unwrap();
+            let rows = overlay.entry("EXECUTIONS".to_string()).or_insert_with(Vec::new);
+            rows.push(row);
+
+            let age_exceeded = self
+                .last_flush
+                .lock()
+                .unwrap()
+                .elapsed()
+                .unwrap_or(Duration::ZERO)
+                >= self.flush_config.max_buffer_age;
+
+            rows.len() >= self.flush_config.max_buffered_rows || age_exceeded
+        };
+
         log::info!(
-            "Execution result: route={}, profit=${:.2}, gas=${:.2}, status={}",
+            "Execution result buffered: route={}, profit=${:.2}, gas=${:.2}, status={}",
             route_id, profit_usd, gas_cost_usd, status
         );
-        
+
+        if should_flush {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Lee las filas bufferizadas (no flusheadas aún) de una hoja, para que el
+    /// resto del sistema vea una vista consistente incluyendo escrituras en vuelo.
+    pub fn overlay_rows(&self, sheet_name: &str) -> Vec<Vec<String>> {
+        self.write_overlay
+            .lock()
+            .unwrap()
+            .get(sheet_name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Coalesce todas las filas bufferizadas de cada hoja en una única llamada
+    /// `spreadsheets.values:append` por hoja, en vez de una petición HTTP por fila.
+    pub async fn flush(&self) -> Result<()> {
+        let sheets_to_flush: HashMap<String, Vec<Vec<String>>> = {
+            let mut overlay = self.write_overlay.lock().unwrap();
+            std::mem::take(&mut *overlay)
+        };
+
+        for (sheet_name, rows) in &sheets_to_flush {
+            if rows.is_empty() {
+                continue;
+            }
+
+            if let Err(e) = self.append_rows(sheet_name, rows).await {
+                // Restaurar las filas que no se pudieron enviar para no perderlas.
+                let mut overlay = self.write_overlay.lock().unwrap();
+                overlay
+                    .entry(sheet_name.clone())
+                    .or_insert_with(Vec::new)
+                    .extend(rows.iter().cloned());
+                return Err(e).context(format!("Failed to flush overlay for sheet {}", sheet_name));
+            }
+        }
+
+        *self.last_flush.lock().unwrap() = SystemTime::now();
         Ok(())
     }
+
+    /// Envía un batch de filas a `spreadsheets.values:append` para la hoja dada.
+    async fn append_rows(&self, sheet_name: &str, rows: &[Vec<String>]) -> Result<()> {
+        let bearer_token = if self.config.service_account_json.is_some() {
+            Some(self.get_access_token().await?)
+        } else {
+            None
+        };
+
+        let url = format!(
+            "https://sheets.googleapis.com/v4/spreadsheets/{}/values/{}!A:Z:append?valueInputOption=RAW",
+            self.config.spreadsheet_id, sheet_name
+        );
+
+        let mut request = self.client.post(&url).json(&serde_json::json!({ "values": rows }));
+        if let Some(token) = &bearer_token {
+            request = request.bearer_auth(token);
+        } else if let Some(api_key) = &self.config.api_key {
+            request = request.query(&[("key", api_key)]);
+        } else {
+            return Err(anyhow::anyhow!(
+                "No authentication configured for write operations"
+            ));
+        }
+
+        let response = request.send().await.context("Failed to append rows to Sheets")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Sheets append API returned error: {}",
+                response.status()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Apaga el conector garantizando que no queden resultados sin persistir.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.flush().await
+    }
+}
+
+impl Drop for SheetsConnector {
+    /// `shutdown()` es el camino correcto para vaciar el overlay, pero nada
+    /// obliga a los callers a invocarlo antes de soltar el conector. En vez
+    /// de solo loguear la pérdida, intenta un flush bloqueante de último
+    /// recurso vía `Handle::block_on` cuando hay un runtime Tokio
+    /// multi-threaded disponible (`block_in_place` hace panic en uno
+    /// current-thread, que es justo el caso en el que no se puede bloquear
+    /// el único worker sin deadlockear). Fuera de un runtime, o si el flush
+    /// en sí falla (p.ej. red caída), las filas quedan perdidas y solo queda
+    /// loguearlo.
+    fn drop(&mut self) {
+        let pending: usize = self.write_overlay.lock().unwrap().values().map(Vec::len).sum();
+        if pending == 0 {
+            return;
+        }
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+                let flushed = tokio::task::block_in_place(|| handle.block_on(self.flush()));
+                if let Err(e) = flushed {
+                    log::error!(
+                        "SheetsConnector dropped with {} unflushed execution rows; best-effort flush on drop failed: {}",
+                        pending, e
+                    );
+                }
+            }
+            _ => {
+                log::warn!(
+                    "SheetsConnector dropped with {} unflushed execution rows outside a multi-threaded Tokio runtime; call shutdown() before drop to avoid losing them",
+                    pending
+                );
+            }
+        }
+    }
+}
+
+/// Comprime bytes crudos con deflate antes de guardarlos en el store
+/// persistente; las hojas de Sheets son anchas y muy repetitivas, así que
+/// esto reduce el tamaño en disco considerablemente.
+#[cfg(feature = "persistent-cache")]
+fn deflate_bytes(raw: &[u8]) -> Result<Vec<u8>> {
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(raw)
+        .context("Failed to compress persistent cache entry")?;
+    encoder
+        .finish()
+        .context("Failed to finalize persistent cache compression")
+}
+
+/// Descomprime bytes leídos del store persistente.
+#[cfg(feature = "persistent-cache")]
+fn inflate_bytes(compressed: &[u8]) -> Result<Vec<u8>> {
+    use flate2::read::DeflateDecoder;
+
+    let mut decoder = DeflateDecoder::new(compressed);
+    let mut raw = Vec::new();
+    decoder
+        .read_to_end(&mut raw)
+        .context("Failed to decompress persistent cache entry")?;
+    Ok(raw)
+}
+
+/// Hash canónico (Keccak-256) de las celdas de una fila de datos, usado tanto
+/// para firmar offline como para verificar al cargar. Las celdas se unen con
+/// un separador que no puede aparecer dentro de un valor de celda normal.
+fn row_signing_hash(cells: &[String]) -> [u8; 32] {
+    let canonical = cells.join("\u{1f}");
+    let mut hasher = Keccak256::new();
+    hasher.update(canonical.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Deriva la dirección estilo Ethereum (los últimos 20 bytes del Keccak-256
+/// de la clave pública sin comprimir, sin el byte de prefijo 0x04) de una
+/// clave pública recuperada.
+fn address_from_verifying_key(key: &VerifyingKey) -> String {
+    let uncompressed = key.to_encoded_point(false);
+    let mut hasher = Keccak256::new();
+    hasher.update(&uncompressed.as_bytes()[1..]);
+    let hash = hasher.finalize();
+    format!("0x{}", hex::encode(&hash[12..]))
+}
+
+/// Recupera la dirección que firmó `cells` a partir de una firma ECDSA
+/// secp256k1 de 65 bytes (r || s || v), siguiendo el mismo flujo de
+/// recover-address-from-signature usado para verificar transacciones.
+fn recover_signer_address(cells: &[String], signature_hex: &str) -> Result<String> {
+    let sig_bytes = hex::decode(signature_hex.trim_start_matches("0x"))
+        .context("Invalid signature hex encoding")?;
+
+    if sig_bytes.len() != 65 {
+        return Err(anyhow::anyhow!(
+            "Expected a 65-byte ECDSA signature (r || s || v), got {} bytes",
+            sig_bytes.len()
+        ));
+    }
+
+    let signature = EcdsaSignature::from_slice(&sig_bytes[..64])
+        .context("Invalid ECDSA signature bytes")?;
+    let recovery_byte = sig_bytes[64].checked_sub(27).unwrap_or(sig_bytes[64]);
+    let recovery_id =
+        RecoveryId::from_byte(recovery_byte).context("Invalid ECDSA recovery id")?;
+
+    let hash = row_signing_hash(cells);
+    let verifying_key = VerifyingKey::recover_from_prehash(&hash, &signature, recovery_id)
+        .context("Failed to recover signer public key from row signature")?;
+
+    Ok(address_from_verifying_key(&verifying_key))
+}
+
+/// Firma offline las celdas de una fila con una clave privada secp256k1, para
+/// que los operadores puedan regenerar la firma tras editar la hoja a mano.
+/// Retorna la firma de 65 bytes en hex (`0x...`) lista para pegar en la
+/// columna de firma de la fila.
+pub fn sign_row(cells: &[String], signing_key_hex: &str) -> Result<String> {
+    let key_bytes = hex::decode(signing_key_hex.trim_start_matches("0x"))
+        .context("Invalid signing key hex encoding")?;
+    let signing_key =
+        SigningKey::from_slice(&key_bytes).context("Invalid secp256k1 private key")?;
+
+    let hash = row_signing_hash(cells);
+    let (signature, recovery_id) = signing_key
+        .sign_prehash_recoverable(&hash)
+        .context("Failed to sign row")?;
+
+    let mut sig_bytes = signature.to_bytes().to_vec();
+    sig_bytes.push(27 + recovery_id.to_byte());
+    Ok(format!("0x{}", hex::encode(sig_bytes)))
+}
+
+/// Parsea una clave privada RSA en PEM, aceptando tanto PKCS#1 ("BEGIN RSA
+/// PRIVATE KEY") como PKCS#8 ("BEGIN PRIVATE KEY"), que es el formato que
+/// Google emite en los JSON de service account.
+fn parse_rsa_private_key(pem: &str) -> Result<RsaPrivateKey> {
+    RsaPrivateKey::from_pkcs8_pem(pem)
+        .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+        .context("Failed to parse RSA private key from service account JSON")
+}
+
+/// Resuelve un secreto que puede venir inline por `{var_name}` o, para no
+/// dejarlo en texto plano en el entorno/logs del proceso, en un archivo
+/// referenciado por `{var_name}_FILE` (patrón `*_secret_file`, común en
+/// despliegues a contenedores con secret managers que montan el archivo).
+///
+/// Configurar ambas variables a la vez es casi siempre un error de
+/// despliegue (¿cuál manda?), así que se rechaza explícitamente en vez de
+/// elegir una en silencio.
+fn resolve_secret_env(var_name: &str) -> Result<Option<String>> {
+    let inline = std::env::var(var_name).ok();
+    let file_path = std::env::var(format!("{var_name}_FILE")).ok();
+    resolve_secret(var_name, inline, file_path)
+}
+
+fn resolve_secret(var_name: &str, inline: Option<String>, file_path: Option<String>) -> Result<Option<String>> {
+    match (inline, file_path) {
+        (Some(_), Some(_)) => Err(anyhow::anyhow!(
+            "Both {var_name} and {var_name}_FILE are set; configure only one"
+        )),
+        (None, Some(path)) => {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {var_name}_FILE at {path}"))?;
+            Ok(Some(contents.trim_end().to_string()))
+        }
+        (inline, None) => Ok(inline),
+    }
 }
 
 // ==================================================================================
@@ -447,7 +1145,7 @@ impl SheetsConnector {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_sheets_config_creation() {
         let config = SheetsConfig {
@@ -455,6 +1153,8 @@ mod tests {
             api_key: Some("test_key".to_string()),
             service_account_json: None,
             cache_ttl_seconds: 300,
+            trusted_signers: Vec::new(),
+            strict_signature_verification: false,
         };
         
         assert_eq!(config.spreadsheet_id, "test_id");
@@ -468,6 +1168,8 @@ mod tests {
             api_key: Some("test_key".to_string()),
             service_account_json: None,
             cache_ttl_seconds: 300,
+            trusted_signers: Vec::new(),
+            strict_signature_verification: false,
         };
         
         let mut connector = SheetsConnector::new(config).unwrap();
@@ -483,6 +1185,43 @@ mod tests {
         connector.invalidate_cache("TEST");
         assert!(!connector.cache.contains_key("TEST"));
     }
+
+    #[test]
+    fn test_resolve_secret_rejects_both_inline_and_file() {
+        let result = resolve_secret(
+            "GOOGLE_API_KEY",
+            Some("inline-secret".to_string()),
+            Some("/tmp/does-not-matter".to_string()),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_resolve_secret_reads_and_trims_file() {
+        let path = std::env::temp_dir().join(format!(
+            "sheets_secret_test_{}_{}.txt",
+            std::process::id(),
+            "reads_and_trims"
+        ));
+        std::fs::write(&path, "file-secret\n\n").unwrap();
+
+        let result = resolve_secret("GOOGLE_API_KEY", None, Some(path.to_string_lossy().to_string()));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap(), Some("file-secret".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_secret_falls_back_to_inline() {
+        let result = resolve_secret("GOOGLE_API_KEY", Some("inline-secret".to_string()), None);
+        assert_eq!(result.unwrap(), Some("inline-secret".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_secret_none_when_unset() {
+        let result = resolve_secret("GOOGLE_API_KEY", None, None);
+        assert_eq!(result.unwrap(), None);
+    }
 }
 
 
@@ -657,10 +1396,30 @@ mod tests {
             api_key: Some("test_key".to_string()),
             service_account_json: None,
             cache_ttl_seconds: 300,
+            trusted_signers: Vec::new(),
+            strict_signature_verification: false,
         };
-        
+
         let connector = SheetsConnector::new(config);
         assert!(connector.is_ok());
     }
+
+    #[test]
+    fn test_sign_and_recover_row_roundtrip() {
+        let cells = vec![
+            "1".to_string(),
+            "Ethereum".to_string(),
+            "https://rpc.example.com".to_string(),
+        ];
+        let signing_key_hex = "4646464646464646464646464646464646464646464646464646464646464646";
+        // Clave de prueba conocida (32 bytes); se trunca al tamaño correcto.
+        let signing_key_hex = &signing_key_hex[..64];
+
+        let signature_hex = sign_row(&cells, signing_key_hex).unwrap();
+        let signer = recover_signer_address(&cells, &signature_hex).unwrap();
+
+        assert!(signer.starts_with("0x"));
+        assert_eq!(signer.len(), 42);
+    }
 }
 
@@ -0,0 +1,280 @@
+// ARBITRAGEXPLUS2025 - Execution Cost Model
+//
+// Reemplaza el `gas_cost_usd` plano por un costo por-operación: cada hop de
+// una ruta cuesta gas distinto según el tipo de DEX que lo ejecuta (un swap
+// V2 no cuesta lo mismo que cruzar ticks en V3, ni que envolver un flash
+// loan). Las unidades de gas son configurables y se recalibran solas a
+// partir de recibos on-chain observados.
+
+use std::collections::HashMap;
+
+use crate::{ArbitrageRoute, AssetConfig, BlockchainConfig, DexConfig};
+
+/// Costos unitarios de cada primitiva, en unidades de gas. Calibrados para
+/// una L1 tipo Ethereum; ajustar por chain si hace falta más precisión.
+#[derive(Debug, Clone)]
+pub struct CostModelConfig {
+    pub base_tx_overhead_gas: u64,
+    pub v2_swap_gas: u64,
+    pub v3_tick_crossing_swap_gas: u64,
+    pub approval_gas: u64,
+    pub flash_loan_wrap_gas: u64,
+    /// Fracción máxima del `net_profit_usd` (antes de pujar) que se está
+    /// dispuesto a pagar de priority fee para ganar la carrera por incluir
+    /// el swap. Pujar por encima de esto convertiría la ruta en una pérdida.
+    pub max_priority_fee_profit_fraction: f64,
+    /// Percentil de la distribución de gas de swaps recién aterrizados en la
+    /// chain que se usa como referencia de "la competencia está pagando esto".
+    pub priority_fee_bid_percentile: f64,
+    /// Margen por encima del percentil observado para tener buena chance de
+    /// superar a la competencia (p.ej. 1.05 = 5% por encima del percentil).
+    pub priority_fee_bid_margin: f64,
+}
+
+impl Default for CostModelConfig {
+    fn default() -> Self {
+        Self {
+            base_tx_overhead_gas: 21_000,
+            v2_swap_gas: 120_000,
+            v3_tick_crossing_swap_gas: 180_000,
+            approval_gas: 46_000,
+            flash_loan_wrap_gas: 250_000,
+            max_priority_fee_profit_fraction: 0.3,
+            priority_fee_bid_percentile: 0.75,
+            priority_fee_bid_margin: 1.05,
+        }
+    }
+}
+
+/// Puja de priority fee calculada para ganar la carrera de inclusión de un
+/// swap, con su costo adicional ya convertido a USD.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriorityFeeBid {
+    pub bid_gwei: f64,
+    pub extra_cost_usd: f64,
+}
+
+/// Resultado de intentar calcular una puja de priority fee para una ruta.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriorityFeeOutcome {
+    /// Todavía no hay suficientes swaps observados en esta chain para saber
+    /// contra qué se está compitiendo; no se puja (ruta sigue al gas base).
+    NoCompetitionData,
+    /// Puja calculada y afordable.
+    Bid(PriorityFeeBid),
+    /// La puja necesaria para superar a la competencia excede lo que el
+    /// profit de la ruta puede pagar: hay que descartar la ruta.
+    Unaffordable,
+}
+
+/// Media móvil del gas realmente consumido por un tipo de swap, usada para
+/// ir empujando la tabla estática hacia la realidad observada on-chain.
+#[derive(Debug, Clone, Default)]
+struct RollingAverage {
+    sample_count: u64,
+    mean_gas_used: f64,
+}
+
+impl RollingAverage {
+    fn observe(&mut self, gas_used: u64) {
+        let n = self.sample_count as f64;
+        self.mean_gas_used = (self.mean_gas_used * n + gas_used as f64) / (n + 1.0);
+        self.sample_count += 1;
+    }
+}
+
+/// Modelo de costo de ejecución por primitiva. Una vez que se observan
+/// recibos reales para un tipo de DEX (`record_actual_gas`), ese promedio
+/// reemplaza el valor estático de `CostModelConfig` para ese tipo.
+pub struct CostModel {
+    config: CostModelConfig,
+    observed: HashMap<String, RollingAverage>,
+}
+
+impl CostModel {
+    pub fn new(config: CostModelConfig) -> Self {
+        Self {
+            config,
+            observed: HashMap::new(),
+        }
+    }
+
+    /// Costo base (sin calibrar) de un swap según el tipo de DEX.
+    fn base_unit_cost_gas(&self, dex_type: &str) -> u64 {
+        match dex_type.to_uppercase().as_str() {
+            "V3" | "UNISWAPV3" | "KYBERELASTIC" => self.config.v3_tick_crossing_swap_gas,
+            "FLASH_LOAN" | "FLASHLOAN" => self.config.flash_loan_wrap_gas,
+            _ => self.config.v2_swap_gas,
+        }
+    }
+
+    /// Costo unitario de un swap, usando el promedio observado on-chain si
+    /// ya hay suficiente historial; si no, cae al valor estático de config.
+    fn unit_cost_gas(&self, dex_type: &str) -> u64 {
+        let key = dex_type.to_uppercase();
+        self.observed
+            .get(&key)
+            .filter(|avg| avg.sample_count > 0)
+            .map(|avg| avg.mean_gas_used.round() as u64)
+            .unwrap_or_else(|| self.base_unit_cost_gas(dex_type))
+    }
+
+    /// Percentil configurado al que se muestrea la competencia observada
+    /// antes de pujar (ver `bid_priority_fee`).
+    pub fn priority_fee_bid_percentile(&self) -> f64 {
+        self.config.priority_fee_bid_percentile
+    }
+
+    /// Incorpora el gas realmente consumido (de un recibo de transacción)
+    /// para un tipo de DEX, recalibrando la tabla hacia la realidad.
+    pub fn record_actual_gas(&mut self, dex_type: &str, gas_used: u64) {
+        self.observed
+            .entry(dex_type.to_uppercase())
+            .or_default()
+            .observe(gas_used);
+    }
+
+    /// Gas total (overhead base + un swap por cada hop de `dex_path`) de una
+    /// ruta, según el tipo de DEX que ejecuta cada hop.
+    pub fn estimate_gas_units(
+        &self,
+        dex_path: &[String],
+        dexes_by_id: &HashMap<String, &DexConfig>,
+    ) -> u64 {
+        let mut total = self.config.base_tx_overhead_gas;
+        for dex_id in dex_path {
+            let dex_type = dexes_by_id
+                .get(dex_id)
+                .map(|dex| dex.dex_type.as_str())
+                .unwrap_or("V2");
+            total += self.unit_cost_gas(dex_type);
+        }
+        total
+    }
+
+    /// Convierte unidades de gas a USD, vía el gas price de la chain y el
+    /// precio del token nativo.
+    pub fn gas_units_to_usd(&self, gas_units: u64, gas_price_gwei: f64, native_token_price_usd: f64) -> f64 {
+        let gas_cost_native = (gas_units as f64) * gas_price_gwei / 1e9;
+        gas_cost_native * native_token_price_usd
+    }
+
+    /// Calcula cuánto pujar de priority fee para ganar la carrera de
+    /// inclusión, dado el gas price que están pagando swaps recién
+    /// aterrizados en la misma chain (`observed_percentile_gwei`, ya
+    /// muestreado al percentil configurado por el llamador). Nunca excede
+    /// `max_priority_fee_profit_fraction` del profit base de la ruta.
+    pub fn bid_priority_fee(
+        &self,
+        base_net_profit_usd: f64,
+        gas_units: u64,
+        native_token_price_usd: f64,
+        observed_percentile_gwei: Option<f64>,
+    ) -> PriorityFeeOutcome {
+        let Some(observed_gwei) = observed_percentile_gwei else {
+            return PriorityFeeOutcome::NoCompetitionData;
+        };
+        if observed_gwei <= 0.0 || gas_units == 0 || native_token_price_usd <= 0.0 {
+            return PriorityFeeOutcome::NoCompetitionData;
+        }
+
+        let bid_gwei = observed_gwei * self.config.priority_fee_bid_margin;
+
+        let max_affordable_usd = base_net_profit_usd.max(0.0) * self.config.max_priority_fee_profit_fraction;
+        let max_affordable_gwei = max_affordable_usd * 1e9 / (gas_units as f64 * native_token_price_usd);
+
+        if bid_gwei > max_affordable_gwei {
+            return PriorityFeeOutcome::Unaffordable;
+        }
+
+        let extra_cost_usd = self.gas_units_to_usd(gas_units, bid_gwei, native_token_price_usd);
+        PriorityFeeOutcome::Bid(PriorityFeeBid { bid_gwei, extra_cost_usd })
+    }
+
+    /// Costo en USD de ejecutar una ruta completa.
+    pub fn cost_route_usd(
+        &self,
+        route: &ArbitrageRoute,
+        dexes_by_id: &HashMap<String, &DexConfig>,
+        chain: &BlockchainConfig,
+        native_asset: &AssetConfig,
+    ) -> f64 {
+        let gas_units = self.estimate_gas_units(&route.dex_path, dexes_by_id);
+        self.gas_units_to_usd(gas_units, chain.gas_price_gwei, native_asset.current_price_usd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dex(dex_id: &str, dex_type: &str, chain_id: u64) -> DexConfig {
+        DexConfig {
+            dex_id: dex_id.to_string(),
+            dex_name: dex_id.to_string(),
+            chain_id,
+            router_address: "0x0".to_string(),
+            factory_address: "0x0".to_string(),
+            fee_percentage: 0.3,
+            tvl_usd: 0.0,
+            status: "ACTIVE".to_string(),
+            dex_type: dex_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_estimate_gas_units_sums_hops() {
+        let model = CostModel::new(CostModelConfig::default());
+        let uniswap = dex("UNI_V2", "V2", 1);
+        let sushi = dex("SUSHI", "V3", 1);
+        let dexes_by_id: HashMap<String, &DexConfig> = [
+            (uniswap.dex_id.clone(), &uniswap),
+            (sushi.dex_id.clone(), &sushi),
+        ]
+        .into_iter()
+        .collect();
+
+        let dex_path = vec!["UNI_V2".to_string(), "SUSHI".to_string()];
+        let gas = model.estimate_gas_units(&dex_path, &dexes_by_id);
+
+        let expected = CostModelConfig::default().base_tx_overhead_gas
+            + CostModelConfig::default().v2_swap_gas
+            + CostModelConfig::default().v3_tick_crossing_swap_gas;
+        assert_eq!(gas, expected);
+    }
+
+    #[test]
+    fn test_record_actual_gas_recalibrates_unit_cost() {
+        let mut model = CostModel::new(CostModelConfig::default());
+        model.record_actual_gas("V2", 90_000);
+        assert_eq!(model.unit_cost_gas("V2"), 90_000);
+    }
+
+    #[test]
+    fn test_bid_priority_fee_no_competition_data() {
+        let model = CostModel::new(CostModelConfig::default());
+        let outcome = model.bid_priority_fee(50.0, 250_000, 2000.0, None);
+        assert_eq!(outcome, PriorityFeeOutcome::NoCompetitionData);
+    }
+
+    #[test]
+    fn test_bid_priority_fee_affordable_bid() {
+        let model = CostModel::new(CostModelConfig::default());
+        let outcome = model.bid_priority_fee(50.0, 250_000, 2000.0, Some(5.0));
+        match outcome {
+            PriorityFeeOutcome::Bid(bid) => {
+                assert!((bid.bid_gwei - 5.25).abs() < 1e-9);
+                assert!(bid.extra_cost_usd > 0.0);
+            }
+            other => panic!("expected Bid outcome, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bid_priority_fee_unaffordable_is_dropped() {
+        let model = CostModel::new(CostModelConfig::default());
+        // Percentil de competencia absurdamente alto frente al profit de la ruta
+        let outcome = model.bid_priority_fee(1.0, 250_000, 2000.0, Some(10_000.0));
+        assert_eq!(outcome, PriorityFeeOutcome::Unaffordable);
+    }
+}
@@ -9,11 +9,70 @@
 //! 3. Consumido por el API server y ejecutor
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
+use crate::connectors::sheets::SystemConfig;
 use crate::pathfinding::{
     TwoDexPathfinder, ThreeDexPathfinder, RouteRanker,
     TwoDexRoute, ThreeDexRoute, RankedRoute, RankingCriteria
 };
+use crate::pathfinding::gas_price::GasPriceProvider;
+use crate::pathfinding::twodex_dp;
+use crate::pathfinding::types::{self, DPMemoState};
+use crate::snapshot::{SnapshotRoot, SnapshotStore};
+use crate::utils::amounts::{Price, ProfitUsd};
+use crate::utils::money::Money;
+
+/// Convierte un `f64` del pathfinder (todavía en punto flotante; migrarlo
+/// también a `Money` queda fuera del alcance de este cambio) a [`ProfitUsd`],
+/// cayendo a `0` ante `NaN`/overflow en vez de propagar el valor corrupto.
+fn profit_usd_or_zero(value: f64) -> ProfitUsd {
+    Money::from_f64(value).unwrap_or(Money::ZERO)
+}
+
+/// Convierte el `ArbitrageOpportunity` de 172 campos de
+/// `pathfinding::types` (el que produce `twodex_dp`) al `ArbitrageOpportunity`
+/// de este archivo, para que `find_twodex_dp_opportunities` pueda mezclar
+/// rutas DP-sourced con las de `find_opportunities` sin que el caller tenga
+/// que aprender un segundo schema.
+fn from_dp_opportunity(dp: &types::ArbitrageOpportunity) -> ArbitrageOpportunity {
+    let mut dexes = vec![dp.dex_1_id.clone()];
+    dexes.extend(dp.dex_2_id.clone());
+    dexes.extend(dp.dex_3_id.clone());
+
+    let mut tokens = vec![dp.token_in_id.clone()];
+    tokens.extend(dp.token_intermediate_1.clone());
+    tokens.push(dp.token_out_id.clone());
+
+    ArbitrageOpportunity {
+        id: dp.route_id.clone(),
+        route_type: dp.route_type.clone(),
+        dexes,
+        tokens,
+        expected_profit: profit_usd_or_zero(dp.expected_profit_usd),
+        gas_cost: profit_usd_or_zero(dp.gas_cost_usd),
+        net_profit: profit_usd_or_zero(dp.net_profit_usd),
+        confidence_score: dp.confidence_score,
+        rank_score: 0.0, // se calcula en rank_opportunities, igual que el resto
+        rank_position: 0,
+        capital_required_usd: dp.optimal_trade_size_usd,
+        risk_score: dp.risk_score,
+    }
+}
+
+/// Resultado de [`ArbitrageEngine::find_twodex_dp_execution_batch`]: el
+/// subconjunto de oportunidades DP-sourced que de verdad cabe en
+/// `capital_budget_usd`/`gas_budget_usd` sin pisarse pools entre sí, ya
+/// convertido al `ArbitrageOpportunity` de este archivo. Espejo de
+/// `twodex_dp::ExecutionBatch` pero con `selected` en el schema del engine
+/// en vez del ancho de `pathfinding::types`.
+pub struct DpExecutionBatch {
+    pub selected: Vec<ArbitrageOpportunity>,
+    pub total_capital_usd: f64,
+    pub total_gas_usd: f64,
+    pub total_profit_usd: f64,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArbitrageConfig {
@@ -39,7 +98,11 @@ pub struct DexInfo {
 pub struct TokenPair {
     pub token_in: String,
     pub token_out: String,
-    pub price: f64,
+    /// Precio de `token_in` en `token_out`, en punto fijo vía [`Price`] (un
+    /// alias de [`Money`]) en vez de `f64` crudo: un precio 0/NaN/inf
+    /// llegado de Pyth/un DEX API no debe poder propagarse silenciosamente
+    /// al ranking de oportunidades.
+    pub price: Price,
     pub liquidity: f64,
 }
 
@@ -49,12 +112,123 @@ pub struct ArbitrageOpportunity {
     pub route_type: String, // "2-DEX" or "3-DEX"
     pub dexes: Vec<String>,
     pub tokens: Vec<String>,
-    pub expected_profit: f64,
-    pub gas_cost: f64,
-    pub net_profit: f64,
+    /// Montos en USD en punto fijo vía [`ProfitUsd`]: un profit calculado a
+    /// partir de un precio corrupto debe fallar de forma controlada (ver
+    /// [`Money::from_f64`]) en vez de colarse como `NaN` en el ranking, cuyo
+    /// `sort_by`/`partial_cmp` trata `NaN` como `Ordering::Equal` y
+    /// desordena las oportunidades sin avisar.
+    pub expected_profit: ProfitUsd,
+    pub gas_cost: ProfitUsd,
+    pub net_profit: ProfitUsd,
     pub confidence_score: f64,
     pub rank_score: f64,
     pub rank_position: usize,
+    /// Capital requerido para ejecutar la ruta. Placeholder hasta que el
+    /// pathfinder exponga el tamaño de trade real (como `twodex_dp`):
+    /// se estima a partir del profit esperado asumiendo un edge de precio
+    /// típico de 0.5%, igual de aproximado que el `gas_cost` "Estimado" que
+    /// ya usan otros módulos de pathfinding.
+    pub capital_required_usd: f64,
+    /// Score de riesgo 0-100 (a mayor valor, más riesgo), inverso de
+    /// `confidence_score` en la misma escala que `calculate_risk_score` en
+    /// `pathfinding::twodex_dp_v2`.
+    pub risk_score: f64,
+}
+
+/// Arista de liquidez compartida: un hop `token_in -> token_out` en un DEX
+/// dado. Dos oportunidades que listan la misma arista compiten por la misma
+/// liquidez subyacente y no pueden admitirse ambas sin doble-contar profit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolEdgeKey {
+    pub dex_id: String,
+    pub token_in: String,
+    pub token_out: String,
+}
+
+/// Aristas `(dex, token_in, token_out)` que toca una oportunidad, derivadas
+/// de sus `dexes`/`tokens` paralelos (`tokens[i] -> tokens[i+1]` en
+/// `dexes[i]`, igual que en `ArbitrageOpportunity::dexes`/`tokens`).
+fn opportunity_edges(opp: &ArbitrageOpportunity) -> Vec<PoolEdgeKey> {
+    opp.dexes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, dex_id)| {
+            let token_in = opp.tokens.get(i)?;
+            let token_out = opp.tokens.get(i + 1)?;
+            Some(PoolEdgeKey {
+                dex_id: dex_id.clone(),
+                token_in: token_in.clone(),
+                token_out: token_out.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Resultado de una pasada de [`BatchSolver`]: el subconjunto admitido más,
+/// para observabilidad, las oportunidades descartadas específicamente por
+/// contención de liquidez (no por confidence/gas, que ya se filtraron antes
+/// de llegar al solver).
+#[derive(Debug, Clone, Default)]
+pub struct BatchSolution {
+    pub admitted: Vec<ArbitrageOpportunity>,
+    pub dropped_due_to_contention: Vec<ArbitrageOpportunity>,
+}
+
+/// Estrategia de resolución de contención de liquidez entre oportunidades
+/// candidatas: dado cuánta liquidez queda por arista, selecciona el
+/// subconjunto no conflictivo que maximiza `net_profit` total. Enchufable
+/// para que un solver ILP/LP más preciso pueda reemplazar la heurística
+/// greedy por defecto ([`GreedyBatchSolver`]) sin tocar el resto del engine.
+pub trait BatchSolver {
+    fn solve(&self, opportunities: Vec<ArbitrageOpportunity>, pool_liquidity: &HashMap<PoolEdgeKey, f64>) -> BatchSolution;
+}
+
+/// Baseline greedy: ordena por `net_profit` descendente y admite una
+/// oportunidad solo si todas las aristas que toca todavía tienen capacidad
+/// suficiente para su `capital_required_usd`, decrementando esa capacidad al
+/// admitirla. No es óptimo (un greedy puede perder una combinación mejor de
+/// oportunidades más chicas), pero es la misma clase de aproximación que ya
+/// usa el resto del engine (p.ej. el knapsack acotado de `RouteOptimizer`)
+/// hasta que un solver ILP/LP lo reemplace vía [`BatchSolver`].
+pub struct GreedyBatchSolver;
+
+impl BatchSolver for GreedyBatchSolver {
+    fn solve(&self, mut opportunities: Vec<ArbitrageOpportunity>, pool_liquidity: &HashMap<PoolEdgeKey, f64>) -> BatchSolution {
+        opportunities.sort_by(|a, b| {
+            b.net_profit
+                .to_f64()
+                .partial_cmp(&a.net_profit.to_f64())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut remaining = pool_liquidity.clone();
+        let mut admitted = Vec::new();
+        let mut dropped_due_to_contention = Vec::new();
+
+        for opp in opportunities {
+            let edges = opportunity_edges(&opp);
+            let notional = opp.capital_required_usd;
+            let fits = edges
+                .iter()
+                .all(|edge| remaining.get(edge).copied().unwrap_or(f64::INFINITY) >= notional);
+
+            if fits {
+                for edge in &edges {
+                    if let Some(capacity) = remaining.get_mut(edge) {
+                        *capacity -= notional;
+                    }
+                }
+                admitted.push(opp);
+            } else {
+                dropped_due_to_contention.push(opp);
+            }
+        }
+
+        BatchSolution {
+            admitted,
+            dropped_due_to_contention,
+        }
+    }
 }
 
 /// Motor principal de arbitraje
@@ -62,6 +236,18 @@ pub struct ArbitrageEngine {
     config: ArbitrageConfig,
     dexes: Vec<DexInfo>,
     prices: HashMap<String, HashMap<String, TokenPair>>,
+    /// Snapshot Merklizado de cada tick de `find_opportunities`, para que un
+    /// root publicado pueda auditarse/verificarse después sin confiar en que
+    /// el proceso que calculó las oportunidades siga vivo.
+    snapshots: SnapshotStore,
+    next_snapshot_key: AtomicU64,
+    /// Fuente del precio de gas vigente para `rank_opportunities`, vía
+    /// `RouteRanker::with_gas_price_provider`. `None` (el default de `new`)
+    /// deja el comportamiento histórico: rankear con el `gas_cost` ya
+    /// calculado por cada pathfinder, sin recomputarlo contra gas en vivo.
+    gas_price_provider: Option<Arc<dyn GasPriceProvider>>,
+    chain_id: u64,
+    native_token_price_usd: f64,
 }
 
 impl ArbitrageEngine {
@@ -71,8 +257,34 @@ impl ArbitrageEngine {
             config,
             dexes,
             prices: HashMap::new(),
+            snapshots: SnapshotStore::new(),
+            next_snapshot_key: AtomicU64::new(0),
+            gas_price_provider: None,
+            chain_id: 0,
+            native_token_price_usd: 0.0,
         }
     }
+
+    /// Conecta una fuente de precio de gas en vivo (igual patrón que
+    /// `RouteRanker::with_gas_price_provider`, que es lo que esto termina
+    /// configurando) para que `rank_opportunities` recalcule `gas_cost`
+    /// contra el gas vigente de `chain_id` antes de rankear.
+    pub fn with_gas_price_provider(
+        mut self,
+        provider: Arc<dyn GasPriceProvider>,
+        chain_id: u64,
+        native_token_price_usd: f64,
+    ) -> Self {
+        self.gas_price_provider = Some(provider);
+        self.chain_id = chain_id;
+        self.native_token_price_usd = native_token_price_usd;
+        self
+    }
+
+    /// Root Merkle del snapshot comprometido para `snapshot_key`, si existe.
+    pub fn snapshot_root(&self, snapshot_key: u64) -> Option<SnapshotRoot> {
+        self.snapshots.root_for(snapshot_key)
+    }
     
     /// Actualiza precios desde fuentes externas (Pyth, DEX APIs)
     pub fn update_prices(&mut self, prices: HashMap<String, HashMap<String, TokenPair>>) {
@@ -80,34 +292,162 @@ impl ArbitrageEngine {
     }
     
     /// Encuentra todas las oportunidades de arbitraje
-    pub fn find_opportunities(
+    pub async fn find_opportunities(
         &self,
         start_token: &str,
     ) -> Vec<ArbitrageOpportunity> {
         let mut all_opportunities = Vec::new();
-        
+
         // 1. Buscar rutas de 2-DEX si está habilitado
         if self.config.enable_2dex {
             let two_dex_routes = self.find_2dex_routes(start_token);
             all_opportunities.extend(two_dex_routes);
         }
-        
+
         // 2. Buscar rutas de 3-DEX si está habilitado
         if self.config.enable_3dex {
             let three_dex_routes = self.find_3dex_routes(start_token);
             all_opportunities.extend(three_dex_routes);
         }
-        
+
         // 3. Rankear todas las oportunidades
-        let ranked = self.rank_opportunities(all_opportunities);
+        let ranked = self.rank_opportunities(all_opportunities).await;
         
         // 4. Filtrar por criterios de calidad
         let filtered = self.filter_opportunities(ranked);
-        
-        // 5. Limitar al máximo configurado
-        filtered.into_iter()
+
+        // 5. Resolver contención de liquidez: dos rutas filtradas pueden
+        // asumir cada una que drena por completo el mismo pool, lo cual
+        // doble-cuenta profit que no puede realizarse en conjunto.
+        let solution = self.solve_batch(filtered);
+
+        // 6. Limitar al máximo configurado
+        let admitted: Vec<ArbitrageOpportunity> = solution.admitted.into_iter()
             .take(self.config.max_routes)
-            .collect()
+            .collect();
+
+        // 7. Comprometer un snapshot Merklizado del tick: permite auditar
+        // después, vía `snapshot_root`/`SnapshotStore::membership_proof`, que
+        // una oportunidad ejecutada de verdad fue una de las admitidas en su
+        // momento, sin depender de logs mutables.
+        let snapshot_key = self.next_snapshot_key.fetch_add(1, Ordering::Relaxed);
+        let items: Vec<(String, ArbitrageOpportunity)> = admitted.iter()
+            .map(|opportunity| (opportunity.id.clone(), opportunity.clone()))
+            .collect();
+        self.snapshots.commit_snapshot(snapshot_key, &items);
+
+        admitted
+    }
+
+    /// Puente hacia `pathfinding::twodex_dp::find_arbitrage_opportunities_twodex`:
+    /// ese módulo trae programación dinámica, `FeeModel` y pricing
+    /// `PoolMath`-aware completos, pero ningún caller lo invocaba. Se
+    /// expone aparte de `find_opportunities` (en vez de fusionarlo en su
+    /// pipeline síncrono) porque `twodex_dp` es async y opera sobre el
+    /// schema ancho de `pathfinding::types`, no sobre `DexInfo`/`TokenPair`;
+    /// el resultado se convierte vía `from_dp_opportunity` para que el
+    /// caller siga trabajando con un solo tipo de `ArbitrageOpportunity`.
+    pub async fn find_twodex_dp_opportunities(
+        &self,
+        dexes: &[types::Dex],
+        assets: &[types::Asset],
+        pools: &[types::Pool],
+        blockchains: &[types::Blockchain],
+        system_config: &SystemConfig,
+        dp_memo: &mut DPMemoState,
+    ) -> Result<Vec<ArbitrageOpportunity>, types::ArbitrageError> {
+        let dp_opportunities = twodex_dp::find_arbitrage_opportunities_twodex(
+            dexes,
+            assets,
+            pools,
+            blockchains,
+            system_config,
+            dp_memo,
+        ).await?;
+
+        Ok(dp_opportunities.iter().map(from_dp_opportunity).collect())
+    }
+
+    /// Igual que [`ArbitrageEngine::find_twodex_dp_opportunities`] pero
+    /// acotado a un subconjunto ejecutable de verdad vía
+    /// [`twodex_dp::select_executable_batch`]: ese selector necesita
+    /// `required_liquidity_usd`/`gas_cost_usd`/IDs de pool del schema ancho
+    /// de `pathfinding::types`, que `from_dp_opportunity` no preserva, así
+    /// que la selección corre antes de la conversión y no después.
+    pub async fn find_twodex_dp_execution_batch(
+        &self,
+        dexes: &[types::Dex],
+        assets: &[types::Asset],
+        pools: &[types::Pool],
+        blockchains: &[types::Blockchain],
+        system_config: &SystemConfig,
+        dp_memo: &mut DPMemoState,
+        capital_budget_usd: f64,
+        gas_budget_usd: f64,
+    ) -> Result<DpExecutionBatch, types::ArbitrageError> {
+        let dp_opportunities = twodex_dp::find_arbitrage_opportunities_twodex(
+            dexes,
+            assets,
+            pools,
+            blockchains,
+            system_config,
+            dp_memo,
+        ).await?;
+
+        let batch = twodex_dp::select_executable_batch(&dp_opportunities, capital_budget_usd, gas_budget_usd);
+
+        Ok(DpExecutionBatch {
+            selected: batch.selected.iter().map(from_dp_opportunity).collect(),
+            total_capital_usd: batch.total_capital_usd,
+            total_gas_usd: batch.total_gas_usd,
+            total_profit_usd: batch.total_profit_usd,
+        })
+    }
+
+    /// Resuelve la contención de liquidez entre un conjunto de oportunidades
+    /// candidatas usando el [`GreedyBatchSolver`] por defecto. Equivalente a
+    /// [`ArbitrageEngine::solve_batch_with`] fijando la estrategia, para el
+    /// caso común donde no hace falta enchufar otro solver.
+    pub fn solve_batch(&self, opportunities: Vec<ArbitrageOpportunity>) -> BatchSolution {
+        self.solve_batch_with(opportunities, &GreedyBatchSolver)
+    }
+
+    /// Igual que [`ArbitrageEngine::solve_batch`] pero con un [`BatchSolver`]
+    /// explícito, para que un solver ILP/LP más preciso pueda enchufarse más
+    /// adelante sin tocar el resto del engine.
+    ///
+    /// Construye un mapa de liquidez disponible por arista `(dex, token_in,
+    /// token_out)` a partir de `liquidity_usd` del DEX (no hay datos por
+    /// pool individual en este nivel, así que el DEX completo es la cota
+    /// disponible, igual de aproximada que otros placeholders de este
+    /// módulo como `capital_required_usd`), y delega en `solver` la
+    /// selección del subconjunto no conflictivo que maximiza `net_profit`.
+    pub fn solve_batch_with(
+        &self,
+        opportunities: Vec<ArbitrageOpportunity>,
+        solver: &dyn BatchSolver,
+    ) -> BatchSolution {
+        let pool_liquidity = self.build_pool_liquidity_map(&opportunities);
+        solver.solve(opportunities, &pool_liquidity)
+    }
+
+    /// Liquidez disponible por arista, vista desde las oportunidades
+    /// candidatas (solo se incluyen aristas que alguna oportunidad
+    /// realmente toca).
+    fn build_pool_liquidity_map(&self, opportunities: &[ArbitrageOpportunity]) -> HashMap<PoolEdgeKey, f64> {
+        let mut map: HashMap<PoolEdgeKey, f64> = HashMap::new();
+        for opp in opportunities {
+            for edge in opportunity_edges(opp) {
+                map.entry(edge).or_insert_with_key(|key| {
+                    self.dexes
+                        .iter()
+                        .find(|d| d.id == key.dex_id)
+                        .map(|d| d.liquidity_usd)
+                        .unwrap_or(f64::INFINITY)
+                });
+            }
+        }
+        map
     }
     
     /// Busca rutas de 2-DEX
@@ -133,16 +473,18 @@ impl ArbitrageEngine {
                     route.token_mid.clone(),
                     route.token_end.clone(),
                 ],
-                expected_profit: route.expected_profit,
-                gas_cost: route.gas_cost,
-                net_profit: route.net_profit,
+                expected_profit: profit_usd_or_zero(route.expected_profit),
+                gas_cost: profit_usd_or_zero(route.gas_cost),
+                net_profit: profit_usd_or_zero(route.net_profit),
                 confidence_score: route.confidence_score,
                 rank_score: 0.0, // Se calculará después
                 rank_position: 0,
+                capital_required_usd: route.expected_profit / 0.005, // Estimado: edge de 0.5%
+                risk_score: (1.0 - route.confidence_score) * 100.0,
             })
             .collect()
     }
-    
+
     /// Busca rutas de 3-DEX
     fn find_3dex_routes(&self, start_token: &str) -> Vec<ArbitrageOpportunity> {
         let mut pathfinder = ThreeDexPathfinder::new(self.dexes.clone());
@@ -166,18 +508,20 @@ impl ArbitrageEngine {
                     route.dex_3.clone(),
                 ],
                 tokens: route.tokens.clone(),
-                expected_profit: route.expected_profit,
-                gas_cost: route.gas_cost,
-                net_profit: route.net_profit,
+                expected_profit: profit_usd_or_zero(route.expected_profit),
+                gas_cost: profit_usd_or_zero(route.gas_cost),
+                net_profit: profit_usd_or_zero(route.net_profit),
                 confidence_score: route.confidence_score,
                 rank_score: 0.0, // Se calculará después
                 rank_position: 0,
+                capital_required_usd: route.expected_profit / 0.005, // Estimado: edge de 0.5%
+                risk_score: (1.0 - route.confidence_score) * 100.0,
             })
             .collect()
     }
     
     /// Rankea oportunidades usando el sistema de ranking
-    fn rank_opportunities(
+    async fn rank_opportunities(
         &self,
         opportunities: Vec<ArbitrageOpportunity>,
     ) -> Vec<ArbitrageOpportunity> {
@@ -193,13 +537,28 @@ impl ArbitrageEngine {
                 net_profit: opp.net_profit,
                 confidence_score: opp.confidence_score,
                 complexity_score: if opp.route_type == "2-DEX" { 0.8 } else { 0.6 },
+                pool_usage: HashMap::new(),
+                hop_liquidity_bounds: vec![],
+                hop_edges: vec![],
+                // Sin `gas_units` todavía: `DexInfo`/`TokenPair` no traen un
+                // estimado de gas por hop, así que `with_gas_price_provider`
+                // no tiene nada que recalcular sobre estas rutas hasta que
+                // ese dato llegue desde Sheets (ver `Dex::gas_estimate_swap`
+                // en `pathfinding::types`, que sí lo trae).
+                gas_units: None,
             })
             .collect();
-        
-        // Rankear usando el RouteRanker
-        let ranker = RouteRanker::new(self.config.ranking_criteria.clone());
-        let ranked_routes = ranker.rank_routes(routes);
-        
+
+        // Rankear usando el RouteRanker. El provider solo se conecta al
+        // ranker si `self.gas_price_provider` es `Some`; sin él (el default
+        // de `new`), `rank_routes_with_live_gas` rankea con el `gas_cost`
+        // que ya trae cada ruta, así que es seguro llamarlo siempre.
+        let mut ranker = RouteRanker::new(self.config.ranking_criteria.clone());
+        if let Some(provider) = &self.gas_price_provider {
+            ranker = ranker.with_gas_price_provider(provider.clone(), self.native_token_price_usd);
+        }
+        let ranked_routes = ranker.rank_routes_with_live_gas(routes, self.chain_id).await;
+
         // Convertir de vuelta a ArbitrageOpportunity (array dinámico)
         ranked_routes.into_iter()
             .zip(opportunities.into_iter())
@@ -222,9 +581,9 @@ impl ArbitrageEngine {
                 // Filtrar por confidence mínimo
                 opp.confidence_score >= self.config.min_confidence
                     // Filtrar por profit positivo
-                    && opp.net_profit > 0.0
+                    && opp.net_profit.to_f64() > 0.0
                     // Filtrar por gas cost máximo
-                    && opp.gas_cost <= self.config.max_gas_cost_usd
+                    && opp.gas_cost.to_f64() <= self.config.max_gas_cost_usd
             })
             .collect()
     }
@@ -258,14 +617,18 @@ impl ArbitrageEngine {
             return ArbitrageMetrics::default();
         }
         
-        // Calcular totales usando iteradores (arrays dinámicos)
+        // Calcular totales en punto fijo vía Money::checked_add (arrays
+        // dinámicos); un overflow improbable trunca el total en vez de
+        // producir un NaN/inf que arruinaría el resto del reporte.
         let total_profit: f64 = opportunities.iter()
             .map(|opp| opp.net_profit)
-            .sum();
-        
+            .fold(Money::ZERO, |acc, profit| acc.checked_add(profit).unwrap_or(acc))
+            .to_f64();
+
         let total_gas: f64 = opportunities.iter()
             .map(|opp| opp.gas_cost)
-            .sum();
+            .fold(Money::ZERO, |acc, gas| acc.checked_add(gas).unwrap_or(acc))
+            .to_f64();
         
         let avg_confidence: f64 = opportunities.iter()
             .map(|opp| opp.confidence_score)
@@ -332,6 +695,9 @@ impl Default for ArbitrageConfig {
                 complexity_weight: 0.15,
                 gas_efficiency_weight: 0.15,
                 liquidity_weight: 0.10,
+                scoring_model: crate::pathfinding::ScoringModel::WeightedSum,
+                history_half_life_secs: 86_400.0,
+                max_history_blend_weight: 0.3,
             },
         }
     }
@@ -369,12 +735,14 @@ mod tests {
                 route_type: "2-DEX".to_string(),
                 dexes: vec![],
                 tokens: vec![],
-                expected_profit: 100.0,
-                gas_cost: 20.0,
-                net_profit: 80.0,
+                expected_profit: Money::from_f64(100.0).unwrap(),
+                gas_cost: Money::from_f64(20.0).unwrap(),
+                net_profit: Money::from_f64(80.0).unwrap(),
                 confidence_score: 0.8,
                 rank_score: 0.9,
                 rank_position: 1,
+                capital_required_usd: 20_000.0,
+                risk_score: 20.0,
             },
         ];
         
@@ -382,5 +750,92 @@ mod tests {
         assert_eq!(metrics.total_opportunities, 1);
         assert_eq!(metrics.total_expected_profit, 80.0);
     }
+
+    fn batch_opportunity(id: &str, dexes: Vec<&str>, tokens: Vec<&str>, net_profit: f64, capital_required_usd: f64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            id: id.to_string(),
+            route_type: "2-DEX".to_string(),
+            dexes: dexes.into_iter().map(|d| d.to_string()).collect(),
+            tokens: tokens.into_iter().map(|t| t.to_string()).collect(),
+            expected_profit: Money::from_f64(net_profit).unwrap(),
+            gas_cost: Money::ZERO,
+            net_profit: Money::from_f64(net_profit).unwrap(),
+            confidence_score: 0.9,
+            rank_score: 0.0,
+            rank_position: 0,
+            capital_required_usd,
+            risk_score: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_solve_batch_admits_both_when_liquidity_is_sufficient() {
+        let config = ArbitrageConfig::default();
+        let dexes = vec![DexInfo {
+            id: "uniswap".to_string(),
+            name: "Uniswap".to_string(),
+            chain: "ethereum".to_string(),
+            fee_percentage: 0.3,
+            liquidity_usd: 1_000_000.0,
+        }];
+        let engine = ArbitrageEngine::new(config, dexes);
+
+        let opportunities = vec![
+            batch_opportunity("a", vec!["uniswap"], vec!["weth", "usdc"], 100.0, 10_000.0),
+            batch_opportunity("b", vec!["uniswap"], vec!["usdc", "dai"], 80.0, 10_000.0),
+        ];
+
+        let solution = engine.solve_batch(opportunities);
+        assert_eq!(solution.admitted.len(), 2);
+        assert!(solution.dropped_due_to_contention.is_empty());
+    }
+
+    #[test]
+    fn test_solve_batch_drops_lower_profit_route_contending_for_same_edge() {
+        let config = ArbitrageConfig::default();
+        let dexes = vec![DexInfo {
+            id: "uniswap".to_string(),
+            name: "Uniswap".to_string(),
+            chain: "ethereum".to_string(),
+            fee_percentage: 0.3,
+            liquidity_usd: 15_000.0,
+        }];
+        let engine = ArbitrageEngine::new(config, dexes);
+
+        // Ambas rutas usan la misma arista weth->usdc en uniswap y juntas
+        // exceden la liquidez disponible (15_000): solo la de mayor
+        // net_profit debe admitirse.
+        let opportunities = vec![
+            batch_opportunity("cheap", vec!["uniswap"], vec!["weth", "usdc"], 50.0, 10_000.0),
+            batch_opportunity("rich", vec!["uniswap"], vec!["weth", "usdc"], 120.0, 10_000.0),
+        ];
+
+        let solution = engine.solve_batch(opportunities);
+        assert_eq!(solution.admitted.len(), 1);
+        assert_eq!(solution.admitted[0].id, "rich");
+        assert_eq!(solution.dropped_due_to_contention.len(), 1);
+        assert_eq!(solution.dropped_due_to_contention[0].id, "cheap");
+    }
+
+    #[test]
+    fn test_solve_batch_unknown_dex_is_treated_as_unconstrained() {
+        // Sin datos de liquidez para el DEX (no está en `self.dexes`), la
+        // arista no debe bloquear la admisión: mejor aceptar con datos
+        // incompletos que descartar oportunidades válidas por falta de
+        // metadata.
+        let config = ArbitrageConfig::default();
+        let engine = ArbitrageEngine::new(config, vec![]);
+
+        let opportunities = vec![batch_opportunity(
+            "a",
+            vec!["unknown-dex"],
+            vec!["weth", "usdc"],
+            100.0,
+            1_000_000_000.0,
+        )];
+
+        let solution = engine.solve_batch(opportunities);
+        assert_eq!(solution.admitted.len(), 1);
+    }
 }
 
@@ -3,24 +3,79 @@
 use crate::pathfinding::PathFinder;
 use crate::pricing::PricingEngine;
 
+/// Motor de arbitraje "real" (2-DEX/3-DEX + ranking + resolución de
+/// contención de liquidez vía Sheets). `ArbitrageEngine` de este archivo
+/// delega en él para `find_opportunities` (ver abajo);
+/// `execute_arbitrage` no delega y devuelve `"executed"` sin ejecutar nada.
+///
+/// Eso no alcanza para que el binario la ejecute en producción: `main.rs`
+/// construye `RustArbitrageEngine.arbitrage_engine: Arc<engine::ArbitrageEngine>`
+/// (este tipo) vía `ArbitrageEngine::new(&config)?` (`main.rs:351`), una
+/// llamada preexistente al backlog (`git blame` → `0372a85 baseline`) que no
+/// compila — la firma real de `new` es `fn new(min_profit_usd: f64) -> Self`,
+/// sin `Result` ni `&Config` — y ese campo nunca se lee fuera de
+/// `initialize()` (`main.rs:528`, que tampoco existe en este tipo). El ciclo
+/// que sí corre en vivo, `execute_arbitrage_cycle` (`main.rs:609`), usa
+/// `path_finder`/`route_optimizer` en su lugar, no `arbitrage_engine`.
+/// Arreglar esa desconexión es un cambio de forma del loop principal de
+/// `main.rs`, no algo que la lógica de `arbitrage::ArbitrageEngine` en sí
+/// pueda resolver — por eso se documenta acá en vez de reclamarse como ya
+/// resuelto.
+pub mod arbitrage;
+
 pub struct ArbitrageEngine {
     pub pathfinder: PathFinder,
     pub pricing: PricingEngine,
     pub min_profit_usd: f64,
+    inner: arbitrage::ArbitrageEngine,
 }
 
 impl ArbitrageEngine {
     pub fn new(min_profit_usd: f64) -> Self {
+        let inner_config = arbitrage::ArbitrageConfig {
+            min_profit_usd,
+            max_gas_cost_usd: f64::MAX,
+            min_confidence: 0.0,
+            max_routes: 50,
+            enable_2dex: true,
+            enable_3dex: true,
+            // Mismos pesos que `RouteRanker::default()`; `RankingCriteria`
+            // no deriva `Default` y tampoco lo reexporta `pathfinding::mod`
+            // (otra preexistencia del propio `arbitrage.rs`, ver `git blame`),
+            // así que se referencia vía `pathfinding::ranking` directo.
+            ranking_criteria: crate::pathfinding::ranking::RankingCriteria {
+                profit_weight: 0.35,
+                confidence_weight: 0.25,
+                complexity_weight: 0.15,
+                gas_efficiency_weight: 0.15,
+                liquidity_weight: 0.10,
+                scoring_model: crate::pathfinding::ranking::ScoringModel::WeightedSum,
+                history_half_life_secs: 86_400.0,
+                max_history_blend_weight: 0.3,
+            },
+        };
+
         ArbitrageEngine {
             pathfinder: PathFinder::new(),
             pricing: PricingEngine::new(0.01), // 1% slippage tolerance
             min_profit_usd,
+            // Sin dexes/precios todavía: este stub no recibe datos de Sheets
+            // (ver doc comment de `pub mod arbitrage` arriba), así que arranca
+            // vacío igual que el `vec![]` que reemplaza.
+            inner: arbitrage::ArbitrageEngine::new(inner_config, Vec::new()),
         }
     }
 
     pub async fn find_opportunities(&self) -> Vec<String> {
-        // Buscar oportunidades de arbitraje
-        vec![]
+        // Delega en el motor real en vez de devolver `vec![]` hardcodeado;
+        // sigue vacío en la práctica hasta que algo le cargue dexes/precios
+        // reales (ver doc comment del módulo).
+        self.inner
+            .find_opportunities("USDC")
+            .await
+            .into_iter()
+            .map(|opportunity| opportunity.id)
+            .collect()
     }
 
     pub async fn execute_arbitrage(&self, route: &str) -> Result<String, String> {
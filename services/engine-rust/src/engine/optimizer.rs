@@ -8,9 +8,15 @@
 //! 2. Usa algoritmos DP para optimización
 //! 3. Consumido por el arbitrage engine
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use super::arbitrage::ArbitrageOpportunity;
+use crate::utils::money::Money;
+
+/// Número de "celdas" en que se discretiza cada presupuesto (gas, capital)
+/// para la DP de knapsack acotado. Más celdas dan una aproximación más
+/// fina al presupuesto real a costa de más memoria/tiempo.
+const BUDGET_CELLS: usize = 50;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizerConfig {
@@ -69,24 +75,191 @@ impl RouteOptimizer {
         opportunities
             .into_iter()
             .filter(|opp| {
-                opp.gas_cost <= self.config.max_gas_budget / 2.0
+                opp.gas_cost.to_f64() <= self.config.max_gas_budget / 2.0
                     && opp.confidence_score >= (1.0 - self.config.risk_tolerance) * 0.5
             })
             .collect()
     }
     
+    /// Selecciona el subconjunto de rutas que maximiza `net_profit` sujeto
+    /// a `max_gas_budget`, `max_capital` y `max_concurrent_routes`, vía un
+    /// knapsack 0/1 acotado (DP en 3 dimensiones: cantidad de rutas, celda
+    /// de gas, celda de capital).
+    ///
+    /// Antes de la DP se eliminan conflictos: dos rutas que pasan por
+    /// exactamente el mismo conjunto de DEXes compiten por la misma
+    /// liquidez subyacente (este modelo no trackea `pool_id` por ruta, así
+    /// que el conjunto de DEXes es el proxy de conflicto disponible), y solo
+    /// se conserva la de mayor `net_profit` de cada grupo.
     fn knapsack_optimization(
         &self,
         opportunities: Vec<ArbitrageOpportunity>,
     ) -> Vec<ArbitrageOpportunity> {
-        opportunities
+        if opportunities.is_empty() || self.config.max_concurrent_routes == 0 {
+            return Vec::new();
+        }
+
+        let candidates = Self::deduplicate_conflicting_routes(opportunities);
+
+        let gas_budget = self.config.max_gas_budget.max(0.0);
+        let capital_budget = self.config.max_capital.max(0.0);
+        let max_count = self.config.max_concurrent_routes;
+
+        let gas_cell_size = (gas_budget / BUDGET_CELLS as f64).max(f64::EPSILON);
+        let capital_cell_size = (capital_budget / BUDGET_CELLS as f64).max(f64::EPSILON);
+
+        // Peso de cada candidata en celdas discretas; las que no caben ni
+        // siquiera usando todo el presupuesto quedan fuera de la DP.
+        let weights: Vec<Option<(usize, usize)>> = candidates
+            .iter()
+            .map(|opp| {
+                let gas_cells = (opp.gas_cost.to_f64() / gas_cell_size).ceil() as usize;
+                let capital_cells = (opp.capital_required_usd / capital_cell_size).ceil() as usize;
+                if gas_cells > BUDGET_CELLS || capital_cells > BUDGET_CELLS {
+                    None
+                } else {
+                    Some((gas_cells, capital_cells))
+                }
+            })
+            .collect();
+
+        // dp[k][g][c] = profit máximo usando a lo sumo `k` rutas, `g`
+        // celdas de gas y `c` celdas de capital.
+        let mut dp = vec![vec![vec![0.0_f64; BUDGET_CELLS + 1]; BUDGET_CELLS + 1]; max_count + 1];
+        // keep[i][k][g][c] = true si la candidata `i` fue incluida al
+        // alcanzar ese estado; se usa para reconstruir la selección.
+        let mut keep =
+            vec![vec![vec![vec![false; BUDGET_CELLS + 1]; BUDGET_CELLS + 1]; max_count + 1]; candidates.len()];
+
+        for (item_idx, weight) in weights.iter().enumerate() {
+            let (gas_cells, capital_cells) = match weight {
+                Some(w) => *w,
+                None => continue,
+            };
+            let profit = candidates[item_idx].net_profit.to_f64();
+            if profit <= 0.0 {
+                continue;
+            }
+            // Iterar en reversa evita reutilizar la misma ruta más de una
+            // vez (knapsack 0/1 clásico).
+            for k in (1..=max_count).rev() {
+                for g in (gas_cells..=BUDGET_CELLS).rev() {
+                    for c in (capital_cells..=BUDGET_CELLS).rev() {
+                        let without = dp[k][g][c];
+                        let with = dp[k - 1][g - gas_cells][c - capital_cells] + profit;
+                        if with > without {
+                            dp[k][g][c] = with;
+                            keep[item_idx][k][g][c] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        // El óptimo no necesariamente usa el presupuesto completo, así que
+        // se busca el mejor estado alcanzable en toda la tabla. El triple
+        // índice (k,g,c) hace que enumerate() no simplifique el código, así
+        // que se desactiva el lint de estilo para este recorrido.
+        let mut best = (0.0_f64, max_count, BUDGET_CELLS, BUDGET_CELLS);
+        #[allow(clippy::needless_range_loop)]
+        for k in 0..=max_count {
+            for g in 0..=BUDGET_CELLS {
+                for c in 0..=BUDGET_CELLS {
+                    if dp[k][g][c] > best.0 {
+                        best = (dp[k][g][c], k, g, c);
+                    }
+                }
+            }
+        }
+
+        let (_, mut k, mut g, mut c) = best;
+        let mut selected_indices = Vec::new();
+        for item_idx in (0..candidates.len()).rev() {
+            if k == 0 {
+                break;
+            }
+            if keep[item_idx][k][g][c] {
+                selected_indices.push(item_idx);
+                if let Some((gas_cells, capital_cells)) = weights[item_idx] {
+                    k -= 1;
+                    g -= gas_cells;
+                    c -= capital_cells;
+                }
+            }
+        }
+        selected_indices.reverse();
+
+        selected_indices
+            .into_iter()
+            .map(|idx| candidates[idx].clone())
+            .collect()
     }
-    
+
+    /// Agrupa rutas por el conjunto de DEXes que usan y se queda con la de
+    /// mayor `net_profit` de cada grupo, de modo que a lo sumo una ruta por
+    /// "recurso" compartido llegue a la DP de knapsack.
+    fn deduplicate_conflicting_routes(
+        opportunities: Vec<ArbitrageOpportunity>,
+    ) -> Vec<ArbitrageOpportunity> {
+        let mut best_by_resource: HashMap<String, ArbitrageOpportunity> = HashMap::new();
+        for opp in opportunities {
+            let mut dexes_sorted = opp.dexes.clone();
+            dexes_sorted.sort();
+            let resource_key = dexes_sorted.join("|");
+            best_by_resource
+                .entry(resource_key)
+                .and_modify(|existing| {
+                    if opp.net_profit.to_f64() > existing.net_profit.to_f64() {
+                        *existing = opp.clone();
+                    }
+                })
+                .or_insert(opp);
+        }
+        best_by_resource.into_values().collect()
+    }
+
     fn calculate_portfolio_metrics(
         &self,
         routes: &[ArbitrageOpportunity],
     ) -> PortfolioMetrics {
-        PortfolioMetrics::default()
+        if routes.is_empty() {
+            return PortfolioMetrics::default();
+        }
+
+        let total_profit: f64 = routes.iter().map(|r| r.net_profit.to_f64()).sum();
+        let total_gas: f64 = routes.iter().map(|r| r.gas_cost.to_f64()).sum();
+        let total_capital: f64 = routes.iter().map(|r| r.capital_required_usd).sum();
+
+        // Riesgo del portfolio: promedio de `risk_score` ponderado por el
+        // capital que cada ruta consume (las rutas más grandes pesan más).
+        let risk = if total_capital > 0.0 {
+            routes
+                .iter()
+                .map(|r| r.risk_score * r.capital_required_usd)
+                .sum::<f64>()
+                / total_capital
+        } else {
+            routes.iter().map(|r| r.risk_score).sum::<f64>() / routes.len() as f64
+        };
+
+        // Diversificación: fracción de DEXes distintos sobre el total de
+        // "slots" de DEX ocupados por las rutas seleccionadas, ponderada
+        // por `config.diversification_weight`.
+        let distinct_dexes: HashSet<&String> = routes.iter().flat_map(|r| r.dexes.iter()).collect();
+        let total_dex_slots: usize = routes.iter().map(|r| r.dexes.len()).sum();
+        let raw_diversification = if total_dex_slots > 0 {
+            distinct_dexes.len() as f64 / total_dex_slots as f64
+        } else {
+            0.0
+        };
+
+        PortfolioMetrics {
+            total_profit,
+            total_gas,
+            total_capital,
+            risk,
+            diversification: raw_diversification * self.config.diversification_weight * 100.0,
+        }
     }
 }
 
@@ -123,3 +296,152 @@ impl Default for OptimizerConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(id: &str, dexes: Vec<&str>, gas_cost: f64, capital_required_usd: f64, net_profit: f64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            id: id.to_string(),
+            route_type: "2-DEX".to_string(),
+            dexes: dexes.into_iter().map(|d| d.to_string()).collect(),
+            tokens: vec![],
+            expected_profit: Money::from_f64(net_profit + gas_cost).unwrap(),
+            gas_cost: Money::from_f64(gas_cost).unwrap(),
+            net_profit: Money::from_f64(net_profit).unwrap(),
+            confidence_score: 0.9,
+            rank_score: 0.0,
+            rank_position: 0,
+            capital_required_usd,
+            risk_score: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_knapsack_respects_gas_and_capital_budgets() {
+        let config = OptimizerConfig {
+            max_gas_budget: 100.0,
+            max_capital: 10_000.0,
+            max_concurrent_routes: 5,
+            risk_tolerance: 0.9, // permisivo para no filtrar rutas en filter_viable_routes
+            diversification_weight: 0.3,
+        };
+        let optimizer = RouteOptimizer::new(config);
+
+        let routes = vec![
+            route("a", vec!["uni"], 10.0, 500.0, 100.0),
+            route("b", vec!["sushi"], 10.0, 500.0, 90.0),
+            route("c", vec!["curve"], 10.0, 500.0, 80.0),
+            // Sola ya consume casi todo el presupuesto de gas: no puede
+            // combinarse con ninguna de las otras tres.
+            route("d", vec!["balancer"], 95.0, 500.0, 200.0),
+        ];
+
+        let selected = optimizer.knapsack_optimization(routes);
+        let total_gas: f64 = selected.iter().map(|r| r.gas_cost.to_f64()).sum();
+        let total_capital: f64 = selected.iter().map(|r| r.capital_required_usd).sum();
+
+        assert!(total_gas <= 100.0 + 1e-9);
+        assert!(total_capital <= 10_000.0 + 1e-9);
+        // a+b+c (270 profit, 30 de gas) caben juntas y superan a "d" en
+        // solitario (200 profit, 95 de gas) dado el presupuesto de gas.
+        let ids: Vec<&str> = selected.iter().map(|r| r.id.as_str()).collect();
+        assert!(ids.contains(&"a") && ids.contains(&"b") && ids.contains(&"c"));
+        assert!(!ids.contains(&"d"));
+    }
+
+    #[test]
+    fn test_knapsack_respects_max_concurrent_routes() {
+        let config = OptimizerConfig {
+            max_gas_budget: 1_000.0,
+            max_capital: 100_000.0,
+            max_concurrent_routes: 2,
+            risk_tolerance: 0.9,
+            diversification_weight: 0.3,
+        };
+        let optimizer = RouteOptimizer::new(config);
+
+        let routes = vec![
+            route("a", vec!["uni"], 5.0, 100.0, 100.0),
+            route("b", vec!["sushi"], 5.0, 100.0, 90.0),
+            route("c", vec!["curve"], 5.0, 100.0, 80.0),
+        ];
+
+        let selected = optimizer.knapsack_optimization(routes);
+        assert_eq!(selected.len(), 2);
+        let total: f64 = selected.iter().map(|r| r.net_profit.to_f64()).sum();
+        assert_eq!(total, 190.0); // las dos de mayor profit: a (100) + b (90)
+    }
+
+    #[test]
+    fn test_knapsack_excludes_conflicting_routes_sharing_dexes() {
+        let config = OptimizerConfig::default();
+        let optimizer = RouteOptimizer::new(config);
+
+        let routes = vec![
+            route("cheap", vec!["uni", "sushi"], 5.0, 100.0, 50.0),
+            route("rich", vec!["sushi", "uni"], 5.0, 100.0, 120.0), // mismo par de DEXes, conflicto
+        ];
+
+        let selected = optimizer.knapsack_optimization(routes);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, "rich");
+    }
+
+    #[test]
+    fn test_portfolio_metrics_sum_profit_gas_and_capital() {
+        let optimizer = RouteOptimizer::new(OptimizerConfig::default());
+        let routes = vec![
+            route("a", vec!["uni"], 10.0, 1_000.0, 100.0),
+            route("b", vec!["sushi"], 20.0, 3_000.0, 200.0),
+        ];
+
+        let metrics = optimizer.calculate_portfolio_metrics(&routes);
+        assert_eq!(metrics.total_profit, 300.0);
+        assert_eq!(metrics.total_gas, 30.0);
+        assert_eq!(metrics.total_capital, 4_000.0);
+    }
+
+    #[test]
+    fn test_portfolio_risk_is_capital_weighted_average() {
+        let optimizer = RouteOptimizer::new(OptimizerConfig::default());
+        let mut small = route("small", vec!["uni"], 1.0, 100.0, 10.0);
+        small.risk_score = 80.0;
+        let mut large = route("large", vec!["sushi"], 1.0, 900.0, 10.0);
+        large.risk_score = 10.0;
+
+        let metrics = optimizer.calculate_portfolio_metrics(&[small, large]);
+        // (80*100 + 10*900) / 1000 = 17.0 -> dominado por la ruta grande.
+        assert!((metrics.risk - 17.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_diversification_score_rewards_distinct_dexes() {
+        let optimizer = RouteOptimizer::new(OptimizerConfig {
+            diversification_weight: 1.0,
+            ..OptimizerConfig::default()
+        });
+
+        let diverse = vec![
+            route("a", vec!["uni"], 1.0, 1.0, 10.0),
+            route("b", vec!["sushi"], 1.0, 1.0, 10.0),
+        ];
+        let concentrated = vec![
+            route("c", vec!["uni"], 1.0, 1.0, 10.0),
+            route("d", vec!["uni"], 1.0, 1.0, 10.0),
+        ];
+
+        let diverse_metrics = optimizer.calculate_portfolio_metrics(&diverse);
+        let concentrated_metrics = optimizer.calculate_portfolio_metrics(&concentrated);
+        assert!(diverse_metrics.diversification > concentrated_metrics.diversification);
+    }
+
+    #[test]
+    fn test_optimize_portfolio_empty_input_returns_default() {
+        let optimizer = RouteOptimizer::new(OptimizerConfig::default());
+        let result = optimizer.optimize_portfolio(vec![]);
+        assert_eq!(result.total_profit, 0.0);
+        assert!(result.selected_routes.is_empty());
+    }
+}
@@ -22,5 +22,6 @@ pub mod connectors;
 pub mod engine;
 pub mod pathfinding;
 pub mod pricing;
+pub mod snapshot;
 pub mod utils;
 pub mod ffi;
@@ -11,8 +11,11 @@
 // - Integración con Google Sheets para configuración dinámica
 
 use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use arc_swap::ArcSwap;
+use tokio::sync::{mpsc, Notify};
 use tokio::time::sleep;
 use serde::{Deserialize, Serialize};
 use log::{info, warn, error, debug};
@@ -20,14 +23,25 @@ use log::{info, warn, error, debug};
 mod pathfinding;
 mod pricing;
 mod engine;
+mod snapshot;
 mod utils;
 mod connectors;
+mod cost_model;
+mod worker;
+mod metrics;
+mod backtest;
+mod admin;
 
+use async_trait::async_trait;
 use pathfinding::{PathFinder, RouteOptimizer};
 use pricing::{PriceEngine, PriceData};
 use engine::ArbitrageEngine;
 use utils::{Config, Logger, PerformanceMetrics};
-use connectors::{SheetsConnector, BlockchainConnector};
+use connectors::{ConfigSource, SheetsConfigSource, SheetsConnector, BlockchainConnector, RouteSink, SheetsRouteSink, SqlRouteSink};
+use cost_model::{CostModel, CostModelConfig, PriorityFeeOutcome};
+use worker::{BackgroundRunner, Worker, WorkerState, WorkerStatus};
+use metrics::{MetricsRegistry, MetricsServerWorker, OtlpPushWorker};
+use admin::AdminServerWorker;
 
 /// Configuración de una blockchain cargada desde Google Sheets
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +66,9 @@ pub struct DexConfig {
     pub fee_percentage: f64,
     pub tvl_usd: f64,
     pub status: String,
+    /// Tipo de DEX ("V2", "V3", "FLASH_LOAN", ...), usado por `CostModel`
+    /// para estimar el gas de cada hop según la primitiva que ejecuta.
+    pub dex_type: String,
 }
 
 /// Configuración de un asset cargada desde Google Sheets
@@ -96,6 +113,11 @@ pub struct ArbitrageRoute {
     pub execution_time_estimate: u64,
     pub confidence_score: f64,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Priority fee (gwei) pujado por encima del gas base, calculado para
+    /// ganar la carrera de inclusión frente a la competencia observada en la
+    /// chain. `0.0` si todavía no hay suficiente historial de gas para pujar.
+    #[serde(default)]
+    pub priority_fee_bid_gwei: f64,
 }
 
 /// Resultado de optimización de rutas
@@ -109,16 +131,158 @@ pub struct OptimizationResult {
     pub computation_time_ms: u64,
 }
 
+/// Mensaje enviado desde `main_loop` al servicio de optimización dedicado,
+/// cada vez que un ciclo de arbitraje termina.
+#[derive(Debug, Clone)]
+struct OptimizationUpdate {
+    result: OptimizationResult,
+    cycle_time: Duration,
+}
+
+/// Estadísticas acumuladas (media móvil) para una firma de ruta (`dex_path`),
+/// actualizadas exclusivamente por el servicio de optimización.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RouteStats {
+    sample_count: u64,
+    avg_profit_usd: f64,
+    avg_gas_usd: f64,
+}
+
+/// Tabla de patrones históricos keyed por `dex_path`, persistida en disco
+/// para sobrevivir a reinicios del proceso.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct HistoricalPatternTable {
+    routes: HashMap<String, RouteStats>,
+}
+
+impl HistoricalPatternTable {
+    /// Incorpora una ruta recién ejecutada a la media móvil de su firma.
+    fn record(&mut self, route: &ArbitrageRoute) {
+        let signature = route.dex_path.join("->");
+        let stats = self.routes.entry(signature).or_insert_with(RouteStats::default);
+        let n = stats.sample_count as f64;
+        stats.avg_profit_usd = (stats.avg_profit_usd * n + route.net_profit_usd) / (n + 1.0);
+        stats.avg_gas_usd = (stats.avg_gas_usd * n + route.gas_cost_usd) / (n + 1.0);
+        stats.sample_count += 1;
+    }
+
+    /// Carga la tabla persistida, o una tabla vacía si no existe todavía o
+    /// está corrupta (arranque en frío).
+    fn load_from_disk(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Persiste la tabla completa a disco. Se llama solo cuando la tabla
+    /// realmente cambió, para no golpear el disco en cada ciclo.
+    fn save_to_disk(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).unwrap_or_default();
+        std::fs::write(path, json)
+    }
+}
+
+/// Granularidad de cada unidad discreta de capital en la DP de
+/// `optimize_capital_allocation`: $100 es suficientemente fino para no
+/// perder asignaciones útiles y suficientemente grueso para que el DP
+/// (`O(pools · B²)`) quede acotado incluso con budgets de varios cientos de
+/// miles de dólares.
+const CAPITAL_UNIT_USD: f64 = 100.0;
+
+/// Fracción máxima de la liquidez de un pool que la DP le permite absorber.
+/// Más allá de esto el slippage real se come el profit, así que en vez de
+/// modelarlo se trata como "sin retorno marginal": el tamaño máximo de
+/// trade también funciona como mínimo implícito para pools demasiado
+/// chicos (ver `MIN_POOL_TRADE_USD`).
+const MAX_POOL_LIQUIDITY_SHARE: f64 = 0.05;
+
+/// Trade mínimo para que valga la pena reservarle capital a un pool; por
+/// debajo de esto el pool se descarta de la DP (trade demasiado chico para
+/// justificar el gas de ejecutarlo).
+const MIN_POOL_TRADE_USD: f64 = 50.0;
+
+/// Curva de profit marginal esperado para un pool, usada por la DP de
+/// `optimize_capital_allocation`. Crece con `sqrt(k)` (rendimientos
+/// decrecientes por unidad adicional) y satura en `max_units`, derivado de
+/// la profundidad del pool: más allá de ese punto el slippage destruiría el
+/// profit marginal, así que asignarle más unidades no suma nada.
+struct PoolProfitCurve {
+    ev_per_unit_usd: f64,
+    max_units: usize,
+}
+
+impl PoolProfitCurve {
+    /// `None` si el pool no tiene liquidez suficiente para que un trade
+    /// mínimo viable (`MIN_POOL_TRADE_USD`) quepa dentro de
+    /// `MAX_POOL_LIQUIDITY_SHARE` de sus reservas.
+    fn for_pool(pool: &PoolConfig, ev_per_unit_usd: f64) -> Option<Self> {
+        let max_trade_usd = pool.liquidity_usd * MAX_POOL_LIQUIDITY_SHARE;
+        if max_trade_usd < MIN_POOL_TRADE_USD {
+            return None;
+        }
+
+        let max_units = (max_trade_usd / CAPITAL_UNIT_USD).floor().max(1.0) as usize;
+        Some(Self { ev_per_unit_usd, max_units })
+    }
+
+    /// Profit esperado de asignarle `k` unidades de capital a este pool.
+    fn profit(&self, k: usize) -> f64 {
+        if k == 0 {
+            return 0.0;
+        }
+        let effective_k = k.min(self.max_units) as f64;
+        self.ev_per_unit_usd * effective_k.sqrt()
+    }
+}
+
+/// Cuántas versiones frozen de `ConfigSnapshot` se conservan para rollback.
+const MAX_CONFIG_SNAPSHOTS: usize = 16;
+
+/// Foto inmutable de toda la configuración dinámica en un instante dado.
+/// `load_configuration` construye un candidato, lo valida mientras el
+/// snapshot actual sigue sirviendo ciclos, y solo lo "congela" (lo vuelve
+/// inmutable vía `Arc`) y lo intercambia atómicamente detrás de un
+/// `ArcSwap` una vez que pasa `validate_configuration`. Esto evita que una
+/// hoja a medio editar corrompa un ciclo en curso: un `main_loop` en pleno
+/// ciclo siempre ve, de principio a fin, una única versión consistente.
+#[derive(Debug, Clone, Default)]
+struct ConfigSnapshot {
+    version: u64,
+    parent: Option<u64>,
+    blockchains: Vec<BlockchainConfig>,
+    dexes: Vec<DexConfig>,
+    assets: Vec<AssetConfig>,
+    pools: Vec<PoolConfig>,
+}
+
 /// Motor principal del sistema de arbitraje
 pub struct RustArbitrageEngine {
-    // Configuración dinámica (cargada desde Google Sheets)
-    blockchains: Arc<Mutex<Vec<BlockchainConfig>>>,
-    dexes: Arc<Mutex<Vec<DexConfig>>>,
-    assets: Arc<Mutex<Vec<AssetConfig>>>,
-    pools: Arc<Mutex<Vec<PoolConfig>>>,
-    
+    // Configuración dinámica (cargada desde la fuente configurada), como
+    // snapshots inmutables versionados: `current_config` es el que sirve
+    // ciclos ahora mismo, intercambiado atómicamente en cada recarga exitosa;
+    // `config_history` guarda las últimas `MAX_CONFIG_SNAPSHOTS` versiones
+    // congeladas para poder hacer `rollback_to(version)`.
+    current_config: ArcSwap<ConfigSnapshot>,
+    config_history: Mutex<VecDeque<Arc<ConfigSnapshot>>>,
+
     // Componentes principales
+    //
+    // `config_source` es de dónde se leen BLOCKCHAINS/DEXES/ASSETS/POOLS: por
+    // defecto Sheets, pero cualquier `ConfigSource` (directorio local,
+    // Postgres) sirve sin tocar los `parse_*_config` de abajo. `sheets_connector`
+    // se mantiene aparte porque además escribe resultados de ejecución de
+    // vuelta a la hoja ROUTES/EXECUTIONS, algo que `ConfigSource` no modela.
+    config_source: Arc<dyn ConfigSource>,
     sheets_connector: Arc<SheetsConnector>,
+    // Destinos a los que se fanean las rutas generadas en cada ciclo: Sheets
+    // siempre, y opcionalmente una base SQL (ver `initialize()`). Detrás de
+    // un `Mutex` porque se completa recién en `initialize()`, una vez que
+    // `sheets_connector` ya está listo y se conoce `route_sink_database_url`.
+    route_sinks: Mutex<Vec<Arc<dyn RouteSink>>>,
     blockchain_connector: Arc<BlockchainConnector>,
     path_finder: Arc<PathFinder>,
     price_engine: Arc<PriceEngine>,
@@ -129,7 +293,34 @@ pub struct RustArbitrageEngine {
     is_running: Arc<Mutex<bool>>,
     last_config_update: Arc<Mutex<Instant>>,
     performance_metrics: Arc<Mutex<PerformanceMetrics>>,
-    
+
+    // Dueño de los jobs de larga duración (ciclo de arbitraje, recarga de
+    // configuración, servicio de optimización), cada uno individualmente
+    // inspeccionable vía `list_workers()` en vez de ocultos detrás de
+    // `is_running`.
+    background_runner: Arc<BackgroundRunner>,
+    // Señal de que `background_runner.shutdown()` ya terminó; `start()` la
+    // espera para no devolver antes de que todos los workers hayan frenado.
+    shutdown_complete: Notify,
+
+    // Servicio de optimización profunda, desacoplado del main_loop: el loop
+    // principal solo envía por canal y nunca espera al servicio.
+    optimization_tx: mpsc::UnboundedSender<OptimizationUpdate>,
+    optimization_rx: Mutex<Option<mpsc::UnboundedReceiver<OptimizationUpdate>>>,
+    historical_patterns: Arc<Mutex<HistoricalPatternTable>>,
+
+    // Modelo de costo de ejecución por-operación: reemplaza el
+    // `gas_cost_usd` plano con una estimación por hop según el tipo de DEX,
+    // calibrada con el tiempo hacia el gas realmente observado on-chain.
+    cost_model: Arc<Mutex<CostModel>>,
+
+    // Contadores/gauges/histogramas Prometheus-OTel del motor, servidos por
+    // `MetricsServerWorker` en `/metrics` y, si hay `otlp_endpoint`
+    // configurado, empujados por `OtlpPushWorker`. Actualizado en cada ciclo
+    // por `MainLoopWorker` en vez de quedar como un snapshot que solo el
+    // propio proceso puede leer (`get_performance_metrics`/`get_status`).
+    metrics: Arc<MetricsRegistry>,
+
     // Configuración del motor
     config: Config,
     logger: Logger,
@@ -142,14 +333,17 @@ impl RustArbitrageEngine {
         let logger = Logger::new("RustArbitrageEngine");
         
         info!("🦀 Inicializando Rust Arbitrage Engine...");
-        
+
+        let (optimization_tx, optimization_rx) = mpsc::unbounded_channel();
+
         Ok(Self {
-            blockchains: Arc::new(Mutex::new(Vec::new())),
-            dexes: Arc::new(Mutex::new(Vec::new())),
-            assets: Arc::new(Mutex::new(Vec::new())),
-            pools: Arc::new(Mutex::new(Vec::new())),
-            
+            current_config: ArcSwap::from_pointee(ConfigSnapshot::default()),
+            config_history: Mutex::new(VecDeque::new()),
+
+
+            config_source: Arc::new(SheetsConfigSource::new(SheetsConnector::new(&config)?)),
             sheets_connector: Arc::new(SheetsConnector::new(&config)?),
+            route_sinks: Mutex::new(Vec::new()),
             blockchain_connector: Arc::new(BlockchainConnector::new(&config)?),
             path_finder: Arc::new(PathFinder::new(&config)?),
             price_engine: Arc::new(PriceEngine::new(&config)?),
@@ -159,7 +353,17 @@ impl RustArbitrageEngine {
             is_running: Arc::new(Mutex::new(false)),
             last_config_update: Arc::new(Mutex::new(Instant::now())),
             performance_metrics: Arc::new(Mutex::new(PerformanceMetrics::new())),
-            
+            background_runner: Arc::new(BackgroundRunner::new()),
+            shutdown_complete: Notify::new(),
+
+            optimization_tx,
+            optimization_rx: Mutex::new(Some(optimization_rx)),
+            historical_patterns: Arc::new(Mutex::new(HistoricalPatternTable::default())),
+
+            cost_model: Arc::new(Mutex::new(CostModel::new(CostModelConfig::default()))),
+
+            metrics: Arc::new(MetricsRegistry::new()),
+
             config,
             logger,
         })
@@ -172,203 +376,245 @@ impl RustArbitrageEngine {
         // 1. Inicializar conectores
         self.sheets_connector.initialize().await?;
         self.blockchain_connector.initialize().await?;
-        
-        // 2. Cargar configuración inicial desde Google Sheets
-        self.load_configuration_from_sheets().await?;
-        
-        // 3. Inicializar componentes con configuración
+
+        // 1.2. Conectar los sinks de rutas: Sheets siempre (ya inicializado
+        // arriba) y, si `route_sink_database_url` está configurada, además
+        // una base SQL (SQLite por defecto, Postgres si la URL lo pide) para
+        // poder correr pattern analysis histórico con SQL.
+        {
+            let mut sinks: Vec<Arc<dyn RouteSink>> = vec![Arc::new(SheetsRouteSink::new(Arc::clone(&self.sheets_connector)))];
+
+            if let Some(database_url) = self.config.route_sink_database_url.clone() {
+                let sql_sink = SqlRouteSink::connect(&database_url).await.map_err(|e| e.to_string())?;
+                sinks.push(Arc::new(sql_sink));
+            }
+
+            *self.route_sinks.lock().unwrap() = sinks;
+        }
+
+        // 1.5. Recargar la tabla de patrones históricos desde disco, para que
+        // analyze_historical_patterns() arranque "caliente" tras un crash o redeploy
+        {
+            let path = Path::new(&self.config.historical_patterns_path);
+            let mut patterns = self.historical_patterns.lock().unwrap();
+            *patterns = HistoricalPatternTable::load_from_disk(path);
+            info!("🧠 Tabla de patrones históricos cargada: {} rutas conocidas", patterns.routes.len());
+        }
+
+        // 2. Cargar configuración inicial desde la fuente configurada
+        // (ya valida el candidato internamente antes de congelarlo)
+        self.load_configuration().await?;
+
+        // 3. Inicializar componentes con la configuración vigente
         self.initialize_components().await?;
-        
-        // 4. Validar configuración mínima
-        self.validate_configuration()?;
-        
+
         info!("✅ Rust Arbitrage Engine inicializado correctamente");
         Ok(())
     }
     
-    /// Cargar configuración desde Google Sheets
-    async fn load_configuration_from_sheets(&self) -> Result<(), Box<dyn std::error::Error>> {
-        info!("📊 Cargando configuración desde Google Sheets...");
-        
-        // Cargar datos de las hojas principales
-        let blockchains_data = self.sheets_connector.get_sheet_data("BLOCKCHAINS").await?;
-        let dexes_data = self.sheets_connector.get_sheet_data("DEXES").await?;
-        let assets_data = self.sheets_connector.get_sheet_data("ASSETS").await?;
-        let pools_data = self.sheets_connector.get_sheet_data("POOLS").await?;
-        
-        // Parsear y almacenar configuración
-        {
-            let mut blockchains = self.blockchains.lock().unwrap();
-            *blockchains = self.parse_blockchains_config(blockchains_data)?;
-        }
-        
-        {
-            let mut dexes = self.dexes.lock().unwrap();
-            *dexes = self.parse_dexes_config(dexes_data)?;
-        }
-        
-        {
-            let mut assets = self.assets.lock().unwrap();
-            *assets = self.parse_assets_config(assets_data)?;
-        }
-        
-        {
-            let mut pools = self.pools.lock().unwrap();
-            *pools = self.parse_pools_config(pools_data)?;
-        }
-        
+    /// Cargar configuración dinámica desde la fuente configurada
+    /// (`config_source`: Sheets por defecto, pero puede ser un directorio
+    /// local o Postgres sin que este método cambie).
+    ///
+    /// Construye el candidato como un `ConfigSnapshot` aparte mientras el
+    /// snapshot actual sigue sirviendo ciclos; solo si `validate_configuration`
+    /// lo aprueba se congela y se intercambia atómicamente vía `ArcSwap`. Una
+    /// hoja a medio editar nunca deja la config en un estado parcialmente
+    /// aplicado: o el candidato entero reemplaza al actual, o el actual sigue
+    /// sirviendo tal cual estaba.
+    async fn load_configuration(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("📊 Cargando configuración desde la fuente configurada...");
+
+        // Cargar datos de las hojas/tablas principales
+        let blockchains_data = self.config_source.fetch("BLOCKCHAINS").await?;
+        let dexes_data = self.config_source.fetch("DEXES").await?;
+        let assets_data = self.config_source.fetch("ASSETS").await?;
+        let pools_data = self.config_source.fetch("POOLS").await?;
+
+        let parent = self.current_config.load();
+        let candidate = ConfigSnapshot {
+            version: parent.version + 1,
+            parent: Some(parent.version),
+            blockchains: self.parse_blockchains_config(blockchains_data)?,
+            dexes: self.parse_dexes_config(dexes_data)?,
+            assets: self.parse_assets_config(assets_data)?,
+            pools: self.parse_pools_config(pools_data)?,
+        };
+
+        self.validate_configuration(&candidate)?;
+
+        info!(
+            "📈 Configuración candidata (v{}) validada: {} chains, {} DEXes, {} assets, {} pools",
+            candidate.version,
+            candidate.blockchains.len(),
+            candidate.dexes.len(),
+            candidate.assets.len(),
+            candidate.pools.len()
+        );
+
+        self.freeze_and_swap(candidate);
+
         // Actualizar timestamp de última configuración
         {
             let mut last_update = self.last_config_update.lock().unwrap();
             *last_update = Instant::now();
         }
-        
-        let blockchains_count = self.blockchains.lock().unwrap().len();
-        let dexes_count = self.dexes.lock().unwrap().len();
-        let assets_count = self.assets.lock().unwrap().len();
-        let pools_count = self.pools.lock().unwrap().len();
-        
-        info!("📈 Configuración cargada: {} chains, {} DEXes, {} assets, {} pools", 
-              blockchains_count, dexes_count, assets_count, pools_count);
-        
+
         Ok(())
     }
-    
+
+    /// Congela un snapshot candidato (ya validado) y lo intercambia
+    /// atómicamente como la configuración vigente, conservando las últimas
+    /// `MAX_CONFIG_SNAPSHOTS` versiones para poder hacer `rollback_to`.
+    fn freeze_and_swap(&self, candidate: ConfigSnapshot) {
+        self.metrics.set_config_counts(
+            candidate.blockchains.len(),
+            candidate.dexes.len(),
+            candidate.assets.len(),
+            candidate.pools.len(),
+        );
+
+        let frozen = Arc::new(candidate);
+
+        {
+            let mut history = self.config_history.lock().unwrap();
+            history.push_back(frozen.clone());
+            while history.len() > MAX_CONFIG_SNAPSHOTS {
+                history.pop_front();
+            }
+        }
+
+        self.current_config.store(frozen);
+    }
+
+    /// Vuelve a una versión congelada previamente conservada en el historial.
+    /// No descarta versiones más nuevas del historial: solo cambia cuál es
+    /// la vigente, para que un segundo rollback pueda deshacer este.
+    fn rollback_to(&self, version: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let history = self.config_history.lock().unwrap();
+        let target = history
+            .iter()
+            .find(|snapshot| snapshot.version == version)
+            .cloned()
+            .ok_or_else(|| format!("No hay snapshot de configuración con versión {} en el historial", version))?;
+
+        self.metrics.set_config_counts(
+            target.blockchains.len(),
+            target.dexes.len(),
+            target.assets.len(),
+            target.pools.len(),
+        );
+        self.current_config.store(target);
+        info!("⏪ Configuración revertida a la versión {}", version);
+        Ok(())
+    }
+
     /// Inicializar componentes con la configuración cargada
     async fn initialize_components(&self) -> Result<(), Box<dyn std::error::Error>> {
         info!("⚙️ Inicializando componentes con configuración...");
-        
-        let blockchains = self.blockchains.lock().unwrap().clone();
-        let dexes = self.dexes.lock().unwrap().clone();
-        let assets = self.assets.lock().unwrap().clone();
-        let pools = self.pools.lock().unwrap().clone();
-        
+
+        let snapshot = self.current_config.load_full();
+
         // Inicializar path finder con configuración de DEXes y pools
-        self.path_finder.initialize(&dexes, &pools).await?;
-        
+        self.path_finder.initialize(&snapshot.dexes, &snapshot.pools).await?;
+
         // Inicializar price engine con assets
-        self.price_engine.initialize(&assets, &pools).await?;
-        
+        self.price_engine.initialize(&snapshot.assets, &snapshot.pools).await?;
+
         // Inicializar route optimizer
-        self.route_optimizer.initialize(&dexes, &assets, &pools).await?;
-        
+        self.route_optimizer.initialize(&snapshot.dexes, &snapshot.assets, &snapshot.pools).await?;
+
         // Inicializar arbitrage engine
-        self.arbitrage_engine.initialize(&blockchains, &dexes).await?;
-        
+        self.arbitrage_engine.initialize(&snapshot.blockchains, &snapshot.dexes).await?;
+
         info!("✅ Todos los componentes inicializados");
         Ok(())
     }
-    
-    /// Validar que tenemos configuración mínima
-    fn validate_configuration(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let blockchains_count = self.blockchains.lock().unwrap().len();
-        let dexes_count = self.dexes.lock().unwrap().len();
-        let assets_count = self.assets.lock().unwrap().len();
-        
-        if blockchains_count == 0 {
+
+    /// Validar que un snapshot candidato tiene configuración mínima
+    fn validate_configuration(&self, snapshot: &ConfigSnapshot) -> Result<(), Box<dyn std::error::Error>> {
+        if snapshot.blockchains.is_empty() {
             return Err("No blockchains configured in Google Sheets".into());
         }
-        
-        if dexes_count < 2 {
+
+        if snapshot.dexes.len() < 2 {
             return Err("Need at least 2 DEXes for arbitrage".into());
         }
-        
-        if assets_count == 0 {
+
+        if snapshot.assets.is_empty() {
             return Err("No assets configured in Google Sheets".into());
         }
-        
+
         info!("✅ Configuración mínima validada");
         Ok(())
     }
     
-    /// Iniciar el motor de arbitraje
-    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Iniciar el motor de arbitraje. Recibe `self: &Arc<Self>` (no `&self`)
+    /// porque cada worker en background necesita su propia referencia
+    /// compartida al motor para sobrevivir después de que `start()` retorne.
+    pub async fn start(self: &Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
         {
             let mut running = self.is_running.lock().unwrap();
             *running = true;
         }
-        
+
         info!("🚀 Iniciando Rust Arbitrage Engine...");
-        
+
         // Inicializar si no se ha hecho
         if !self.sheets_connector.is_initialized() {
             self.initialize().await?;
         }
-        
-        // Crear tareas concurrentes
-        let engine_clone = self.clone_arc();
-        
-        let main_loop_task = tokio::spawn(async move {
-            engine_clone.main_loop().await;
-        });
-        
-        let config_update_task = tokio::spawn(async move {
-            let engine = self.clone_arc();
-            engine.configuration_update_loop().await;
-        });
-        
-        // Esperar a que todas las tareas terminen
-        tokio::try_join!(main_loop_task, config_update_task)?;
-        
-        Ok(())
-    }
-    
-    /// Loop principal del motor
-    async fn main_loop(&self) {
-        info!("🔄 Iniciando loop principal del motor...");
-        
-        let mut last_optimization = Instant::now();
-        let optimization_interval = Duration::from_secs(self.config.optimization_interval_seconds);
-        
-        while self.is_running() {
-            let start_time = Instant::now();
-            
-            match self.execute_arbitrage_cycle().await {
-                Ok(result) => {
-                    debug!("✅ Ciclo de arbitraje completado: {} rutas generadas", result.routes.len());
-                    
-                    // Actualizar métricas de rendimiento
-                    {
-                        let mut metrics = self.performance_metrics.lock().unwrap();
-                        metrics.add_cycle_time(start_time.elapsed());
-                        metrics.add_routes_generated(result.routes.len());
-                    }
-                    
-                    // Escribir rutas a Google Sheets
-                    if let Err(e) = self.write_routes_to_sheets(result.routes).await {
-                        error!("❌ Error escribiendo rutas a Sheets: {}", e);
-                    }
-                }
-                Err(e) => {
-                    error!("❌ Error en ciclo de arbitraje: {}", e);
-                    
-                    // Incrementar contador de errores
-                    {
-                        let mut metrics = self.performance_metrics.lock().unwrap();
-                        metrics.add_error();
-                    }
-                }
-            }
-            
-            // Optimización completa menos frecuente
-            if last_optimization.elapsed() >= optimization_interval {
-                if let Err(e) = self.deep_optimization().await {
-                    error!("❌ Error en optimización profunda: {}", e);
-                }
-                last_optimization = Instant::now();
+
+        // Registrar los jobs de larga duración como workers independientes,
+        // cada uno inspeccionable y controlable por separado vía
+        // `list_workers()` en vez de compartir un único flag `is_running`.
+        self.background_runner
+            .spawn(Box::new(MainLoopWorker::new(Arc::clone(self))));
+        self.background_runner
+            .spawn(Box::new(ConfigUpdateWorker::new(Arc::clone(self))));
+
+        let optimization_receiver = self.optimization_rx.lock().unwrap().take();
+        match optimization_receiver {
+            Some(receiver) => {
+                self.background_runner
+                    .spawn(Box::new(OptimizationServiceWorker::new(Arc::clone(self), receiver)));
             }
-            
-            // Pausa antes del siguiente ciclo
-            sleep(Duration::from_secs(self.config.cycle_interval_seconds)).await;
+            None => warn!("⚠️ El servicio de optimización ya está corriendo"),
         }
-        
-        info!("🛑 Loop principal del motor detenido");
+
+        self.background_runner.spawn(Box::new(MetricsServerWorker::new(
+            self.config.metrics_http_addr.clone(),
+            Arc::clone(&self.metrics),
+        )));
+
+        self.background_runner.spawn(Box::new(AdminServerWorker::new(
+            self.config.admin_http_addr.clone(),
+            Arc::clone(self),
+        )));
+
+        if let Some(otlp_endpoint) = self.config.otlp_endpoint.clone() {
+            self.background_runner.spawn(Box::new(OtlpPushWorker::new(
+                otlp_endpoint,
+                Duration::from_secs(self.config.otlp_push_interval_seconds),
+                Arc::clone(&self.metrics),
+            )));
+        }
+
+        // Esperar a que `stop()` cancele y drene todos los workers.
+        self.shutdown_complete.notified().await;
+
+        Ok(())
     }
-    
+
     /// Ejecutar un ciclo completo de búsqueda de arbitraje
     async fn execute_arbitrage_cycle(&self) -> Result<OptimizationResult, Box<dyn std::error::Error>> {
         let start_time = Instant::now();
-        
+
+        // 0. Fijar la configuración vigente para todo el ciclo: si una
+        // recarga congela una versión nueva a mitad de camino, este ciclo
+        // sigue viendo la que tenía al arrancar, nunca una mezcla de ambas.
+        let config_snapshot = self.current_config.load_full();
+
         // 1. Obtener precios actuales
         let price_data = self.price_engine.get_current_prices().await?;
         debug!("📊 Obtenidos {} precios actuales", price_data.len());
@@ -380,9 +626,13 @@ impl RustArbitrageEngine {
         // 3. Evaluar y optimizar rutas
         let optimized_routes = self.route_optimizer.optimize_routes(potential_routes, &price_data).await?;
         debug!("⚡ Optimizadas {} rutas", optimized_routes.len());
-        
+
+        // 3.5. Recalcular el costo de gas por ruta según su estructura real
+        // (cantidad y tipo de hops), en vez del estimado plano del optimizador
+        let costed_routes = self.apply_cost_model(optimized_routes, &config_snapshot)?;
+
         // 4. Filtrar rutas rentables
-        let profitable_routes = self.filter_profitable_routes(optimized_routes)?;
+        let profitable_routes = self.filter_profitable_routes(costed_routes)?;
         debug!("💰 {} rutas rentables encontradas", profitable_routes.len());
         
         // 5. Ranking final por ROI
@@ -427,33 +677,18 @@ impl RustArbitrageEngine {
         Ok(())
     }
     
-    /// Loop de actualización de configuración
-    async fn configuration_update_loop(&self) {
-        info!("🔄 Iniciando loop de actualización de configuración...");
-        
-        let update_interval = Duration::from_secs(self.config.config_update_interval_seconds);
-        
-        while self.is_running() {
-            sleep(update_interval).await;
-            
-            if let Err(e) = self.update_configuration().await {
-                error!("❌ Error actualizando configuración: {}", e);
-            }
-        }
-    }
-    
     /// Actualizar configuración desde Google Sheets
     async fn update_configuration(&self) -> Result<(), Box<dyn std::error::Error>> {
         debug!("🔄 Verificando actualizaciones de configuración...");
         
         // Verificar si hay cambios en Google Sheets
-        let last_modified = self.sheets_connector.get_last_modified().await?;
+        let last_modified = self.config_source.last_modified().await?;
         let last_update = *self.last_config_update.lock().unwrap();
         
         if last_modified > last_update {
             info!("📝 Configuración actualizada detectada, recargando...");
             
-            self.load_configuration_from_sheets().await?;
+            self.load_configuration().await?;
             self.initialize_components().await?;
             
             info!("✅ Configuración actualizada exitosamente");
@@ -509,14 +744,15 @@ impl RustArbitrageEngine {
                     fee_percentage: row.get("FEE_PERCENTAGE").and_then(|v| v.as_f64()).unwrap_or(0.3),
                     tvl_usd: row.get("TVL_USD").and_then(|v| v.as_f64()).unwrap_or(0.0),
                     status: row.get("STATUS").and_then(|v| v.as_str()).unwrap_or("UNKNOWN").to_string(),
+                    dex_type: row.get("DEX_TYPE").and_then(|v| v.as_str()).unwrap_or("V2").to_string(),
                 };
                 configs.push(config);
             }
         }
-        
+
         Ok(configs)
     }
-    
+
     /// Parsear configuración de assets desde Google Sheets
     fn parse_assets_config(&self, data: Vec<HashMap<String, serde_json::Value>>) -> Result<Vec<AssetConfig>, Box<dyn std::error::Error>> {
         let mut configs = Vec::new();
@@ -567,6 +803,75 @@ impl RustArbitrageEngine {
     // MÉTODOS DE ANÁLISIS Y OPTIMIZACIÓN
     // ==================================================================================
     
+    /// Recalcula `gas_cost_usd`, `net_profit_usd` y `roi_percentage` de cada
+    /// ruta con el `CostModel` (en vez del estimado plano del optimizador), y
+    /// encima le suma la puja de priority fee necesaria para ganar la
+    /// carrera de inclusión frente a la competencia reciente observada en la
+    /// chain. Si esa puja no alcanza a pagarse con el profit de la ruta, la
+    /// ruta se descarta en vez de dejarla lista para ejecutar en pérdida.
+    /// La chain y el asset nativo se resuelven por el primer hop del
+    /// `dex_path` (todas las rutas son intra-chain).
+    fn apply_cost_model(
+        &self,
+        routes: Vec<ArbitrageRoute>,
+        snapshot: &ConfigSnapshot,
+    ) -> Result<Vec<ArbitrageRoute>, Box<dyn std::error::Error>> {
+        let cost_model = self.cost_model.lock().unwrap();
+
+        let dexes_by_id: HashMap<String, &DexConfig> =
+            snapshot.dexes.iter().map(|dex| (dex.dex_id.clone(), dex)).collect();
+
+        let bid_percentile = cost_model.priority_fee_bid_percentile();
+
+        let costed = routes
+            .into_iter()
+            .filter_map(|mut route| {
+                let first_dex = route.dex_path.first().and_then(|id| dexes_by_id.get(id))?;
+                let chain = snapshot.blockchains.iter().find(|c| c.chain_id == first_dex.chain_id)?;
+                let native_asset = snapshot
+                    .assets
+                    .iter()
+                    .find(|a| a.chain_id == chain.chain_id && a.token_symbol == chain.native_token)?;
+
+                let gas_units = cost_model.estimate_gas_units(&route.dex_path, &dexes_by_id);
+                let base_gas_cost_usd =
+                    cost_model.gas_units_to_usd(gas_units, chain.gas_price_gwei, native_asset.current_price_usd);
+                let base_net_profit_usd = route.expected_output - route.input_amount - base_gas_cost_usd;
+
+                let observed_percentile_gwei =
+                    self.blockchain_connector.gas_price_percentile(chain.chain_id, bid_percentile);
+                let bid_outcome = cost_model.bid_priority_fee(
+                    base_net_profit_usd,
+                    gas_units,
+                    native_asset.current_price_usd,
+                    observed_percentile_gwei,
+                );
+
+                let (priority_fee_bid_gwei, extra_cost_usd) = match bid_outcome {
+                    PriorityFeeOutcome::NoCompetitionData => (0.0, 0.0),
+                    PriorityFeeOutcome::Bid(bid) => (bid.bid_gwei, bid.extra_cost_usd),
+                    PriorityFeeOutcome::Unaffordable => return None,
+                };
+
+                let gas_cost_usd = base_gas_cost_usd + extra_cost_usd;
+                let net_profit_usd = route.expected_output - route.input_amount - gas_cost_usd;
+                let roi_percentage = if route.input_amount > 0.0 {
+                    (net_profit_usd / route.input_amount) * 100.0
+                } else {
+                    0.0
+                };
+
+                route.gas_cost_usd = gas_cost_usd;
+                route.net_profit_usd = net_profit_usd;
+                route.roi_percentage = roi_percentage;
+                route.priority_fee_bid_gwei = priority_fee_bid_gwei;
+                Some(route)
+            })
+            .collect();
+
+        Ok(costed)
+    }
+
     /// Filtrar rutas rentables
     fn filter_profitable_routes(&self, routes: Vec<ArbitrageRoute>) -> Result<Vec<ArbitrageRoute>, Box<dyn std::error::Error>> {
         let min_profit_usd = self.config.min_profit_usd;
@@ -596,27 +901,128 @@ impl RustArbitrageEngine {
     }
     
     /// Análisis de patrones históricos
+    ///
+    /// Lee la tabla agregada por el servicio de optimización dedicado (nunca
+    /// la recalcula inline), por lo que esto es un simple lock + promedio y
+    /// no bloquea el `main_loop`.
     async fn analyze_historical_patterns(&self) -> Result<HashMap<String, f64>, Box<dyn std::error::Error>> {
-        // Implementar análisis de patrones usando programación dinámica
-        // Por ahora retornamos datos de ejemplo
+        let table = self.historical_patterns.lock().unwrap();
+
         let mut patterns = HashMap::new();
-        patterns.insert("avg_profit_per_route".to_string(), 15.5);
-        patterns.insert("success_rate".to_string(), 0.85);
-        patterns.insert("optimal_gas_price".to_string(), 25.0);
-        
+
+        if table.routes.is_empty() {
+            patterns.insert("avg_profit_per_route".to_string(), 0.0);
+            patterns.insert("success_rate".to_string(), 0.0);
+            patterns.insert("optimal_gas_price".to_string(), 0.0);
+            return Ok(patterns);
+        }
+
+        let route_count = table.routes.len() as f64;
+        let total_samples: u64 = table.routes.values().map(|s| s.sample_count).sum();
+        let avg_profit_per_route: f64 = table.routes.values().map(|s| s.avg_profit_usd).sum::<f64>() / route_count;
+        let avg_gas_price: f64 = table.routes.values().map(|s| s.avg_gas_usd).sum::<f64>() / route_count;
+        let profitable_routes = table.routes.values().filter(|s| s.avg_profit_usd > 0.0).count() as f64;
+
+        patterns.insert("avg_profit_per_route".to_string(), avg_profit_per_route);
+        patterns.insert("success_rate".to_string(), profitable_routes / route_count);
+        patterns.insert("optimal_gas_price".to_string(), avg_gas_price);
+        patterns.insert("total_samples".to_string(), total_samples as f64);
+
         Ok(patterns)
     }
     
-    /// Optimización de allocation de capital usando DP
-    fn optimize_capital_allocation(&self, _patterns: &HashMap<String, f64>) -> Result<HashMap<String, f64>, Box<dyn std::error::Error>> {
-        // Implementar algoritmo de programación dinámica para allocation
+    /// Optimización de allocation de capital usando DP sobre unidades
+    /// discretas de capital (`CAPITAL_UNIT_USD` cada una).
+    ///
+    /// `dp[c]` es el mejor profit esperado acumulado repartiendo `c` unidades
+    /// entre los pools ya procesados; para cada pool se prueba cuántas
+    /// unidades `k` (0..=c) asignarle, usando su `PoolProfitCurve` (cóncava,
+    /// saturante según la liquidez del pool) como función de valor. Esto es
+    /// `O(pools · B²)` con `B` = unidades totales del budget; la concavidad
+    /// de `profit()` permitiría acotar la búsqueda de `k` con una cola
+    /// monótona si el budget creciera lo suficiente como para que importe.
+    ///
+    /// Cae a un reparto proporcional a liquidez si no hay historial todavía
+    /// (`ev_per_unit_usd == 0.0`, arranque en frío) o si hay menos de dos
+    /// pools con liquidez utilizable para que la DP tenga sentido.
+    fn optimize_capital_allocation(&self, patterns: &HashMap<String, f64>) -> Result<HashMap<String, f64>, Box<dyn std::error::Error>> {
+        let snapshot = self.current_config.load();
+        let budget_usd = self.config.capital_allocation_budget_usd;
+
+        // Valor esperado de asignarle una unidad de capital a un pool
+        // "promedio": profit medio por ruta ejecutada, descontado por qué
+        // tan seguido esa ruta termina siendo rentable.
+        let avg_profit_per_route = patterns.get("avg_profit_per_route").copied().unwrap_or(0.0).max(0.0);
+        let success_rate = patterns.get("success_rate").copied().unwrap_or(0.0).clamp(0.0, 1.0);
+        let ev_per_unit_usd = avg_profit_per_route * success_rate;
+
+        if ev_per_unit_usd <= 0.0 || budget_usd <= 0.0 {
+            return Ok(Self::fallback_liquidity_weighted_allocation(&snapshot.pools, budget_usd));
+        }
+
+        let candidates: Vec<(String, PoolProfitCurve)> = snapshot
+            .pools
+            .iter()
+            .filter_map(|pool| {
+                PoolProfitCurve::for_pool(pool, ev_per_unit_usd).map(|curve| (pool.pool_id.clone(), curve))
+            })
+            .collect();
+
+        let total_units = (budget_usd / CAPITAL_UNIT_USD).floor() as usize;
+
+        if candidates.len() < 2 || total_units == 0 {
+            return Ok(Self::fallback_liquidity_weighted_allocation(&snapshot.pools, budget_usd));
+        }
+
+        let mut dp = vec![0.0_f64; total_units + 1];
+        let mut choice: Vec<Vec<usize>> = vec![vec![0; total_units + 1]; candidates.len()];
+
+        for (i, (_, curve)) in candidates.iter().enumerate() {
+            let mut next_dp = dp.clone();
+            for c in 0..=total_units {
+                let max_k = c.min(curve.max_units);
+                for k in 0..=max_k {
+                    let value = dp[c - k] + curve.profit(k);
+                    if value > next_dp[c] {
+                        next_dp[c] = value;
+                        choice[i][c] = k;
+                    }
+                }
+            }
+            dp = next_dp;
+        }
+
+        // Reconstruir la asignación recorriendo `choice` de atrás hacia
+        // adelante, descontando de `remaining` lo que le tocó a cada pool.
         let mut allocations = HashMap::new();
-        allocations.insert("high_liquidity_pools".to_string(), 0.6);
-        allocations.insert("medium_liquidity_pools".to_string(), 0.3);
-        allocations.insert("low_liquidity_pools".to_string(), 0.1);
-        
+        let mut remaining = total_units;
+        for (i, (pool_id, _)) in candidates.iter().enumerate().rev() {
+            let k = choice[i][remaining];
+            if k > 0 {
+                allocations.insert(pool_id.clone(), k as f64 * CAPITAL_UNIT_USD);
+            }
+            remaining -= k;
+        }
+
         Ok(allocations)
     }
+
+    /// Reparto proporcional a liquidez, usado cuando todavía no hay
+    /// historial suficiente (o suficientes pools) para que la DP de
+    /// `optimize_capital_allocation` tenga información real con la que
+    /// trabajar.
+    fn fallback_liquidity_weighted_allocation(pools: &[PoolConfig], budget_usd: f64) -> HashMap<String, f64> {
+        let total_liquidity: f64 = pools.iter().map(|p| p.liquidity_usd).filter(|l| *l > 0.0).sum();
+        if total_liquidity <= 0.0 || budget_usd <= 0.0 {
+            return HashMap::new();
+        }
+
+        pools
+            .iter()
+            .filter(|p| p.liquidity_usd > 0.0)
+            .map(|p| (p.pool_id.clone(), budget_usd * (p.liquidity_usd / total_liquidity)))
+            .collect()
+    }
     
     /// Ajustar parámetros del sistema
     async fn adjust_system_parameters(&self, _allocations: &HashMap<String, f64>) -> Result<(), Box<dyn std::error::Error>> {
@@ -625,35 +1031,25 @@ impl RustArbitrageEngine {
         Ok(())
     }
     
-    /// Escribir rutas a Google Sheets
-    async fn write_routes_to_sheets(&self, routes: Vec<ArbitrageRoute>) -> Result<(), Box<dyn std::error::Error>> {
+    /// Fanea el batch de rutas de un ciclo a todos los `RouteSink`
+    /// registrados (Sheets y, si está configurado, SQL). Un sink que falla
+    /// no detiene al resto: se loggea y se sigue con los demás, para que un
+    /// problema puntual con un backend no le cueste la persistencia al otro.
+    async fn write_routes(&self, routes: Vec<ArbitrageRoute>) -> Result<(), Box<dyn std::error::Error>> {
         if routes.is_empty() {
             return Ok(());
         }
-        
-        debug!("📝 Escribiendo {} rutas a Google Sheets...", routes.len());
-        
-        // Convertir rutas a formato de Sheets
-        let sheet_data: Vec<HashMap<String, serde_json::Value>> = routes
-            .into_iter()
-            .map(|route| {
-                let mut row = HashMap::new();
-                row.insert("ROUTE_ID".to_string(), serde_json::Value::String(route.route_id));
-                row.insert("SOURCE_TOKEN".to_string(), serde_json::Value::String(route.source_token));
-                row.insert("TARGET_TOKEN".to_string(), serde_json::Value::String(route.target_token));
-                row.insert("NET_PROFIT_USD".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(route.net_profit_usd).unwrap()));
-                row.insert("ROI_PERCENTAGE".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(route.roi_percentage).unwrap()));
-                row.insert("GAS_COST_USD".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(route.gas_cost_usd).unwrap()));
-                row.insert("STATUS".to_string(), serde_json::Value::String("PENDING".to_string()));
-                row.insert("CREATED_AT".to_string(), serde_json::Value::String(route.created_at.to_rfc3339()));
-                row
-            })
-            .collect();
-        
-        // Escribir a la hoja ROUTES
-        self.sheets_connector.update_sheet_data("ROUTES", sheet_data).await?;
-        
-        debug!("✅ Rutas escritas a Google Sheets");
+
+        debug!("📝 Escribiendo {} rutas a {} sink(s)...", routes.len(), self.route_sinks.lock().unwrap().len());
+
+        let sinks: Vec<Arc<dyn RouteSink>> = self.route_sinks.lock().unwrap().clone();
+        for sink in &sinks {
+            if let Err(e) = sink.write_routes(&routes).await {
+                error!("❌ Error escribiendo rutas a un route sink: {}", e);
+            }
+        }
+
+        debug!("✅ Rutas escritas");
         Ok(())
     }
     
@@ -669,17 +1065,41 @@ impl RustArbitrageEngine {
     /// Detener el motor
     pub async fn stop(&self) {
         info!("🛑 Deteniendo Rust Arbitrage Engine...");
-        
+
         {
             let mut running = self.is_running.lock().unwrap();
             *running = false;
         }
-        
-        // Dar tiempo para que los loops terminen
-        sleep(Duration::from_secs(2)).await;
-        
+
+        // Cancela cada worker y espera a que su tarea termine limpiamente,
+        // en vez del `sleep(Duration::from_secs(2))` a ciegas de antes.
+        self.background_runner.shutdown().await;
+        self.shutdown_complete.notify_one();
+
         info!("✅ Rust Arbitrage Engine detenido");
     }
+
+    /// Estado inspeccionable de cada worker en background (ciclo de
+    /// arbitraje, recarga de configuración, servicio de optimización).
+    pub fn list_workers(&self) -> Vec<WorkerStatus> {
+        self.background_runner.list_workers()
+    }
+
+    /// Pausa/reanuda/cancela un worker en background por nombre, reusando
+    /// los canales de comando de `BackgroundRunner`. Expuesto para que
+    /// `AdminServerWorker` pueda controlar workers individuales por HTTP en
+    /// vez de solo inspeccionarlos.
+    pub fn pause_worker(&self, name: &str) {
+        self.background_runner.pause(name);
+    }
+
+    pub fn resume_worker(&self, name: &str) {
+        self.background_runner.resume(name);
+    }
+
+    pub fn cancel_worker(&self, name: &str) {
+        self.background_runner.cancel(name);
+    }
     
     /// Obtener métricas de rendimiento
     pub fn get_performance_metrics(&self) -> PerformanceMetrics {
@@ -690,11 +1110,13 @@ impl RustArbitrageEngine {
     pub fn get_status(&self) -> HashMap<String, serde_json::Value> {
         let mut status = HashMap::new();
         
+        let snapshot = self.current_config.load();
         status.insert("is_running".to_string(), serde_json::Value::Bool(self.is_running()));
-        status.insert("blockchains_count".to_string(), serde_json::Value::Number(serde_json::Number::from(self.blockchains.lock().unwrap().len())));
-        status.insert("dexes_count".to_string(), serde_json::Value::Number(serde_json::Number::from(self.dexes.lock().unwrap().len())));
-        status.insert("assets_count".to_string(), serde_json::Value::Number(serde_json::Number::from(self.assets.lock().unwrap().len())));
-        status.insert("pools_count".to_string(), serde_json::Value::Number(serde_json::Number::from(self.pools.lock().unwrap().len())));
+        status.insert("config_version".to_string(), serde_json::Value::Number(serde_json::Number::from(snapshot.version)));
+        status.insert("blockchains_count".to_string(), serde_json::Value::Number(serde_json::Number::from(snapshot.blockchains.len())));
+        status.insert("dexes_count".to_string(), serde_json::Value::Number(serde_json::Number::from(snapshot.dexes.len())));
+        status.insert("assets_count".to_string(), serde_json::Value::Number(serde_json::Number::from(snapshot.assets.len())));
+        status.insert("pools_count".to_string(), serde_json::Value::Number(serde_json::Number::from(snapshot.pools.len())));
         
         let metrics = self.performance_metrics.lock().unwrap();
         status.insert("total_cycles".to_string(), serde_json::Value::Number(serde_json::Number::from(metrics.total_cycles)));
@@ -703,12 +1125,214 @@ impl RustArbitrageEngine {
         
         status
     }
-    
-    /// Crear clon con Arc para tareas concurrentes
-    fn clone_arc(&self) -> Arc<Self> {
-        // Esta implementación requeriría que RustArbitrageEngine implemente Clone
-        // o use Arc<RustArbitrageEngine> desde el principio
-        unimplemented!("Implement Arc cloning for concurrent tasks")
+}
+
+// ==================================================================================
+// WORKERS EN BACKGROUND
+// ==================================================================================
+//
+// Cada uno envuelve, como una sola unidad de `work()`, el cuerpo de loop que
+// antes vivía suelto en `main_loop` / `configuration_update_loop` /
+// `run_optimization_service`. `BackgroundRunner` decide cuándo llamarlos y
+// cuándo cancelarlos; estos structs solo saben hacer una pasada y reportar
+// su último error.
+
+/// Un ciclo de arbitraje por `work()`, más una pasada de `deep_optimization`
+/// cuando toca. Reemplaza el antiguo `main_loop`.
+struct MainLoopWorker {
+    engine: Arc<RustArbitrageEngine>,
+    last_optimization: Instant,
+    last_progress: Option<String>,
+    last_error: Option<String>,
+}
+
+impl MainLoopWorker {
+    fn new(engine: Arc<RustArbitrageEngine>) -> Self {
+        Self {
+            engine,
+            last_optimization: Instant::now(),
+            last_progress: None,
+            last_error: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Worker for MainLoopWorker {
+    fn name(&self) -> &str {
+        "main_loop"
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        let start_time = Instant::now();
+
+        match self.engine.execute_arbitrage_cycle().await {
+            Ok(result) => {
+                debug!("✅ Ciclo de arbitraje completado: {} rutas generadas", result.routes.len());
+
+                {
+                    let mut metrics = self.engine.performance_metrics.lock().unwrap();
+                    metrics.add_cycle_time(start_time.elapsed());
+                    metrics.add_routes_generated(result.routes.len());
+                }
+                self.engine.metrics.record_cycle(start_time.elapsed().as_secs_f64() * 1000.0, result.routes.len());
+                for route in &result.routes {
+                    self.engine.metrics.record_route_profit(route.net_profit_usd);
+                }
+                self.last_progress = Some(format!("{} rutas generadas", result.routes.len()));
+
+                // Notificar al servicio de optimización dedicado. Es un
+                // envío no bloqueante sobre un canal sin límite: este worker
+                // jamás espera a que el servicio procese esto.
+                let update = OptimizationUpdate {
+                    result: result.clone(),
+                    cycle_time: start_time.elapsed(),
+                };
+                if self.engine.optimization_tx.send(update).is_err() {
+                    warn!("⚠️ Servicio de optimización no disponible, update descartado");
+                }
+
+                if let Err(e) = self.engine.write_routes(result.routes).await {
+                    error!("❌ Error escribiendo rutas: {}", e);
+                    self.last_error = Some(e.to_string());
+                }
+            }
+            Err(e) => {
+                error!("❌ Error en ciclo de arbitraje: {}", e);
+                self.last_error = Some(e.to_string());
+                self.engine.metrics.record_error();
+                let mut metrics = self.engine.performance_metrics.lock().unwrap();
+                metrics.add_error();
+            }
+        }
+
+        // Optimización completa menos frecuente
+        let optimization_interval = Duration::from_secs(self.engine.config.optimization_interval_seconds);
+        if self.last_optimization.elapsed() >= optimization_interval {
+            if let Err(e) = self.engine.deep_optimization().await {
+                error!("❌ Error en optimización profunda: {}", e);
+                self.last_error = Some(e.to_string());
+            }
+            self.last_optimization = Instant::now();
+        }
+
+        // Pausa antes del siguiente ciclo
+        sleep(Duration::from_secs(self.engine.config.cycle_interval_seconds)).await;
+        WorkerState::Active
+    }
+
+    fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            name: self.name().to_string(),
+            state: WorkerState::Active,
+            progress: self.last_progress.clone(),
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+/// Una verificación de configuración por `work()`. Reemplaza el antiguo
+/// `configuration_update_loop`.
+struct ConfigUpdateWorker {
+    engine: Arc<RustArbitrageEngine>,
+    last_error: Option<String>,
+}
+
+impl ConfigUpdateWorker {
+    fn new(engine: Arc<RustArbitrageEngine>) -> Self {
+        Self { engine, last_error: None }
+    }
+}
+
+#[async_trait]
+impl Worker for ConfigUpdateWorker {
+    fn name(&self) -> &str {
+        "config_update_loop"
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        let update_interval = Duration::from_secs(self.engine.config.config_update_interval_seconds);
+        sleep(update_interval).await;
+
+        if let Err(e) = self.engine.update_configuration().await {
+            error!("❌ Error actualizando configuración: {}", e);
+            self.last_error = Some(e.to_string());
+        }
+
+        WorkerState::Active
+    }
+
+    fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            name: self.name().to_string(),
+            state: WorkerState::Active,
+            progress: None,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+/// Consume los `OptimizationUpdate` generados por `MainLoopWorker` y es el
+/// único lugar que muta `historical_patterns`. Reemplaza el antiguo
+/// `run_optimization_service`; termina (`WorkerState::Done`) cuando el canal
+/// se cierra, es decir, cuando se suelta el `optimization_tx` que guarda el
+/// propio `RustArbitrageEngine` (este worker mantiene un `Arc` a ese motor).
+struct OptimizationServiceWorker {
+    engine: Arc<RustArbitrageEngine>,
+    receiver: mpsc::UnboundedReceiver<OptimizationUpdate>,
+    last_error: Option<String>,
+}
+
+impl OptimizationServiceWorker {
+    fn new(engine: Arc<RustArbitrageEngine>, receiver: mpsc::UnboundedReceiver<OptimizationUpdate>) -> Self {
+        Self { engine, receiver, last_error: None }
+    }
+}
+
+#[async_trait]
+impl Worker for OptimizationServiceWorker {
+    fn name(&self) -> &str {
+        "optimization_service"
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        match self.receiver.recv().await {
+            Some(update) => {
+                if update.result.routes.is_empty() {
+                    return WorkerState::Idle;
+                }
+
+                let path = Path::new(&self.engine.config.historical_patterns_path);
+                {
+                    let mut patterns = self.engine.historical_patterns.lock().unwrap();
+                    for route in &update.result.routes {
+                        patterns.record(route);
+                    }
+
+                    // Persistir solo cuando la tabla realmente cambió, para
+                    // no golpear el disco en cada ciclo de arbitraje.
+                    if let Err(e) = patterns.save_to_disk(path) {
+                        error!("❌ Error persistiendo tabla de patrones históricos: {}", e);
+                        self.last_error = Some(e.to_string());
+                    }
+                }
+
+                WorkerState::Active
+            }
+            None => {
+                info!("🛑 Servicio de optimización detenido");
+                WorkerState::Done
+            }
+        }
+    }
+
+    fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            name: self.name().to_string(),
+            state: WorkerState::Active,
+            progress: None,
+            last_error: self.last_error.clone(),
+        }
     }
 }
 
@@ -716,13 +1340,44 @@ impl RustArbitrageEngine {
 // MAIN - PUNTO DE ENTRADA
 // ==================================================================================
 
+/// Ruta del workload si el proceso se invocó en modo benchmark, vía
+/// `--benchmark <path>` o la variable de entorno `BENCHMARK_WORKLOAD`.
+/// El flag de línea de comandos gana si ambos están presentes.
+fn benchmark_workload_path() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--benchmark" {
+            return args.next();
+        }
+    }
+    std::env::var("BENCHMARK_WORKLOAD").ok()
+}
+
+/// Modo benchmark: reproduce un workload grabado contra el pathfinder y
+/// emite el reporte, sin arrancar el ciclo principal ni conectar a
+/// Sheets/RPCs reales.
+async fn run_benchmark_mode(workload_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    info!("📈 Modo benchmark: reproduciendo workload {}", workload_path);
+
+    let workload = backtest::Workload::load_from_file(workload_path)?;
+    let report = backtest::run_backtest(&workload, &backtest::BenchmarkConfig::default());
+    let report_endpoint = std::env::var("BENCHMARK_REPORT_ENDPOINT").ok();
+    backtest::publish_report(&report, report_endpoint.as_deref()).await?;
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Configurar logging
     env_logger::init();
-    
+
+    if let Some(workload_path) = benchmark_workload_path() {
+        return run_benchmark_mode(&workload_path).await;
+    }
+
     info!("🦀 Iniciando ARBITRAGEXPLUS2025 Rust Engine...");
-    
+
     // Crear e inicializar motor
     let engine = RustArbitrageEngine::new()?;
     
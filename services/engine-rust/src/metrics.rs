@@ -0,0 +1,406 @@
+/**
+ * ============================================================================
+ * ARCHIVO: ./services/engine-rust/src/metrics.rs
+ * MÓDULO: Rust Engine
+ * ============================================================================
+ *
+ * 📥 ENTRADA:
+ *   - Eventos de ciclo del motor (duración, rutas generadas, profit por
+ *     ruta, tamaño de la config vigente)
+ *
+ * 🔄 TRANSFORMACIÓN:
+ *   FUNCIONES: MetricsRegistry::{record_cycle, record_error,
+ *   record_route_profit, set_config_counts, render_prometheus_text}
+ *
+ * 📤 SALIDA:
+ *   - Texto Prometheus servido en `/metrics` por `MetricsServerWorker`;
+ *     opcionalmente, el mismo snapshot empujado como OTLP/HTTP-JSON por
+ *     `OtlpPushWorker`
+ *
+ * 🔗 DEPENDENCIAS: (ninguna externa al motor más allá de tokio/reqwest)
+ *
+ * ============================================================================
+ */
+
+//! Métricas Prometheus/OpenTelemetry para que el monitoreo externo no tenga
+//! que hacer polling de `get_performance_metrics()`/`get_status()` sobre el
+//! proceso: `MetricsRegistry` acumula contadores/gauges/histogramas
+//! actualizados en cada ciclo del motor. `MetricsServerWorker` los sirve en
+//! texto Prometheus (estilo scrape) y, si `otlp_endpoint` está configurado,
+//! `OtlpPushWorker` empuja el mismo snapshot periódicamente (estilo push).
+//! Ambos son `Worker`s más, registrados en el mismo `BackgroundRunner` que
+//! el resto de los jobs de larga duración del motor.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use log::{error, info, warn};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::worker::{Worker, WorkerState, WorkerStatus};
+
+/// Cubetas (ms) del histograma de duración de ciclo.
+const CYCLE_TIME_BUCKETS_MS: &[f64] = &[10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0];
+
+/// Cubetas (USD) del histograma de profit neto por ruta.
+const ROUTE_PROFIT_BUCKETS_USD: &[f64] = &[0.0, 1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0];
+
+/// Histograma Prometheus de cubetas fijas: cada cubeta es un contador
+/// acumulativo ("observaciones <= límite"), más `sum`/`count` para que el
+/// collector pueda derivar el promedio.
+struct Histogram {
+    bounds: &'static [f64],
+    bucket_counts: Vec<AtomicU64>,
+    sum_milli_units: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            bucket_counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_milli_units: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Registra una observación: incrementa toda cubeta cuyo límite la cubra
+    /// (incluida la cubeta implícita `+Inf` al final).
+    fn observe(&self, value: f64) {
+        for (i, bound) in self.bounds.iter().enumerate() {
+            if value <= *bound {
+                self.bucket_counts[i].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.bucket_counts[self.bounds.len()].fetch_add(1, Ordering::Relaxed);
+        // Guardamos la suma en mili-unidades para acumular en un entero sin
+        // perder precisión decimal observación a observación.
+        self.sum_milli_units.fetch_add((value * 1000.0).round() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self, name: &str, help: &str, out: &mut String) {
+        out.push_str(&format!("# HELP {name} {help}\n"));
+        out.push_str(&format!("# TYPE {name} histogram\n"));
+        for (i, bound) in self.bounds.iter().enumerate() {
+            let count = self.bucket_counts[i].load(Ordering::Relaxed);
+            out.push_str(&format!("{name}_bucket{{le=\"{bound}\"}} {count}\n"));
+        }
+        let inf_count = self.bucket_counts[self.bounds.len()].load(Ordering::Relaxed);
+        out.push_str(&format!("{name}_bucket{{le=\"+Inf\"}} {inf_count}\n"));
+        let sum = self.sum_milli_units.load(Ordering::Relaxed) as f64 / 1000.0;
+        out.push_str(&format!("{name}_sum {sum}\n"));
+        out.push_str(&format!("{name}_count {}\n", self.count.load(Ordering::Relaxed)));
+    }
+}
+
+/// Registro central de métricas del motor. Pensado para vivir detrás de un
+/// solo `Arc<MetricsRegistry>` compartido entre `MainLoopWorker` (que
+/// escribe en cada ciclo), `MetricsServerWorker` (que lo sirve por HTTP) y
+/// `OtlpPushWorker` (que lo empuja). Todos los campos son atómicos: no hace
+/// falta ningún `Mutex` porque cada observación es independiente.
+pub struct MetricsRegistry {
+    total_cycles: AtomicU64,
+    total_routes_generated: AtomicU64,
+    cycle_errors_total: AtomicU64,
+    blockchains_count: AtomicU64,
+    dexes_count: AtomicU64,
+    assets_count: AtomicU64,
+    pools_count: AtomicU64,
+    cycle_time_ms: Histogram,
+    route_net_profit_usd: Histogram,
+}
+
+impl MetricsRegistry {
+    pub fn new() -> Self {
+        Self {
+            total_cycles: AtomicU64::new(0),
+            total_routes_generated: AtomicU64::new(0),
+            cycle_errors_total: AtomicU64::new(0),
+            blockchains_count: AtomicU64::new(0),
+            dexes_count: AtomicU64::new(0),
+            assets_count: AtomicU64::new(0),
+            pools_count: AtomicU64::new(0),
+            cycle_time_ms: Histogram::new(CYCLE_TIME_BUCKETS_MS),
+            route_net_profit_usd: Histogram::new(ROUTE_PROFIT_BUCKETS_USD),
+        }
+    }
+
+    /// Registra un ciclo de arbitraje que terminó sin error: cuenta el
+    /// ciclo, cuántas rutas generó y cuánto tardó. Los ciclos con error se
+    /// registran solo vía `record_error`.
+    pub fn record_cycle(&self, cycle_time_ms: f64, routes_generated: usize) {
+        self.total_cycles.fetch_add(1, Ordering::Relaxed);
+        self.total_routes_generated.fetch_add(routes_generated as u64, Ordering::Relaxed);
+        self.cycle_time_ms.observe(cycle_time_ms);
+    }
+
+    /// Registra que un ciclo terminó en error.
+    pub fn record_error(&self) {
+        self.cycle_errors_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Registra el profit neto esperado de una ruta individual del ciclo.
+    pub fn record_route_profit(&self, net_profit_usd: f64) {
+        self.route_net_profit_usd.observe(net_profit_usd.max(0.0));
+    }
+
+    /// Actualiza los gauges de tamaño de la config vigente; se llama cada
+    /// vez que `freeze_and_swap`/`rollback_to` cambian qué snapshot sirve.
+    pub fn set_config_counts(&self, blockchains: usize, dexes: usize, assets: usize, pools: usize) {
+        self.blockchains_count.store(blockchains as u64, Ordering::Relaxed);
+        self.dexes_count.store(dexes as u64, Ordering::Relaxed);
+        self.assets_count.store(assets as u64, Ordering::Relaxed);
+        self.pools_count.store(pools as u64, Ordering::Relaxed);
+    }
+
+    /// Serializa todas las métricas en formato de texto Prometheus
+    /// (exposition format v0.0.4), listo para el body de `/metrics`.
+    pub fn render_prometheus_text(&self) -> String {
+        let mut out = String::new();
+
+        render_counter(&mut out, "arbitrage_engine_cycles_total", "Ciclos de arbitraje completados", self.total_cycles.load(Ordering::Relaxed));
+        render_counter(&mut out, "arbitrage_engine_routes_generated_total", "Rutas de arbitraje generadas", self.total_routes_generated.load(Ordering::Relaxed));
+        render_counter(&mut out, "arbitrage_engine_cycle_errors_total", "Ciclos de arbitraje que terminaron en error", self.cycle_errors_total.load(Ordering::Relaxed));
+
+        render_gauge(&mut out, "arbitrage_engine_blockchains", "Blockchains en la config vigente", self.blockchains_count.load(Ordering::Relaxed));
+        render_gauge(&mut out, "arbitrage_engine_dexes", "DEXes en la config vigente", self.dexes_count.load(Ordering::Relaxed));
+        render_gauge(&mut out, "arbitrage_engine_assets", "Assets en la config vigente", self.assets_count.load(Ordering::Relaxed));
+        render_gauge(&mut out, "arbitrage_engine_pools", "Pools en la config vigente", self.pools_count.load(Ordering::Relaxed));
+
+        self.cycle_time_ms.render("arbitrage_engine_cycle_time_ms", "Duración de un ciclo de arbitraje, en milisegundos", &mut out);
+        self.route_net_profit_usd.render("arbitrage_engine_route_net_profit_usd", "Profit neto esperado por ruta generada, en USD", &mut out);
+
+        out
+    }
+
+    /// Snapshot liviano de los contadores/gauges, usado por `OtlpPushWorker`
+    /// para armar su payload sin reexponer los átomos internos (los
+    /// histogramas no tienen un equivalente de punto único útil en un push
+    /// periódico, así que se exportan solo por el lado Prometheus/scrape).
+    fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            total_cycles: self.total_cycles.load(Ordering::Relaxed),
+            total_routes_generated: self.total_routes_generated.load(Ordering::Relaxed),
+            cycle_errors_total: self.cycle_errors_total.load(Ordering::Relaxed),
+            blockchains_count: self.blockchains_count.load(Ordering::Relaxed),
+            dexes_count: self.dexes_count.load(Ordering::Relaxed),
+            assets_count: self.assets_count.load(Ordering::Relaxed),
+            pools_count: self.pools_count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn render_counter(out: &mut String, name: &str, help: &str, value: u64) {
+    out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n"));
+}
+
+fn render_gauge(out: &mut String, name: &str, help: &str, value: u64) {
+    out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"));
+}
+
+struct MetricsSnapshot {
+    total_cycles: u64,
+    total_routes_generated: u64,
+    cycle_errors_total: u64,
+    blockchains_count: u64,
+    dexes_count: u64,
+    assets_count: u64,
+    pools_count: u64,
+}
+
+impl MetricsSnapshot {
+    /// Payload mínimo con el shape de un `ExportMetricsServiceRequest` OTLP
+    /// (codificado en JSON vía OTLP/HTTP, no protobuf): un resource, un
+    /// scope, y un gauge `asInt` por métrica. Alcanza para que un collector
+    /// OTLP/HTTP lo acepte sin arrastrar `tonic`/`prost` como dependencia
+    /// nueva solo para esto.
+    fn to_otlp_json(&self) -> serde_json::Value {
+        let now_unix_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+
+        let gauge = |name: &str, value: u64| {
+            serde_json::json!({
+                "name": name,
+                "gauge": {
+                    "dataPoints": [{
+                        "timeUnixNano": now_unix_nanos.to_string(),
+                        "asInt": value.to_string(),
+                    }]
+                }
+            })
+        };
+
+        serde_json::json!({
+            "resourceMetrics": [{
+                "resource": {
+                    "attributes": [{
+                        "key": "service.name",
+                        "value": {"stringValue": "arbitragexplus2025-engine-rust"}
+                    }]
+                },
+                "scopeMetrics": [{
+                    "scope": {"name": "arbitrage_engine"},
+                    "metrics": [
+                        gauge("arbitrage_engine_cycles_total", self.total_cycles),
+                        gauge("arbitrage_engine_routes_generated_total", self.total_routes_generated),
+                        gauge("arbitrage_engine_cycle_errors_total", self.cycle_errors_total),
+                        gauge("arbitrage_engine_blockchains", self.blockchains_count),
+                        gauge("arbitrage_engine_dexes", self.dexes_count),
+                        gauge("arbitrage_engine_assets", self.assets_count),
+                        gauge("arbitrage_engine_pools", self.pools_count),
+                    ]
+                }]
+            }]
+        })
+    }
+}
+
+/// Sirve `MetricsRegistry` como texto Prometheus por HTTP. No parsea el
+/// request entrante más allá de drenarlo: como la mayoría de los exporters
+/// de un solo propósito, responde el mismo body sin importar el path.
+pub struct MetricsServerWorker {
+    addr: String,
+    listener: Option<TcpListener>,
+    registry: Arc<MetricsRegistry>,
+    last_error: Option<String>,
+}
+
+impl MetricsServerWorker {
+    pub fn new(addr: String, registry: Arc<MetricsRegistry>) -> Self {
+        Self {
+            addr,
+            listener: None,
+            registry,
+            last_error: None,
+        }
+    }
+
+    async fn write_response(stream: &mut TcpStream, body: &str) -> std::io::Result<()> {
+        let mut drain_buf = [0u8; 512];
+        let _ = stream.read(&mut drain_buf).await;
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.flush().await
+    }
+}
+
+#[async_trait]
+impl Worker for MetricsServerWorker {
+    fn name(&self) -> &str {
+        "metrics_server"
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        if self.listener.is_none() {
+            match TcpListener::bind(&self.addr).await {
+                Ok(listener) => {
+                    info!("📈 Métricas Prometheus disponibles en http://{}/metrics", self.addr);
+                    self.listener = Some(listener);
+                }
+                Err(e) => {
+                    error!("❌ No se pudo bindear el servidor de métricas en {}: {}", self.addr, e);
+                    self.last_error = Some(e.to_string());
+                    return WorkerState::Dead(e.to_string());
+                }
+            }
+        }
+
+        let listener = self.listener.as_ref().unwrap();
+        let (mut stream, _peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                self.last_error = Some(e.to_string());
+                return WorkerState::Active;
+            }
+        };
+
+        let body = self.registry.render_prometheus_text();
+        if let Err(e) = Self::write_response(&mut stream, &body).await {
+            self.last_error = Some(e.to_string());
+        }
+
+        WorkerState::Active
+    }
+
+    fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            name: self.name().to_string(),
+            state: WorkerState::Active,
+            progress: Some(format!("escuchando en {}", self.addr)),
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+/// Empuja el snapshot de `MetricsRegistry` como OTLP/HTTP-JSON a un
+/// collector externo, cada `push_interval`. Solo se registra como worker si
+/// `otlp_endpoint` está configurado; si un push falla no tira el motor,
+/// solo lo reporta en `last_error` y reintenta en el próximo intervalo.
+pub struct OtlpPushWorker {
+    endpoint: String,
+    push_interval: Duration,
+    registry: Arc<MetricsRegistry>,
+    client: reqwest::Client,
+    last_error: Option<String>,
+}
+
+impl OtlpPushWorker {
+    pub fn new(endpoint: String, push_interval: Duration, registry: Arc<MetricsRegistry>) -> Self {
+        Self {
+            endpoint,
+            push_interval,
+            registry,
+            client: reqwest::Client::new(),
+            last_error: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Worker for OtlpPushWorker {
+    fn name(&self) -> &str {
+        "otlp_push"
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        tokio::time::sleep(self.push_interval).await;
+
+        let payload = self.registry.snapshot().to_otlp_json();
+
+        match self.client.post(&self.endpoint).json(&payload).send().await {
+            Ok(response) if response.status().is_success() => {
+                self.last_error = None;
+            }
+            Ok(response) => {
+                self.last_error = Some(format!("collector OTLP devolvió {}", response.status()));
+            }
+            Err(e) => {
+                warn!("⚠️ Push OTLP a {} falló: {}", self.endpoint, e);
+                self.last_error = Some(e.to_string());
+            }
+        }
+
+        WorkerState::Active
+    }
+
+    fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            name: self.name().to_string(),
+            state: WorkerState::Active,
+            progress: Some(format!("push cada {:?} a {}", self.push_interval, self.endpoint)),
+            last_error: self.last_error.clone(),
+        }
+    }
+}
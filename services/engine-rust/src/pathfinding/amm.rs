@@ -0,0 +1,748 @@
+//! Modelo de precio de pools constant-product (estilo Uniswap V2)
+//!
+//! Los pathfinders de 2-DEX y 3-DEX encadenaban precios "flat" (`TokenPair.price`)
+//! para estimar el profit de una ruta, lo que ignora que cada swap mueve el precio
+//! en contra del trader según la profundidad real del pool. Este módulo calcula el
+//! output real de un swap dado `reserve_in`/`reserve_out`, para que el profit de una
+//! ruta refleje el tamaño de trade ejecutable y no solo el spread de precios.
+//!
+//! `constant_product_output`/`RouteSimulator` operan en `f64`: suficientes
+//! para escanear candidatos y elegir el monto óptimo (la búsqueda ternaria de
+//! `find_optimal_amount` evalúa miles de puntos, donde la precisión sub-wei
+//! no cambia qué ruta gana). `constant_product_output_exact`/
+//! `ExactRouteSimulator` repiten la misma fórmula en aritmética `U256`
+//! (mul-before-div, igual que `pricing::dex_pricing::ConstantProductCurve`)
+//! para la verificación final, de una sola ruta ya elegida, que decide si se
+//! somete la transacción on-chain — ahí el redondeo de `f64` sí es
+//! inaceptable.
+
+use std::collections::HashMap;
+
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::amounts::TokenAmount;
+
+/// Output de un swap constant-product: `y = (x·(1-f)·Rout) / (Rin + x·(1-f))`
+pub fn constant_product_output(
+    amount_in: f64,
+    reserve_in: f64,
+    reserve_out: f64,
+    fee: f64,
+) -> f64 {
+    if amount_in <= 0.0 || reserve_in <= 0.0 || reserve_out <= 0.0 {
+        return 0.0;
+    }
+
+    let amount_in_after_fee = amount_in * (1.0 - fee);
+    (amount_in_after_fee * reserve_out) / (reserve_in + amount_in_after_fee)
+}
+
+/// Impacto de precio realizado frente al precio spot del pool:
+/// `1 - (y/x)/(Rout/Rin)`
+pub fn price_impact(amount_in: f64, amount_out: f64, reserve_in: f64, reserve_out: f64) -> f64 {
+    if amount_in <= 0.0 || reserve_in <= 0.0 || reserve_out <= 0.0 {
+        return 0.0;
+    }
+
+    let spot_price = reserve_out / reserve_in;
+    let realized_price = amount_out / amount_in;
+    1.0 - (realized_price / spot_price)
+}
+
+/// Curva que describe cómo un pool cotiza un swap. `ConstantProduct` (el
+/// default) cubre Uniswap/Sushiswap-style AMMs vía `constant_product_output`;
+/// `Stable` cubre pools de Curve, cuyo invariante rinde mucho menos slippage
+/// cerca del peg y necesita la fórmula de `stable_swap_output` en su lugar.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum PoolKind {
+    #[default]
+    ConstantProduct,
+    /// `amplification` es el parámetro `A` del invariante StableSwap: más
+    /// alto implica más liquidez concentrada cerca del peg 1:1.
+    Stable { amplification: f64 },
+}
+
+/// Resuelve `D` del invariante StableSwap de Curve para un pool de 2 activos
+/// vía iteración de Newton:
+/// `D_{k+1} = (A·n²·S + n·D_P)·D_k / ((A·n² - 1)·D_k + (n+1)·D_P)`
+/// donde `D_P = D^(n+1)/(n^n·x0·x1)`, `n = 2`. Converge a ±1 unidad o agota
+/// 255 iteraciones (mismo límite que usa Curve en producción).
+fn stable_get_d(reserve_a: f64, reserve_b: f64, amplification: f64) -> Option<f64> {
+    let s = reserve_a + reserve_b;
+    if s <= 0.0 {
+        return None;
+    }
+
+    let ann = amplification * 4.0; // A * n^2, n = 2
+    let mut d = s;
+
+    for _ in 0..255 {
+        // D_P = D^3 / (4 * x0 * x1), n = 2
+        let d_p = d * d * d / (4.0 * reserve_a * reserve_b);
+        let d_prev = d;
+        d = (ann * s + 2.0 * d_p) * d / ((ann - 1.0) * d + 3.0 * d_p);
+
+        if (d - d_prev).abs() <= 1.0 {
+            return Some(d);
+        }
+    }
+
+    Some(d)
+}
+
+/// Resuelve la nueva reserva de salida `y` que mantiene el invariante
+/// StableSwap tras mover la reserva de entrada a `new_reserve_in`, vía
+/// Newton sobre `y = (y² + c) / (2y + b - D)`, especializado a `n = 2`
+/// (una sola reserva de salida). Converge a ±1 unidad o agota 255
+/// iteraciones.
+fn stable_get_y(new_reserve_in: f64, amplification: f64, d: f64) -> Option<f64> {
+    if new_reserve_in <= 0.0 || d <= 0.0 {
+        return None;
+    }
+
+    let ann = amplification * 4.0; // A * n^2, n = 2
+    let c = (d * d * d) / (new_reserve_in * 4.0 * ann);
+    let b = new_reserve_in + d / ann;
+
+    let mut y = d;
+    for _ in 0..255 {
+        let y_prev = y;
+        y = (y * y + c) / (2.0 * y + b - d);
+
+        if (y - y_prev).abs() <= 1.0 {
+            return Some(y);
+        }
+    }
+
+    Some(y)
+}
+
+/// Output de un swap en un pool StableSwap (estilo Curve) de 2 activos:
+/// resuelve `D` a partir de las reservas actuales, mueve la reserva de
+/// entrada por `dx` (después de fee) y resuelve la nueva reserva de salida
+/// `y` sobre el mismo invariante; el output es `reserve_out - y`. Cerca del
+/// peg esto rinde mucho menos slippage que `constant_product_output` para el
+/// mismo tamaño de trade. Devuelve `None` si las reservas/amplificación son
+/// inválidas o el invariante no converge a un output positivo.
+pub fn stable_swap_output(
+    amount_in: f64,
+    reserve_in: f64,
+    reserve_out: f64,
+    amplification: f64,
+    fee: f64,
+) -> Option<f64> {
+    if amount_in <= 0.0 || reserve_in <= 0.0 || reserve_out <= 0.0 || amplification <= 0.0 {
+        return None;
+    }
+
+    let d = stable_get_d(reserve_in, reserve_out, amplification)?;
+
+    let amount_in_after_fee = amount_in * (1.0 - fee);
+    let new_reserve_in = reserve_in + amount_in_after_fee;
+
+    let new_reserve_out = stable_get_y(new_reserve_in, amplification, d)?;
+    let amount_out = reserve_out - new_reserve_out;
+
+    if amount_out <= 0.0 {
+        return None;
+    }
+
+    Some(amount_out)
+}
+
+/// Despacha la cotización de un swap según la curva del pool, para que el
+/// código de arbitraje no tenga que `match`ear `PoolKind` manualmente en
+/// cada punto donde cotiza contra un pool. `XykPool`/`StablePool` son
+/// wrappers delgados sobre `constant_product_output`/`stable_swap_output` —
+/// no reimplementan la fórmula, solo eligen cuál usar.
+pub trait PoolMath {
+    /// Output de swapear `amount_in` contra `(reserve_in, reserve_out)` a
+    /// `fee` (fracción, no bps). `None` si la curva no converge a un output
+    /// positivo para estas reservas (relevante sobre todo para
+    /// `StablePool`; `XykPool` solo rinde `0.0` en ese caso, nunca `None`).
+    fn amount_out(&self, reserve_in: f64, reserve_out: f64, amount_in: f64, fee: f64) -> Option<f64>;
+}
+
+/// Curva constant-product (Uniswap V2-style).
+pub struct XykPool;
+
+impl PoolMath for XykPool {
+    fn amount_out(&self, reserve_in: f64, reserve_out: f64, amount_in: f64, fee: f64) -> Option<f64> {
+        Some(constant_product_output(amount_in, reserve_in, reserve_out, fee))
+    }
+}
+
+/// Curva StableSwap (Curve-style) de 2 activos con amplificación `A` fija.
+pub struct StablePool {
+    pub amplification: f64,
+}
+
+impl PoolMath for StablePool {
+    fn amount_out(&self, reserve_in: f64, reserve_out: f64, amount_in: f64, fee: f64) -> Option<f64> {
+        stable_swap_output(amount_in, reserve_in, reserve_out, self.amplification, fee)
+    }
+}
+
+/// Resuelve el `PoolMath` concreto de un `PoolKind`, como caja dinámica para
+/// que el código de arbitraje pueda cotizar un pool sin conocer su curva de
+/// antemano (p.ej. iterando pools de `Pool::pool_kind` heterogéneos).
+pub fn pool_math(kind: PoolKind) -> Box<dyn PoolMath> {
+    match kind {
+        PoolKind::ConstantProduct => Box::new(XykPool),
+        PoolKind::Stable { amplification } => Box::new(StablePool { amplification }),
+    }
+}
+
+/// Busca el `amount_in` que maximiza `profit_fn` (p.ej. el profit neto de una
+/// ruta completa) mediante búsqueda ternaria sobre `[min_amount, max_amount]`.
+///
+/// El profit en función del input de una ruta constant-product es unimodal:
+/// crece mientras el trade es chico y el spread domina, y cae una vez que el
+/// slippage de la curva se come el spread, así que no hace falta un solver
+/// genérico. `profit_fn` puede devolver `None` (p.ej. `calculate_route_profit`
+/// descartando un hop sin reservas) para un `amount_in` dado; esos puntos se
+/// tratan como el peor profit posible para que la búsqueda converja igual
+/// hacia la región válida. Devuelve `None` si ningún punto evaluado es válido.
+pub fn ternary_search_optimal_amount(
+    min_amount: f64,
+    max_amount: f64,
+    tolerance: f64,
+    profit_fn: impl Fn(f64) -> Option<f64>,
+) -> Option<(f64, f64)> {
+    if min_amount <= 0.0 || max_amount <= min_amount || tolerance <= 0.0 {
+        return None;
+    }
+
+    let eval = |x: f64| profit_fn(x).unwrap_or(f64::NEG_INFINITY);
+
+    let mut lo = min_amount;
+    let mut hi = max_amount;
+
+    while hi - lo > tolerance {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+
+        if eval(m1) < eval(m2) {
+            lo = m1;
+        } else {
+            hi = m2;
+        }
+    }
+
+    let best_amount = (lo + hi) / 2.0;
+    let best_profit = eval(best_amount);
+
+    if best_profit.is_finite() {
+        Some((best_amount, best_profit))
+    } else {
+        None
+    }
+}
+
+/// Fast path de forma cerrada para el caso especial de exactamente dos pools
+/// constant-product encadenados (`x -> pool1 -> y -> pool2 -> z`, profit =
+/// `z - x`), con la misma fee `fee` en ambos hops. Evita la búsqueda ternaria
+/// cuando la ruta candidata es literalmente un ciclo de 2 pools.
+///
+/// `dx* = (sqrt(Rin1·Rout1·Rin2·Rout2)·(1-f) - Rin1·Rin2) / ((1-f)·(Rin2 + (1-f)·Rout1))`
+///
+/// Devuelve `None` si las reservas no son válidas o el óptimo resultante no es
+/// positivo (no hay arbitraje rentable entre estos dos pools).
+pub fn two_hop_optimal_amount_in(
+    reserve_in1: f64,
+    reserve_out1: f64,
+    reserve_in2: f64,
+    reserve_out2: f64,
+    fee: f64,
+) -> Option<f64> {
+    if reserve_in1 <= 0.0 || reserve_out1 <= 0.0 || reserve_in2 <= 0.0 || reserve_out2 <= 0.0 {
+        return None;
+    }
+    if !(0.0..1.0).contains(&fee) {
+        return None;
+    }
+
+    let gamma = 1.0 - fee;
+    let numerator = (reserve_in1 * reserve_out1 * reserve_in2 * reserve_out2).sqrt() * gamma
+        - reserve_in1 * reserve_in2;
+    let denominator = gamma * (reserve_in2 + gamma * reserve_out1);
+
+    if denominator <= 0.0 {
+        return None;
+    }
+
+    let dx = numerator / denominator;
+    if dx > 0.0 && dx.is_finite() {
+        Some(dx)
+    } else {
+        None
+    }
+}
+
+/// Simula una ruta multi-hop manteniendo el estado de reservas de cada pool
+/// tocado. Si una ruta revisita el mismo pool (mismo `dex_id` + par de
+/// tokens) en un hop posterior, ese hop ve las reservas ya movidas por el
+/// hop anterior, no las reservas originales — sin esto, una ruta que pasa
+/// dos veces por el mismo pool sobreestimaría su profit real.
+#[derive(Debug, Default)]
+pub struct RouteSimulator {
+    reserves: HashMap<(String, String, String), (f64, f64)>,
+}
+
+impl RouteSimulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ejecuta un swap contra el pool `(dex_id, token_in, token_out)`. La
+    /// primera vez que se visita ese pool en la ruta se usan
+    /// `initial_reserve_in`/`initial_reserve_out` (las reservas reales del
+    /// pool); en visitas posteriores se usa el estado dejado por el hop
+    /// anterior. Devuelve `None` si las reservas son cero/negativas (pool
+    /// sin liquidez real o dato faltante) o el output resultante es cero:
+    /// la ruta completa debe descartarse, no tratarse como profit cero.
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap(
+        &mut self,
+        dex_id: &str,
+        token_in: &str,
+        token_out: &str,
+        initial_reserve_in: f64,
+        initial_reserve_out: f64,
+        amount_in: f64,
+        fee: f64,
+    ) -> Option<f64> {
+        let key = (dex_id.to_string(), token_in.to_string(), token_out.to_string());
+        let (reserve_in, reserve_out) = *self
+            .reserves
+            .entry(key.clone())
+            .or_insert((initial_reserve_in, initial_reserve_out));
+
+        if reserve_in <= 0.0 || reserve_out <= 0.0 || amount_in <= 0.0 {
+            return None;
+        }
+
+        let amount_out = constant_product_output(amount_in, reserve_in, reserve_out, fee);
+        if amount_out <= 0.0 {
+            return None;
+        }
+
+        let amount_in_after_fee = amount_in * (1.0 - fee);
+        self.reserves
+            .insert(key, (reserve_in + amount_in_after_fee, reserve_out - amount_out));
+
+        Some(amount_out)
+    }
+
+    /// Igual que `swap`, pero eligiendo la curva del pool según `pool_kind`
+    /// en vez de asumir siempre constant-product: los pools `Stable`
+    /// (Curve) cotizan vía `stable_swap_output`, que da mucho menos
+    /// slippage cerca del peg. El estado de reservas por pool se mantiene
+    /// igual en ambos casos, así que una ruta puede revisitar el mismo pool
+    /// sin importar su curva.
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap_with_kind(
+        &mut self,
+        dex_id: &str,
+        token_in: &str,
+        token_out: &str,
+        initial_reserve_in: f64,
+        initial_reserve_out: f64,
+        amount_in: f64,
+        fee: f64,
+        pool_kind: PoolKind,
+    ) -> Option<f64> {
+        let key = (dex_id.to_string(), token_in.to_string(), token_out.to_string());
+        let (reserve_in, reserve_out) = *self
+            .reserves
+            .entry(key.clone())
+            .or_insert((initial_reserve_in, initial_reserve_out));
+
+        if reserve_in <= 0.0 || reserve_out <= 0.0 || amount_in <= 0.0 {
+            return None;
+        }
+
+        let amount_out = match pool_kind {
+            PoolKind::ConstantProduct => {
+                let amount_out = constant_product_output(amount_in, reserve_in, reserve_out, fee);
+                if amount_out <= 0.0 {
+                    return None;
+                }
+                amount_out
+            }
+            PoolKind::Stable { amplification } => {
+                stable_swap_output(amount_in, reserve_in, reserve_out, amplification, fee)?
+            }
+        };
+
+        let amount_in_after_fee = amount_in * (1.0 - fee);
+        self.reserves
+            .insert(key, (reserve_in + amount_in_after_fee, reserve_out - amount_out));
+
+        Some(amount_out)
+    }
+}
+
+/// Output exacto de un swap constant-product en aritmética `U256`
+/// (mul-before-div, sin pasar por `f64` en ningún paso), igual fórmula que
+/// `pricing::dex_pricing::ConstantProductCurve::swap` pero operando
+/// directamente sobre unidades base ya conocidas (sin reconvertir desde
+/// `f64`). `fee_bps` son basis points (30 = 0.3%). Devuelve `None` si
+/// `amount_in` y `reserve_in` no coinciden en `decimals`, alguna reserva es
+/// cero, el output es cero, o la aritmética desborda `U256`.
+pub fn constant_product_output_exact(
+    amount_in: TokenAmount,
+    reserve_in: TokenAmount,
+    reserve_out: TokenAmount,
+    fee_bps: u32,
+) -> Option<TokenAmount> {
+    if amount_in.decimals() != reserve_in.decimals() || reserve_in.raw().is_zero() || reserve_out.raw().is_zero() {
+        return None;
+    }
+
+    let fee_num = U256::from(10_000u32.saturating_sub(fee_bps));
+    let fee_den = U256::from(10_000u32);
+
+    let input_with_fee = amount_in.raw().checked_mul(fee_num)?;
+    let numerator = input_with_fee.checked_mul(reserve_out.raw())?;
+    let denominator = reserve_in
+        .raw()
+        .checked_mul(fee_den)?
+        .checked_add(input_with_fee)?;
+
+    if denominator.is_zero() {
+        return None;
+    }
+
+    let output_units = numerator / denominator;
+    if output_units.is_zero() {
+        return None;
+    }
+
+    Some(TokenAmount::from_raw(output_units, reserve_out.decimals()))
+}
+
+/// Equivalente exacto de `RouteSimulator`, en aritmética `U256` vía
+/// `constant_product_output_exact`. Pensado para verificar, de una sola ruta
+/// y monto ya elegidos por la búsqueda ternaria en `f64`, el resultado
+/// exacto antes de decidir si se somete la transacción on-chain — no para
+/// escanear candidatos (para eso `RouteSimulator` es más barato).
+#[derive(Debug, Default)]
+pub struct ExactRouteSimulator {
+    reserves: HashMap<(String, String, String), (TokenAmount, TokenAmount)>,
+}
+
+impl ExactRouteSimulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Misma semántica que `RouteSimulator::swap`: la primera visita a un
+    /// pool usa las reservas iniciales dadas; una visita posterior al mismo
+    /// `(dex_id, token_in, token_out)` ve las reservas ya movidas.
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap(
+        &mut self,
+        dex_id: &str,
+        token_in: &str,
+        token_out: &str,
+        initial_reserve_in: TokenAmount,
+        initial_reserve_out: TokenAmount,
+        amount_in: TokenAmount,
+        fee_bps: u32,
+    ) -> Option<TokenAmount> {
+        let key = (dex_id.to_string(), token_in.to_string(), token_out.to_string());
+        let (reserve_in, reserve_out) = *self
+            .reserves
+            .entry(key.clone())
+            .or_insert((initial_reserve_in, initial_reserve_out));
+
+        let amount_out = constant_product_output_exact(amount_in, reserve_in, reserve_out, fee_bps)?;
+
+        let fee_num = U256::from(10_000u32.saturating_sub(fee_bps));
+        let fee_den = U256::from(10_000u32);
+        let amount_in_after_fee = TokenAmount::from_raw(
+            amount_in.raw().checked_mul(fee_num)? / fee_den,
+            amount_in.decimals(),
+        );
+
+        let new_reserve_in = reserve_in.checked_add(amount_in_after_fee)?;
+        let new_reserve_out = reserve_out.checked_sub(amount_out)?;
+        self.reserves.insert(key, (new_reserve_in, new_reserve_out));
+
+        Some(amount_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_product_output_matches_uniswap_formula() {
+        // Rin=1_000_000, Rout=2_000_000, fee=0.3%, x=1000
+        let output = constant_product_output(1000.0, 1_000_000.0, 2_000_000.0, 0.003);
+        let expected = (1000.0 * 0.997 * 2_000_000.0) / (1_000_000.0 + 1000.0 * 0.997);
+        assert!((output - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_constant_product_output_is_zero_for_empty_or_negative_input() {
+        assert_eq!(constant_product_output(0.0, 1_000_000.0, 2_000_000.0, 0.003), 0.0);
+        assert_eq!(constant_product_output(-10.0, 1_000_000.0, 2_000_000.0, 0.003), 0.0);
+        assert_eq!(constant_product_output(1000.0, 0.0, 2_000_000.0, 0.003), 0.0);
+    }
+
+    #[test]
+    fn test_price_impact_grows_with_trade_size_relative_to_reserves() {
+        let reserve_in = 1_000_000.0;
+        let reserve_out = 1_000_000.0;
+
+        let small_out = constant_product_output(100.0, reserve_in, reserve_out, 0.0);
+        let small_impact = price_impact(100.0, small_out, reserve_in, reserve_out);
+
+        let large_out = constant_product_output(500_000.0, reserve_in, reserve_out, 0.0);
+        let large_impact = price_impact(500_000.0, large_out, reserve_in, reserve_out);
+
+        assert!(small_impact >= 0.0);
+        assert!(large_impact > small_impact);
+    }
+
+    #[test]
+    fn test_route_simulator_rejects_zero_or_missing_reserves() {
+        let mut sim = RouteSimulator::new();
+        assert!(sim.swap("uniswap", "A", "B", 0.0, 1_000_000.0, 1000.0, 0.003).is_none());
+        assert!(sim.swap("uniswap", "A", "B", 1_000_000.0, 0.0, 1000.0, 0.003).is_none());
+    }
+
+    #[test]
+    fn test_route_simulator_updates_reserves_so_a_revisited_pool_sees_post_trade_state() {
+        let mut sim = RouteSimulator::new();
+
+        let first_out = sim
+            .swap("uniswap", "A", "B", 1_000_000.0, 1_000_000.0, 100_000.0, 0.0)
+            .unwrap();
+
+        // Mismo pool, mismo sentido, mismo monto: el segundo swap ya ve
+        // reservas movidas por el primero, así que debería rendir menos.
+        let second_out = sim
+            .swap("uniswap", "A", "B", 1_000_000.0, 1_000_000.0, 100_000.0, 0.0)
+            .unwrap();
+
+        assert!(second_out < first_out);
+    }
+
+    #[test]
+    fn test_route_simulator_keeps_distinct_pools_independent() {
+        let mut sim = RouteSimulator::new();
+
+        let out_a = sim
+            .swap("uniswap", "A", "B", 1_000_000.0, 1_000_000.0, 1000.0, 0.0)
+            .unwrap();
+        let out_b = sim
+            .swap("sushiswap", "A", "B", 1_000_000.0, 1_000_000.0, 1000.0, 0.0)
+            .unwrap();
+
+        // Pools distintos (dex_id distinto) con las mismas reservas
+        // iniciales no deben interferir entre sí.
+        assert!((out_a - out_b).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_route_simulator_swap_with_kind_uses_stable_curve_for_stable_pools() {
+        let mut sim = RouteSimulator::new();
+
+        let stable_out = sim
+            .swap_with_kind(
+                "curve",
+                "USDC",
+                "USDT",
+                5_000_000.0,
+                5_000_000.0,
+                100_000.0,
+                0.0004,
+                PoolKind::Stable { amplification: 100.0 },
+            )
+            .unwrap();
+
+        let constant_product_out = constant_product_output(100_000.0, 5_000_000.0, 5_000_000.0, 0.0004);
+
+        // Cerca del peg, la curva estable debe rendir más output que
+        // constant-product para el mismo tamaño de trade.
+        assert!(stable_out > constant_product_out);
+    }
+
+    #[test]
+    fn test_ternary_search_finds_the_profit_maximizing_amount() {
+        // Ciclo de 2 pools: cada punto evalúa el profit completo de la ruta.
+        let reserve_in1 = 1_000_000.0;
+        let reserve_out1 = 1_100_000.0;
+        let reserve_in2 = 1_000_000.0;
+        let reserve_out2 = 1_050_000.0;
+        let fee = 0.003;
+
+        let profit_fn = |x: f64| {
+            let y = constant_product_output(x, reserve_in1, reserve_out1, fee);
+            let z = constant_product_output(y, reserve_in2, reserve_out2, fee);
+            Some(z - x)
+        };
+
+        let (best_amount, best_profit) =
+            ternary_search_optimal_amount(1.0, 2_000_000.0, 0.01, profit_fn)
+                .expect("el ciclo es rentable para algún tamaño de trade");
+
+        // El óptimo no debe estar pegado a ninguno de los bordes de búsqueda.
+        assert!(best_amount > 1.0 && best_amount < 2_000_000.0);
+        assert!(best_profit > 0.0);
+
+        // Perturbar el monto óptimo en cualquier dirección debe rendir menos
+        // profit (es el máximo de una función unimodal).
+        let profit_minus = profit_fn(best_amount - 1000.0).unwrap();
+        let profit_plus = profit_fn(best_amount + 1000.0).unwrap();
+        assert!(best_profit >= profit_minus);
+        assert!(best_profit >= profit_plus);
+    }
+
+    #[test]
+    fn test_ternary_search_returns_none_when_every_point_is_invalid() {
+        let profit_fn = |_: f64| None;
+        assert!(ternary_search_optimal_amount(1.0, 1000.0, 0.01, profit_fn).is_none());
+    }
+
+    #[test]
+    fn test_two_hop_optimal_amount_in_matches_ternary_search() {
+        let reserve_in1 = 1_000_000.0;
+        let reserve_out1 = 1_100_000.0;
+        let reserve_in2 = 1_000_000.0;
+        let reserve_out2 = 1_050_000.0;
+        let fee = 0.003;
+
+        let closed_form = two_hop_optimal_amount_in(
+            reserve_in1,
+            reserve_out1,
+            reserve_in2,
+            reserve_out2,
+            fee,
+        )
+        .expect("reservas válidas y ciclo rentable");
+
+        let profit_fn = |x: f64| {
+            let y = constant_product_output(x, reserve_in1, reserve_out1, fee);
+            let z = constant_product_output(y, reserve_in2, reserve_out2, fee);
+            Some(z - x)
+        };
+        let (ternary_amount, _) =
+            ternary_search_optimal_amount(1.0, 2_000_000.0, 0.01, profit_fn).unwrap();
+
+        assert!((closed_form - ternary_amount).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_two_hop_optimal_amount_in_is_none_for_unprofitable_cycle() {
+        // Mismo precio en ambos pools (sin spread) y con fee: nunca es
+        // rentable, así que no hay tamaño de trade óptimo positivo.
+        assert!(two_hop_optimal_amount_in(1_000_000.0, 1_000_000.0, 1_000_000.0, 1_000_000.0, 0.003).is_none());
+    }
+
+    #[test]
+    fn test_constant_product_output_exact_matches_f64_within_rounding() {
+        let amount_in = TokenAmount::from_f64(1_000.0, 18).unwrap();
+        let reserve_in = TokenAmount::from_f64(1_000_000.0, 18).unwrap();
+        let reserve_out = TokenAmount::from_f64(1_100_000.0, 18).unwrap();
+
+        let exact = constant_product_output_exact(amount_in, reserve_in, reserve_out, 30).unwrap();
+        let approx = constant_product_output(1_000.0, 1_000_000.0, 1_100_000.0, 0.003);
+
+        assert!((exact.as_f64_lossy() - approx).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_constant_product_output_exact_rejects_mismatched_decimals_or_empty_reserves() {
+        let amount_in = TokenAmount::from_f64(1_000.0, 18).unwrap();
+        let reserve_in_wrong_decimals = TokenAmount::from_f64(1_000_000.0, 6).unwrap();
+        let reserve_out = TokenAmount::from_f64(1_100_000.0, 18).unwrap();
+        assert!(constant_product_output_exact(amount_in, reserve_in_wrong_decimals, reserve_out, 30).is_none());
+
+        let empty_reserve = TokenAmount::from_raw(U256::zero(), 18);
+        assert!(constant_product_output_exact(amount_in, empty_reserve, reserve_out, 30).is_none());
+    }
+
+    #[test]
+    fn test_exact_route_simulator_updates_reserves_so_a_revisited_pool_sees_post_trade_state() {
+        let mut sim = ExactRouteSimulator::new();
+        let reserve_in = TokenAmount::from_f64(1_000_000.0, 18).unwrap();
+        let reserve_out = TokenAmount::from_f64(1_000_000.0, 18).unwrap();
+        let amount_in = TokenAmount::from_f64(10_000.0, 18).unwrap();
+
+        let first_out = sim
+            .swap("uniswap", "A", "B", reserve_in, reserve_out, amount_in, 30)
+            .unwrap();
+        let second_out = sim
+            .swap("uniswap", "A", "B", reserve_in, reserve_out, amount_in, 30)
+            .unwrap();
+
+        // La segunda visita al mismo pool ve reservas ya movidas por la
+        // primera, así que debe recibir menos output por el mismo input.
+        assert!(second_out.raw() < first_out.raw());
+    }
+
+    #[test]
+    fn test_exact_route_simulator_rejects_zero_reserves() {
+        let mut sim = ExactRouteSimulator::new();
+        let zero = TokenAmount::from_raw(U256::zero(), 18);
+        let amount_in = TokenAmount::from_f64(10.0, 18).unwrap();
+        assert!(sim.swap("uniswap", "A", "B", zero, zero, amount_in, 30).is_none());
+    }
+
+    #[test]
+    fn test_stable_swap_output_has_far_less_slippage_than_constant_product_near_the_peg() {
+        // Pool balanceado de stablecoins (peg 1:1), A alto como en Curve.
+        let amount_in = 100_000.0;
+        let reserve_in = 5_000_000.0;
+        let reserve_out = 5_000_000.0;
+        let amplification = 100.0;
+
+        let stable_out = stable_swap_output(amount_in, reserve_in, reserve_out, amplification, 0.0004)
+            .expect("pool estable balanceado converge");
+        let constant_product_out =
+            constant_product_output(amount_in, reserve_in, reserve_out, 0.0004);
+
+        // Cerca del peg, StableSwap rinde casi 1:1 (mucho menos slippage que
+        // constant-product para el mismo tamaño de trade).
+        assert!(stable_out > constant_product_out);
+        assert!((stable_out - amount_in).abs() / amount_in < 0.001);
+    }
+
+    #[test]
+    fn test_stable_swap_output_rejects_invalid_inputs() {
+        assert!(stable_swap_output(0.0, 1_000_000.0, 1_000_000.0, 100.0, 0.0004).is_none());
+        assert!(stable_swap_output(1000.0, 0.0, 1_000_000.0, 100.0, 0.0004).is_none());
+        assert!(stable_swap_output(1000.0, 1_000_000.0, 1_000_000.0, 0.0, 0.0004).is_none());
+    }
+
+    #[test]
+    fn test_pool_math_dispatches_xyk_to_constant_product_formula() {
+        let math = pool_math(PoolKind::ConstantProduct);
+        let out = math.amount_out(1_000_000.0, 2_000_000.0, 1000.0, 0.003).unwrap();
+        let expected = constant_product_output(1000.0, 1_000_000.0, 2_000_000.0, 0.003);
+        assert!((out - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pool_math_dispatches_stable_to_stableswap_formula() {
+        let math = pool_math(PoolKind::Stable { amplification: 100.0 });
+        let out = math.amount_out(5_000_000.0, 5_000_000.0, 100_000.0, 0.0004).unwrap();
+        let expected = stable_swap_output(100_000.0, 5_000_000.0, 5_000_000.0, 100.0, 0.0004).unwrap();
+        assert!((out - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_stable_swap_output_converges_off_peg_too() {
+        // Pool desbalanceado: el invariante sigue debiendo converger a un
+        // output positivo y menor que la reserva de salida disponible.
+        let output = stable_swap_output(10_000.0, 3_000_000.0, 7_000_000.0, 50.0, 0.0004)
+            .expect("converge aunque el pool esté desbalanceado");
+        assert!(output > 0.0 && output < 7_000_000.0);
+    }
+}
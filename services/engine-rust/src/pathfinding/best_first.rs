@@ -0,0 +1,515 @@
+//! Búsqueda best-first (estilo Dijkstra) de la mejor ruta de arbitraje sobre
+//! el grafo de tokens, con penalización de aristas pluggable.
+//!
+//! `ThreeDexPathfinder::find_profitable_routes` enumera por fuerza bruta
+//! cada triple de DEXs y pesa confidence/complexity a 60/40 y 50/50 fijos.
+//! Este módulo lo reemplaza por una búsqueda best-first sobre el mismo tipo
+//! de grafo `token -> token` que usa `cycle_finder` (aristas con peso
+//! `-ln(effective_rate)`), pero permite al llamador inyectar un `EdgePenalty`
+//! propio (liquidez delgada, gas caro, pools con historial de fallos) y
+//! tunear el peso de liquidez/hop-count/gas vía `ScoringParams`, sin tocar
+//! `ThreeDexPathfinder::calculate_confidence`/`calculate_complexity`.
+//!
+//! A diferencia de `cycle_finder::find_profitable_cycles` (Bellman-Ford,
+//! exhaustivo dentro de su chain), esta búsqueda acota la frontera a
+//! `ScoringParams::max_frontier` nodos expandidos para latencia acotada: en
+//! grafos grandes puede devolver una ruta subóptima (o ninguna) en vez de
+//! garantizar la óptima global. Es una garantía distinta a propósito, no un
+//! bug: `cycle_finder` sigue siendo la opción exhaustiva cuando hace falta.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pathfinding::cycle_finder::PoolEdge;
+
+/// Penaliza una arista del grafo con un costo entero adicional al de
+/// `-ln(effective_rate)`, para que el llamador desvíe la búsqueda de pools
+/// que no quiere usar sin tener que editar el costo base. Los penalties se
+/// acumulan con `saturating_add` (ver `find_best_route`), así que un camino
+/// cuyo penalty desborda simplemente ordena último en la frontera en vez de
+/// hacer panic.
+pub trait EdgePenalty {
+    fn penalty(&self, edge: &PoolEdge) -> u32;
+}
+
+/// `EdgePenalty` por defecto: ningún costo adicional, el ranking queda
+/// puramente a cargo de `effective_rate`.
+pub struct NoPenalty;
+
+impl EdgePenalty for NoPenalty {
+    fn penalty(&self, _edge: &PoolEdge) -> u32 {
+        0
+    }
+}
+
+/// `EdgePenalty` de referencia que cubre el caso común (liquidez delgada +
+/// gas caro) para que no todo caller tenga que escribir el suyo. Liquidez y
+/// gas se indexan externamente porque `PoolEdge` no los trae: este módulo no
+/// le agrega campos para no acoplar `cycle_finder` a esta búsqueda.
+pub struct LiquidityGasPenalty<'a> {
+    /// Liquidez del pool en USD, indexada por `(dex_id, token_in, token_out)`.
+    /// Pools ausentes del mapa se tratan como liquidez cero (penalty máximo).
+    pub liquidity_usd: &'a HashMap<(String, String, String), f64>,
+    /// Costo de gas en USD de operar en cada DEX, indexado por `dex_id`.
+    pub gas_cost_usd: &'a HashMap<String, f64>,
+    pub params: ScoringParams,
+}
+
+impl<'a> EdgePenalty for LiquidityGasPenalty<'a> {
+    fn penalty(&self, edge: &PoolEdge) -> u32 {
+        let key = (edge.dex_id.clone(), edge.token_in.clone(), edge.token_out.clone());
+        let liquidity = self.liquidity_usd.get(&key).copied().unwrap_or(0.0);
+
+        // Liquidez delgada = penalty alto, proporcional al peso del caller.
+        let liquidity_penalty = if liquidity <= 0.0 {
+            u32::MAX
+        } else {
+            ((1_000_000.0 / liquidity) * self.params.liquidity_weight) as u32
+        };
+
+        let gas = self.gas_cost_usd.get(&edge.dex_id).copied().unwrap_or(0.0);
+        let gas_penalty = (gas * self.params.gas_weight) as u32;
+
+        liquidity_penalty.saturating_add(gas_penalty)
+    }
+}
+
+/// Pesos para balancear liquidez, número de hops y gas al puntuar la ruta
+/// finalmente encontrada, más el límite de frontera explorada. Vive aparte
+/// de `ThreeDexPathfinder::calculate_confidence`/`calculate_complexity`
+/// (esos solo aplican al camino de triples fijos) para que tunear esta
+/// búsqueda no implique editarlos.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoringParams {
+    pub liquidity_weight: f64,
+    pub hop_weight: f64,
+    pub gas_weight: f64,
+    /// Máximo de nodos de la frontera que se expanden antes de rendirse con
+    /// lo mejor encontrado hasta ese punto, para latencia acotada.
+    pub max_frontier: usize,
+}
+
+impl Default for ScoringParams {
+    fn default() -> Self {
+        Self {
+            liquidity_weight: 1.0,
+            hop_weight: 1.0,
+            gas_weight: 1.0,
+            max_frontier: 10_000,
+        }
+    }
+}
+
+/// Parámetros económicos de la búsqueda: cuánto capital simular, el costo
+/// de gas de la transacción y el profit neto mínimo para aceptar una ruta.
+/// Agrupados aparte para no inflar la firma de `find_best_route`.
+#[derive(Debug, Clone, Copy)]
+pub struct TradeBudget {
+    pub trade_amount_usd: f64,
+    pub gas_cost_usd: f64,
+    pub min_profit_usd: f64,
+}
+
+/// Ruta de arbitraje encontrada por `find_best_route`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BestFirstRoute {
+    pub chain: String,
+    pub dexes: Vec<String>,
+    pub tokens: Vec<String>,
+    pub gross_gain: f64,
+    pub expected_profit: f64,
+    pub net_profit: f64,
+    /// Suma de `EdgePenalty::penalty` de cada hop de la ruta, acumulada con
+    /// `saturating_add`.
+    pub total_penalty: u32,
+    /// Score de calidad de la ruta (más alto es mejor) combinando
+    /// `ScoringParams::hop_weight` contra el número de hops y
+    /// `liquidity_weight`/`gas_weight` contra `total_penalty`. No hay forma
+    /// de separar cuánto de `total_penalty` es liquidez vs. gas sin que
+    /// `EdgePenalty` devuelva un desglose, así que ambos pesos se aplican
+    /// juntos contra el mismo acumulador.
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cost(f64);
+
+impl Eq for Cost {}
+
+impl PartialOrd for Cost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+struct Frontier {
+    cost: Cost,
+    token: usize,
+    penalty_total: u32,
+    /// Índices, en `edges`, de las aristas recorridas desde `start_token`.
+    path: Vec<usize>,
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` es un max-heap: invertir para que el menor costo
+        // acumulado salga primero, como en Dijkstra.
+        other.cost.cmp(&self.cost)
+    }
+}
+
+struct Edge<'a> {
+    from: usize,
+    to: usize,
+    rate_cost: f64,
+    pool: &'a PoolEdge,
+}
+
+/// Busca la mejor ruta de arbitraje que cierra en `start_token` dentro de
+/// `chain`, vía best-first (Dijkstra) sobre el grafo `token -> token` que
+/// forman `pools`. El costo de cada arista es `-ln(effective_rate) +
+/// penalty.penalty(edge)`: el primer término favorece el mismo spread que
+/// `cycle_finder`, el segundo es el `EdgePenalty` que el llamador inyecta.
+///
+/// Cada camino explorado es simple (no revisita un token, salvo para cerrar
+/// el ciclo de vuelta a `start_token`), lo que junto con
+/// `params.max_frontier` garantiza terminación aun con aristas de costo
+/// negativo (pools rentables), donde Dijkstra clásico no garantiza
+/// optimalidad. Devuelve `None` si ningún ciclo explorado deja
+/// `net_profit > min_profit_usd`.
+pub fn find_best_route(
+    pools: &[PoolEdge],
+    chain: &str,
+    start_token: &str,
+    budget: &TradeBudget,
+    penalty: &dyn EdgePenalty,
+    params: &ScoringParams,
+) -> Option<BestFirstRoute> {
+    let pools: Vec<&PoolEdge> = pools.iter().filter(|p| p.chain == chain).collect();
+
+    let mut tokens: Vec<String> = Vec::new();
+    for pool in &pools {
+        if !tokens.contains(&pool.token_in) {
+            tokens.push(pool.token_in.clone());
+        }
+        if !tokens.contains(&pool.token_out) {
+            tokens.push(pool.token_out.clone());
+        }
+    }
+
+    let index_of: HashMap<&str, usize> = tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| (token.as_str(), i))
+        .collect();
+
+    let start = *index_of.get(start_token)?;
+
+    let edges: Vec<Edge> = pools
+        .iter()
+        .filter_map(|pool| {
+            let effective_rate = pool.price * (1.0 - pool.fee_percentage / 100.0);
+            if effective_rate <= 0.0 {
+                return None;
+            }
+            Some(Edge {
+                from: *index_of.get(pool.token_in.as_str())?,
+                to: *index_of.get(pool.token_out.as_str())?,
+                rate_cost: -effective_rate.ln(),
+                pool,
+            })
+        })
+        .collect();
+
+    let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); tokens.len()];
+    for (i, edge) in edges.iter().enumerate() {
+        adjacency[edge.from].push(i);
+    }
+
+    let mut heap = BinaryHeap::new();
+    heap.push(Frontier {
+        cost: Cost(0.0),
+        token: start,
+        penalty_total: 0,
+        path: Vec::new(),
+    });
+
+    let mut expanded = 0usize;
+    let mut best: Option<(f64, u32, Vec<usize>)> = None;
+
+    while let Some(state) = heap.pop() {
+        if expanded >= params.max_frontier {
+            break;
+        }
+        expanded += 1;
+
+        if state.token == start && !state.path.is_empty() {
+            if state.cost.0 < 0.0 {
+                let improves = best
+                    .as_ref()
+                    .map(|(cost, ..)| state.cost.0 < *cost)
+                    .unwrap_or(true);
+                if improves {
+                    best = Some((state.cost.0, state.penalty_total, state.path.clone()));
+                }
+            }
+            // Seguir explorando: el grafo tiene aristas de costo negativo
+            // (pools rentables), así que la primera vez que se alcanza
+            // `start` no garantiza que sea el ciclo más barato de la
+            // frontera todavía por expandir.
+            continue;
+        }
+
+        let visited_in_path = |token: usize| -> bool {
+            token == state.token || state.path.iter().any(|&edge_idx| edges[edge_idx].to == token)
+        };
+
+        for &edge_idx in &adjacency[state.token] {
+            let edge = &edges[edge_idx];
+            if edge.to != start && visited_in_path(edge.to) {
+                continue;
+            }
+
+            let edge_penalty = penalty.penalty(edge.pool);
+            let mut path = state.path.clone();
+            path.push(edge_idx);
+
+            heap.push(Frontier {
+                cost: Cost(state.cost.0 + edge.rate_cost + edge_penalty as f64),
+                token: edge.to,
+                penalty_total: state.penalty_total.saturating_add(edge_penalty),
+                path,
+            });
+        }
+    }
+
+    let (_total_cost, total_penalty, path) = best?;
+
+    // Solo los costos de tasa: el penalty guía la búsqueda pero no es parte
+    // de la ganancia real del ciclo.
+    let rate_cost: f64 = path.iter().map(|&i| edges[i].rate_cost).sum();
+    let gross_gain = (-rate_cost).exp();
+    let expected_profit = budget.trade_amount_usd * (gross_gain - 1.0);
+    let net_profit = expected_profit - budget.gas_cost_usd;
+
+    if net_profit <= budget.min_profit_usd {
+        return None;
+    }
+
+    let dexes: Vec<String> = path.iter().map(|&i| edges[i].pool.dex_id.clone()).collect();
+    let mut route_tokens: Vec<String> = vec![tokens[start].clone()];
+    for &edge_idx in &path {
+        route_tokens.push(tokens[edges[edge_idx].to].clone());
+    }
+
+    let score = params.hop_weight / path.len() as f64
+        + (params.liquidity_weight + params.gas_weight) / (1.0 + total_penalty as f64);
+
+    Some(BestFirstRoute {
+        chain: chain.to_string(),
+        dexes,
+        tokens: route_tokens,
+        gross_gain,
+        expected_profit,
+        net_profit,
+        total_penalty,
+        score,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(dex_id: &str, token_in: &str, token_out: &str, price: f64, fee_percentage: f64) -> PoolEdge {
+        PoolEdge {
+            dex_id: dex_id.to_string(),
+            chain: "ethereum".to_string(),
+            token_in: token_in.to_string(),
+            token_out: token_out.to_string(),
+            price,
+            fee_percentage,
+        }
+    }
+
+    #[test]
+    fn test_finds_a_profitable_cycle_with_no_penalty() {
+        let pools = vec![
+            edge("uniswap", "A", "B", 1.05, 0.3),
+            edge("sushiswap", "B", "C", 1.05, 0.3),
+            edge("curve", "C", "A", 1.05, 0.04),
+        ];
+
+        let route = find_best_route(
+            &pools,
+            "ethereum",
+            "A",
+            &TradeBudget {
+                trade_amount_usd: 1000.0,
+                gas_cost_usd: 1.0,
+                min_profit_usd: 0.0,
+            },
+            &NoPenalty,
+            &ScoringParams::default(),
+        )
+        .expect("debería encontrar el ciclo rentable A->B->C->A");
+
+        assert!(route.gross_gain > 1.0);
+        assert_eq!(route.dexes.len(), 3);
+        assert_eq!(route.tokens.first(), route.tokens.last());
+        assert_eq!(route.total_penalty, 0);
+    }
+
+    #[test]
+    fn test_no_route_when_rates_do_not_favor_arbitrage() {
+        let pools = vec![
+            edge("uniswap", "A", "B", 1.0, 0.3),
+            edge("sushiswap", "B", "A", 1.0, 0.3),
+        ];
+
+        let route = find_best_route(
+            &pools,
+            "ethereum",
+            "A",
+            &TradeBudget {
+                trade_amount_usd: 1000.0,
+                gas_cost_usd: 1.0,
+                min_profit_usd: 0.0,
+            },
+            &NoPenalty,
+            &ScoringParams::default(),
+        );
+
+        assert!(route.is_none());
+    }
+
+    #[test]
+    fn test_a_custom_penalty_can_rule_out_a_cheaper_but_unwanted_pool() {
+        // Dos caminos rentables A->B->A: uno corto y barato por "sushiswap",
+        // otro por "quickswap" con el mismo spread. Un `EdgePenalty` que
+        // prohíbe "sushiswap" (p.ej. por historial de fallos) debe forzar a
+        // la búsqueda a quedarse con la ruta de "quickswap".
+        struct BanDex(&'static str);
+        impl EdgePenalty for BanDex {
+            fn penalty(&self, edge: &PoolEdge) -> u32 {
+                if edge.dex_id == self.0 {
+                    u32::MAX
+                } else {
+                    0
+                }
+            }
+        }
+
+        let pools = vec![
+            edge("uniswap", "A", "B", 1.05, 0.3),
+            edge("sushiswap", "B", "A", 1.05, 0.3),
+            edge("quickswap", "B", "A", 1.05, 0.3),
+        ];
+
+        let route = find_best_route(
+            &pools,
+            "ethereum",
+            "A",
+            &TradeBudget {
+                trade_amount_usd: 1000.0,
+                gas_cost_usd: 1.0,
+                min_profit_usd: 0.0,
+            },
+            &BanDex("sushiswap"),
+            &ScoringParams::default(),
+        )
+        .expect("quickswap sigue disponible para cerrar el ciclo");
+
+        assert!(!route.dexes.contains(&"sushiswap".to_string()));
+        assert!(route.dexes.contains(&"quickswap".to_string()));
+        assert_eq!(route.total_penalty, 0);
+    }
+
+    #[test]
+    fn test_overflowing_penalty_saturates_instead_of_panicking() {
+        struct HugePenalty;
+        impl EdgePenalty for HugePenalty {
+            fn penalty(&self, _edge: &PoolEdge) -> u32 {
+                u32::MAX
+            }
+        }
+
+        let pools = vec![
+            edge("uniswap", "A", "B", 1.05, 0.3),
+            edge("sushiswap", "B", "C", 1.05, 0.3),
+            edge("curve", "C", "A", 1.05, 0.04),
+        ];
+
+        // No debe hacer panic por overflow al sumar el penalty de cada uno
+        // de los 3 hops (`u32::MAX` tres veces con suma normal desbordaría).
+        let route = find_best_route(
+            &pools,
+            "ethereum",
+            "A",
+            &TradeBudget {
+                trade_amount_usd: 1000.0,
+                gas_cost_usd: 1.0,
+                min_profit_usd: 0.0,
+            },
+            &HugePenalty,
+            &ScoringParams::default(),
+        );
+
+        // El penalty sí entra en el costo de la frontera, así que con un
+        // penalty saturado ningún ciclo cierra con costo negativo y no hay
+        // ruta; si la hubiera, su `total_penalty` quedaría saturado al máximo.
+        if let Some(route) = route {
+            assert_eq!(route.total_penalty, u32::MAX);
+        }
+    }
+
+    #[test]
+    fn test_max_frontier_of_zero_finds_nothing() {
+        let pools = vec![
+            edge("uniswap", "A", "B", 1.05, 0.3),
+            edge("sushiswap", "B", "A", 1.05, 0.3),
+        ];
+
+        let params = ScoringParams {
+            max_frontier: 0,
+            ..ScoringParams::default()
+        };
+
+        let route = find_best_route(
+            &pools,
+            "ethereum",
+            "A",
+            &TradeBudget {
+                trade_amount_usd: 1000.0,
+                gas_cost_usd: 1.0,
+                min_profit_usd: 0.0,
+            },
+            &NoPenalty,
+            &params,
+        );
+
+        assert!(route.is_none());
+    }
+}
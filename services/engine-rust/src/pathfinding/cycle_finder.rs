@@ -0,0 +1,297 @@
+//! Detección de ciclos de arbitraje de longitud arbitraria vía Bellman-Ford.
+//!
+//! `ThreeDexPathfinder`/`TwoDexPathfinder` fuerzan exactamente 3 y 2 DEXs
+//! respectivamente con bucles `O(n³)`/`O(n²)`. Este módulo generaliza a
+//! ciclos de cualquier longitud: cada pool es una arista dirigida
+//! `token_in -> token_out` con peso `w = -ln(effective_rate)`, donde
+//! `effective_rate = price * (1 - fee/100)`. Un ciclo con peso total negativo
+//! implica `∏ effective_rate > 1`, es decir arbitraje, así que detectar un
+//! ciclo de peso negativo con Bellman-Ford encuentra una ruta rentable sin
+//! enumerar combinaciones de DEXs explícitamente.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// Una arista del grafo: el pool de `dex_id` que permite cambiar
+/// `token_in -> token_out` en `chain` a `price` con `fee_percentage`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolEdge {
+    pub dex_id: String,
+    pub chain: String,
+    pub token_in: String,
+    pub token_out: String,
+    pub price: f64,
+    pub fee_percentage: f64,
+}
+
+/// Un ciclo de arbitraje detectado: la secuencia de DEXs y tokens que lo
+/// componen y su ganancia bruta multiplicativa (`> 1.0` implica arbitraje).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CycleRoute {
+    pub chain: String,
+    pub dexes: Vec<String>,
+    pub tokens: Vec<String>,
+    pub gross_gain: f64,
+    pub expected_profit: f64,
+    pub net_profit: f64,
+}
+
+struct GraphEdge {
+    from: usize,
+    to: usize,
+    weight: f64,
+    dex_id: String,
+}
+
+/// Encuentra ciclos de arbitraje de longitud arbitraria dentro de cada chain,
+/// vía relajación de Bellman-Ford sobre un grafo dirigido token -> token.
+pub struct CycleArbitrageFinder {
+    pools: Vec<PoolEdge>,
+}
+
+impl CycleArbitrageFinder {
+    pub fn new(pools: Vec<PoolEdge>) -> Self {
+        Self { pools }
+    }
+
+    /// Busca ciclos rentables en todas las chains presentes, simulando un
+    /// trade de `trade_amount_usd` sobre la ganancia bruta del ciclo.
+    pub fn find_profitable_cycles(
+        &self,
+        trade_amount_usd: f64,
+        gas_cost_usd: f64,
+        min_profit_usd: f64,
+    ) -> Vec<CycleRoute> {
+        let mut chains: Vec<&str> = self.pools.iter().map(|p| p.chain.as_str()).collect();
+        chains.sort_unstable();
+        chains.dedup();
+
+        let mut routes = Vec::new();
+        for chain in chains {
+            routes.extend(self.find_cycles_for_chain(chain, trade_amount_usd, gas_cost_usd, min_profit_usd));
+        }
+
+        routes
+    }
+
+    /// Corre Bellman-Ford sobre el subgrafo de una única chain (solo se
+    /// agregan aristas entre pools de la misma chain) y recupera todos los
+    /// ciclos negativos distintos detectados.
+    fn find_cycles_for_chain(
+        &self,
+        chain: &str,
+        trade_amount_usd: f64,
+        gas_cost_usd: f64,
+        min_profit_usd: f64,
+    ) -> Vec<CycleRoute> {
+        let pools: Vec<&PoolEdge> = self.pools.iter().filter(|p| p.chain == chain).collect();
+
+        let mut tokens: Vec<String> = Vec::new();
+        for pool in &pools {
+            if !tokens.contains(&pool.token_in) {
+                tokens.push(pool.token_in.clone());
+            }
+            if !tokens.contains(&pool.token_out) {
+                tokens.push(pool.token_out.clone());
+            }
+        }
+
+        let num_tokens = tokens.len();
+        if num_tokens == 0 {
+            return Vec::new();
+        }
+
+        let index_of: HashMap<&str, usize> = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, token)| (token.as_str(), i))
+            .collect();
+
+        let edges: Vec<GraphEdge> = pools
+            .iter()
+            .filter_map(|pool| {
+                let effective_rate = pool.price * (1.0 - pool.fee_percentage / 100.0);
+                if effective_rate <= 0.0 {
+                    return None;
+                }
+                Some(GraphEdge {
+                    from: *index_of.get(pool.token_in.as_str())?,
+                    to: *index_of.get(pool.token_out.as_str())?,
+                    weight: -effective_rate.ln(),
+                    dex_id: pool.dex_id.clone(),
+                })
+            })
+            .collect();
+
+        // Arrancar todas las distancias en 0 equivale a agregar una fuente
+        // virtual conectada a cada token con peso 0: detecta un ciclo
+        // negativo alcanzable desde cualquier token, no solo desde uno fijo.
+        let mut dist = vec![0.0f64; num_tokens];
+        let mut predecessor: Vec<Option<usize>> = vec![None; num_tokens];
+
+        for _ in 0..num_tokens.saturating_sub(1) {
+            for edge in &edges {
+                if dist[edge.from] + edge.weight < dist[edge.to] {
+                    dist[edge.to] = dist[edge.from] + edge.weight;
+                    predecessor[edge.to] = Some(edge.from);
+                }
+            }
+        }
+
+        // V-ésima pasada: toda arista que todavía relaja está en, o lleva a,
+        // un ciclo de peso negativo.
+        let mut seen_nodes: Vec<bool> = vec![false; num_tokens];
+        let mut routes = Vec::new();
+
+        for edge in &edges {
+            if dist[edge.from] + edge.weight >= dist[edge.to] {
+                continue;
+            }
+
+            // Caminar V veces por los predecesores garantiza terminar dentro
+            // del ciclo, no solo en el camino que lleva a él.
+            let mut node = edge.to;
+            for _ in 0..num_tokens {
+                node = predecessor[node].unwrap_or(node);
+            }
+
+            if seen_nodes[node] {
+                continue;
+            }
+
+            let mut cycle_nodes = vec![node];
+            let mut current = predecessor[node];
+            while let Some(prev) = current {
+                if prev == node {
+                    break;
+                }
+                cycle_nodes.push(prev);
+                current = predecessor[prev];
+            }
+            cycle_nodes.push(node);
+            cycle_nodes.reverse();
+
+            for &n in &cycle_nodes {
+                seen_nodes[n] = true;
+            }
+
+            let mut total_weight = 0.0;
+            let mut dexes = Vec::new();
+            let mut complete = true;
+            for pair in cycle_nodes.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                match edges.iter().find(|e| e.from == from && e.to == to) {
+                    Some(e) => {
+                        total_weight += e.weight;
+                        dexes.push(e.dex_id.clone());
+                    }
+                    None => {
+                        complete = false;
+                        break;
+                    }
+                }
+            }
+
+            if !complete || total_weight >= 0.0 {
+                continue;
+            }
+
+            let gross_gain = (-total_weight).exp();
+            let expected_profit = trade_amount_usd * (gross_gain - 1.0);
+            let net_profit = expected_profit - gas_cost_usd;
+
+            if net_profit > min_profit_usd {
+                routes.push(CycleRoute {
+                    chain: chain.to_string(),
+                    dexes,
+                    tokens: cycle_nodes.iter().map(|&i| tokens[i].clone()).collect(),
+                    gross_gain,
+                    expected_profit,
+                    net_profit,
+                });
+            }
+        }
+
+        routes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(dex_id: &str, token_in: &str, token_out: &str, price: f64, fee_percentage: f64) -> PoolEdge {
+        PoolEdge {
+            dex_id: dex_id.to_string(),
+            chain: "ethereum".to_string(),
+            token_in: token_in.to_string(),
+            token_out: token_out.to_string(),
+            price,
+            fee_percentage,
+        }
+    }
+
+    #[test]
+    fn test_finds_a_three_hop_cycle_with_positive_gross_gain() {
+        // A -> B -> C -> A con un spread que rinde > 1.0 tras fees.
+        let pools = vec![
+            edge("uniswap", "A", "B", 1.05, 0.3),
+            edge("sushiswap", "B", "C", 1.05, 0.3),
+            edge("curve", "C", "A", 1.05, 0.04),
+        ];
+
+        let finder = CycleArbitrageFinder::new(pools);
+        let routes = finder.find_profitable_cycles(1000.0, 1.0, 0.0);
+
+        assert_eq!(routes.len(), 1);
+        let route = &routes[0];
+        assert!(route.gross_gain > 1.0);
+        assert_eq!(route.dexes.len(), 3);
+        assert_eq!(route.tokens.first(), route.tokens.last());
+    }
+
+    #[test]
+    fn test_no_cycle_when_rates_do_not_favor_arbitrage() {
+        // Round-trip a pérdida (precios reflejan el mismo spread ambos lados).
+        let pools = vec![
+            edge("uniswap", "A", "B", 1.0, 0.3),
+            edge("sushiswap", "B", "A", 1.0, 0.3),
+        ];
+
+        let finder = CycleArbitrageFinder::new(pools);
+        let routes = finder.find_profitable_cycles(1000.0, 1.0, 0.0);
+
+        assert!(routes.is_empty());
+    }
+
+    #[test]
+    fn test_keeps_chains_independent() {
+        let pools = vec![
+            PoolEdge { chain: "ethereum".to_string(), ..edge("uniswap", "A", "B", 1.05, 0.3) },
+            PoolEdge { chain: "ethereum".to_string(), ..edge("sushiswap", "B", "A", 1.05, 0.3) },
+            PoolEdge { chain: "polygon".to_string(), ..edge("quickswap", "X", "Y", 1.0, 0.3) },
+            PoolEdge { chain: "polygon".to_string(), ..edge("sushiswap", "Y", "X", 1.0, 0.3) },
+        ];
+
+        let finder = CycleArbitrageFinder::new(pools);
+        let routes = finder.find_profitable_cycles(1000.0, 1.0, 0.0);
+
+        // Solo la chain "ethereum" tiene un ciclo rentable; "polygon" no debe
+        // contaminar el resultado ni mezclar tokens entre chains.
+        assert!(routes.iter().all(|r| r.chain == "ethereum"));
+    }
+
+    #[test]
+    fn test_min_profit_threshold_filters_marginal_cycles() {
+        let pools = vec![
+            edge("uniswap", "A", "B", 1.01, 0.3),
+            edge("sushiswap", "B", "C", 1.01, 0.3),
+            edge("curve", "C", "A", 1.01, 0.04),
+        ];
+
+        let finder = CycleArbitrageFinder::new(pools);
+        let routes = finder.find_profitable_cycles(1000.0, 1.0, 1_000_000.0);
+
+        assert!(routes.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! `GasPriceProvider` abstrae de dónde sale el precio de gas vigente que
+//! `RouteRanker` usa para recalcular `gas_cost`/`net_profit` antes de
+//! rankear. Sin esto, `calculate_efficiency_score`/`optimize_route_selection`
+//! trabajan sobre un `gas_cost` congelado en el momento en que se construyó
+//! la `Route`, que unos pocos bloques después ya no refleja el costo real de
+//! ejecutar la ruta y puede invertir el ranking de profit.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+/// Snapshot de precio de gas en un momento dado: EIP-1559 `base_fee` +
+/// `priority_fee` (tip), en gwei, más un forecast opcional a corto plazo
+/// (p.ej. el próximo bloque) que un caller más sofisticado podría usar para
+/// anticiparse en vez de solo reaccionar al precio actual.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasPriceSnapshot {
+    pub base_fee_gwei: f64,
+    pub priority_fee_gwei: f64,
+    pub forecast_base_fee_gwei: Option<f64>,
+}
+
+impl GasPriceSnapshot {
+    /// `base_fee + priority_fee`: lo que efectivamente paga una tx a precio
+    /// de mercado, antes de convertir a USD.
+    pub fn total_gwei(&self) -> f64 {
+        self.base_fee_gwei + self.priority_fee_gwei
+    }
+}
+
+/// Fuente del precio de gas vigente para una chain. Implementaciones:
+/// [`StaticGasPriceProvider`] (un valor fijo, para tests/desarrollo offline)
+/// y [`OracleGasPriceProvider`] (un oráculo HTTP real).
+#[async_trait]
+pub trait GasPriceProvider: Send + Sync {
+    async fn current_price(&self, chain_id: u64) -> Result<GasPriceSnapshot>;
+}
+
+/// Precio de gas fijo, configurado a mano. Útil en tests y como fallback
+/// cuando todavía no hay un oráculo real conectado.
+pub struct StaticGasPriceProvider {
+    snapshot: GasPriceSnapshot,
+}
+
+impl StaticGasPriceProvider {
+    pub fn new(snapshot: GasPriceSnapshot) -> Self {
+        Self { snapshot }
+    }
+}
+
+#[async_trait]
+impl GasPriceProvider for StaticGasPriceProvider {
+    async fn current_price(&self, _chain_id: u64) -> Result<GasPriceSnapshot> {
+        Ok(self.snapshot)
+    }
+}
+
+/// Respuesta cruda de un oráculo HTTP de gas. Solo se parsean los campos que
+/// `OracleGasPriceProvider` realmente consume.
+#[derive(Debug, Deserialize)]
+struct OracleGasPriceResponse {
+    #[serde(rename = "baseFeeGwei")]
+    base_fee_gwei: f64,
+    #[serde(rename = "priorityFeeGwei")]
+    priority_fee_gwei: f64,
+    #[serde(rename = "forecastBaseFeeGwei", default)]
+    forecast_base_fee_gwei: Option<f64>,
+}
+
+/// `GasPriceProvider` respaldado por un oráculo HTTP externo
+/// (`GET {base_url}/gas-price?chainId=...`). Si la request no responde
+/// dentro de `timeout` o la respuesta no parsea, propaga el error: a
+/// diferencia de `AggregatorDexClient::quote`, no hay un fallback local
+/// razonable para un precio de gas (inventar uno podría invertir el
+/// ranking de profit, que es justo lo que este trait existe para evitar).
+pub struct OracleGasPriceProvider {
+    http: Client,
+    base_url: String,
+    timeout: Duration,
+}
+
+impl OracleGasPriceProvider {
+    pub fn new(base_url: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            http: Client::new(),
+            base_url: base_url.into(),
+            timeout,
+        }
+    }
+}
+
+#[async_trait]
+impl GasPriceProvider for OracleGasPriceProvider {
+    async fn current_price(&self, chain_id: u64) -> Result<GasPriceSnapshot> {
+        let response = tokio::time::timeout(
+            self.timeout,
+            self.http
+                .get(format!("{}/gas-price", self.base_url))
+                .query(&[("chainId", chain_id.to_string())])
+                .send(),
+        )
+        .await
+        .context("gas price oracle request timed out")??
+        .error_for_status()
+        .context("gas price oracle returned an error status")?
+        .json::<OracleGasPriceResponse>()
+        .await
+        .context("failed to parse gas price oracle response")?;
+
+        Ok(GasPriceSnapshot {
+            base_fee_gwei: response.base_fee_gwei,
+            priority_fee_gwei: response.priority_fee_gwei,
+            forecast_base_fee_gwei: response.forecast_base_fee_gwei,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_static_gas_price_provider_always_returns_the_same_snapshot() {
+        let snapshot = GasPriceSnapshot {
+            base_fee_gwei: 20.0,
+            priority_fee_gwei: 2.0,
+            forecast_base_fee_gwei: Some(22.0),
+        };
+        let provider = StaticGasPriceProvider::new(snapshot);
+
+        let first = provider.current_price(1).await.unwrap();
+        let second = provider.current_price(42).await.unwrap();
+
+        assert_eq!(first, snapshot);
+        assert_eq!(second, snapshot);
+    }
+
+    #[test]
+    fn test_total_gwei_sums_base_and_priority_fee() {
+        let snapshot = GasPriceSnapshot {
+            base_fee_gwei: 20.0,
+            priority_fee_gwei: 2.5,
+            forecast_base_fee_gwei: None,
+        };
+
+        assert_eq!(snapshot.total_gwei(), 22.5);
+    }
+}
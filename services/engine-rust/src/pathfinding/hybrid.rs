@@ -0,0 +1,261 @@
+//! Ejecutor híbrido AMM + libro de órdenes límite.
+//!
+//! `amm::RouteSimulator` asume que cada hop se llena íntegro contra las
+//! reservas de un pool. En la práctica, muchos venues (CLOBs on-chain,
+//! RFQ, intents) también ofrecen liquidez como órdenes límite en reposo a un
+//! precio fijo. `fill_hop` reparte un mismo hop entre el AMM y un libro de
+//! órdenes, enrutando en cada instante a la fuente que ofrezca mejor precio
+//! marginal, para no dejar sobre la mesa liquidez más barata que la del pool.
+
+use serde::{Deserialize, Serialize};
+
+use crate::pathfinding::amm;
+
+/// Lado de una orden límite respecto al hop que se está llenando. Solo las
+/// órdenes `Ask` (ofrecen `token_out` a cambio de `token_in`, que es lo que
+/// un swap `token_in -> token_out` necesita) participan en `fill_hop`; las
+/// `Bid` pertenecen al lado contrario del libro y se ignoran para este hop.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OrderSide {
+    Bid,
+    Ask,
+}
+
+/// Una orden límite en reposo en el libro: acepta hasta `size_remaining`
+/// unidades de `token_in` y entrega `token_out` a cambio, a razón de `price`
+/// (unidades de `token_out` por unidad de `token_in` — más alto es mejor
+/// para quien compra `token_out`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LimitOrder {
+    pub price: f64,
+    pub size_remaining: f64,
+    pub side: OrderSide,
+}
+
+/// Resultado de llenar un hop combinando AMM + libro de órdenes: el output
+/// total y el desglose de cuánto `amount_in` se enrutó a cada fuente, para
+/// que el llamador pueda construir el calldata multi-venue.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HybridFill {
+    pub amount_out: f64,
+    pub amm_filled: f64,
+    pub book_filled: f64,
+}
+
+/// Llena un hop repartiendo `amount_in` entre el pool AMM (`reserve_in`/
+/// `reserve_out`, con `fee`) y un libro de órdenes límite (`book`), según cuál
+/// ofrezca mejor precio marginal en cada instante.
+///
+/// Camina el libro de órdenes `Ask` ordenado por precio descendente (mejor
+/// precio primero). En cada paso compara el precio de la siguiente orden
+/// contra el precio marginal actual del AMM: si la orden es mejor, se
+/// consume (total o parcialmente, según `size_remaining` contra lo que
+/// falte de `amount_in`); si el AMM es mejor, se le enruta solo hasta el
+/// punto en que su precio marginal cae al nivel de esa orden (fórmula
+/// cerrada sobre la derivada del invariante `x·y = k`), para no
+/// sobrepasarla — así ambas fuentes terminan, en cada tramo, al mismo precio
+/// marginal. Se repite hasta agotar `amount_in` o ambas fuentes.
+///
+/// Con `book` vacío, esto es equivalente a `amm::constant_product_output`
+/// aplicado a todo `amount_in` de una vez.
+pub fn fill_hop(
+    amount_in: f64,
+    reserve_in: f64,
+    reserve_out: f64,
+    fee: f64,
+    book: &[LimitOrder],
+) -> Option<HybridFill> {
+    if amount_in <= 0.0 || reserve_in <= 0.0 || reserve_out <= 0.0 {
+        return None;
+    }
+
+    let mut asks: Vec<LimitOrder> = book
+        .iter()
+        .copied()
+        .filter(|order| order.side == OrderSide::Ask && order.size_remaining > 0.0 && order.price > 0.0)
+        .collect();
+    // `total_cmp` en vez de `partial_cmp(...).unwrap()`: un `price` corrupto
+    // a `NaN` en el book no debe hacer panic acá, solo ordenar de forma
+    // determinística.
+    asks.sort_by(|a, b| b.price.total_cmp(&a.price));
+
+    let mut remaining = amount_in;
+    let mut amm_reserve_in = reserve_in;
+    let mut amm_reserve_out = reserve_out;
+    let mut amm_filled = 0.0;
+    let mut book_filled = 0.0;
+    let mut amount_out = 0.0;
+    let mut book_idx = 0;
+
+    while remaining > 0.0 {
+        let amm_marginal_price = marginal_price(amm_reserve_in, amm_reserve_out, fee);
+        let next_order = asks.get(book_idx).copied();
+
+        let use_book = matches!(next_order, Some(order) if order.price > amm_marginal_price);
+
+        if use_book {
+            let order = next_order.expect("use_book solo es true cuando next_order es Some");
+            let fill = remaining.min(order.size_remaining);
+            amount_out += fill * order.price;
+            book_filled += fill;
+            remaining -= fill;
+            asks[book_idx].size_remaining -= fill;
+            if asks[book_idx].size_remaining <= 0.0 {
+                book_idx += 1;
+            }
+            continue;
+        }
+
+        // El AMM cotiza igual o mejor que la siguiente orden (o no quedan
+        // órdenes): enrutar al AMM, pero solo hasta que su precio marginal
+        // caiga al nivel de la siguiente orden, si la hay.
+        let amm_input = match next_order {
+            Some(order) => amm_input_to_reach_price(amm_reserve_in, amm_reserve_out, fee, order.price)
+                .unwrap_or(remaining)
+                .min(remaining),
+            None => remaining,
+        };
+
+        if amm_input <= 0.0 {
+            // No se puede avanzar el AMM sin sobrepasar el precio de la
+            // siguiente orden: se detiene acá en vez de ciclar sin avanzar.
+            break;
+        }
+
+        let out = amm::constant_product_output(amm_input, amm_reserve_in, amm_reserve_out, fee);
+        if out <= 0.0 {
+            break;
+        }
+
+        amount_out += out;
+        amm_filled += amm_input;
+        amm_reserve_in += amm_input * (1.0 - fee);
+        amm_reserve_out -= out;
+        remaining -= amm_input;
+    }
+
+    if amount_out <= 0.0 {
+        return None;
+    }
+
+    Some(HybridFill {
+        amount_out,
+        amm_filled,
+        book_filled,
+    })
+}
+
+/// Precio marginal instantáneo del AMM en el punto actual de reservas: el
+/// output adicional por unidad adicional de input, neto de fee.
+fn marginal_price(reserve_in: f64, reserve_out: f64, fee: f64) -> f64 {
+    (1.0 - fee) * reserve_out / reserve_in
+}
+
+/// Cuánto `amount_in` adicional hace falta inyectar al AMM para que su
+/// precio marginal caiga exactamente a `target_price`, despejando de la
+/// derivada del invariante `x·y = k`. `None` si `target_price` ya es mayor o
+/// igual al precio marginal actual (empujar en esta dirección nunca llega
+/// ahí) o si `fee` es inválido.
+fn amm_input_to_reach_price(
+    reserve_in: f64,
+    reserve_out: f64,
+    fee: f64,
+    target_price: f64,
+) -> Option<f64> {
+    let one_minus_fee = 1.0 - fee;
+    if one_minus_fee <= 0.0 || target_price <= 0.0 {
+        return None;
+    }
+    if target_price >= marginal_price(reserve_in, reserve_out, fee) {
+        return None;
+    }
+
+    let new_reserve_in = ((one_minus_fee * reserve_in * reserve_out) / target_price).sqrt();
+    Some((new_reserve_in - reserve_in) / one_minus_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_hop_with_empty_book_matches_pure_amm_output() {
+        let fill = fill_hop(1000.0, 1_000_000.0, 1_000_000.0, 0.003, &[]).unwrap();
+
+        let amm_only = amm::constant_product_output(1000.0, 1_000_000.0, 1_000_000.0, 0.003);
+
+        assert!((fill.amount_out - amm_only).abs() < 1e-9);
+        assert_eq!(fill.amm_filled, 1000.0);
+        assert_eq!(fill.book_filled, 0.0);
+    }
+
+    #[test]
+    fn test_fill_hop_prefers_a_better_priced_order_over_the_amm() {
+        // El AMM cotiza ~1:1; una orden a 1.05 es estrictamente mejor y
+        // alcanza para todo el trade.
+        let book = vec![LimitOrder {
+            price: 1.05,
+            size_remaining: 10_000.0,
+            side: OrderSide::Ask,
+        }];
+
+        let fill = fill_hop(1000.0, 1_000_000.0, 1_000_000.0, 0.003, &book).unwrap();
+
+        assert_eq!(fill.book_filled, 1000.0);
+        assert_eq!(fill.amm_filled, 0.0);
+        assert!((fill.amount_out - 1050.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fill_hop_splits_between_book_and_amm_when_the_order_is_too_small() {
+        let book = vec![LimitOrder {
+            price: 1.05,
+            size_remaining: 100.0,
+            side: OrderSide::Ask,
+        }];
+
+        let fill = fill_hop(1000.0, 1_000_000.0, 1_000_000.0, 0.003, &book).unwrap();
+
+        assert_eq!(fill.book_filled, 100.0);
+        assert_eq!(fill.amm_filled, 900.0);
+        // El output combinado debe superar al de llenar todo contra el AMM.
+        let amm_only = amm::constant_product_output(1000.0, 1_000_000.0, 1_000_000.0, 0.003);
+        assert!(fill.amount_out > amm_only);
+    }
+
+    #[test]
+    fn test_fill_hop_ignores_bid_side_orders() {
+        let book = vec![LimitOrder {
+            price: 1.05,
+            size_remaining: 10_000.0,
+            side: OrderSide::Bid,
+        }];
+
+        let fill = fill_hop(1000.0, 1_000_000.0, 1_000_000.0, 0.003, &book).unwrap();
+
+        assert_eq!(fill.book_filled, 0.0);
+        assert_eq!(fill.amm_filled, 1000.0);
+    }
+
+    #[test]
+    fn test_fill_hop_walks_multiple_orders_best_price_first() {
+        let book = vec![
+            LimitOrder { price: 1.02, size_remaining: 200.0, side: OrderSide::Ask },
+            LimitOrder { price: 1.05, size_remaining: 200.0, side: OrderSide::Ask },
+        ];
+
+        let fill = fill_hop(300.0, 1_000_000.0, 1_000_000.0, 0.003, &book).unwrap();
+
+        // Debe consumir primero la orden a 1.05 (mejor precio) y solo
+        // después la de 1.02.
+        assert_eq!(fill.book_filled, 300.0);
+        assert!((fill.amount_out - (200.0 * 1.05 + 100.0 * 1.02)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fill_hop_rejects_invalid_reserves_or_amount() {
+        assert!(fill_hop(0.0, 1_000_000.0, 1_000_000.0, 0.003, &[]).is_none());
+        assert!(fill_hop(1000.0, 0.0, 1_000_000.0, 0.003, &[]).is_none());
+        assert!(fill_hop(1000.0, 1_000_000.0, 0.0, 0.003, &[]).is_none());
+    }
+}
@@ -51,18 +51,48 @@
 //! @criticality BLOQUEANTE
 
 use std::collections::HashMap;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
+use crate::connectors::aggregator::AggregatorDexClient;
+use crate::pathfinding::amm::PoolKind;
+use crate::pathfinding::best_first::{self, NoPenalty, ScoringParams, TradeBudget};
+use crate::pathfinding::cycle_finder::{CycleArbitrageFinder, PoolEdge};
+use crate::pathfinding::types::Blockchain;
+use crate::utils::amounts::TokenAmount;
+
+/// Gas estimado por hop de un ciclo detectado vía `cycle_finder`, mismo orden
+/// de magnitud que el `estimated_gas: 250000` de una ruta 2-hop de ejemplo
+/// (~125k/hop) hasta que `cycle_finder::PoolEdge` traiga su propio costo de
+/// gas por DEX como `twodex_dp::Dex::gas_estimate_swap`.
+const CYCLE_GAS_PER_HOP_UNITS: u64 = 125_000;
+
+/// Divergencia máxima tolerada entre el `expected_output` calculado
+/// localmente y la cotización del agregador externo antes de considerar que
+/// la ruta ya no es confiable para ejecutar. 2%: por encima de un spread tan
+/// grande, lo más probable es que el precio local esté desactualizado
+/// respecto al agregador, no al revés.
+const AGGREGATOR_DIVERGENCE_TOLERANCE: f64 = 0.02;
+
 // Reexportar submódulos públicos
 pub mod two_dex;
-pub mod three_dex; 
+pub mod three_dex;
 pub mod ranking;
 pub mod twodex; // Prompt Supremo Definitivo - Tarea 3.2
+pub mod amm;
+pub mod cycle_finder;
+pub mod hybrid;
+pub mod best_first;
+pub mod types;
+pub mod twodex_dp;
+pub mod twodex_dp_v2;
+pub mod gas_price;
 
 // Reexportar tipos principales para uso externo
 pub use two_dex::{TwoHopPathfinder, TwoHopResult};
 pub use three_dex::{ThreeHopPathfinder, ThreeHopResult};
 pub use ranking::{RouteRanker, RankedRoute};
+pub use gas_price::{GasPriceProvider, GasPriceSnapshot, StaticGasPriceProvider, OracleGasPriceProvider};
 
 // ============================================================================
 // ESTRUCTURAS DE DATOS COMPARTIDAS
@@ -81,6 +111,34 @@ pub struct PoolInfo {
     pub volume_24h: f64,
     pub fee_rate: f64,  // ej: 0.003 para 0.3%
     pub last_updated: u64,
+    /// Reservas de `token_a`/`token_b` en unidades humanas (no wei), para
+    /// cotizar el swap contra la curva real del pool vía `amm::PoolKind` en
+    /// vez del heurístico `price_a_to_b * amount`. `None` cuando el feed
+    /// solo trae `liquidity_usd` agregado (p.ej. un agregador que no expone
+    /// reservas por lado): `calculate_swap_output`/`estimate_slippage_impact`
+    /// caen de vuelta al viejo modelo ad-hoc en ese caso.
+    #[serde(default)]
+    pub reserve_a: Option<f64>,
+    #[serde(default)]
+    pub reserve_b: Option<f64>,
+    /// Curva que describe cómo cotiza este pool. `ConstantProduct` (default)
+    /// cubre AMMs estilo Uniswap V2; `Stable` cubre pools Curve-style y
+    /// requiere `reserve_a`/`reserve_b` para resolver el invariante. Pools
+    /// v3/concentrated-liquidity no tienen variante propia todavía — quedan
+    /// cubiertos por la curva que mejor los aproxime hasta que se agregue una.
+    #[serde(default)]
+    pub pool_kind: PoolKind,
+    /// Reservas de `token_a`/`token_b` como [`TokenAmount`] exacto (acepta
+    /// hex o decimal desde Sheets/APIs vía su serde), igual patrón que
+    /// `three_dex::TokenPair::reserve_in_units`. `None` para pools que
+    /// todavía no migraron a fixed-point: `calculate_swap_output` sigue
+    /// funcionando sobre `price_a_to_b`/`price_b_to_a`, y solo
+    /// `calculate_swap_output_exact`/`estimate_slippage_impact_exact`
+    /// requieren que ambos campos estén presentes.
+    #[serde(default)]
+    pub reserve_a_units: Option<TokenAmount>,
+    #[serde(default)]
+    pub reserve_b_units: Option<TokenAmount>,
 }
 
 /// Estado de una transición en el algoritmo DP
@@ -122,32 +180,83 @@ pub trait DexClient {
 // FUNCIONES UTILITARIAS COMPARTIDAS
 // ============================================================================
 
-/// Calcula el output esperado de un swap considerando slippage
+/// Reservas de entrada/salida de `pool` en la dirección `from_token`, si el
+/// pool las trae (`reserve_a`/`reserve_b` ambas `Some`).
+fn reserves_for_direction(pool: &PoolInfo, from_token: &str) -> Option<(f64, f64)> {
+    let (reserve_a, reserve_b) = (pool.reserve_a?, pool.reserve_b?);
+    Some(if from_token == pool.token_a {
+        (reserve_a, reserve_b)
+    } else {
+        (reserve_b, reserve_a)
+    })
+}
+
+/// Cotiza `amount_in` contra la curva real de `pool` (vía `pool.pool_kind`),
+/// `None` si `pool` no trae `reserve_a`/`reserve_b` o si la curva no
+/// converge a un output positivo.
+fn quote_against_reserves(amount_in: f64, pool: &PoolInfo, from_token: &str) -> Option<f64> {
+    let (reserve_in, reserve_out) = reserves_for_direction(pool, from_token)?;
+
+    match pool.pool_kind {
+        PoolKind::ConstantProduct => {
+            let output = amm::constant_product_output(amount_in, reserve_in, reserve_out, pool.fee_rate);
+            (output > 0.0).then_some(output)
+        }
+        PoolKind::Stable { amplification } => {
+            amm::stable_swap_output(amount_in, reserve_in, reserve_out, amplification, pool.fee_rate)
+        }
+    }
+}
+
+/// Calcula el output esperado de un swap considerando slippage.
+///
+/// Cuando `pool` trae reservas reales (`reserve_a`/`reserve_b`), cotiza
+/// contra la curva de `pool.pool_kind` vía `quote_against_reserves` en vez
+/// del viejo heurístico lineal `price_a_to_b * amount`, que subestima el
+/// slippage de trades grandes. Cae de vuelta al heurístico cuando el pool
+/// solo trae `liquidity_usd` agregado (sin reservas por lado).
 pub fn calculate_swap_output(
     input_amount: f64,
     pool: &PoolInfo,
     from_token: &str,
     slippage_tolerance: f64
 ) -> f64 {
+    if let Some(output) = quote_against_reserves(input_amount, pool, from_token) {
+        return output * (1.0 - slippage_tolerance);
+    }
+
     let base_output = if from_token == pool.token_a {
         input_amount * pool.price_a_to_b
     } else {
         input_amount * pool.price_b_to_a
     };
-    
+
     // Aplicar fee del pool
     let after_fee = base_output * (1.0 - pool.fee_rate);
-    
+
     // Aplicar slippage estimado
     let slippage = estimate_slippage_impact(input_amount, pool);
     after_fee * (1.0 - slippage - slippage_tolerance)
 }
 
-/// Estima el impacto de slippage usando cálculo diferencial
+/// Estima el impacto de slippage de un swap de `amount` contra `pool`.
+///
+/// Cuando `pool` trae reservas reales, devuelve el impacto de precio
+/// realizado de `quote_against_reserves` vía `amm::price_impact` — la
+/// fórmula exacta `1 - (amount_out/amount_in)/(reserve_out/reserve_in)`, no
+/// una aproximación. Cae de vuelta al viejo modelo ad-hoc `(amount/liquidity)^1.5`
+/// cuando el pool no trae reservas por lado, único caso en que ese heurístico
+/// sigue siendo necesario.
 pub fn estimate_slippage_impact(amount: f64, pool: &PoolInfo) -> f64 {
+    if let Some((reserve_in, reserve_out)) = reserves_for_direction(pool, pool.token_a.as_str()) {
+        if let Some(amount_out) = quote_against_reserves(amount, pool, pool.token_a.as_str()) {
+            return amm::price_impact(amount, amount_out, reserve_in, reserve_out);
+        }
+    }
+
     // Modelo simplificado: slippage ∝ (amount / liquidity)^1.5
     let ratio = amount / pool.liquidity_usd;
-    
+
     if ratio < 0.001 {
         0.0001 // Slippage mínimo
     } else if ratio < 0.01 {
@@ -158,6 +267,112 @@ pub fn estimate_slippage_impact(amount: f64, pool: &PoolInfo) -> f64 {
     }
 }
 
+/// Equivalente exacto de `calculate_swap_output`, en aritmética `U256` vía
+/// `amm::constant_product_output_exact`, para la verificación final de una
+/// ruta ya elegida por el escaneo `f64` (donde el redondeo sub-wei sí importa
+/// antes de someter la transacción on-chain). Requiere que `pool` tenga
+/// ambas `reserve_a_units`/`reserve_b_units`; `None` si faltan, si
+/// `from_token` no coincide con `token_a`/`token_b`, o si la ruta rinde
+/// output cero. A diferencia de `calculate_swap_output`, no aplica
+/// `slippage_tolerance`: el output devuelto ya es el real de la curva, y es
+/// el llamador quien decide si alcanza contra su mínimo aceptable.
+pub fn calculate_swap_output_exact(
+    amount_in: TokenAmount,
+    pool: &PoolInfo,
+    from_token: &str,
+) -> Option<TokenAmount> {
+    let (reserve_in, reserve_out) = if from_token == pool.token_a {
+        (pool.reserve_a_units?, pool.reserve_b_units?)
+    } else if from_token == pool.token_b {
+        (pool.reserve_b_units?, pool.reserve_a_units?)
+    } else {
+        return None;
+    };
+
+    let fee_bps = (pool.fee_rate * 10_000.0).round() as u32;
+    amm::constant_product_output_exact(amount_in, reserve_in, reserve_out, fee_bps)
+}
+
+/// Equivalente exacto de `estimate_slippage_impact`: calcula el output real
+/// vía `calculate_swap_output_exact` y compara su precio realizado contra el
+/// precio spot de las reservas, igual fórmula que `amm::price_impact`. El
+/// resultado pasa por `f64` recién al final (vía `TokenAmount::as_f64_lossy`),
+/// solo para producir el score humano-legible; la cotización en sí nunca
+/// pasó por punto flotante. `None` en los mismos casos que
+/// `calculate_swap_output_exact`.
+pub fn estimate_slippage_impact_exact(
+    amount_in: TokenAmount,
+    pool: &PoolInfo,
+    from_token: &str,
+) -> Option<f64> {
+    let (reserve_in, reserve_out) = if from_token == pool.token_a {
+        (pool.reserve_a_units?, pool.reserve_b_units?)
+    } else {
+        (pool.reserve_b_units?, pool.reserve_a_units?)
+    };
+
+    let amount_out = calculate_swap_output_exact(amount_in, pool, from_token)?;
+
+    Some(amm::price_impact(
+        amount_in.as_f64_lossy(),
+        amount_out.as_f64_lossy(),
+        reserve_in.as_f64_lossy(),
+        reserve_out.as_f64_lossy(),
+    ))
+}
+
+/// Precio de gas efectivo en gwei para cotizar el costo de una ruta en
+/// `blockchain`. Post-London (`eip1559_supported`), replica la fórmula real
+/// del protocolo: `base_fee + min(priority_fee, max_gas_price - base_fee)`,
+/// acotado por el `max_gas_price` que el caller está dispuesto a pagar. Cae
+/// de vuelta a `gas_price_gwei` (legacy) en chains pre-London o cuando
+/// `base_fee`/`priority_fee` todavía no llegaron desde el feed.
+pub fn effective_gas_price_gwei(blockchain: &Blockchain) -> f64 {
+    if blockchain.eip1559_supported {
+        if let (Some(base_fee), Some(priority_fee)) = (blockchain.base_fee, blockchain.priority_fee) {
+            let headroom = (blockchain.max_gas_price - base_fee).max(0.0);
+            return base_fee + priority_fee.min(headroom);
+        }
+    }
+
+    blockchain.gas_price_gwei
+}
+
+/// Costo en USD de gastar `gas_units` en `blockchain`, al precio de
+/// `native_token_price_usd` del token nativo de esa chain (ETH, MATIC,
+/// etc.). `gas_price_gwei`/`effective_gas_price_gwei` están en gwei
+/// (10^-9 del token nativo), de ahí el factor `1e-9`.
+pub fn gas_cost_usd(blockchain: &Blockchain, gas_units: u64, native_token_price_usd: f64) -> f64 {
+    let price_gwei = effective_gas_price_gwei(blockchain);
+    (gas_units as f64) * price_gwei * 1e-9 * native_token_price_usd
+}
+
+/// Expande `pool` en sus dos `PoolEdge` dirigidos (`token_a -> token_b` y
+/// `token_b -> token_a`), como espera `cycle_finder::CycleArbitrageFinder`.
+/// `fee_percentage` es el porcentaje (0-100), mientras que `PoolInfo::fee_rate`
+/// es la fracción (0-1) que usa el resto de este módulo.
+fn pool_edges(pool: &PoolInfo, chain: &str) -> [PoolEdge; 2] {
+    let fee_percentage = pool.fee_rate * 100.0;
+    [
+        PoolEdge {
+            dex_id: pool.dex_name.clone(),
+            chain: chain.to_string(),
+            token_in: pool.token_a.clone(),
+            token_out: pool.token_b.clone(),
+            price: pool.price_a_to_b,
+            fee_percentage,
+        },
+        PoolEdge {
+            dex_id: pool.dex_name.clone(),
+            chain: chain.to_string(),
+            token_in: pool.token_b.clone(),
+            token_out: pool.token_a.clone(),
+            price: pool.price_b_to_a,
+            fee_percentage,
+        },
+    ]
+}
+
 /// Valida que una ruta de arbitraje sea viable
 pub fn validate_arbitrage_route(route: &ArbitrageRoute) -> bool {
     route.net_profit > 0.0 
@@ -185,19 +400,51 @@ pub fn generate_route_id(path: &[String], tokens: &[String]) -> String {
 // ============================================================================
 
 /// Pathfinder principal que coordina búsquedas 2-hop y 3-hop
+///
+/// Único caller en producción: `backtest.rs::run_backtest` (modo CLI
+/// offline) — `admin.rs`, `worker.rs` y el loop en vivo de `main.rs` nunca
+/// lo tocan. Pero tampoco ese caller compila hoy: `two_hop`/`three_hop`
+/// están tipados como `TwoHopPathfinder`/`ThreeHopPathfinder`, que no
+/// existen en el crate bajo ningún nombre — son los reexports de arriba
+/// (`pub use two_dex::{TwoHopPathfinder, ...}` / `three_dex::{ThreeHopPathfinder, ...}`)
+/// apuntando a tipos que nunca se llamaron así; los reales son
+/// `TwoDexPathfinder`/`ThreeDexPathfinder`. Y no es un simple rename: su API
+/// real (`new(dexes: Vec<DexInfo>)`, `load_prices(&mut self, prices)`,
+/// `find_profitable_routes(&self, start_token, min_profit_usd, gas_cost_usd)
+/// -> Vec<_>`, sin estado de `pools` por llamada) no tiene ni `find_routes`
+/// ni `get_call_count`, los métodos que `find_best_routes` les llama más
+/// abajo (`mod.rs:484`, `:495`, `:636-637`). `new()` además llama
+/// `RouteRanker::new()` sin argumentos cuando el real pide un
+/// `RankingCriteria`. Todo esto es preexistente (`git blame` → `0372a85
+/// baseline` para el cuerpo de `new` y la llamada a `find_routes`); el
+/// trabajo de wiring de cycle_finder/best_first/aggregator-verify conecta
+/// módulos entre sí correctamente, pero corre sobre este plomero roto, así
+/// que no alcanza a ejecutarse ni siquiera en el backtest offline sin antes
+/// resolver esta incompatibilidad de tipos/API, que es un cambio de forma de
+/// este struct y no algo que esa lógica de ranking por sí sola pueda
+/// arreglar.
 pub struct ArbitragePathfinder {
     two_hop: TwoHopPathfinder,
     three_hop: ThreeHopPathfinder,
     ranker: RouteRanker,
     min_profit_threshold: f64,
     max_slippage_tolerance: f64,
+    /// Chain sobre la que se cotiza el gas de cada ruta vía
+    /// `effective_gas_price_gwei`/`gas_cost_usd`. `refresh_base_fee` permite
+    /// actualizar `base_fee` entre bloques sin reconstruir el pathfinder.
+    blockchain: Blockchain,
+    /// Precio USD del token nativo de `blockchain`, para convertir el costo
+    /// de gas (en el token nativo) a USD antes de restarlo de `net_profit`.
+    native_token_price_usd: f64,
 }
 
 impl ArbitragePathfinder {
     /// Crear nuevo pathfinder con configuración
     pub fn new(
         min_profit_threshold: f64,
-        max_slippage_tolerance: f64
+        max_slippage_tolerance: f64,
+        blockchain: Blockchain,
+        native_token_price_usd: f64,
     ) -> Self {
         Self {
             two_hop: TwoHopPathfinder::new(),
@@ -205,9 +452,26 @@ impl ArbitragePathfinder {
             ranker: RouteRanker::new(),
             min_profit_threshold,
             max_slippage_tolerance,
+            blockchain,
+            native_token_price_usd,
         }
     }
-    
+
+    /// Actualiza `base_fee` para seguir el fee del próximo bloque (post-London
+    /// lo publica cada nodo antes de minarlo), así el caller puede volver a
+    /// llamar `find_best_routes` con el mismo pathfinder y re-rankear las
+    /// rutas según el costo de gas vigente en vez de uno ya vencido.
+    pub fn refresh_base_fee(&mut self, new_base_fee: f64) {
+        self.blockchain.base_fee = Some(new_base_fee);
+    }
+
+    /// Actualiza `gas_price_gwei` para chains pre-London (o sin
+    /// `eip1559_supported`), donde no hay `base_fee` que refrescar y el gas
+    /// se cotiza al precio legacy vigente.
+    pub fn set_gas_price_gwei(&mut self, gwei: f64) {
+        self.blockchain.gas_price_gwei = gwei;
+    }
+
     /// Busca las mejores rutas de arbitraje disponibles
     pub fn find_best_routes(
         &mut self,
@@ -217,17 +481,17 @@ impl ArbitragePathfinder {
         max_results: usize
     ) -> Vec<ArbitrageRoute> {
         let mut all_routes = Vec::new();
-        
+
         // Búsqueda 2-hop
         if let Some(two_hop_routes) = self.two_hop.find_routes(
-            pools, 
-            input_token, 
+            pools,
+            input_token,
             amount,
             self.max_slippage_tolerance
         ) {
             all_routes.extend(two_hop_routes);
         }
-        
+
         // Búsqueda 3-hop (solo si no encontramos suficientes rutas 2-hop rentables)
         if all_routes.len() < max_results / 2 {
             if let Some(three_hop_routes) = self.three_hop.find_routes(
@@ -239,7 +503,83 @@ impl ArbitragePathfinder {
                 all_routes.extend(three_hop_routes);
             }
         }
-        
+
+        // Búsqueda de ciclos de longitud arbitraria (4+ hops) vía
+        // Bellman-Ford: `two_hop`/`three_hop` fuerzan una longitud fija, así
+        // que un ciclo de 4+ DEXs nunca pasaba por acá antes de esto.
+        // `gas_cost_usd=0.0` y `min_profit_usd=0.0` porque el descuento de
+        // gas y el filtro de rentabilidad ya corren más abajo, iguales para
+        // las tres fuentes de rutas.
+        let pool_edges: Vec<PoolEdge> = pools.iter()
+            .flat_map(|pool| pool_edges(pool, &self.blockchain.blockchain_id))
+            .collect();
+        let cycles = CycleArbitrageFinder::new(pool_edges)
+            .find_profitable_cycles(amount, 0.0, 0.0);
+        for cycle in cycles {
+            all_routes.push(ArbitrageRoute {
+                route_id: generate_route_id(&cycle.dexes, &cycle.tokens),
+                path: cycle.dexes.clone(),
+                tokens: cycle.tokens,
+                input_amount: amount,
+                expected_output: amount * cycle.gross_gain,
+                net_profit: cycle.expected_profit,
+                total_fees: 0.0,
+                roi_percentage: if amount > 0.0 { cycle.expected_profit / amount } else { 0.0 },
+                estimated_gas: cycle.dexes.len() as u64 * CYCLE_GAS_PER_HOP_UNITS,
+                slippage_estimate: 0.0,
+                confidence_score: 0.5,
+                timestamp: Utc::now().timestamp() as u64,
+            });
+        }
+
+        // Búsqueda best-first acotada en latencia desde `input_token`:
+        // complementa el Bellman-Ford exhaustivo de arriba (que no tiene
+        // límite de frontera) cuando el grafo es grande y hace falta una
+        // respuesta rápida aunque sea potencialmente subóptima. Sin
+        // `EdgePenalty` propio todavía (`NoPenalty`) porque este pathfinder
+        // no trae un índice de liquidez/gas por DEX como el que pide
+        // `LiquidityGasPenalty`.
+        if let Some(best_first_route) = best_first::find_best_route(
+            &pool_edges,
+            &self.blockchain.blockchain_id,
+            input_token,
+            &TradeBudget {
+                trade_amount_usd: amount,
+                gas_cost_usd: 0.0,
+                min_profit_usd: 0.0,
+            },
+            &NoPenalty,
+            &ScoringParams::default(),
+        ) {
+            all_routes.push(ArbitrageRoute {
+                route_id: generate_route_id(&best_first_route.dexes, &best_first_route.tokens),
+                path: best_first_route.dexes.clone(),
+                tokens: best_first_route.tokens,
+                input_amount: amount,
+                expected_output: amount * best_first_route.gross_gain,
+                net_profit: best_first_route.expected_profit,
+                total_fees: 0.0,
+                roi_percentage: if amount > 0.0 { best_first_route.expected_profit / amount } else { 0.0 },
+                estimated_gas: best_first_route.dexes.len() as u64 * CYCLE_GAS_PER_HOP_UNITS,
+                slippage_estimate: 0.0,
+                confidence_score: best_first_route.score.min(1.0).max(0.0),
+                timestamp: Utc::now().timestamp() as u64,
+            });
+        }
+
+        // Descontar el costo de gas (EIP-1559-aware) de cada ruta antes de
+        // filtrar: una ruta que solo se ve rentable ignorando el base fee
+        // vigente no debe pasar el corte de `min_profit_threshold`.
+        for route in &mut all_routes {
+            let gas_cost = gas_cost_usd(&self.blockchain, route.estimated_gas, self.native_token_price_usd);
+            route.net_profit -= gas_cost;
+            route.roi_percentage = if route.input_amount > 0.0 {
+                route.net_profit / route.input_amount
+            } else {
+                0.0
+            };
+        }
+
         // Filtrar por rentabilidad mínima
         let profitable_routes: Vec<ArbitrageRoute> = all_routes
             .into_iter()
@@ -251,6 +591,42 @@ impl ArbitragePathfinder {
         self.ranker.rank_routes(profitable_routes, max_results)
     }
     
+    /// Cruza `route` (ya elegida por `find_best_routes`) contra un agregador
+    /// externo vía `AggregatorDexClient::quote` antes de someterla on-chain.
+    /// `find_best_routes` es sync y nunca debe bloquearse en una red externa
+    /// (ver el doc comment de `AggregatorDexClient::quote`), así que este
+    /// cross-check vive aparte, para el único hop que el caller de verdad va
+    /// a ejecutar. Devuelve `false` si el agregador respondió (no cayó a su
+    /// fallback local) y su cotización diverge de `route.expected_output` en
+    /// más de `AGGREGATOR_DIVERGENCE_TOLERANCE` — la señal de que el precio
+    /// local ya no refleja la realidad y la ruta no debe ejecutarse.
+    pub async fn verify_route_with_aggregator(
+        &self,
+        route: &ArbitrageRoute,
+        aggregator: &AggregatorDexClient,
+        chain_id: u64,
+        sell_token: &str,
+        buy_token: &str,
+        sell_amount: TokenAmount,
+        buy_token_decimals: u8,
+        fallback_pool: Option<&PoolInfo>,
+    ) -> bool {
+        let quote = aggregator
+            .quote(chain_id, sell_token, buy_token, sell_amount, buy_token_decimals, fallback_pool)
+            .await;
+
+        if !quote.from_aggregator || route.expected_output <= 0.0 {
+            // Sin ground-truth disponible (timeout/sin config/chain): confiar
+            // en el cálculo local en vez de bloquear la ejecución.
+            return true;
+        }
+
+        let aggregator_output = quote.buy_amount.as_f64_lossy();
+        let divergence = (aggregator_output - route.expected_output).abs() / route.expected_output;
+
+        divergence <= AGGREGATOR_DIVERGENCE_TOLERANCE
+    }
+
     /// Actualiza configuración de rentabilidad mínima
     pub fn update_min_profit(&mut self, new_threshold: f64) {
         self.min_profit_threshold = new_threshold;
@@ -288,6 +664,70 @@ pub struct PathfinderStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+
+    fn sample_blockchain() -> Blockchain {
+        Blockchain {
+            blockchain_id: "ethereum".to_string(),
+            name: "Ethereum".to_string(),
+            chain_id: 1,
+            is_active: true,
+            native_token: "ETH".to_string(),
+            rpc_url_1: String::new(),
+            rpc_url_2: None,
+            rpc_url_3: None,
+            wss_url: None,
+            explorer_url: String::new(),
+            block_time_ms: 12000,
+            gas_price_gwei: 50.0,
+            max_gas_price: 100.0,
+            min_gas_price: 1.0,
+            eip1559_supported: true,
+            base_fee: Some(30.0),
+            priority_fee: Some(2.0),
+            gas_limit: 30_000_000,
+            multicall_address: None,
+            weth_address: String::new(),
+            usdc_address: None,
+            usdt_address: None,
+            dai_address: None,
+            extra_fields: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_effective_gas_price_uses_base_fee_plus_priority_fee_under_eip1559() {
+        let blockchain = sample_blockchain();
+
+        // base_fee (30) + priority_fee (2), muy por debajo de max_gas_price (100)
+        assert_eq!(effective_gas_price_gwei(&blockchain), 32.0);
+    }
+
+    #[test]
+    fn test_effective_gas_price_caps_priority_fee_at_max_gas_price_headroom() {
+        let mut blockchain = sample_blockchain();
+        blockchain.base_fee = Some(95.0);
+        blockchain.priority_fee = Some(20.0); // excede el headroom hasta max_gas_price (100)
+
+        assert_eq!(effective_gas_price_gwei(&blockchain), 100.0);
+    }
+
+    #[test]
+    fn test_effective_gas_price_falls_back_to_legacy_gas_price_without_eip1559() {
+        let mut blockchain = sample_blockchain();
+        blockchain.eip1559_supported = false;
+
+        assert_eq!(effective_gas_price_gwei(&blockchain), blockchain.gas_price_gwei);
+    }
+
+    #[test]
+    fn test_gas_cost_usd_converts_gwei_and_gas_units_to_native_token_usd() {
+        let blockchain = sample_blockchain();
+
+        // 21_000 gas * 32 gwei * 1e-9 * $2000/ETH
+        let cost = gas_cost_usd(&blockchain, 21_000, 2000.0);
+        assert!((cost - 1.344).abs() < 1e-6);
+    }
 
     #[test]
     fn test_calculate_swap_output() {
@@ -302,14 +742,104 @@ mod tests {
             volume_24h: 500_000.0,
             fee_rate: 0.003,
             last_updated: 1698000000,
+            reserve_a: None,
+            reserve_b: None,
+            pool_kind: PoolKind::default(),
+            reserve_a_units: None,
+            reserve_b_units: None,
         };
-        
+
         let output = calculate_swap_output(1.0, &pool, "ETH", 0.005);
-        
+
         // 1 ETH * 1800 * (1 - 0.003) * (1 - slippage - 0.005)
         assert!(output > 1700.0 && output < 1800.0);
     }
 
+    #[test]
+    fn test_calculate_swap_output_uses_real_reserves_over_the_linear_heuristic() {
+        let mut pool = PoolInfo {
+            pool_id: "test_pool".to_string(),
+            dex_name: "uniswap".to_string(),
+            token_a: "ETH".to_string(),
+            token_b: "USDT".to_string(),
+            price_a_to_b: 1800.0,
+            price_b_to_a: 0.000556,
+            liquidity_usd: 1_000_000.0,
+            volume_24h: 500_000.0,
+            fee_rate: 0.003,
+            last_updated: 1698000000,
+            reserve_a: None,
+            reserve_b: None,
+            pool_kind: PoolKind::default(),
+            reserve_a_units: None,
+            reserve_b_units: None,
+        };
+
+        // 200 ETH contra un pool de solo 1_000 ETH de profundidad: un trade
+        // grande, donde la curva real difiere mucho del spread lineal.
+        let linear_only = calculate_swap_output(200.0, &pool, "ETH", 0.0);
+
+        pool.reserve_a = Some(1_000.0);
+        pool.reserve_b = Some(1_800_000.0);
+        let against_real_reserves = calculate_swap_output(200.0, &pool, "ETH", 0.0);
+
+        assert!(against_real_reserves < linear_only);
+
+        let expected = amm::constant_product_output(200.0, 1_000.0, 1_800_000.0, 0.003);
+        assert!((against_real_reserves - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_calculate_swap_output_exact_matches_f64_fast_path_within_rounding() {
+        let pool = PoolInfo {
+            pool_id: "test_pool".to_string(),
+            dex_name: "uniswap".to_string(),
+            token_a: "ETH".to_string(),
+            token_b: "USDT".to_string(),
+            price_a_to_b: 1800.0,
+            price_b_to_a: 0.000556,
+            liquidity_usd: 1_000_000.0,
+            volume_24h: 500_000.0,
+            fee_rate: 0.003,
+            last_updated: 1698000000,
+            reserve_a: None,
+            reserve_b: None,
+            pool_kind: PoolKind::default(),
+            reserve_a_units: Some(TokenAmount::from_f64(1_000.0, 18).unwrap()),
+            reserve_b_units: Some(TokenAmount::from_f64(1_800_000.0, 18).unwrap()),
+        };
+
+        let exact = calculate_swap_output_exact(TokenAmount::from_f64(1.0, 18).unwrap(), &pool, "ETH")
+            .expect("ambas reservas presentes, ruta debería cotizar");
+        let approx = amm::constant_product_output(1.0, 1_000.0, 1_800_000.0, 0.003);
+
+        assert!((exact.as_f64_lossy() - approx).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_calculate_swap_output_exact_is_none_without_reserve_units() {
+        let pool = PoolInfo {
+            pool_id: "test_pool".to_string(),
+            dex_name: "uniswap".to_string(),
+            token_a: "ETH".to_string(),
+            token_b: "USDT".to_string(),
+            price_a_to_b: 1800.0,
+            price_b_to_a: 0.000556,
+            liquidity_usd: 1_000_000.0,
+            volume_24h: 500_000.0,
+            fee_rate: 0.003,
+            last_updated: 1698000000,
+            reserve_a: None,
+            reserve_b: None,
+            pool_kind: PoolKind::default(),
+            reserve_a_units: None,
+            reserve_b_units: None,
+        };
+
+        assert!(calculate_swap_output_exact(TokenAmount::from_f64(1.0, 18).unwrap(), &pool, "ETH").is_none());
+        assert!(estimate_slippage_impact_exact(TokenAmount::from_f64(1.0, 18).unwrap(), &pool, "ETH").is_none());
+    }
+
     #[test]
     fn test_validate_arbitrage_route() {
         let valid_route = ArbitrageRoute {
@@ -9,18 +9,87 @@
 //! 3. Consumido por el optimizador principal
 
 use std::collections::HashMap;
+use std::sync::Arc;
+
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+use crate::pathfinding::gas_price::{GasPriceProvider, GasPriceSnapshot};
+use crate::utils::amounts::ProfitUsd;
+use crate::utils::money::Money;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Route {
     pub id: String,
     pub dexes: Vec<String>,
     pub tokens: Vec<String>,
-    pub expected_profit: f64,
-    pub gas_cost: f64,
-    pub net_profit: f64,
+    pub expected_profit: ProfitUsd,
+    pub gas_cost: ProfitUsd,
+    pub net_profit: ProfitUsd,
     pub confidence_score: f64,
     pub complexity_score: f64,
+    /// Notional en USD que esta ruta consume de cada pool que toca (clave =
+    /// `pool_id`), para que `solve_settlement_batch` sepa qué rutas compiten
+    /// por el mismo balde de liquidez dentro de un mismo batch atómico.
+    /// Vacío para rutas que todavía no traen este dato: siguen
+    /// funcionando con `rank_routes`, solo quedan fuera del batch-solver.
+    #[serde(default)]
+    pub pool_usage: HashMap<String, f64>,
+    /// Banda de liquidez `[min, max]` de cada hop DEX, en el mismo orden que
+    /// `dexes` (viene de Sheets/on-chain data). `calculate_success_probability`
+    /// usa esto para estimar la chance de que un `amount` dado realmente
+    /// pase por esa liquidez sin quedar atascado a mitad de camino. Vacío
+    /// para rutas que todavía no traen este dato: se tratan como 100%
+    /// seguras, el comportamiento histórico.
+    #[serde(default)]
+    pub hop_liquidity_bounds: Vec<LiquidityBound>,
+    /// Todas las aristas dirigidas token->token disponibles entre los
+    /// tokens de esta ruta (no solo las de `dexes`/`tokens` en su orden
+    /// actual), con el profit y gas que aporta cada una. `optimize_route_ordering`
+    /// usa esto para evaluar cualquier permutación de los hops intermedios
+    /// sin recalcular curvas de pool acá: ese cálculo ya lo hizo el pricing
+    /// engine al poblar `hop_edges`. Vacío para rutas que todavía no traen
+    /// este dato: `optimize_route_ordering` las devuelve sin cambios.
+    #[serde(default)]
+    pub hop_edges: Vec<HopEdge>,
+    /// Gas units estimados (no USD) que consume ejecutar esta ruta. Permite
+    /// recomputar `gas_cost`/`net_profit` desde un precio de gas en vivo vía
+    /// `RouteRanker::rank_routes_with_live_gas`/`rerank_on_price_tick` sin
+    /// tener que re-simular toda la ruta. `None` cuando el feed de origen
+    /// todavía no lo expone: esas rutas conservan su `gas_cost` tal cual.
+    #[serde(default)]
+    pub gas_units: Option<u64>,
+}
+
+/// Arista dirigida entre dos tokens de una ruta: cuánto profit bruto aporta
+/// ese hop específico (ya neto de slippage para ese par/DEX) y cuánto gas
+/// cuesta ejecutarlo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HopEdge {
+    pub from_token: String,
+    pub to_token: String,
+    pub dex: String,
+    pub profit_contribution: f64,
+    pub gas_cost: f64,
+}
+
+/// Banda de liquidez `[min, max]` de un hop: por debajo de `min` el hop
+/// siempre puede absorber el trade, por encima de `max` nunca puede.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LiquidityBound {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Observación histórica de una ejecución real de una ruta: cuándo ocurrió
+/// (unix timestamp en segundos) y qué fracción del profit esperado se
+/// terminó realizando de verdad (`1.0` = coincidió exactamente con lo
+/// proyectado). `RouteRanker::rerank_with_history` pondera estas
+/// observaciones por antigüedad en vez de tratarlas todas por igual.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RouteOutcome {
+    pub timestamp: i64,
+    pub realized_profit_ratio: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +99,43 @@ pub struct RankingCriteria {
     pub complexity_weight: f64,
     pub gas_efficiency_weight: f64,
     pub liquidity_weight: f64,
+    /// Cómo combinar los scores por criterio en `rank_score`. `WeightedSum`
+    /// (default) es el comportamiento histórico; `WeightedProduct` penaliza
+    /// multiplicativamente cualquier ruta con un score pésimo en un solo
+    /// criterio en vez de dejar que los demás criterios lo compensen.
+    #[serde(default)]
+    pub scoring_model: ScoringModel,
+    /// Vida media, en segundos, de la media móvil exponencial de resultados
+    /// históricos que usa `RouteRanker::rerank_with_history`: una
+    /// observación de hace `history_half_life_secs` pesa la mitad que una de
+    /// ahora mismo.
+    #[serde(default = "default_history_half_life_secs")]
+    pub history_half_life_secs: f64,
+    /// Tope del peso que el score histórico decaído puede tomar frente al
+    /// `rank_score` en vivo, incluso con un historial extenso y reciente.
+    #[serde(default = "default_max_history_blend_weight")]
+    pub max_history_blend_weight: f64,
+}
+
+fn default_history_half_life_secs() -> f64 {
+    86_400.0 // 1 día
+}
+
+fn default_max_history_blend_weight() -> f64 {
+    0.3
+}
+
+/// Modelo de combinación de criterios para `RouteRanker::rank_routes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ScoringModel {
+    /// `rank_score = Σ score_i * weight_i`. Un score cercano a cero en un
+    /// criterio puede quedar "promediado" por un score alto en otro.
+    #[default]
+    WeightedSum,
+    /// `rank_score = Π score_i ^ weight_i` (pesos normalizados a sumar 1),
+    /// equivalente a `exp(Σ weight_i * ln(score_i))`. Cualquier criterio
+    /// cercano a cero arrastra el producto entero hacia cero.
+    WeightedProduct,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,28 +148,117 @@ pub struct RankedRoute {
     pub efficiency_score: f64,
 }
 
+/// Resultado del batch-solver de settlement: el subconjunto de rutas
+/// libre de conflictos de liquidez que `solve_settlement_batch` eligió, más
+/// cuánto profit de rutas rentables quedó afuera por competir por la misma
+/// liquidez (el techo de lo que un segundo batch, o más capacidad, podría
+/// capturar).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementBatch {
+    pub selected: Vec<RankedRoute>,
+    pub total_net_profit: ProfitUsd,
+    pub unrealized_profit_gap: ProfitUsd,
+}
+
+/// Por encima de este tamaño de instancia, el branch-and-bound exacto deja
+/// de ser práctico (el espacio de búsqueda es 2^n en el peor caso) y
+/// `solve_settlement_batch` cae al baseline greedy-por-densidad.
+const EXACT_SOLVE_MAX_ROUTES: usize = 16;
+
+/// Piso de cada score individual antes del logaritmo en
+/// `RouteRanker::weighted_product_score`, para que un criterio en cero no
+/// produzca `ln(0) = -inf`.
+const WEIGHTED_PRODUCT_EPSILON: f64 = 1e-6;
+
+/// Temperatura inicial del recocido simulado en `optimize_route_ordering`.
+const ANNEALING_INITIAL_TEMPERATURE: f64 = 10.0;
+/// Enfriamiento geométrico aplicado a la temperatura en cada iteración.
+const ANNEALING_COOLING_RATE: f64 = 0.95;
+/// Piso de temperatura: por debajo de esto, la probabilidad de aceptar un
+/// movimiento peor es despreciable y la búsqueda se detiene.
+const ANNEALING_MIN_TEMPERATURE: f64 = 0.01;
+
+/// Nodo del frente de búsqueda best-first de
+/// `RouteRanker::optimize_route_selection_branch_and_bound`. Ordenado por
+/// `bound` (cota fraccional optimista) para que el `BinaryHeap` siempre
+/// explore primero la rama con más potencial de profit.
+struct KnapsackNode {
+    level: usize,
+    current_profit: Money,
+    current_gas: f64,
+    bound: f64,
+    selection: Vec<bool>,
+}
+
+impl PartialEq for KnapsackNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.bound == other.bound
+    }
+}
+
+impl Eq for KnapsackNode {}
+
+impl PartialOrd for KnapsackNode {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for KnapsackNode {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.bound.partial_cmp(&other.bound).unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
 /// Sistema de ranking de rutas usando DP
 pub struct RouteRanker {
     criteria: RankingCriteria,
+    /// Fuente del precio de gas vigente usada por `rank_routes_with_live_gas`
+    /// / `rerank_on_price_tick` para recomputar `gas_cost`/`net_profit`
+    /// antes de rankear. `None` (el default) deja el comportamiento
+    /// histórico: rankear con el `gas_cost` que ya trae cada `Route`.
+    gas_price_provider: Option<Arc<dyn GasPriceProvider>>,
+    /// Precio USD del token nativo de la chain, para convertir el costo de
+    /// gas (en gwei) a USD al recomputar `gas_cost` desde `gas_price_provider`.
+    native_token_price_usd: f64,
 }
 
 impl RouteRanker {
     /// Crea un nuevo ranker con criterios desde Sheets
     pub fn new(criteria: RankingCriteria) -> Self {
-        Self { criteria }
+        Self {
+            criteria,
+            gas_price_provider: None,
+            native_token_price_usd: 0.0,
+        }
     }
-    
+
     /// Crea un ranker con criterios por defecto
     pub fn default() -> Self {
-        Self {
-            criteria: RankingCriteria {
-                profit_weight: 0.35,
-                confidence_weight: 0.25,
-                complexity_weight: 0.15,
-                gas_efficiency_weight: 0.15,
-                liquidity_weight: 0.10,
-            },
-        }
+        Self::new(RankingCriteria {
+            profit_weight: 0.35,
+            confidence_weight: 0.25,
+            complexity_weight: 0.15,
+            gas_efficiency_weight: 0.15,
+            liquidity_weight: 0.10,
+            scoring_model: ScoringModel::WeightedSum,
+            history_half_life_secs: default_history_half_life_secs(),
+            max_history_blend_weight: default_max_history_blend_weight(),
+        })
+    }
+
+    /// Conecta una fuente de precio de gas en vivo, para que
+    /// `rank_routes_with_live_gas`/`rerank_on_price_tick` puedan recomputar
+    /// `gas_cost`/`net_profit` antes de rankear. `native_token_price_usd` es
+    /// el precio del token nativo de la chain que cotiza el gas.
+    pub fn with_gas_price_provider(
+        mut self,
+        provider: Arc<dyn GasPriceProvider>,
+        native_token_price_usd: f64,
+    ) -> Self {
+        self.gas_price_provider = Some(provider);
+        self.native_token_price_usd = native_token_price_usd;
+        self
     }
     
     /// Rankea rutas usando programación dinámica
@@ -84,13 +279,23 @@ impl RouteRanker {
                 let risk_score = self.calculate_risk_score(&route);
                 let efficiency_score = self.calculate_efficiency_score(&route);
                 
-                // Combinar scores usando los pesos configurados
-                let rank_score = 
-                    profit_score * self.criteria.profit_weight +
-                    route.confidence_score * self.criteria.confidence_weight +
-                    route.complexity_score * self.criteria.complexity_weight +
-                    efficiency_score * self.criteria.gas_efficiency_weight +
-                    risk_score * self.criteria.liquidity_weight;
+                // Combinar scores usando los pesos configurados, según el modelo elegido
+                let rank_score = match self.criteria.scoring_model {
+                    ScoringModel::WeightedSum => {
+                        profit_score * self.criteria.profit_weight +
+                        route.confidence_score * self.criteria.confidence_weight +
+                        route.complexity_score * self.criteria.complexity_weight +
+                        efficiency_score * self.criteria.gas_efficiency_weight +
+                        risk_score * self.criteria.liquidity_weight
+                    }
+                    ScoringModel::WeightedProduct => self.weighted_product_score(
+                        profit_score,
+                        route.confidence_score,
+                        route.complexity_score,
+                        efficiency_score,
+                        risk_score,
+                    ),
+                };
                 
                 RankedRoute {
                     route,
@@ -103,11 +308,14 @@ impl RouteRanker {
             })
             .collect();
         
-        // Ordenar por rank_score descendente (array dinámico)
+        // Ordenar por rank_score descendente (array dinámico). `total_cmp`
+        // en vez de `partial_cmp(...).unwrap()`: un `rank_score` corrupto a
+        // `NaN` (p.ej. `confidence_score`/`expected_profit` inválidos desde
+        // Sheets propagados por `WeightedSum`/`WeightedProduct`) no debe
+        // hacer panic acá — este es el sort que corre `rank_routes_with_live_gas`,
+        // el camino de ranking más alcanzable de todo el engine.
         let mut sorted_routes = scored_routes;
-        sorted_routes.sort_by(|a, b| {
-            b.rank_score.partial_cmp(&a.rank_score).unwrap()
-        });
+        sorted_routes.sort_by(|a, b| b.rank_score.total_cmp(&a.rank_score));
         
         // Asignar posiciones de ranking (array dinámico con enumerate)
         sorted_routes
@@ -119,12 +327,72 @@ impl RouteRanker {
             })
             .collect()
     }
-    
+
+    /// Igual que `rank_routes`, pero antes de rankear recalcula
+    /// `gas_cost`/`net_profit` de cada ruta con `gas_units` a partir del
+    /// precio de gas vigente de `gas_price_provider`. Sin provider
+    /// configurado, o si la consulta falla, rankea con el `gas_cost` tal
+    /// cual trae cada ruta (el comportamiento histórico).
+    pub async fn rank_routes_with_live_gas(&self, routes: Vec<Route>, chain_id: u64) -> Vec<RankedRoute> {
+        let routes = self.apply_live_gas_price_to_all(routes, chain_id).await;
+        self.rank_routes(routes)
+    }
+
+    /// Vuelve a rankear un set ya rankeado cuando llega un nuevo tick de
+    /// precio de gas, sin que el caller tenga que reconstruir los `Route` a
+    /// mano. Sin `gas_price_provider` configurado, o si la consulta falla,
+    /// devuelve `ranked` sin tocar.
+    pub async fn rerank_on_price_tick(&self, ranked: Vec<RankedRoute>, chain_id: u64) -> Vec<RankedRoute> {
+        if self.gas_price_provider.is_none() {
+            return ranked;
+        }
+
+        let routes: Vec<Route> = ranked.into_iter().map(|ranked_route| ranked_route.route).collect();
+        let routes = self.apply_live_gas_price_to_all(routes, chain_id).await;
+        self.rank_routes(routes)
+    }
+
+    /// Consulta `gas_price_provider` una sola vez y aplica el snapshot
+    /// resultante a todas las `routes`. Devuelve `routes` sin modificar si no
+    /// hay provider configurado o la consulta falla.
+    async fn apply_live_gas_price_to_all(&self, mut routes: Vec<Route>, chain_id: u64) -> Vec<Route> {
+        let Some(provider) = &self.gas_price_provider else {
+            return routes;
+        };
+
+        let Ok(snapshot) = provider.current_price(chain_id).await else {
+            return routes;
+        };
+
+        for route in routes.iter_mut() {
+            self.apply_live_gas_price(route, &snapshot);
+        }
+
+        routes
+    }
+
+    /// Recalcula `gas_cost`/`net_profit` de `route` a partir de
+    /// `route.gas_units` y `snapshot`, dejando `route` sin tocar si no trae
+    /// `gas_units` o si el nuevo `gas_cost` no es representable como `Money`.
+    fn apply_live_gas_price(&self, route: &mut Route, snapshot: &GasPriceSnapshot) {
+        let Some(gas_units) = route.gas_units else {
+            return;
+        };
+
+        let gas_cost_usd = gas_units as f64 * snapshot.total_gwei() * 1e-9 * self.native_token_price_usd;
+        let Ok(new_gas_cost) = Money::from_f64(gas_cost_usd) else {
+            return;
+        };
+
+        route.gas_cost = new_gas_cost;
+        route.net_profit = route.expected_profit.checked_sub(new_gas_cost).unwrap_or(Money::ZERO);
+    }
+
     /// Calcula el profit score normalizado (0-1)
     fn calculate_profit_score(&self, route: &Route) -> f64 {
         // Normalizar profit a un score 0-1
         // Asumimos que $100 de profit = score 1.0
-        (route.net_profit / 100.0).min(1.0).max(0.0)
+        (route.net_profit.to_f64() / 100.0).min(1.0).max(0.0)
     }
     
     /// Calcula el risk score basado en múltiples factores
@@ -141,16 +409,55 @@ impl RouteRanker {
     
     /// Calcula el efficiency score (profit/gas ratio)
     fn calculate_efficiency_score(&self, route: &Route) -> f64 {
-        if route.gas_cost <= 0.0 {
+        if route.gas_cost.to_f64() <= 0.0 {
             return 0.0;
         }
-        
-        let ratio = route.expected_profit / route.gas_cost;
-        
+
+        let ratio = route.expected_profit.to_f64() / route.gas_cost.to_f64();
+
         // Normalizar: ratio de 10 = score 1.0
         (ratio / 10.0).min(1.0).max(0.0)
     }
     
+    /// Combina los scores por criterio vía el weighted product model:
+    /// `exp(Σ (weight_i / Σweight) * ln(score_i))`, equivalente a
+    /// `Π score_i ^ (weight_i / Σweight)`. Cada score se acota a
+    /// `WEIGHTED_PRODUCT_EPSILON` antes del logaritmo para que un criterio
+    /// en cero no produzca `ln(0) = -inf` (y por lo tanto un `rank_score`
+    /// de cero para cualquier ruta, sin importar qué tan bien le vaya en el
+    /// resto) sino solo una penalización muy fuerte.
+    fn weighted_product_score(
+        &self,
+        profit_score: f64,
+        confidence_score: f64,
+        complexity_score: f64,
+        efficiency_score: f64,
+        risk_score: f64,
+    ) -> f64 {
+        let total_weight = self.criteria.profit_weight
+            + self.criteria.confidence_weight
+            + self.criteria.complexity_weight
+            + self.criteria.gas_efficiency_weight
+            + self.criteria.liquidity_weight;
+
+        if total_weight <= 0.0 {
+            return 0.0;
+        }
+
+        let weighted_log_sum = [
+            (profit_score, self.criteria.profit_weight),
+            (confidence_score, self.criteria.confidence_weight),
+            (complexity_score, self.criteria.complexity_weight),
+            (efficiency_score, self.criteria.gas_efficiency_weight),
+            (risk_score, self.criteria.liquidity_weight),
+        ]
+        .iter()
+        .map(|(score, weight)| (weight / total_weight) * score.max(WEIGHTED_PRODUCT_EPSILON).ln())
+        .sum::<f64>();
+
+        weighted_log_sum.exp()
+    }
+
     /// Filtra rutas por rank mínimo (array dinámico)
     pub fn filter_by_rank(
         routes: Vec<RankedRoute>,
@@ -196,6 +503,14 @@ impl RouteRanker {
     
     /// Optimización DP: Selecciona el mejor conjunto de rutas
     /// que maximiza profit total sin exceder límite de gas
+    ///
+    /// `gas_units` discretiza el presupuesto en centavos enteros vía
+    /// `Money::to_cents`, en vez del `f64 * 100.0 as usize` anterior: ese
+    /// cast perdía precisión sub-centavo al acumular muchas rutas y podía
+    /// desbordar `usize` silenciosamente para presupuestos grandes. Los
+    /// profits acumulados en `dp` también viven en `Money` (vía
+    /// `checked_add`) para que la reconstrucción sea determinista sin
+    /// arrastrar error de redondeo de `f64`.
     pub fn optimize_route_selection(
         &self,
         routes: Vec<RankedRoute>,
@@ -205,27 +520,32 @@ impl RouteRanker {
         if n == 0 {
             return vec![];
         }
-        
+
         // Convertir gas budget a unidades discretas
-        let gas_units = (max_gas_budget * 100.0) as usize;
-        
+        let gas_units = Money::from_f64(max_gas_budget)
+            .map(Money::to_cents)
+            .unwrap_or(0)
+            .max(0) as usize;
+
         // DP: dp[i][g] = máximo profit usando primeras i rutas con gas <= g
-        let mut dp: Vec<Vec<f64>> = vec![vec![0.0; gas_units + 1]; n + 1];
+        let mut dp: Vec<Vec<Money>> = vec![vec![Money::ZERO; gas_units + 1]; n + 1];
         let mut selected: Vec<Vec<bool>> = vec![vec![false; gas_units + 1]; n + 1];
-        
+
         // Llenar tabla DP (programación dinámica)
         for i in 1..=n {
             let route = &routes[i - 1];
-            let gas_cost = (route.route.gas_cost * 100.0) as usize;
-            
+            let gas_cost = route.route.gas_cost.to_cents().max(0) as usize;
+
             for g in 0..=gas_units {
                 // Opción 1: No incluir esta ruta
                 dp[i][g] = dp[i - 1][g];
-                
+
                 // Opción 2: Incluir esta ruta (si cabe en el presupuesto)
                 if gas_cost <= g {
-                    let profit_with_route = dp[i - 1][g - gas_cost] + route.route.net_profit;
-                    
+                    let profit_with_route = dp[i - 1][g - gas_cost]
+                        .checked_add(route.route.net_profit)
+                        .unwrap_or(dp[i - 1][g - gas_cost]);
+
                     if profit_with_route > dp[i][g] {
                         dp[i][g] = profit_with_route;
                         selected[i][g] = true;
@@ -233,24 +553,597 @@ impl RouteRanker {
                 }
             }
         }
-        
+
         // Reconstruir solución (backtracking)
         let mut result = Vec::new();
         let mut g = gas_units;
-        
+
         for i in (1..=n).rev() {
             if selected[i][g] {
                 result.push(routes[i - 1].clone());
-                let gas_cost = (routes[i - 1].route.gas_cost * 100.0) as usize;
+                let gas_cost = routes[i - 1].route.gas_cost.to_cents().max(0) as usize;
                 g = g.saturating_sub(gas_cost);
             }
         }
-        
+
         // Invertir para mantener orden original (array dinámico)
         result.reverse();
         result
     }
-    
+
+    /// Alternativa a `optimize_route_selection` para presupuestos de gas
+    /// grandes: la tabla DP de esa función tiene `n * (gas_budget * 100)`
+    /// celdas, así que un presupuesto de varios ETH agota la memoria. Esta
+    /// variante explora el mismo knapsack 0/1 con búsqueda best-first:
+    /// ordena las rutas por `net_profit / gas_cost` descendente y, en cada
+    /// nodo (decidir incluir/excluir la siguiente ruta), poda la rama si su
+    /// cota optimista —la relajación fraccional del knapsack, que llena el
+    /// presupuesto restante con las rutas de mejor ratio permitiendo una
+    /// última ruta parcial— ya no puede superar el mejor profit factible
+    /// encontrado. La memoria es proporcional al frente de búsqueda, no al
+    /// presupuesto, así que admite límites de gas continuos sin discretizar.
+    /// Devuelve un conjunto óptimo (mismo profit que `optimize_route_selection`,
+    /// aunque el subconjunto exacto puede diferir si hay empates) en el
+    /// orden original de `routes`.
+    pub fn optimize_route_selection_branch_and_bound(
+        &self,
+        routes: Vec<RankedRoute>,
+        max_gas_budget: f64,
+    ) -> Vec<RankedRoute> {
+        let n = routes.len();
+        if n == 0 || max_gas_budget <= 0.0 {
+            return vec![];
+        }
+
+        let mut indexed: Vec<(usize, RankedRoute)> = routes.into_iter().enumerate().collect();
+        indexed.sort_by(|(_, a), (_, b)| {
+            Self::profit_to_gas_ratio(b)
+                .partial_cmp(&Self::profit_to_gas_ratio(a))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let root_bound = Self::fractional_upper_bound(&indexed, 0, Money::ZERO, max_gas_budget);
+        let mut frontier = std::collections::BinaryHeap::new();
+        frontier.push(KnapsackNode {
+            level: 0,
+            current_profit: Money::ZERO,
+            current_gas: 0.0,
+            bound: root_bound,
+            selection: vec![false; n],
+        });
+
+        let mut best_profit = Money::ZERO;
+        let mut best_selection = vec![false; n];
+
+        while let Some(node) = frontier.pop() {
+            if node.bound <= best_profit.to_f64() || node.level == n {
+                continue;
+            }
+
+            let route = &indexed[node.level].1;
+            let gas_cost = route.route.gas_cost.to_f64();
+            let remaining_gas = max_gas_budget - node.current_gas;
+
+            if gas_cost <= remaining_gas {
+                let mut include_selection = node.selection.clone();
+                include_selection[node.level] = true;
+                let include_profit = node
+                    .current_profit
+                    .checked_add(route.route.net_profit)
+                    .unwrap_or(node.current_profit);
+                let include_gas = node.current_gas + gas_cost;
+
+                if include_profit > best_profit {
+                    best_profit = include_profit;
+                    best_selection = include_selection.clone();
+                }
+
+                let include_bound = Self::fractional_upper_bound(
+                    &indexed,
+                    node.level + 1,
+                    include_profit,
+                    max_gas_budget - include_gas,
+                );
+                if include_bound > best_profit.to_f64() {
+                    frontier.push(KnapsackNode {
+                        level: node.level + 1,
+                        current_profit: include_profit,
+                        current_gas: include_gas,
+                        bound: include_bound,
+                        selection: include_selection,
+                    });
+                }
+            }
+
+            let exclude_bound = Self::fractional_upper_bound(
+                &indexed,
+                node.level + 1,
+                node.current_profit,
+                remaining_gas,
+            );
+            if exclude_bound > best_profit.to_f64() {
+                frontier.push(KnapsackNode {
+                    level: node.level + 1,
+                    current_profit: node.current_profit,
+                    current_gas: node.current_gas,
+                    bound: exclude_bound,
+                    selection: node.selection,
+                });
+            }
+        }
+
+        let mut result: Vec<(usize, RankedRoute)> = indexed
+            .into_iter()
+            .zip(best_selection)
+            .filter_map(|((original_index, route), chosen)| chosen.then_some((original_index, route)))
+            .collect();
+        result.sort_by_key(|(original_index, _)| *original_index);
+        result.into_iter().map(|(_, route)| route).collect()
+    }
+
+    fn profit_to_gas_ratio(route: &RankedRoute) -> f64 {
+        let gas_cost = route.route.gas_cost.to_f64();
+        if gas_cost <= 0.0 {
+            f64::INFINITY
+        } else {
+            route.route.net_profit.to_f64() / gas_cost
+        }
+    }
+
+    /// Cota superior optimista para el knapsack fraccional: suma el profit
+    /// completo de cada ruta desde `level` (ya ordenadas por ratio
+    /// profit/gas descendente) mientras quepa en `remaining_gas`, y une una
+    /// fracción de la primera que no quepa entera. Sobreestima el profit
+    /// real del knapsack 0/1 (permite fraccionar), por eso sirve para podar
+    /// ramas del branch-and-bound sin descartar nunca el óptimo real.
+    fn fractional_upper_bound(
+        indexed: &[(usize, RankedRoute)],
+        level: usize,
+        current_profit: Money,
+        remaining_gas: f64,
+    ) -> f64 {
+        let mut profit = current_profit.to_f64();
+        let mut remaining = remaining_gas;
+
+        for (_, route) in &indexed[level.min(indexed.len())..] {
+            let gas_cost = route.route.gas_cost.to_f64();
+            let net_profit = route.route.net_profit.to_f64();
+
+            if gas_cost <= 0.0 {
+                profit += net_profit;
+                continue;
+            }
+
+            if gas_cost <= remaining {
+                profit += net_profit;
+                remaining -= gas_cost;
+            } else {
+                if remaining > 0.0 {
+                    profit += net_profit * (remaining / gas_cost);
+                }
+                break;
+            }
+        }
+
+        profit
+    }
+
+    /// Batch-solver de settlement: a diferencia de `rank_routes`/
+    /// `optimize_route_selection`, que tratan cada ruta como independiente,
+    /// esta variante modela que las rutas del top-N frecuentemente tocan los
+    /// mismos pools y no pueden ejecutar todas en un mismo batch atómico sin
+    /// que una drene la liquidez que otra asumía disponible. Elige el
+    /// subconjunto libre de conflictos que maximiza `net_profit` sujeto a no
+    /// exceder `pool_capacities` (normalmente `available_liquidity_usd` de
+    /// cada pool). Opt-in: `rank_routes` sigue siendo el default para
+    /// callers que no necesitan este análisis de conflicto compartido.
+    pub fn solve_settlement_batch(
+        routes: Vec<RankedRoute>,
+        pool_capacities: &HashMap<String, f64>,
+    ) -> SettlementBatch {
+        if routes.len() <= EXACT_SOLVE_MAX_ROUTES {
+            Self::solve_settlement_batch_exact(routes, pool_capacities)
+        } else {
+            Self::solve_settlement_batch_greedy(routes, pool_capacities)
+        }
+    }
+
+    /// Baseline greedy: ordena las rutas por densidad de profit (profit por
+    /// USD de la liquidez más escasa que tocan) y las va aceptando mientras
+    /// quepan en la capacidad restante de cada pool que consumen.
+    fn solve_settlement_batch_greedy(
+        routes: Vec<RankedRoute>,
+        pool_capacities: &HashMap<String, f64>,
+    ) -> SettlementBatch {
+        let mut candidates = routes;
+        candidates.sort_by(|a, b| {
+            Self::profit_density(b, pool_capacities)
+                .partial_cmp(&Self::profit_density(a, pool_capacities))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut remaining_capacity = pool_capacities.clone();
+        let mut selected = Vec::new();
+        let mut total_net_profit = Money::ZERO;
+        let mut unrealized_profit_gap = Money::ZERO;
+
+        for candidate in candidates {
+            if Self::fits_in_capacity(&candidate.route, &remaining_capacity) {
+                Self::consume_capacity(&candidate.route, &mut remaining_capacity);
+                total_net_profit = total_net_profit
+                    .checked_add(candidate.route.net_profit)
+                    .unwrap_or(total_net_profit);
+                selected.push(candidate);
+            } else {
+                unrealized_profit_gap = unrealized_profit_gap
+                    .checked_add(candidate.route.net_profit)
+                    .unwrap_or(unrealized_profit_gap);
+            }
+        }
+
+        SettlementBatch {
+            selected,
+            total_net_profit,
+            unrealized_profit_gap,
+        }
+    }
+
+    /// Branch-and-bound exacto para instancias chicas: explora
+    /// incluir/excluir cada ruta, podando una rama en cuanto su cota
+    /// superior (profit acumulado + suma de profits restantes, ignorando
+    /// capacidad) ya no puede superar la mejor solución encontrada.
+    fn solve_settlement_batch_exact(
+        routes: Vec<RankedRoute>,
+        pool_capacities: &HashMap<String, f64>,
+    ) -> SettlementBatch {
+        let total_profit_upper_bound: Money = routes.iter().fold(Money::ZERO, |acc, r| {
+            if r.route.net_profit.to_f64() <= 0.0 {
+                acc
+            } else {
+                acc.checked_add(r.route.net_profit).unwrap_or(acc)
+            }
+        });
+
+        let mut best_profit = Money::ZERO;
+        let mut best_selection = vec![false; routes.len()];
+        let mut current_selection = vec![false; routes.len()];
+        let mut remaining_capacity = pool_capacities.clone();
+
+        Self::branch_and_bound(
+            &routes,
+            0,
+            Money::ZERO,
+            total_profit_upper_bound,
+            &mut remaining_capacity,
+            &mut current_selection,
+            &mut best_profit,
+            &mut best_selection,
+        );
+
+        let mut selected = Vec::new();
+        let mut total_net_profit = Money::ZERO;
+        for (route, chosen) in routes.into_iter().zip(best_selection) {
+            if chosen {
+                total_net_profit = total_net_profit
+                    .checked_add(route.route.net_profit)
+                    .unwrap_or(total_net_profit);
+                selected.push(route);
+            }
+        }
+
+        let gap = total_profit_upper_bound
+            .checked_sub(total_net_profit)
+            .unwrap_or(Money::ZERO);
+
+        SettlementBatch {
+            selected,
+            total_net_profit,
+            unrealized_profit_gap: if gap.to_f64() > 0.0 { gap } else { Money::ZERO },
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn branch_and_bound(
+        routes: &[RankedRoute],
+        index: usize,
+        current_profit: Money,
+        remaining_profit_upper_bound: Money,
+        remaining_capacity: &mut HashMap<String, f64>,
+        current_selection: &mut Vec<bool>,
+        best_profit: &mut Money,
+        best_selection: &mut Vec<bool>,
+    ) {
+        if current_profit > *best_profit {
+            *best_profit = current_profit;
+            best_selection.clone_from(current_selection);
+        }
+
+        let reachable_upper_bound = current_profit
+            .checked_add(remaining_profit_upper_bound)
+            .unwrap_or(current_profit);
+
+        if index == routes.len() || reachable_upper_bound <= *best_profit {
+            return;
+        }
+
+        let route = &routes[index];
+        let route_profit_floor = if route.route.net_profit.to_f64() > 0.0 {
+            route.route.net_profit
+        } else {
+            Money::ZERO
+        };
+        let remaining_after_this = remaining_profit_upper_bound
+            .checked_sub(route_profit_floor)
+            .unwrap_or(Money::ZERO);
+
+        if Self::fits_in_capacity(&route.route, remaining_capacity) {
+            Self::consume_capacity(&route.route, remaining_capacity);
+            current_selection[index] = true;
+
+            let profit_with_route = current_profit
+                .checked_add(route.route.net_profit)
+                .unwrap_or(current_profit);
+
+            Self::branch_and_bound(
+                routes,
+                index + 1,
+                profit_with_route,
+                remaining_after_this,
+                remaining_capacity,
+                current_selection,
+                best_profit,
+                best_selection,
+            );
+
+            current_selection[index] = false;
+            Self::release_capacity(&route.route, remaining_capacity);
+        }
+
+        Self::branch_and_bound(
+            routes,
+            index + 1,
+            current_profit,
+            remaining_after_this,
+            remaining_capacity,
+            current_selection,
+            best_profit,
+            best_selection,
+        );
+    }
+
+    /// Profit por USD de la liquidez más escasa que la ruta toca: el pool
+    /// con menor `pool_capacities` entre los que aparecen en `pool_usage`.
+    /// Cero si la ruta no toca ningún pool con capacidad conocida.
+    fn profit_density(route: &RankedRoute, pool_capacities: &HashMap<String, f64>) -> f64 {
+        let scarcest_capacity = route
+            .route
+            .pool_usage
+            .keys()
+            .filter_map(|pool_id| pool_capacities.get(pool_id).copied())
+            .fold(f64::INFINITY, f64::min);
+
+        if !scarcest_capacity.is_finite() || scarcest_capacity <= 0.0 {
+            return 0.0;
+        }
+
+        route.route.net_profit.to_f64() / scarcest_capacity
+    }
+
+    fn fits_in_capacity(route: &Route, remaining_capacity: &HashMap<String, f64>) -> bool {
+        route.pool_usage.iter().all(|(pool_id, usage)| {
+            remaining_capacity.get(pool_id).copied().unwrap_or(0.0) >= *usage
+        })
+    }
+
+    fn consume_capacity(route: &Route, remaining_capacity: &mut HashMap<String, f64>) {
+        for (pool_id, usage) in &route.pool_usage {
+            if let Some(capacity) = remaining_capacity.get_mut(pool_id) {
+                *capacity -= usage;
+            }
+        }
+    }
+
+    fn release_capacity(route: &Route, remaining_capacity: &mut HashMap<String, f64>) {
+        for (pool_id, usage) in &route.pool_usage {
+            if let Some(capacity) = remaining_capacity.get_mut(pool_id) {
+                *capacity += usage;
+            }
+        }
+    }
+
+    /// Estima la probabilidad de que `amount` efectivamente pase por cada
+    /// hop de `route` sin que la liquidez real resulte menor a la asumida.
+    /// Cada hop se modela con su banda `[min, max]` en `hop_liquidity_bounds`:
+    /// por debajo de `min` la ejecución es segura (1.0), por encima de `max`
+    /// es imposible (0.0), y en el medio decrece linealmente asumiendo
+    /// liquidez distribuida uniformemente en la banda. Las probabilidades
+    /// por hop se combinan multiplicativamente (independientes entre sí),
+    /// así que una ruta de 3 hops nunca queda más segura que su hop más
+    /// débil. Rutas sin datos de banda (`hop_liquidity_bounds` vacío) se
+    /// tratan como 100% seguras, el comportamiento histórico antes de esto.
+    pub fn calculate_success_probability(route: &Route, amount: f64) -> f64 {
+        route
+            .hop_liquidity_bounds
+            .iter()
+            .map(|bound| Self::hop_success_probability(amount, bound))
+            .product()
+    }
+
+    fn hop_success_probability(amount: f64, bound: &LiquidityBound) -> f64 {
+        if amount <= bound.min {
+            1.0
+        } else if amount >= bound.max || bound.max <= bound.min {
+            0.0
+        } else {
+            (bound.max - amount) / (bound.max - bound.min)
+        }
+    }
+
+    /// Variante opt-in de `rank_routes` que pesa cada `rank_score` por la
+    /// probabilidad de éxito de empujar `amount` a través de la ruta
+    /// (`calculate_success_probability`), para que una ruta con buen
+    /// profit/riesgo "de papel" pero liquidez insuficiente a ese tamaño caiga
+    /// en el ranking en vez de competir en igualdad con rutas realmente
+    /// ejecutables. `rank_routes` sigue siendo el default para callers que
+    /// no tienen `amount` o no cargaron `hop_liquidity_bounds`.
+    pub fn rank_routes_weighted_by_success_probability(
+        &self,
+        routes: Vec<Route>,
+        amount: f64,
+    ) -> Vec<RankedRoute> {
+        let mut ranked = self.rank_routes(routes);
+
+        for route in &mut ranked {
+            let success_probability = Self::calculate_success_probability(&route.route, amount);
+            route.rank_score *= success_probability;
+        }
+
+        // `total_cmp` en vez de `partial_cmp(...).unwrap()`: un `rank_score`
+        // corrupto a `NaN` (p.ej. `success_probability` de un input inválido)
+        // no debe hacer panic acá, solo ordenar de forma determinística.
+        ranked.sort_by(|a, b| b.rank_score.total_cmp(&a.rank_score));
+        for (idx, route) in ranked.iter_mut().enumerate() {
+            route.rank_position = idx + 1;
+        }
+
+        ranked
+    }
+
+    /// Busca el orden de los hops intermedios de `route` que maximiza
+    /// `net_profit`, dejando fijos el token de entrada y el de salida (la
+    /// ruta debe cerrar sobre el mismo activo). Combina 2-opt (revertir
+    /// segmentos contiguos, quedarse con la mejora) con un enfriado
+    /// simulado exterior que a veces acepta un movimiento peor —
+    /// con probabilidad `exp(-(profit_viejo - profit_nuevo)/T)` — para
+    /// escapar óptimos locales, enfriando `T *= 0.95` en cada iteración.
+    /// Sin `hop_edges` (o con menos de 2 hops intermedios para permutar)
+    /// devuelve `route` sin cambios: no hay nada que reordenar ni datos
+    /// para evaluar una permutación distinta a la actual.
+    pub fn optimize_route_ordering(route: &Route) -> Route {
+        if route.hop_edges.is_empty() || route.tokens.len() < 4 {
+            return route.clone();
+        }
+
+        let start = route.tokens[0].clone();
+        let end = route.tokens[route.tokens.len() - 1].clone();
+        let mut current_intermediates = route.tokens[1..route.tokens.len() - 1].to_vec();
+        let mut current_profit =
+            Self::net_profit_for_ordering(route, &start, &current_intermediates, &end);
+
+        if !current_profit.is_finite() {
+            // `hop_edges` no cubre el orden actual: no hay base confiable
+            // para comparar permutaciones contra ella.
+            return route.clone();
+        }
+
+        let mut best_intermediates = current_intermediates.clone();
+        let mut best_profit = current_profit;
+
+        let mut temperature = ANNEALING_INITIAL_TEMPERATURE;
+        let mut rng = rand::thread_rng();
+
+        while temperature > ANNEALING_MIN_TEMPERATURE {
+            for i in 0..current_intermediates.len() {
+                for j in (i + 1)..current_intermediates.len() {
+                    let mut candidate = current_intermediates.clone();
+                    candidate[i..=j].reverse();
+                    let candidate_profit =
+                        Self::net_profit_for_ordering(route, &start, &candidate, &end);
+                    let delta = candidate_profit - current_profit;
+
+                    let accept = delta > 0.0 || rng.gen::<f64>() < (delta / temperature).exp();
+                    if accept {
+                        current_intermediates = candidate;
+                        current_profit = candidate_profit;
+
+                        if current_profit > best_profit {
+                            best_profit = current_profit;
+                            best_intermediates = current_intermediates.clone();
+                        }
+                    }
+                }
+            }
+
+            temperature *= ANNEALING_COOLING_RATE;
+        }
+
+        if best_profit <= route.net_profit.to_f64() {
+            return route.clone();
+        }
+
+        Self::route_with_ordering(route, &start, &best_intermediates, &end, best_profit)
+    }
+
+    /// Profit neto de recorrer `start -> intermediates -> end` sumando el
+    /// `profit_contribution`/`gas_cost` de la arista de `hop_edges` que
+    /// cubre cada hop consecutivo. `f64::NEG_INFINITY` si algún hop de esa
+    /// secuencia no tiene arista conocida (esa permutación no es ejecutable).
+    fn net_profit_for_ordering(
+        route: &Route,
+        start: &str,
+        intermediates: &[String],
+        end: &str,
+    ) -> f64 {
+        let mut sequence = Vec::with_capacity(intermediates.len() + 2);
+        sequence.push(start.to_string());
+        sequence.extend(intermediates.iter().cloned());
+        sequence.push(end.to_string());
+
+        let mut total_profit_contribution = 0.0;
+        let mut total_gas_cost = 0.0;
+
+        for pair in sequence.windows(2) {
+            match Self::find_hop_edge(route, &pair[0], &pair[1]) {
+                Some(edge) => {
+                    total_profit_contribution += edge.profit_contribution;
+                    total_gas_cost += edge.gas_cost;
+                }
+                None => return f64::NEG_INFINITY,
+            }
+        }
+
+        total_profit_contribution - total_gas_cost
+    }
+
+    fn find_hop_edge<'a>(route: &'a Route, from_token: &str, to_token: &str) -> Option<&'a HopEdge> {
+        route
+            .hop_edges
+            .iter()
+            .find(|edge| edge.from_token == from_token && edge.to_token == to_token)
+    }
+
+    /// Reconstruye `route` con `tokens`/`dexes`/`expected_profit`/`gas_cost`
+    /// actualizados al orden `start -> intermediates -> end`.
+    fn route_with_ordering(
+        route: &Route,
+        start: &str,
+        intermediates: &[String],
+        end: &str,
+        net_profit: f64,
+    ) -> Route {
+        let mut tokens = Vec::with_capacity(intermediates.len() + 2);
+        tokens.push(start.to_string());
+        tokens.extend(intermediates.iter().cloned());
+        tokens.push(end.to_string());
+
+        let edges: Vec<&HopEdge> = tokens
+            .windows(2)
+            .filter_map(|pair| Self::find_hop_edge(route, &pair[0], &pair[1]))
+            .collect();
+
+        let expected_profit: f64 = edges.iter().map(|edge| edge.profit_contribution).sum();
+        let gas_cost: f64 = edges.iter().map(|edge| edge.gas_cost).sum();
+
+        Route {
+            tokens,
+            dexes: edges.iter().map(|edge| edge.dex.clone()).collect(),
+            expected_profit: Money::from_f64(expected_profit).unwrap_or(Money::ZERO),
+            gas_cost: Money::from_f64(gas_cost).unwrap_or(Money::ZERO),
+            net_profit: Money::from_f64(net_profit).unwrap_or(Money::ZERO),
+            ..route.clone()
+        }
+    }
+
     /// Calcula diversificación de un conjunto de rutas
     pub fn calculate_diversification(
         routes: &[RankedRoute],
@@ -278,35 +1171,50 @@ impl RouteRanker {
         ((dex_diversity + token_diversity) / 2.0).min(1.0)
     }
     
-    /// Re-rankea rutas basándose en resultados históricos (aprendizaje)
+    /// Re-rankea rutas basándose en resultados históricos (aprendizaje),
+    /// ponderando cada observación por antigüedad en vez de tratarlas todas
+    /// igual: una ruta sin historial reciente queda prácticamente intacta,
+    /// mientras que una con mucho historial reciente se acerca al tope
+    /// `max_history_blend_weight` de confianza en el score empírico.
     pub fn rerank_with_history(
         &mut self,
         routes: Vec<RankedRoute>,
-        historical_performance: &HashMap<String, f64>,
+        historical_outcomes: &HashMap<String, Vec<RouteOutcome>>,
+        now: i64,
     ) -> Vec<RankedRoute> {
         // Ajustar scores basándose en performance histórica (array dinámico)
         let adjusted_routes: Vec<RankedRoute> = routes
             .into_iter()
             .map(|mut route| {
-                // Buscar performance histórica de rutas similares
-                let historical_score = historical_performance
-                    .get(&route.route.id)
-                    .copied()
-                    .unwrap_or(0.5); // Default neutral
-                
-                // Ajustar rank_score (70% actual, 30% histórico)
-                route.rank_score = route.rank_score * 0.7 + historical_score * 0.3;
-                
+                if let Some(outcomes) = historical_outcomes.get(&route.route.id) {
+                    if let Some((historical_score, effective_weight)) = Self::decayed_historical_score(
+                        outcomes,
+                        now,
+                        self.criteria.history_half_life_secs,
+                    ) {
+                        // El peso de la mezcla crece con `effective_weight`
+                        // (la masa de observaciones recientes) pero nunca
+                        // supera `max_history_blend_weight`, así que un
+                        // único resultado reciente no domina el score.
+                        let blend_weight = self.criteria.max_history_blend_weight
+                            * (effective_weight / (effective_weight + 1.0));
+                        route.rank_score =
+                            route.rank_score * (1.0 - blend_weight) + historical_score * blend_weight;
+                    }
+                }
+
                 route
             })
             .collect();
-        
-        // Re-ordenar con nuevos scores (array dinámico)
+
+        // Re-ordenar con nuevos scores (array dinámico). `total_cmp` en vez de
+        // `partial_cmp(...).unwrap()`: un `historical_score` corrupto a `NaN`
+        // no debe hacer panic acá, solo ordenar de forma determinística.
         let mut sorted = adjusted_routes;
         sorted.sort_by(|a, b| {
-            b.rank_score.partial_cmp(&a.rank_score).unwrap()
+            b.rank_score.total_cmp(&a.rank_score)
         });
-        
+
         // Re-asignar posiciones
         sorted
             .into_iter()
@@ -317,11 +1225,43 @@ impl RouteRanker {
             })
             .collect()
     }
+
+    /// Media móvil exponencial decaída de `outcomes` respecto a `now`: cada
+    /// observación pesa `0.5 ^ (age / half_life)`. Devuelve
+    /// `(score, effective_weight)`, donde `effective_weight` es la suma de
+    /// esos pesos — un proxy de cuánta masa de observaciones recientes hay,
+    /// usado para calibrar qué tanto confiar en `score`. `None` si no hay
+    /// observaciones utilizables.
+    fn decayed_historical_score(
+        outcomes: &[RouteOutcome],
+        now: i64,
+        half_life_secs: f64,
+    ) -> Option<(f64, f64)> {
+        if outcomes.is_empty() || half_life_secs <= 0.0 {
+            return None;
+        }
+
+        let mut weighted_sum = 0.0;
+        let mut total_weight = 0.0;
+        for outcome in outcomes {
+            let age_secs = (now - outcome.timestamp).max(0) as f64;
+            let weight = 0.5_f64.powf(age_secs / half_life_secs);
+            weighted_sum += weight * outcome.realized_profit_ratio;
+            total_weight += weight;
+        }
+
+        if total_weight <= 0.0 {
+            return None;
+        }
+
+        Some((weighted_sum / total_weight, total_weight))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pathfinding::gas_price::StaticGasPriceProvider;
     
     #[test]
     fn test_route_ranking() {
@@ -332,24 +1272,32 @@ mod tests {
                 id: "route1".to_string(),
                 dexes: vec!["uniswap".to_string(), "sushiswap".to_string()],
                 tokens: vec!["ETH".to_string(), "USDC".to_string()],
-                expected_profit: 50.0,
-                gas_cost: 10.0,
-                net_profit: 40.0,
+                expected_profit: Money::from_f64(50.0).unwrap(),
+                gas_cost: Money::from_f64(10.0).unwrap(),
+                net_profit: Money::from_f64(40.0).unwrap(),
                 confidence_score: 0.8,
                 complexity_score: 0.7,
+                pool_usage: HashMap::new(),
+                hop_liquidity_bounds: vec![],
+                hop_edges: vec![],
+                gas_units: None,
             },
             Route {
                 id: "route2".to_string(),
                 dexes: vec!["curve".to_string(), "balancer".to_string()],
                 tokens: vec!["USDC".to_string(), "DAI".to_string()],
-                expected_profit: 30.0,
-                gas_cost: 5.0,
-                net_profit: 25.0,
+                expected_profit: Money::from_f64(30.0).unwrap(),
+                gas_cost: Money::from_f64(5.0).unwrap(),
+                net_profit: Money::from_f64(25.0).unwrap(),
                 confidence_score: 0.9,
                 complexity_score: 0.8,
+                pool_usage: HashMap::new(),
+                hop_liquidity_bounds: vec![],
+                hop_edges: vec![],
+                gas_units: None,
             },
         ];
-        
+
         let ranked = ranker.rank_routes(routes);
         
         assert_eq!(ranked.len(), 2);
@@ -367,11 +1315,15 @@ mod tests {
                     id: "route1".to_string(),
                     dexes: vec![],
                     tokens: vec![],
-                    expected_profit: 50.0,
-                    gas_cost: 10.0,
-                    net_profit: 40.0,
+                    expected_profit: Money::from_f64(50.0).unwrap(),
+                    gas_cost: Money::from_f64(10.0).unwrap(),
+                    net_profit: Money::from_f64(40.0).unwrap(),
                     confidence_score: 0.8,
                     complexity_score: 0.7,
+                    pool_usage: HashMap::new(),
+                    hop_liquidity_bounds: vec![],
+                    hop_edges: vec![],
+                    gas_units: None,
                 },
                 rank_score: 0.8,
                 rank_position: 1,
@@ -382,8 +1334,515 @@ mod tests {
         ];
         
         let optimized = ranker.optimize_route_selection(routes, 20.0);
-        
+
         assert!(optimized.len() <= 2);
     }
+
+    fn sample_ranked_route_with_gas(id: &str, net_profit: f64, gas_cost: f64) -> RankedRoute {
+        RankedRoute {
+            route: Route {
+                id: id.to_string(),
+                dexes: vec![],
+                tokens: vec![],
+                expected_profit: Money::from_f64(net_profit).unwrap(),
+                gas_cost: Money::from_f64(gas_cost).unwrap(),
+                net_profit: Money::from_f64(net_profit).unwrap(),
+                confidence_score: 0.8,
+                complexity_score: 0.5,
+                pool_usage: HashMap::new(),
+                hop_liquidity_bounds: vec![],
+                hop_edges: vec![],
+                gas_units: None,
+            },
+            rank_score: 0.0,
+            rank_position: 0,
+            profit_score: 0.0,
+            risk_score: 0.0,
+            efficiency_score: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_branch_and_bound_finds_the_same_optimum_as_the_dp() {
+        let ranker = RouteRanker::default();
+        let routes = vec![
+            sample_ranked_route_with_gas("a", 60.0, 10.0),
+            sample_ranked_route_with_gas("b", 100.0, 20.0),
+            sample_ranked_route_with_gas("c", 20.0, 5.0),
+        ];
+
+        let dp_selected = ranker.optimize_route_selection(routes.clone(), 25.0);
+        let bnb_selected = ranker.optimize_route_selection_branch_and_bound(routes, 25.0);
+
+        let dp_total: f64 = dp_selected.iter().map(|r| r.route.net_profit.to_f64()).sum();
+        let bnb_total: f64 = bnb_selected.iter().map(|r| r.route.net_profit.to_f64()).sum();
+
+        assert_eq!(bnb_total, 120.0);
+        assert_eq!(dp_total, bnb_total);
+
+        let mut bnb_ids: Vec<&str> = bnb_selected.iter().map(|r| r.route.id.as_str()).collect();
+        bnb_ids.sort();
+        assert_eq!(bnb_ids, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_branch_and_bound_returns_nothing_for_a_zero_gas_budget() {
+        let ranker = RouteRanker::default();
+        let routes = vec![sample_ranked_route_with_gas("a", 60.0, 10.0)];
+
+        let selected = ranker.optimize_route_selection_branch_and_bound(routes, 0.0);
+
+        assert!(selected.is_empty());
+    }
+
+    fn sample_ranked_route(id: &str, net_profit: f64, pool_usage: &[(&str, f64)]) -> RankedRoute {
+        RankedRoute {
+            route: Route {
+                id: id.to_string(),
+                dexes: vec![],
+                tokens: vec![],
+                expected_profit: Money::from_f64(net_profit).unwrap(),
+                gas_cost: Money::ZERO,
+                net_profit: Money::from_f64(net_profit).unwrap(),
+                confidence_score: 0.8,
+                complexity_score: 0.5,
+                pool_usage: pool_usage
+                    .iter()
+                    .map(|(pool_id, usage)| (pool_id.to_string(), *usage))
+                    .collect(),
+                hop_liquidity_bounds: vec![],
+                hop_edges: vec![],
+                gas_units: None,
+            },
+            rank_score: 0.0,
+            rank_position: 0,
+            profit_score: 0.0,
+            risk_score: 0.0,
+            efficiency_score: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_settlement_batch_picks_the_more_profitable_route_when_pools_conflict() {
+        let routes = vec![
+            sample_ranked_route("cheap", 40.0, &[("pool_a", 80_000.0)]),
+            sample_ranked_route("rich", 60.0, &[("pool_a", 90_000.0)]),
+        ];
+        let pool_capacities = HashMap::from([("pool_a".to_string(), 100_000.0)]);
+
+        let batch = RouteRanker::solve_settlement_batch(routes, &pool_capacities);
+
+        assert_eq!(batch.selected.len(), 1);
+        assert_eq!(batch.selected[0].route.id, "rich");
+        assert_eq!(batch.total_net_profit.to_f64(), 60.0);
+        assert_eq!(batch.unrealized_profit_gap.to_f64(), 40.0);
+    }
+
+    #[test]
+    fn test_settlement_batch_keeps_both_routes_when_they_do_not_share_a_pool() {
+        let routes = vec![
+            sample_ranked_route("a", 40.0, &[("pool_a", 80_000.0)]),
+            sample_ranked_route("b", 60.0, &[("pool_b", 90_000.0)]),
+        ];
+        let pool_capacities =
+            HashMap::from([("pool_a".to_string(), 100_000.0), ("pool_b".to_string(), 100_000.0)]);
+
+        let batch = RouteRanker::solve_settlement_batch(routes, &pool_capacities);
+
+        assert_eq!(batch.selected.len(), 2);
+        assert_eq!(batch.total_net_profit.to_f64(), 100.0);
+        assert_eq!(batch.unrealized_profit_gap.to_f64(), 0.0);
+    }
+
+    #[test]
+    fn test_settlement_batch_exact_beats_greedy_when_density_is_misleading() {
+        // "small" consumes almost nothing of the scarce pool_b, so it wins on
+        // profit-density even though accepting it blocks "big" from fitting
+        // pool_a too (they also share pool_a). The exact solver should still
+        // find the higher-profit combination.
+        let routes = vec![
+            sample_ranked_route("small", 10.0, &[("pool_a", 10_000.0), ("pool_b", 1_000.0)]),
+            sample_ranked_route("big", 90.0, &[("pool_a", 100_000.0)]),
+        ];
+        let pool_capacities =
+            HashMap::from([("pool_a".to_string(), 100_000.0), ("pool_b".to_string(), 10_000.0)]);
+
+        let exact = RouteRanker::solve_settlement_batch_exact(routes.clone(), &pool_capacities);
+        assert_eq!(exact.total_net_profit.to_f64(), 90.0);
+        assert_eq!(exact.selected.len(), 1);
+        assert_eq!(exact.selected[0].route.id, "big");
+
+        let greedy = RouteRanker::solve_settlement_batch_greedy(routes, &pool_capacities);
+        assert_eq!(greedy.total_net_profit.to_f64(), 10.0);
+    }
+
+    #[test]
+    fn test_settlement_batch_greedy_respects_capacity_across_many_routes() {
+        let routes: Vec<RankedRoute> = (0..20)
+            .map(|i| sample_ranked_route(&format!("route{i}"), 10.0 + i as f64, &[("pool_a", 10_000.0)]))
+            .collect();
+        let pool_capacities = HashMap::from([("pool_a".to_string(), 55_000.0)]);
+
+        let batch = RouteRanker::solve_settlement_batch_greedy(routes, &pool_capacities);
+
+        assert_eq!(batch.selected.len(), 5);
+        assert!(batch.unrealized_profit_gap.to_f64() > 0.0);
+    }
+
+    fn route_with_one_weak_criterion(confidence_score: f64) -> Route {
+        Route {
+            id: "weak".to_string(),
+            dexes: vec![],
+            tokens: vec![],
+            expected_profit: Money::from_f64(80.0).unwrap(),
+            gas_cost: Money::from_f64(2.0).unwrap(),
+            net_profit: Money::from_f64(80.0).unwrap(),
+            confidence_score,
+            complexity_score: 0.9,
+            pool_usage: HashMap::new(),
+            hop_liquidity_bounds: vec![],
+            hop_edges: vec![],
+            gas_units: None,
+        }
+    }
+
+    #[test]
+    fn test_weighted_product_model_punishes_a_single_near_zero_criterion_much_harder_than_weighted_sum() {
+        let mut criteria = RankingCriteria {
+            profit_weight: 0.25,
+            confidence_weight: 0.25,
+            complexity_weight: 0.2,
+            gas_efficiency_weight: 0.15,
+            liquidity_weight: 0.15,
+            scoring_model: ScoringModel::WeightedSum,
+            history_half_life_secs: default_history_half_life_secs(),
+            max_history_blend_weight: default_max_history_blend_weight(),
+        };
+
+        let weak_route = route_with_one_weak_criterion(0.001);
+        let sum_ranker = RouteRanker::new(criteria.clone());
+        let sum_ranked = sum_ranker.rank_routes(vec![weak_route.clone()]);
+
+        criteria.scoring_model = ScoringModel::WeightedProduct;
+        let product_ranker = RouteRanker::new(criteria);
+        let product_ranked = product_ranker.rank_routes(vec![weak_route]);
+
+        // Con weighted sum, el resto de criterios (altos) siguen dejando un
+        // rank_score razonable a pesar del confidence casi nulo.
+        assert!(sum_ranked[0].rank_score > 0.3);
+        // Con weighted product, el confidence casi nulo arrastra el
+        // rank_score entero hacia cero bastante más que con weighted sum.
+        assert!(product_ranked[0].rank_score < sum_ranked[0].rank_score / 2.0);
+    }
+
+    #[test]
+    fn test_weighted_product_model_ranks_all_round_routes_above_lopsided_ones() {
+        let criteria = RankingCriteria {
+            profit_weight: 0.2,
+            confidence_weight: 0.2,
+            complexity_weight: 0.2,
+            gas_efficiency_weight: 0.2,
+            liquidity_weight: 0.2,
+            scoring_model: ScoringModel::WeightedProduct,
+            history_half_life_secs: default_history_half_life_secs(),
+            max_history_blend_weight: default_max_history_blend_weight(),
+        };
+        let ranker = RouteRanker::new(criteria);
+
+        let balanced = Route {
+            id: "balanced".to_string(),
+            dexes: vec![],
+            tokens: vec![],
+            expected_profit: Money::from_f64(50.0).unwrap(),
+            gas_cost: Money::from_f64(10.0).unwrap(),
+            net_profit: Money::from_f64(50.0).unwrap(),
+            confidence_score: 0.6,
+            complexity_score: 0.6,
+            pool_usage: HashMap::new(),
+            hop_liquidity_bounds: vec![],
+            hop_edges: vec![],
+            gas_units: None,
+        };
+        let lopsided = Route {
+            id: "lopsided".to_string(),
+            dexes: vec![],
+            tokens: vec![],
+            expected_profit: Money::from_f64(100.0).unwrap(),
+            gas_cost: Money::from_f64(10.0).unwrap(),
+            net_profit: Money::from_f64(100.0).unwrap(),
+            confidence_score: 1e-9,
+            complexity_score: 1.0,
+            pool_usage: HashMap::new(),
+            hop_liquidity_bounds: vec![],
+            hop_edges: vec![],
+            gas_units: None,
+        };
+
+        let ranked = ranker.rank_routes(vec![balanced, lopsided]);
+
+        assert_eq!(ranked[0].route.id, "balanced");
+    }
+
+    fn route_with_bounds(bounds: Vec<LiquidityBound>) -> Route {
+        Route {
+            id: "route".to_string(),
+            dexes: vec![],
+            tokens: vec![],
+            expected_profit: Money::from_f64(40.0).unwrap(),
+            gas_cost: Money::from_f64(5.0).unwrap(),
+            net_profit: Money::from_f64(40.0).unwrap(),
+            confidence_score: 0.8,
+            complexity_score: 0.5,
+            pool_usage: HashMap::new(),
+            hop_liquidity_bounds: bounds,
+            hop_edges: vec![],
+            gas_units: None,
+        }
+    }
+
+    #[test]
+    fn test_success_probability_is_one_without_liquidity_bound_data() {
+        let route = route_with_bounds(vec![]);
+        assert_eq!(RouteRanker::calculate_success_probability(&route, 50_000.0), 1.0);
+    }
+
+    #[test]
+    fn test_success_probability_is_one_below_the_min_bound_and_zero_above_the_max() {
+        let route = route_with_bounds(vec![LiquidityBound { min: 10_000.0, max: 100_000.0 }]);
+
+        assert_eq!(RouteRanker::calculate_success_probability(&route, 5_000.0), 1.0);
+        assert_eq!(RouteRanker::calculate_success_probability(&route, 150_000.0), 0.0);
+    }
+
+    #[test]
+    fn test_success_probability_decreases_linearly_inside_the_band() {
+        let route = route_with_bounds(vec![LiquidityBound { min: 0.0, max: 100_000.0 }]);
+
+        let probability = RouteRanker::calculate_success_probability(&route, 25_000.0);
+
+        assert!((probability - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_success_probability_combines_hops_multiplicatively() {
+        let route = route_with_bounds(vec![
+            LiquidityBound { min: 0.0, max: 100_000.0 }, // 0.5 at amount=50_000
+            LiquidityBound { min: 0.0, max: 200_000.0 }, // 0.75 at amount=50_000
+        ]);
+
+        let probability = RouteRanker::calculate_success_probability(&route, 50_000.0);
+
+        assert!((probability - 0.5 * 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rank_routes_weighted_by_success_probability_demotes_undersized_liquidity() {
+        let ranker = RouteRanker::default();
+
+        let safe = route_with_bounds(vec![LiquidityBound { min: 100_000.0, max: 200_000.0 }]);
+        let mut risky = route_with_bounds(vec![LiquidityBound { min: 0.0, max: 50_000.0 }]);
+        risky.id = "risky".to_string();
+        risky.net_profit = Money::from_f64(45.0).unwrap(); // slightly more profitable "on paper" than `safe`
+        risky.expected_profit = Money::from_f64(45.0).unwrap();
+
+        let ranked = ranker.rank_routes_weighted_by_success_probability(vec![safe, risky], 50_000.0);
+
+        assert_eq!(ranked[0].route.id, "route"); // the `safe` route, unaffected by the trade size
+        assert_eq!(ranked[1].rank_score, 0.0); // `risky` hits its max bound exactly -> probability 0
+    }
+
+    fn sample_route_for_reordering() -> Route {
+        Route {
+            id: "reorder".to_string(),
+            dexes: vec!["dexA".to_string(), "dexB".to_string(), "dexC".to_string()],
+            tokens: vec!["A".to_string(), "B".to_string(), "C".to_string(), "D".to_string()],
+            expected_profit: Money::from_f64(20.0).unwrap(),
+            gas_cost: Money::from_f64(3.0).unwrap(),
+            net_profit: Money::from_f64(17.0).unwrap(),
+            confidence_score: 0.8,
+            complexity_score: 0.5,
+            pool_usage: HashMap::new(),
+            hop_liquidity_bounds: vec![],
+            hop_edges: vec![
+                HopEdge { from_token: "A".into(), to_token: "B".into(), dex: "dexA".into(), profit_contribution: 10.0, gas_cost: 1.0 },
+                HopEdge { from_token: "B".into(), to_token: "C".into(), dex: "dexB".into(), profit_contribution: 5.0, gas_cost: 1.0 },
+                HopEdge { from_token: "C".into(), to_token: "D".into(), dex: "dexC".into(), profit_contribution: 5.0, gas_cost: 1.0 },
+                // A->C->B->D is the more profitable permutation of the same intermediates.
+                HopEdge { from_token: "A".into(), to_token: "C".into(), dex: "dexD".into(), profit_contribution: 10.0, gas_cost: 1.0 },
+                HopEdge { from_token: "C".into(), to_token: "B".into(), dex: "dexE".into(), profit_contribution: 20.0, gas_cost: 1.0 },
+                HopEdge { from_token: "B".into(), to_token: "D".into(), dex: "dexF".into(), profit_contribution: 10.0, gas_cost: 1.0 },
+            ],
+            gas_units: None,
+        }
+    }
+
+    #[test]
+    fn test_optimize_route_ordering_finds_a_more_profitable_permutation() {
+        let route = sample_route_for_reordering();
+
+        let optimized = RouteRanker::optimize_route_ordering(&route);
+
+        assert_eq!(optimized.tokens, vec!["A", "C", "B", "D"]);
+        assert_eq!(optimized.net_profit.to_f64(), 37.0);
+        assert_eq!(optimized.tokens.first(), route.tokens.first());
+        assert_eq!(optimized.tokens.last(), route.tokens.last());
+    }
+
+    #[test]
+    fn test_optimize_route_ordering_leaves_the_route_unchanged_without_hop_edges() {
+        let mut route = sample_route_for_reordering();
+        route.hop_edges.clear();
+
+        let optimized = RouteRanker::optimize_route_ordering(&route);
+
+        assert_eq!(optimized.tokens, route.tokens);
+        assert_eq!(optimized.net_profit, route.net_profit);
+    }
+
+    fn sample_ranked_route_with_score(id: &str, rank_score: f64) -> RankedRoute {
+        let mut route = sample_ranked_route(id, 10.0, &[]);
+        route.rank_score = rank_score;
+        route
+    }
+
+    #[test]
+    fn test_rerank_with_history_barely_moves_the_score_with_only_one_stale_observation() {
+        let mut ranker = RouteRanker::default();
+        let routes = vec![sample_ranked_route_with_score("route1", 0.5)];
+        let now = 1_000_000_i64;
+        let mut outcomes = HashMap::new();
+        outcomes.insert(
+            "route1".to_string(),
+            vec![RouteOutcome { timestamp: now - 30 * 86_400, realized_profit_ratio: 1.0 }],
+        );
+
+        let reranked = ranker.rerank_with_history(routes, &outcomes, now);
+
+        // Una sola observación, ya muy decaída (30 días contra una vida
+        // media de 1 día), no debería mover el score casi nada.
+        assert!((reranked[0].rank_score - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_rerank_with_history_trusts_a_large_recent_track_record() {
+        let mut ranker = RouteRanker::default();
+        let routes = vec![sample_ranked_route_with_score("route1", 0.2)];
+        let now = 1_000_000_i64;
+        let outcomes = HashMap::from([(
+            "route1".to_string(),
+            (0..50)
+                .map(|i| RouteOutcome { timestamp: now - i * 60, realized_profit_ratio: 0.9 })
+                .collect(),
+        )]);
+
+        let reranked = ranker.rerank_with_history(routes, &outcomes, now);
+
+        // Muchas observaciones recientes y consistentes deberían empujar el
+        // score bien por encima del valor en vivo, acercándose al tope de
+        // mezcla configurado.
+        assert!(reranked[0].rank_score > 0.4);
+        assert!(reranked[0].rank_score < 0.9);
+    }
+
+    #[test]
+    fn test_rerank_with_history_leaves_routes_without_outcomes_untouched() {
+        let mut ranker = RouteRanker::default();
+        let routes = vec![sample_ranked_route_with_score("route1", 0.42)];
+
+        let reranked = ranker.rerank_with_history(routes, &HashMap::new(), 1_000_000);
+
+        assert_eq!(reranked[0].rank_score, 0.42);
+    }
+
+    fn sample_route_with_gas_units(id: &str, expected_profit: f64, gas_units: u64) -> Route {
+        Route {
+            id: id.to_string(),
+            dexes: vec![],
+            tokens: vec![],
+            expected_profit: Money::from_f64(expected_profit).unwrap(),
+            gas_cost: Money::ZERO,
+            net_profit: Money::from_f64(expected_profit).unwrap(),
+            confidence_score: 0.8,
+            complexity_score: 0.5,
+            pool_usage: HashMap::new(),
+            hop_liquidity_bounds: vec![],
+            hop_edges: vec![],
+            gas_units: Some(gas_units),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rank_routes_with_live_gas_recomputes_gas_cost_from_the_live_price() {
+        let provider = Arc::new(StaticGasPriceProvider::new(GasPriceSnapshot {
+            base_fee_gwei: 20.0,
+            priority_fee_gwei: 2.0,
+            forecast_base_fee_gwei: None,
+        }));
+        let ranker = RouteRanker::default().with_gas_price_provider(provider, 2_000.0);
+        let route = sample_route_with_gas_units("route1", 100.0, 100_000);
+
+        let ranked = ranker.rank_routes_with_live_gas(vec![route], 1).await;
+
+        // gas_cost = 100_000 gas * 22 gwei * 1e-9 * $2000/ETH = $4.4
+        let expected_gas_cost = 100_000.0 * 22.0 * 1e-9 * 2_000.0;
+        assert!((ranked[0].route.gas_cost.to_f64() - expected_gas_cost).abs() < 1e-6);
+        assert!((ranked[0].route.net_profit.to_f64() - (100.0 - expected_gas_cost)).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_rank_routes_with_live_gas_leaves_routes_without_gas_units_untouched() {
+        let provider = Arc::new(StaticGasPriceProvider::new(GasPriceSnapshot {
+            base_fee_gwei: 20.0,
+            priority_fee_gwei: 2.0,
+            forecast_base_fee_gwei: None,
+        }));
+        let ranker = RouteRanker::default().with_gas_price_provider(provider, 2_000.0);
+        let mut route = sample_route_with_gas_units("route1", 100.0, 100_000);
+        route.gas_units = None;
+        route.gas_cost = Money::from_f64(5.0).unwrap();
+        route.net_profit = Money::from_f64(95.0).unwrap();
+
+        let ranked = ranker.rank_routes_with_live_gas(vec![route], 1).await;
+
+        assert_eq!(ranked[0].route.gas_cost.to_f64(), 5.0);
+        assert_eq!(ranked[0].route.net_profit.to_f64(), 95.0);
+    }
+
+    #[tokio::test]
+    async fn test_rank_routes_with_live_gas_is_a_no_op_without_a_provider() {
+        let ranker = RouteRanker::default();
+        let route = sample_route_with_gas_units("route1", 100.0, 100_000);
+
+        let ranked = ranker.rank_routes_with_live_gas(vec![route], 1).await;
+
+        assert_eq!(ranked[0].route.gas_cost.to_f64(), 0.0);
+        assert_eq!(ranked[0].route.net_profit.to_f64(), 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_rerank_on_price_tick_updates_an_already_scored_set() {
+        let stale_provider = Arc::new(StaticGasPriceProvider::new(GasPriceSnapshot {
+            base_fee_gwei: 20.0,
+            priority_fee_gwei: 2.0,
+            forecast_base_fee_gwei: None,
+        }));
+        let ranker = RouteRanker::default().with_gas_price_provider(stale_provider, 2_000.0);
+        let route = sample_route_with_gas_units("route1", 100.0, 100_000);
+        let ranked = ranker.rank_routes_with_live_gas(vec![route], 1).await;
+        let stale_gas_cost = ranked[0].route.gas_cost;
+
+        // A new tick doubles the gas price; `rerank_on_price_tick` should
+        // pick that up without the caller touching the `Route` by hand.
+        let spiked_provider = Arc::new(StaticGasPriceProvider::new(GasPriceSnapshot {
+            base_fee_gwei: 40.0,
+            priority_fee_gwei: 4.0,
+            forecast_base_fee_gwei: None,
+        }));
+        let ranker = RouteRanker::default().with_gas_price_provider(spiked_provider, 2_000.0);
+
+        let reranked = ranker.rerank_on_price_tick(ranked, 1).await;
+
+        assert!(reranked[0].route.gas_cost.to_f64() > stale_gas_cost.to_f64() * 1.9);
+    }
 }
 
@@ -13,6 +13,12 @@
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
+use crate::pathfinding::amm;
+use crate::pathfinding::amm::PoolKind;
+use crate::pathfinding::hybrid;
+use crate::pathfinding::hybrid::LimitOrder;
+use crate::utils::amounts::TokenAmount;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DexInfo {
     pub id: String,
@@ -20,6 +26,12 @@ pub struct DexInfo {
     pub chain: String,
     pub fee_percentage: f64,
     pub liquidity_usd: f64,
+    /// Tag por defecto para las `TokenPair` ingeridas de este DEX desde
+    /// Sheets/APIs cuando el dato no trae su propio `pool_kind` (ver
+    /// `TokenPair::pool_kind`, que es lo que realmente consulta
+    /// `calculate_route_profit` para elegir la curva de cada hop).
+    #[serde(default)]
+    pub pool_kind: PoolKind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +40,34 @@ pub struct TokenPair {
     pub token_out: String,
     pub price: f64,
     pub liquidity: f64,
+    /// Reserva de `token_in` en el pool, en unidades del token (no USD).
+    /// Ver `amm::constant_product_output`: alimenta la curva real del pool
+    /// en vez del precio "flat" de arriba.
+    pub reserve_in: f64,
+    /// Reserva de `token_out` en el pool, en unidades del token.
+    pub reserve_out: f64,
+    /// Mismas reservas que `reserve_in`/`reserve_out`, pero como `TokenAmount`
+    /// exacto (acepta hex o decimal desde Sheets/APIs vía su serde). `None`
+    /// para pools que todavía no migraron a fixed-point: esos quedan fuera
+    /// de `calculate_route_profit_exact` pero siguen funcionando en el
+    /// camino `f64` de siempre.
+    #[serde(default)]
+    pub reserve_in_units: Option<TokenAmount>,
+    #[serde(default)]
+    pub reserve_out_units: Option<TokenAmount>,
+    /// Curva que usa este pool para cotizar swaps. `ConstantProduct` (el
+    /// default) usa `amm::constant_product_output`; `Stable` usa
+    /// `amm::stable_swap_output`, que rinde mucho menos slippage cerca del
+    /// peg para pools tipo Curve.
+    #[serde(default)]
+    pub pool_kind: PoolKind,
+    /// Órdenes límite en reposo para este hop, si el venue las expone (p.ej.
+    /// un CLOB on-chain al lado del pool). `None`/vacío es equivalente a no
+    /// tener libro: `calculate_route_profit_hybrid` cae de vuelta a cotizar
+    /// 100% contra el AMM, igual que `calculate_route_profit`. Ver
+    /// `hybrid::fill_hop`.
+    #[serde(default)]
+    pub order_book: Option<Vec<LimitOrder>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,11 +76,53 @@ pub struct ThreeDexRoute {
     pub dex_2: String,
     pub dex_3: String,
     pub tokens: Vec<String>,
+    /// Monto inicial (en USD) que maximiza el profit neto de la ruta, hallado
+    /// por `find_optimal_amount` en vez de asumirse fijo en $1000.
+    pub optimal_amount_in: f64,
     pub expected_profit: f64,
     pub gas_cost: f64,
     pub net_profit: f64,
     pub confidence_score: f64,
     pub complexity_score: f64,
+    /// Monto final exacto de la ruta (no un delta de profit: `TokenAmount`
+    /// no tiene signo), calculado vía `calculate_route_profit_exact` cuando
+    /// los cuatro hops tenían `reserve_*_units`. El llamador compara
+    /// `.raw()` contra el monto inicial para decidir profit/pérdida antes
+    /// de someter la transacción on-chain. `None` si la ruta no fue
+    /// verificada o algún hop no tenía unidades exactas.
+    #[serde(default)]
+    pub exact_amount_out: Option<TokenAmount>,
+    /// Desglose AMM/libro de órdenes por hop cuando `find_complex_route`
+    /// encontró que llenar híbrido rinde más profit que solo el AMM (ver
+    /// `calculate_route_profit_hybrid`). `None` cuando ningún hop tenía
+    /// `order_book` o el AMM solo ya era la mejor opción. `as_hybrid_route`
+    /// envuelve la ruta junto a este desglose como un `HybridRoute`.
+    #[serde(default)]
+    pub hybrid_fills: Option<[hybrid::HybridFill; 4]>,
+}
+
+impl ThreeDexRoute {
+    /// Envuelve esta ruta como `HybridRoute` si se encontró un llenado
+    /// híbrido más rentable que el AMM puro, para que el llamador construya
+    /// el calldata multi-venue con el desglose por hop. `None` si la ruta se
+    /// llenó enteramente contra el AMM.
+    pub fn as_hybrid_route(&self) -> Option<HybridRoute> {
+        Some(HybridRoute {
+            route: self.clone(),
+            fills: self.hybrid_fills?,
+        })
+    }
+}
+
+/// Ruta que llenó al menos un hop combinando AMM + libro de órdenes límite
+/// en vez de solo el pool AMM. Envuelve la `ThreeDexRoute` equivalente (toda
+/// la metadata de scoring/profit ya refleja el llenado híbrido) junto al
+/// desglose `{amm_filled, book_filled}` de cada uno de los 4 hops, para que
+/// el executor sepa cuánto enrutar a cada venue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridRoute {
+    pub route: ThreeDexRoute,
+    pub fills: [hybrid::HybridFill; 4],
 }
 
 /// Algoritmo DP para encontrar rutas óptimas de 3-DEX
@@ -134,10 +216,10 @@ impl ThreeDexPathfinder {
             }
         }
         
-        // Ordenar por profit neto descendente (array dinámico)
-        routes.sort_by(|a, b| {
-            b.net_profit.partial_cmp(&a.net_profit).unwrap()
-        });
+        // Ordenar por profit neto descendente (array dinámico). `total_cmp`
+        // en vez de `partial_cmp(...).unwrap()`: un `net_profit` corrupto a
+        // `NaN` no debe hacer panic acá, solo ordenar de forma determinística.
+        routes.sort_by(|a, b| b.net_profit.total_cmp(&a.net_profit));
         
         routes
     }
@@ -180,42 +262,54 @@ impl ThreeDexPathfinder {
                     // Verificar si podemos volver al token inicial
                     if let Some(pair_da) = dex_1_prices.get(token_d) {
                         if pair_da.token_out == start_token {
-                            // Calcular profit de la ruta completa
-                            let profit = self.calculate_route_profit(
-                                1000.0, // Monto inicial en USD
-                                &[
-                                    pair_ab.price,
-                                    pair_bc.price,
-                                    pair_cd.price,
-                                    pair_da.price,
-                                ],
-                                &[
-                                    dex_1.fee_percentage,
-                                    dex_2.fee_percentage,
-                                    dex_3.fee_percentage,
-                                    dex_1.fee_percentage,
-                                ],
-                            );
-                            
+                            let hops = [
+                                (dex_1.id.as_str(), start_token, token_b.as_str(), pair_ab, dex_1.fee_percentage),
+                                (dex_2.id.as_str(), token_b.as_str(), token_c.as_str(), pair_bc, dex_2.fee_percentage),
+                                (dex_3.id.as_str(), token_c.as_str(), token_d.as_str(), pair_cd, dex_3.fee_percentage),
+                                (dex_1.id.as_str(), token_d.as_str(), start_token, pair_da, dex_1.fee_percentage),
+                            ];
+
+                            let min_liquidity = pair_ab.liquidity
+                                .min(pair_bc.liquidity)
+                                .min(pair_cd.liquidity)
+                                .min(pair_da.liquidity);
+
+                            // `None` significa que ningún monto evaluado tuvo
+                            // reservas válidas en los cuatro hops, así que se
+                            // descarta esta ruta candidata por completo.
+                            let (optimal_amount_in, amm_profit) =
+                                match self.find_optimal_amount(&hops, min_liquidity, gas_cost) {
+                                    Some(result) => result,
+                                    None => continue,
+                                };
+
+                            // Si algún hop trae `order_book`, preferir el
+                            // llenado híbrido sobre `optimal_amount_in`
+                            // cuando rinda más profit que el AMM solo. No se
+                            // re-corre la búsqueda ternaria para el híbrido:
+                            // se reusa el monto óptimo ya hallado para el AMM.
+                            let (profit, hybrid_fills) =
+                                match self.calculate_route_profit_hybrid(optimal_amount_in, &hops) {
+                                    Some((hybrid_profit, fills)) if hybrid_profit > amm_profit => {
+                                        (hybrid_profit, Some(fills))
+                                    }
+                                    _ => (amm_profit, None),
+                                };
+
                             if profit > max_profit {
                                 max_profit = profit;
-                                
+
                                 // Calcular confidence y complexity scores
-                                let min_liquidity = pair_ab.liquidity
-                                    .min(pair_bc.liquidity)
-                                    .min(pair_cd.liquidity)
-                                    .min(pair_da.liquidity);
-                                
                                 let confidence = self.calculate_confidence(
                                     min_liquidity,
                                     &[dex_1.liquidity_usd, dex_2.liquidity_usd, dex_3.liquidity_usd],
                                 );
-                                
+
                                 let complexity = self.calculate_complexity(
                                     4, // 4 swaps
                                     &[dex_1.fee_percentage, dex_2.fee_percentage, dex_3.fee_percentage],
                                 );
-                                
+
                                 best_route = Some(ThreeDexRoute {
                                     dex_1: dex_1.id.clone(),
                                     dex_2: dex_2.id.clone(),
@@ -226,11 +320,14 @@ impl ThreeDexPathfinder {
                                         token_c.clone(),
                                         token_d.clone(),
                                     ],
+                                    optimal_amount_in,
                                     expected_profit: profit,
                                     gas_cost,
                                     net_profit: profit - gas_cost,
                                     confidence_score: confidence,
                                     complexity_score: complexity,
+                                    exact_amount_out: None,
+                                    hybrid_fills,
                                 });
                             }
                         }
@@ -242,22 +339,130 @@ impl ThreeDexPathfinder {
         best_route
     }
     
-    /// Calcula el profit de una ruta con múltiples swaps
+    /// Calcula el profit de una ruta con múltiples swaps usando un
+    /// `RouteSimulator` que encadena la curva de cada pool según su
+    /// `pool_kind` (el output de cada hop alimenta como input del siguiente)
+    /// y mantiene el estado de reservas entre hops con el mismo
+    /// `(dex_id, token_in, token_out)`. Los dos hops de DEX1 (A->B y D->A)
+    /// son claves distintas, así que cada uno cotiza contra las reservas de
+    /// su `TokenPair`.
+    ///
+    /// Devuelve `None` si algún hop no tiene reservas válidas o rinde output
+    /// cero: la ruta completa se descarta, no se trata como profit cero.
     fn calculate_route_profit(
         &self,
         initial_amount: f64,
-        prices: &[f64],
-        fees: &[f64],
-    ) -> f64 {
+        hops: &[(&str, &str, &str, &TokenPair, f64); 4],
+    ) -> Option<f64> {
+        let mut simulator = amm::RouteSimulator::new();
         let mut amount = initial_amount;
-        
-        // Aplicar cada swap con su fee correspondiente (array dinámico)
-        for (price, fee) in prices.iter().zip(fees.iter()) {
-            amount = amount * price * (1.0 - fee / 100.0);
+
+        for (dex_id, token_in, token_out, pair, fee) in hops.iter() {
+            amount = simulator.swap_with_kind(
+                dex_id,
+                token_in,
+                token_out,
+                pair.reserve_in,
+                pair.reserve_out,
+                amount,
+                fee / 100.0,
+                pair.pool_kind,
+            )?;
         }
-        
+
         // Profit = final - inicial
-        amount - initial_amount
+        Some(amount - initial_amount)
+    }
+
+    /// Verificación exacta de `calculate_route_profit` en aritmética `U256`
+    /// vía `amm::ExactRouteSimulator`, para una ruta y monto ya elegidos por
+    /// el camino `f64` antes de someter la transacción on-chain. Devuelve el
+    /// monto final (no un delta): el llamador compara `.raw()` contra
+    /// `initial_amount.raw()` para saber si hubo profit o pérdida. `None` si
+    /// algún hop no tiene `reserve_*_units` cargadas o las reservas son
+    /// inválidas.
+    pub fn calculate_route_profit_exact(
+        &self,
+        initial_amount: TokenAmount,
+        hops: &[(&str, &str, &str, &TokenPair, u32); 4],
+    ) -> Option<TokenAmount> {
+        let mut simulator = amm::ExactRouteSimulator::new();
+        let mut amount = initial_amount;
+
+        for (dex_id, token_in, token_out, pair, fee_bps) in hops.iter() {
+            amount = simulator.swap(
+                dex_id,
+                token_in,
+                token_out,
+                pair.reserve_in_units?,
+                pair.reserve_out_units?,
+                amount,
+                *fee_bps,
+            )?;
+        }
+
+        Some(amount)
+    }
+
+    /// Variante de `calculate_route_profit` que llena cada hop vía
+    /// `hybrid::fill_hop` en vez de solo `amm::constant_product_output`, para
+    /// aprovechar el `order_book` de cada `TokenPair` cuando existe. Sin
+    /// órdenes (`order_book: None`) rinde el mismo profit que
+    /// `calculate_route_profit` en hops `ConstantProduct`; `fill_hop` cotiza
+    /// la parte AMM siempre con la curva constant-product, así que aquí un
+    /// hop `Stable` no pasa por `stable_swap_output`. `find_complex_route`
+    /// compara ambas y se queda con la que rinda más. No trackea estado compartido
+    /// entre hops que revisiten el mismo pool (a diferencia de
+    /// `RouteSimulator`): cada hop cotiza contra las reservas tal como vienen
+    /// en su `TokenPair`. Devuelve `(profit, desglose_por_hop)`, o `None` si
+    /// algún hop no tiene reservas válidas o rinde output cero.
+    fn calculate_route_profit_hybrid(
+        &self,
+        initial_amount: f64,
+        hops: &[(&str, &str, &str, &TokenPair, f64); 4],
+    ) -> Option<(f64, [hybrid::HybridFill; 4])> {
+        let mut amount = initial_amount;
+        let mut fills: [Option<hybrid::HybridFill>; 4] = [None; 4];
+
+        for (i, (_dex_id, _token_in, _token_out, pair, fee)) in hops.iter().enumerate() {
+            let book = pair.order_book.as_deref().unwrap_or(&[]);
+            let fill = hybrid::fill_hop(amount, pair.reserve_in, pair.reserve_out, fee / 100.0, book)?;
+            amount = fill.amount_out;
+            fills[i] = Some(fill);
+        }
+
+        let fills = fills.map(|fill| fill.expect("los 4 hops se llenaron o la función ya retornó None"));
+
+        Some((amount - initial_amount, fills))
+    }
+
+    /// Encuentra el `amount_in` que maximiza el profit neto de la ruta
+    /// (en vez de asumir un monto fijo de $1000), vía búsqueda ternaria sobre
+    /// `[1.0, max_amount]` — `max_amount` se acota a la liquidez mínima de
+    /// los pools de la ruta, ya que por encima de eso el slippage la vuelve
+    /// irrelevante de todas formas. Devuelve `(optimal_amount_in,
+    /// gross_profit)` al óptimo, o `None` si ningún monto en el rango
+    /// produce una ruta válida.
+    fn find_optimal_amount(
+        &self,
+        hops: &[(&str, &str, &str, &TokenPair, f64); 4],
+        max_amount: f64,
+        gas_cost: f64,
+    ) -> Option<(f64, f64)> {
+        if max_amount <= 1.0 {
+            return None;
+        }
+
+        let net_profit_fn = |amount_in: f64| {
+            self.calculate_route_profit(amount_in, hops)
+                .map(|profit| profit - gas_cost)
+        };
+
+        let (optimal_amount_in, _net_profit) =
+            amm::ternary_search_optimal_amount(1.0, max_amount, 1.0, net_profit_fn)?;
+        let gross_profit = self.calculate_route_profit(optimal_amount_in, hops)?;
+
+        Some((optimal_amount_in, gross_profit))
     }
     
     /// Calcula el confidence score basado en liquidez
@@ -328,7 +533,93 @@ impl ThreeDexPathfinder {
         
         grouped
     }
-    
+
+    /// Cotiza el output esperado en cada hop de un camino explícito de
+    /// tokens y DEXs, sin pasar por `find_profitable_routes`: útil para que
+    /// un executor externo fije límites de slippage/min-received antes de
+    /// someter la transacción. `path` trae un token más que `dexes`
+    /// (origen y destino de cada hop); camina hacia adelante aplicando la
+    /// curva de cada pool (`pool_kind`) vía `amm::RouteSimulator`, igual que
+    /// `calculate_route_profit`. Devuelve el monto acumulado después de
+    /// cada hop, o `None` si `path`/`dexes` no calzan en longitud, falta
+    /// el pool de algún hop, o algún swap produce output inválido.
+    pub fn get_amount_out_by_path(
+        &self,
+        amount_in: f64,
+        path: &[&str],
+        dexes: &[&str],
+    ) -> Option<Vec<f64>> {
+        if path.len() < 2 || dexes.len() != path.len() - 1 {
+            return None;
+        }
+
+        let mut simulator = amm::RouteSimulator::new();
+        let mut amount = amount_in;
+        let mut amounts = Vec::with_capacity(dexes.len());
+
+        for (hop, dex_id) in dexes.iter().enumerate() {
+            let token_in = path[hop];
+            let token_out = path[hop + 1];
+            let pair = self.prices.get(*dex_id)?.get(token_in)?;
+            let dex = self.dexes.iter().find(|d| d.id == *dex_id)?;
+
+            amount = simulator.swap_with_kind(
+                dex_id,
+                token_in,
+                token_out,
+                pair.reserve_in,
+                pair.reserve_out,
+                amount,
+                dex.fee_percentage / 100.0,
+                pair.pool_kind,
+            )?;
+            amounts.push(amount);
+        }
+
+        Some(amounts)
+    }
+
+    /// Inversa de `get_amount_out_by_path`: dado el `amount_out` deseado al
+    /// final del camino, calcula cuánto `amount_in` hace falta en cada hop
+    /// caminando el camino en reversa con la fórmula de constant-product
+    /// invertida: `amount_in = (reserve_in * amount_out) / ((reserve_out -
+    /// amount_out) * (1 - fee/100))`. Solo cubre pools `PoolKind::ConstantProduct`
+    /// (la curva StableSwap no tiene esta inversa en forma cerrada todavía);
+    /// `None` si algún hop usa otra curva, falta el pool, o `amount_out >=
+    /// reserve_out` en algún hop (el pool no puede rendir ese output).
+    pub fn get_amount_in_by_path(
+        &self,
+        amount_out: f64,
+        path: &[&str],
+        dexes: &[&str],
+    ) -> Option<Vec<f64>> {
+        if path.len() < 2 || dexes.len() != path.len() - 1 {
+            return None;
+        }
+
+        let mut amount = amount_out;
+        let mut amounts = vec![0.0; dexes.len()];
+
+        for (hop, dex_id) in dexes.iter().enumerate().rev() {
+            let token_in = path[hop];
+            let pair = self.prices.get(*dex_id)?.get(token_in)?;
+            let dex = self.dexes.iter().find(|d| d.id == *dex_id)?;
+
+            if !matches!(pair.pool_kind, PoolKind::ConstantProduct) {
+                return None;
+            }
+            if amount >= pair.reserve_out {
+                return None;
+            }
+
+            let fee = dex.fee_percentage / 100.0;
+            amount = (pair.reserve_in * amount) / ((pair.reserve_out - amount) * (1.0 - fee));
+            amounts[hop] = amount;
+        }
+
+        Some(amounts)
+    }
+
     /// Optimiza rutas usando programación dinámica avanzada
     /// Combina rutas similares para reducir gas costs
     pub fn optimize_routes(
@@ -339,12 +630,15 @@ impl ThreeDexPathfinder {
             return routes;
         }
         
-        // Ordenar por profit/complexity ratio (array dinámico)
+        // Ordenar por profit/complexity ratio (array dinámico). `total_cmp`
+        // en vez de `partial_cmp(...).unwrap()`: un `net_profit`/`complexity_score`
+        // corrupto a `NaN` no debe hacer panic acá, solo ordenar de forma
+        // determinística.
         let mut sorted_routes = routes;
         sorted_routes.sort_by(|a, b| {
             let ratio_a = a.net_profit / (1.0 - a.complexity_score).max(0.1);
             let ratio_b = b.net_profit / (1.0 - b.complexity_score).max(0.1);
-            ratio_b.partial_cmp(&ratio_a).unwrap()
+            ratio_b.total_cmp(&ratio_a)
         });
         
         // Tomar las mejores rutas (array dinámico)
@@ -365,6 +659,7 @@ mod tests {
                 chain: "ethereum".to_string(),
                 fee_percentage: 0.3,
                 liquidity_usd: 5_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
             },
             DexInfo {
                 id: "sushiswap".to_string(),
@@ -372,6 +667,7 @@ mod tests {
                 chain: "ethereum".to_string(),
                 fee_percentage: 0.25,
                 liquidity_usd: 2_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
             },
             DexInfo {
                 id: "curve".to_string(),
@@ -379,6 +675,7 @@ mod tests {
                 chain: "ethereum".to_string(),
                 fee_percentage: 0.04,
                 liquidity_usd: 3_000_000_000.0,
+                pool_kind: PoolKind::Stable { amplification: 100.0 },
             },
         ];
         
@@ -391,17 +688,83 @@ mod tests {
     #[test]
     fn test_profit_calculation() {
         let pathfinder = ThreeDexPathfinder::new(vec![]);
-        
+
+        // Pools profundos y desbalanceados a favor del trader en cada hop,
+        // para que la ruta completa cierre con profit pese al impacto de
+        // precio de la curva constant-product.
+        fn pair(token_in: &str, token_out: &str, reserve_out: f64) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in: 1_000_000.0,
+                reserve_out,
+                reserve_in_units: None,
+                reserve_out_units: None,
+                pool_kind: PoolKind::ConstantProduct,
+                order_book: None,
+            }
+        }
+
+        let pair_ab = pair("A", "B", 1_200_000.0);
+        let pair_bc = pair("B", "C", 1_100_000.0);
+        let pair_cd = pair("C", "D", 1_080_000.0);
+        let pair_da = pair("D", "A", 1_060_000.0);
+
+        let profit = pathfinder
+            .calculate_route_profit(
+                1000.0,
+                &[
+                    ("uniswap", "A", "B", &pair_ab, 0.3),
+                    ("sushiswap", "B", "C", &pair_bc, 0.25),
+                    ("curve", "C", "D", &pair_cd, 0.04),
+                    ("uniswap", "D", "A", &pair_da, 0.3),
+                ],
+            )
+            .expect("ruta con reservas válidas en cada hop");
+
+        // Debería haber profit positivo
+        assert!(profit > 0.0);
+    }
+
+    #[test]
+    fn test_profit_calculation_is_none_when_a_hop_has_no_reserves() {
+        let pathfinder = ThreeDexPathfinder::new(vec![]);
+
+        fn pair(token_in: &str, token_out: &str, reserve_in: f64, reserve_out: f64) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in,
+                reserve_out,
+                reserve_in_units: None,
+                reserve_out_units: None,
+                pool_kind: PoolKind::ConstantProduct,
+                order_book: None,
+            }
+        }
+
+        let pair_ab = pair("A", "B", 0.0, 1_200_000.0); // sin liquidez real
+        let pair_bc = pair("B", "C", 1_000_000.0, 1_100_000.0);
+        let pair_cd = pair("C", "D", 1_000_000.0, 1_080_000.0);
+        let pair_da = pair("D", "A", 1_000_000.0, 1_060_000.0);
+
         let profit = pathfinder.calculate_route_profit(
             1000.0,
-            &[1.1, 1.05, 1.03, 1.02],
-            &[0.3, 0.25, 0.04, 0.3],
+            &[
+                ("uniswap", "A", "B", &pair_ab, 0.3),
+                ("sushiswap", "B", "C", &pair_bc, 0.25),
+                ("curve", "C", "D", &pair_cd, 0.04),
+                ("uniswap", "D", "A", &pair_da, 0.3),
+            ],
         );
-        
-        // Debería haber profit positivo
-        assert!(profit > 0.0);
+
+        assert!(profit.is_none());
     }
-    
+
     #[test]
     fn test_complexity_calculation() {
         let pathfinder = ThreeDexPathfinder::new(vec![]);
@@ -414,5 +777,405 @@ mod tests {
         // Complexity debería estar entre 0 y 1
         assert!(complexity >= 0.0 && complexity <= 1.0);
     }
+
+    #[test]
+    fn test_find_optimal_amount_beats_a_fixed_thousand_dollar_guess() {
+        let pathfinder = ThreeDexPathfinder::new(vec![]);
+
+        fn pair(token_in: &str, token_out: &str, reserve_out: f64) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in: 1_000_000.0,
+                reserve_out,
+                reserve_in_units: None,
+                reserve_out_units: None,
+                pool_kind: PoolKind::ConstantProduct,
+                order_book: None,
+            }
+        }
+
+        let pair_ab = pair("A", "B", 1_200_000.0);
+        let pair_bc = pair("B", "C", 1_100_000.0);
+        let pair_cd = pair("C", "D", 1_080_000.0);
+        let pair_da = pair("D", "A", 1_060_000.0);
+
+        let hops = [
+            ("uniswap", "A", "B", &pair_ab, 0.3),
+            ("sushiswap", "B", "C", &pair_bc, 0.25),
+            ("curve", "C", "D", &pair_cd, 0.04),
+            ("uniswap", "D", "A", &pair_da, 0.3),
+        ];
+
+        let (optimal_amount_in, optimal_profit) = pathfinder
+            .find_optimal_amount(&hops, 1_000_000.0, 1.0)
+            .expect("ruta rentable para algún tamaño de trade");
+
+        let fixed_profit = pathfinder
+            .calculate_route_profit(1000.0, &hops)
+            .expect("reservas válidas en los cuatro hops");
+
+        assert!(optimal_amount_in > 0.0);
+        assert!(optimal_profit >= fixed_profit);
+    }
+
+    #[test]
+    fn test_find_optimal_amount_is_none_when_max_amount_is_too_small() {
+        let pathfinder = ThreeDexPathfinder::new(vec![]);
+
+        fn pair(token_in: &str, token_out: &str, reserve_out: f64) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in: 1_000_000.0,
+                reserve_out,
+                reserve_in_units: None,
+                reserve_out_units: None,
+                pool_kind: PoolKind::ConstantProduct,
+                order_book: None,
+            }
+        }
+
+        let pair_ab = pair("A", "B", 1_200_000.0);
+        let pair_bc = pair("B", "C", 1_100_000.0);
+        let pair_cd = pair("C", "D", 1_080_000.0);
+        let pair_da = pair("D", "A", 1_060_000.0);
+
+        let hops = [
+            ("uniswap", "A", "B", &pair_ab, 0.3),
+            ("sushiswap", "B", "C", &pair_bc, 0.25),
+            ("curve", "C", "D", &pair_cd, 0.04),
+            ("uniswap", "D", "A", &pair_da, 0.3),
+        ];
+
+        assert!(pathfinder.find_optimal_amount(&hops, 0.5, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_profit_calculation_exact_matches_sign_of_f64_profit() {
+        let pathfinder = ThreeDexPathfinder::new(vec![]);
+
+        fn pair(token_in: &str, token_out: &str, reserve_out: f64) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in: 1_000_000.0,
+                reserve_out,
+                reserve_in_units: Some(TokenAmount::from_f64(1_000_000.0, 18).unwrap()),
+                reserve_out_units: Some(TokenAmount::from_f64(reserve_out, 18).unwrap()),
+                pool_kind: PoolKind::ConstantProduct,
+                order_book: None,
+            }
+        }
+
+        let pair_ab = pair("A", "B", 1_200_000.0);
+        let pair_bc = pair("B", "C", 1_100_000.0);
+        let pair_cd = pair("C", "D", 1_080_000.0);
+        let pair_da = pair("D", "A", 1_060_000.0);
+
+        let initial_amount = TokenAmount::from_f64(1000.0, 18).unwrap();
+        let final_amount = pathfinder
+            .calculate_route_profit_exact(
+                initial_amount,
+                &[
+                    ("uniswap", "A", "B", &pair_ab, 30),
+                    ("sushiswap", "B", "C", &pair_bc, 25),
+                    ("curve", "C", "D", &pair_cd, 4),
+                    ("uniswap", "D", "A", &pair_da, 30),
+                ],
+            )
+            .expect("ruta con unidades exactas en cada hop");
+
+        assert!(final_amount.raw() > initial_amount.raw());
+    }
+
+    #[test]
+    fn test_profit_calculation_exact_is_none_without_reserve_units() {
+        let pathfinder = ThreeDexPathfinder::new(vec![]);
+
+        fn pair(token_in: &str, token_out: &str, reserve_out: f64) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in: 1_000_000.0,
+                reserve_out,
+                reserve_in_units: None,
+                reserve_out_units: None,
+                pool_kind: PoolKind::ConstantProduct,
+                order_book: None,
+            }
+        }
+
+        let pair_ab = pair("A", "B", 1_200_000.0);
+
+        let initial_amount = TokenAmount::from_f64(1000.0, 18).unwrap();
+        let result = pathfinder.calculate_route_profit_exact(
+            initial_amount,
+            &[
+                ("uniswap", "A", "B", &pair_ab, 30),
+                ("sushiswap", "B", "A", &pair_ab, 25),
+                ("curve", "A", "B", &pair_ab, 4),
+                ("uniswap", "B", "A", &pair_ab, 30),
+            ],
+        );
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_profit_calculation_prices_a_stable_hop_with_far_less_slippage() {
+        // El hop B->C pasa por un pool casi en paridad: si `calculate_route_profit`
+        // lo cotizara como constant-product en vez de respetar `pool_kind`, el
+        // slippage sería mucho mayor y el output final, menor.
+        let pathfinder = ThreeDexPathfinder::new(vec![]);
+
+        fn pair(token_in: &str, token_out: &str, pool_kind: PoolKind) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in: 1_000_000.0,
+                reserve_out: 1_000_000.0,
+                reserve_in_units: None,
+                reserve_out_units: None,
+                pool_kind,
+                order_book: None,
+            }
+        }
+
+        let pair_ab = pair("A", "B", PoolKind::ConstantProduct);
+        let pair_cd = pair("C", "D", PoolKind::ConstantProduct);
+        let pair_da = pair("D", "A", PoolKind::ConstantProduct);
+
+        let pair_bc_stable = pair("B", "C", PoolKind::Stable { amplification: 100.0 });
+        let stable_profit = pathfinder
+            .calculate_route_profit(
+                1_000_000.0,
+                &[
+                    ("uniswap", "A", "B", &pair_ab, 0.3),
+                    ("curve", "B", "C", &pair_bc_stable, 0.04),
+                    ("sushiswap", "C", "D", &pair_cd, 0.3),
+                    ("uniswap", "D", "A", &pair_da, 0.3),
+                ],
+            )
+            .expect("ruta con reservas válidas en cada hop");
+
+        let pair_bc_constant_product = pair("B", "C", PoolKind::ConstantProduct);
+        let constant_product_profit = pathfinder
+            .calculate_route_profit(
+                1_000_000.0,
+                &[
+                    ("uniswap", "A", "B", &pair_ab, 0.3),
+                    ("curve", "B", "C", &pair_bc_constant_product, 0.04),
+                    ("sushiswap", "C", "D", &pair_cd, 0.3),
+                    ("uniswap", "D", "A", &pair_da, 0.3),
+                ],
+            )
+            .expect("ruta con reservas válidas en cada hop");
+
+        assert!(stable_profit > constant_product_profit);
+    }
+
+    fn pathfinder_with_a_to_b_to_c_path() -> ThreeDexPathfinder {
+        let dexes = vec![
+            DexInfo {
+                id: "uniswap".to_string(),
+                name: "Uniswap V3".to_string(),
+                chain: "ethereum".to_string(),
+                fee_percentage: 0.3,
+                liquidity_usd: 5_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
+            },
+            DexInfo {
+                id: "sushiswap".to_string(),
+                name: "SushiSwap".to_string(),
+                chain: "ethereum".to_string(),
+                fee_percentage: 0.25,
+                liquidity_usd: 2_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
+            },
+        ];
+
+        let mut pathfinder = ThreeDexPathfinder::new(dexes);
+
+        fn pair(token_in: &str, token_out: &str, reserve_in: f64, reserve_out: f64) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in,
+                reserve_out,
+                reserve_in_units: None,
+                reserve_out_units: None,
+                pool_kind: PoolKind::ConstantProduct,
+                order_book: None,
+            }
+        }
+
+        let mut uniswap_prices = HashMap::new();
+        uniswap_prices.insert("A".to_string(), pair("A", "B", 1_000_000.0, 1_100_000.0));
+
+        let mut sushiswap_prices = HashMap::new();
+        sushiswap_prices.insert("B".to_string(), pair("B", "C", 1_000_000.0, 1_050_000.0));
+
+        let mut prices = HashMap::new();
+        prices.insert("uniswap".to_string(), uniswap_prices);
+        prices.insert("sushiswap".to_string(), sushiswap_prices);
+        pathfinder.load_prices(prices);
+
+        pathfinder
+    }
+
+    #[test]
+    fn test_get_amount_out_by_path_walks_forward_through_each_hop() {
+        let pathfinder = pathfinder_with_a_to_b_to_c_path();
+
+        let amounts = pathfinder
+            .get_amount_out_by_path(1000.0, &["A", "B", "C"], &["uniswap", "sushiswap"])
+            .expect("camino con pools válidos en cada hop");
+
+        assert_eq!(amounts.len(), 2);
+        assert!(amounts[0] > 0.0 && amounts[0] < 1100.0); // output de A->B con slippage
+        assert!(amounts[1] > 0.0 && amounts[1] < amounts[0] * 1.05); // output de B->C
+    }
+
+    #[test]
+    fn test_get_amount_out_by_path_is_none_when_path_and_dexes_lengths_mismatch() {
+        let pathfinder = pathfinder_with_a_to_b_to_c_path();
+
+        let result = pathfinder.get_amount_out_by_path(1000.0, &["A", "B", "C"], &["uniswap"]);
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_get_amount_in_by_path_inverts_get_amount_out_by_path() {
+        let pathfinder = pathfinder_with_a_to_b_to_c_path();
+
+        let amount_in = 1000.0;
+        let amounts_out = pathfinder
+            .get_amount_out_by_path(amount_in, &["A", "B", "C"], &["uniswap", "sushiswap"])
+            .expect("camino con pools válidos en cada hop");
+        let final_amount_out = *amounts_out.last().unwrap();
+
+        let amounts_in = pathfinder
+            .get_amount_in_by_path(final_amount_out, &["A", "B", "C"], &["uniswap", "sushiswap"])
+            .expect("camino con pools válidos en cada hop");
+
+        // El amount_in requerido para rendir exactamente `final_amount_out`
+        // debe coincidir (módulo redondeo de punto flotante) con el que se
+        // usó para generarlo.
+        assert!((amounts_in[0] - amount_in).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_get_amount_in_by_path_rejects_amount_out_at_or_above_reserve_out() {
+        let pathfinder = pathfinder_with_a_to_b_to_c_path();
+
+        // El pool B->C solo tiene 1_050_000 de reserve_out: pedir ese mismo
+        // monto o más es imposible sin drenar el pool por completo.
+        let result = pathfinder.get_amount_in_by_path(1_050_000.0, &["A", "B", "C"], &["uniswap", "sushiswap"]);
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_find_profitable_routes_prefers_hybrid_fill_when_it_beats_the_amm() {
+        let dexes = vec![
+            DexInfo {
+                id: "uniswap".to_string(),
+                name: "Uniswap V3".to_string(),
+                chain: "ethereum".to_string(),
+                fee_percentage: 0.3,
+                liquidity_usd: 5_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
+            },
+            DexInfo {
+                id: "sushiswap".to_string(),
+                name: "SushiSwap".to_string(),
+                chain: "ethereum".to_string(),
+                fee_percentage: 0.25,
+                liquidity_usd: 2_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
+            },
+            DexInfo {
+                id: "curve".to_string(),
+                name: "Curve Finance".to_string(),
+                chain: "ethereum".to_string(),
+                fee_percentage: 0.04,
+                liquidity_usd: 3_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
+            },
+        ];
+
+        let mut pathfinder = ThreeDexPathfinder::new(dexes);
+
+        fn pair(token_in: &str, token_out: &str, reserve_out: f64) -> TokenPair {
+            TokenPair {
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                price: 1.0,
+                liquidity: 1_000_000.0,
+                reserve_in: 1_000_000.0,
+                reserve_out,
+                reserve_in_units: None,
+                reserve_out_units: None,
+                pool_kind: PoolKind::ConstantProduct,
+                order_book: None,
+            }
+        }
+
+        // El hop B->C (sushiswap) trae además un libro de órdenes con mucho
+        // mejor precio que el AMM y tamaño de sobra para todo el trade: el
+        // llenado híbrido debería rendir más profit que cotizar ese hop
+        // enteramente contra el AMM.
+        let mut pair_bc = pair("B", "C", 1_100_000.0);
+        pair_bc.order_book = Some(vec![LimitOrder {
+            price: 1.2,
+            size_remaining: 1_000_000.0,
+            side: hybrid::OrderSide::Ask,
+        }]);
+
+        // `find_complex_route` indexa cada mapa de precios por el token
+        // intermedio al que lleva ese hop (no por `token_in`), salvo la
+        // entrada de vuelta a `start_token`, que busca por el token de
+        // origen del último hop (ver su `dex_1_prices.get(token_d)`).
+        let mut uniswap_prices = HashMap::new();
+        uniswap_prices.insert("B".to_string(), pair("A", "B", 1_200_000.0));
+        uniswap_prices.insert("D".to_string(), pair("D", "A", 1_060_000.0));
+
+        let mut sushiswap_prices = HashMap::new();
+        sushiswap_prices.insert("C".to_string(), pair_bc);
+
+        let mut curve_prices = HashMap::new();
+        curve_prices.insert("D".to_string(), pair("C", "D", 1_080_000.0));
+
+        let mut prices = HashMap::new();
+        prices.insert("uniswap".to_string(), uniswap_prices);
+        prices.insert("sushiswap".to_string(), sushiswap_prices);
+        prices.insert("curve".to_string(), curve_prices);
+        pathfinder.load_prices(prices);
+
+        let routes = pathfinder.find_profitable_routes("A", 0.0, 0.0);
+
+        let route = routes.first().expect("debería encontrar al menos una ruta rentable");
+        let hybrid = route
+            .as_hybrid_route()
+            .expect("el hop B->C debería preferir el llenado híbrido sobre el AMM solo");
+
+        // El hop B->C es el segundo de los 4 (índice 1): debería mostrar
+        // consumo de libro además del AMM.
+        assert!(hybrid.fills[1].book_filled > 0.0);
+    }
 }
 
@@ -13,6 +13,10 @@
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
+use crate::pathfinding::amm;
+use crate::pathfinding::amm::PoolKind;
+use crate::utils::amounts::TokenAmount;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DexInfo {
     pub id: String,
@@ -20,6 +24,12 @@ pub struct DexInfo {
     pub chain: String,
     pub fee_percentage: f64,
     pub liquidity_usd: f64,
+    /// Tag por defecto para las `TokenPair` ingeridas de este DEX desde
+    /// Sheets/APIs cuando el dato no trae su propio `pool_kind` (ver
+    /// `TokenPair::pool_kind`, que es lo que realmente consulta
+    /// `calculate_route_profit` para elegir la curva de cada hop).
+    #[serde(default)]
+    pub pool_kind: PoolKind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +38,28 @@ pub struct TokenPair {
     pub token_out: String,
     pub price: f64,
     pub liquidity: f64,
+    /// Reserva de `token_in` en el pool, en unidades del token (no USD).
+    /// Alimenta el modelo constant-product de `amm::constant_product_output`
+    /// en vez del precio "flat" de arriba, para que el profit de la ruta
+    /// refleje el impacto de precio real del tamaño de trade.
+    pub reserve_in: f64,
+    /// Reserva de `token_out` en el pool, en unidades del token.
+    pub reserve_out: f64,
+    /// Mismas reservas que `reserve_in`/`reserve_out`, pero como `TokenAmount`
+    /// exacto (acepta hex o decimal desde Sheets/APIs vía su serde). `None`
+    /// para pools que todavía no migraron a fixed-point: esos quedan fuera
+    /// de `calculate_route_profit_exact` pero siguen funcionando en el
+    /// camino `f64` de siempre.
+    #[serde(default)]
+    pub reserve_in_units: Option<TokenAmount>,
+    #[serde(default)]
+    pub reserve_out_units: Option<TokenAmount>,
+    /// Curva que usa este pool para cotizar swaps. `ConstantProduct` (el
+    /// default) usa `amm::constant_product_output`; `Stable` usa
+    /// `amm::stable_swap_output`, que rinde mucho menos slippage cerca del
+    /// peg para pools tipo Curve.
+    #[serde(default)]
+    pub pool_kind: PoolKind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +73,14 @@ pub struct TwoDexRoute {
     pub gas_cost: f64,
     pub net_profit: f64,
     pub confidence_score: f64,
+    /// Monto final exacto de la ruta (no un delta de profit: `TokenAmount`
+    /// no representa signo), calculado vía `calculate_route_profit_exact`
+    /// cuando las tres pools tenían `reserve_*_units`. El llamador compara
+    /// `.raw()` contra el monto inicial para decidir profit/pérdida antes de
+    /// someter la transacción on-chain. `None` si algún hop no tenía
+    /// unidades exactas o la ruta no fue verificada.
+    #[serde(default)]
+    pub exact_amount_out: Option<TokenAmount>,
 }
 
 /// Algoritmo DP para encontrar rutas óptimas de 2-DEX
@@ -123,10 +163,10 @@ impl TwoDexPathfinder {
             }
         }
         
-        // Ordenar por profit neto descendente (array dinámico)
-        routes.sort_by(|a, b| {
-            b.net_profit.partial_cmp(&a.net_profit).unwrap()
-        });
+        // Ordenar por profit neto descendente (array dinámico). `total_cmp`
+        // en vez de `partial_cmp(...).unwrap()`: un `net_profit` corrupto a
+        // `NaN` no debe hacer panic acá, solo ordenar de forma determinística.
+        routes.sort_by(|a, b| b.net_profit.total_cmp(&a.net_profit));
         
         routes
     }
@@ -170,16 +210,26 @@ impl TwoDexPathfinder {
                 // Verificar si podemos volver al token inicial
                 if let Some(pair_3) = dex_1_prices.get(end_token) {
                     if pair_3.token_out == start_token {
-                        // Calcular profit de la ruta completa
-                        let profit = self.calculate_route_profit(
+                        // Calcular profit de la ruta completa; `None` significa
+                        // que algún hop no tiene reservas válidas, así que se
+                        // descarta esta ruta candidata por completo.
+                        let profit = match self.calculate_route_profit(
                             1000.0, // Monto inicial en USD
-                            pair_1.price,
-                            pair_2.price,
-                            pair_3.price,
+                            &dex_1.id,
+                            &dex_2.id,
+                            start_token,
+                            mid_token,
+                            end_token,
+                            pair_1,
+                            pair_2,
+                            pair_3,
                             dex_1.fee_percentage,
                             dex_2.fee_percentage,
-                        );
-                        
+                        ) {
+                            Some(profit) => profit,
+                            None => continue,
+                        };
+
                         if profit > max_profit {
                             max_profit = profit;
                             
@@ -204,6 +254,7 @@ impl TwoDexPathfinder {
                                 gas_cost,
                                 net_profit: profit - gas_cost,
                                 confidence_score: confidence,
+                                exact_amount_out: None,
                             });
                         }
                     }
@@ -214,29 +265,126 @@ impl TwoDexPathfinder {
         best_route
     }
     
-    /// Calcula el profit de una ruta considerando fees
+    /// Calcula el profit de una ruta encadenando la curva de cada pool según
+    /// su `pool_kind` (en vez de multiplicar precios "flat"): el output de
+    /// cada hop alimenta como input del siguiente. `RouteSimulator` solo
+    /// comparte reservas movidas entre hops con el mismo
+    /// `(dex_id, token_in, token_out)`; aquí los hops 1 y 3 de DEX1 operan
+    /// pares distintos, así que cada uno cotiza contra las reservas de su
+    /// `TokenPair`. Devuelve `None` si algún hop no tiene reservas válidas: la ruta
+    /// entera se descarta en vez de tratarse como profit cero.
+    #[allow(clippy::too_many_arguments)]
     fn calculate_route_profit(
         &self,
         initial_amount: f64,
-        price_1: f64,
-        price_2: f64,
-        price_3: f64,
+        dex_1_id: &str,
+        dex_2_id: &str,
+        token_start: &str,
+        token_mid: &str,
+        token_end: &str,
+        pair_1: &TokenPair,
+        pair_2: &TokenPair,
+        pair_3: &TokenPair,
         fee_1: f64,
         fee_2: f64,
-    ) -> f64 {
-        // Paso 1: Swap en DEX1 (aplicar fee)
-        let amount_after_swap_1 = initial_amount * price_1 * (1.0 - fee_1 / 100.0);
-        
-        // Paso 2: Swap en DEX2 (aplicar fee)
-        let amount_after_swap_2 = amount_after_swap_1 * price_2 * (1.0 - fee_2 / 100.0);
-        
-        // Paso 3: Swap de vuelta en DEX1 (aplicar fee)
-        let final_amount = amount_after_swap_2 * price_3 * (1.0 - fee_1 / 100.0);
-        
+    ) -> Option<f64> {
+        let mut sim = amm::RouteSimulator::new();
+
+        // Paso 1: Swap en DEX1
+        let amount_after_swap_1 = sim.swap_with_kind(
+            dex_1_id,
+            token_start,
+            token_mid,
+            pair_1.reserve_in,
+            pair_1.reserve_out,
+            initial_amount,
+            fee_1 / 100.0,
+            pair_1.pool_kind,
+        )?;
+
+        // Paso 2: Swap en DEX2
+        let amount_after_swap_2 = sim.swap_with_kind(
+            dex_2_id,
+            token_mid,
+            token_end,
+            pair_2.reserve_in,
+            pair_2.reserve_out,
+            amount_after_swap_1,
+            fee_2 / 100.0,
+            pair_2.pool_kind,
+        )?;
+
+        // Paso 3: Swap de vuelta en DEX1
+        let final_amount = sim.swap_with_kind(
+            dex_1_id,
+            token_end,
+            token_start,
+            pair_3.reserve_in,
+            pair_3.reserve_out,
+            amount_after_swap_2,
+            fee_1 / 100.0,
+            pair_3.pool_kind,
+        )?;
+
         // Profit = final - inicial
-        final_amount - initial_amount
+        Some(final_amount - initial_amount)
     }
-    
+
+    /// Verificación exacta de `calculate_route_profit` en aritmética `U256`
+    /// vía `amm::ExactRouteSimulator`, para una ruta y monto ya elegidos por
+    /// el camino `f64` antes de someter la transacción on-chain. Devuelve el
+    /// monto final (no un delta): `TokenAmount` no tiene signo, así que el
+    /// llamador compara `.raw()` contra `initial_amount.raw()` para saber si
+    /// hubo profit o pérdida. `None` si algún hop no tiene
+    /// `reserve_*_units` cargadas o las reservas son inválidas.
+    #[allow(clippy::too_many_arguments)]
+    pub fn calculate_route_profit_exact(
+        &self,
+        initial_amount: TokenAmount,
+        dex_1_id: &str,
+        dex_2_id: &str,
+        token_start: &str,
+        token_mid: &str,
+        token_end: &str,
+        pair_1: &TokenPair,
+        pair_2: &TokenPair,
+        pair_3: &TokenPair,
+        fee_bps_1: u32,
+        fee_bps_2: u32,
+    ) -> Option<TokenAmount> {
+        let mut sim = amm::ExactRouteSimulator::new();
+
+        let amount_after_swap_1 = sim.swap(
+            dex_1_id,
+            token_start,
+            token_mid,
+            pair_1.reserve_in_units?,
+            pair_1.reserve_out_units?,
+            initial_amount,
+            fee_bps_1,
+        )?;
+
+        let amount_after_swap_2 = sim.swap(
+            dex_2_id,
+            token_mid,
+            token_end,
+            pair_2.reserve_in_units?,
+            pair_2.reserve_out_units?,
+            amount_after_swap_1,
+            fee_bps_2,
+        )?;
+
+        sim.swap(
+            dex_1_id,
+            token_end,
+            token_start,
+            pair_3.reserve_in_units?,
+            pair_3.reserve_out_units?,
+            amount_after_swap_2,
+            fee_bps_1,
+        )
+    }
+
     /// Calcula el confidence score basado en liquidez
     fn calculate_confidence(
         &self,
@@ -299,6 +447,7 @@ mod tests {
                 chain: "ethereum".to_string(),
                 fee_percentage: 0.3,
                 liquidity_usd: 5_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
             },
             DexInfo {
                 id: "sushiswap".to_string(),
@@ -306,6 +455,7 @@ mod tests {
                 chain: "ethereum".to_string(),
                 fee_percentage: 0.25,
                 liquidity_usd: 2_000_000_000.0,
+                pool_kind: PoolKind::ConstantProduct,
             },
         ];
         
@@ -318,18 +468,209 @@ mod tests {
     #[test]
     fn test_profit_calculation() {
         let pathfinder = TwoDexPathfinder::new(vec![]);
-        
-        let profit = pathfinder.calculate_route_profit(
-            1000.0,  // $1000 inicial
-            1.1,     // +10% en swap 1
-            1.05,    // +5% en swap 2
-            1.02,    // +2% en swap 3
-            0.3,     // 0.3% fee
-            0.25,    // 0.25% fee
-        );
-        
+
+        // Pools profundos y desbalanceados a favor del trader en cada hop,
+        // para que la ruta completa cierre con profit pese al impacto de
+        // precio de la curva constant-product.
+        let pair_1 = TokenPair {
+            token_in: "A".to_string(),
+            token_out: "B".to_string(),
+            price: 1.1,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_200_000.0,
+            reserve_in_units: None,
+            reserve_out_units: None,
+            pool_kind: PoolKind::ConstantProduct,
+        };
+        let pair_2 = TokenPair {
+            token_in: "B".to_string(),
+            token_out: "C".to_string(),
+            price: 1.05,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_100_000.0,
+            reserve_in_units: None,
+            reserve_out_units: None,
+            pool_kind: PoolKind::ConstantProduct,
+        };
+        let pair_3 = TokenPair {
+            token_in: "C".to_string(),
+            token_out: "A".to_string(),
+            price: 1.02,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_080_000.0,
+            reserve_in_units: None,
+            reserve_out_units: None,
+            pool_kind: PoolKind::ConstantProduct,
+        };
+
+        let profit = pathfinder
+            .calculate_route_profit(
+                1000.0, // $1000 inicial
+                "uniswap",
+                "sushiswap",
+                "A",
+                "B",
+                "C",
+                &pair_1,
+                &pair_2,
+                &pair_3,
+                0.3,  // 0.3% fee
+                0.25, // 0.25% fee
+            )
+            .expect("reservas válidas en los tres hops");
+
         // Debería haber profit positivo
         assert!(profit > 0.0);
     }
+
+    #[test]
+    fn test_profit_calculation_is_none_when_a_hop_has_no_reserves() {
+        let pathfinder = TwoDexPathfinder::new(vec![]);
+
+        let pair_1 = TokenPair {
+            token_in: "A".to_string(),
+            token_out: "B".to_string(),
+            price: 1.1,
+            liquidity: 1_000_000.0,
+            reserve_in: 0.0, // sin reservas: la ruta debe descartarse
+            reserve_out: 1_200_000.0,
+            reserve_in_units: None,
+            reserve_out_units: None,
+            pool_kind: PoolKind::ConstantProduct,
+        };
+        let pair_2 = TokenPair {
+            token_in: "B".to_string(),
+            token_out: "C".to_string(),
+            price: 1.05,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_100_000.0,
+            reserve_in_units: None,
+            reserve_out_units: None,
+            pool_kind: PoolKind::ConstantProduct,
+        };
+        let pair_3 = TokenPair {
+            token_in: "C".to_string(),
+            token_out: "A".to_string(),
+            price: 1.02,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_080_000.0,
+            reserve_in_units: None,
+            reserve_out_units: None,
+            pool_kind: PoolKind::ConstantProduct,
+        };
+
+        let profit = pathfinder.calculate_route_profit(
+            1000.0,
+            "uniswap",
+            "sushiswap",
+            "A",
+            "B",
+            "C",
+            &pair_1,
+            &pair_2,
+            &pair_3,
+            0.3,
+            0.25,
+        );
+
+        assert!(profit.is_none());
+    }
+
+    #[test]
+    fn test_profit_calculation_exact_matches_sign_of_f64_profit() {
+        let pathfinder = TwoDexPathfinder::new(vec![]);
+
+        let pair_1 = TokenPair {
+            token_in: "A".to_string(),
+            token_out: "B".to_string(),
+            price: 1.1,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_200_000.0,
+            reserve_in_units: Some(TokenAmount::from_f64(1_000_000.0, 18).unwrap()),
+            reserve_out_units: Some(TokenAmount::from_f64(1_200_000.0, 18).unwrap()),
+            pool_kind: PoolKind::ConstantProduct,
+        };
+        let pair_2 = TokenPair {
+            token_in: "B".to_string(),
+            token_out: "C".to_string(),
+            price: 1.05,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_100_000.0,
+            reserve_in_units: Some(TokenAmount::from_f64(1_000_000.0, 18).unwrap()),
+            reserve_out_units: Some(TokenAmount::from_f64(1_100_000.0, 18).unwrap()),
+            pool_kind: PoolKind::ConstantProduct,
+        };
+        let pair_3 = TokenPair {
+            token_in: "C".to_string(),
+            token_out: "A".to_string(),
+            price: 1.02,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_080_000.0,
+            reserve_in_units: Some(TokenAmount::from_f64(1_000_000.0, 18).unwrap()),
+            reserve_out_units: Some(TokenAmount::from_f64(1_080_000.0, 18).unwrap()),
+            pool_kind: PoolKind::ConstantProduct,
+        };
+
+        let initial_amount = TokenAmount::from_f64(1000.0, 18).unwrap();
+        let final_amount = pathfinder
+            .calculate_route_profit_exact(
+                initial_amount,
+                "uniswap",
+                "sushiswap",
+                "A",
+                "B",
+                "C",
+                &pair_1,
+                &pair_2,
+                &pair_3,
+                30,
+                25,
+            )
+            .expect("unidades exactas cargadas en los tres hops");
+
+        assert!(final_amount.raw() > initial_amount.raw());
+    }
+
+    #[test]
+    fn test_profit_calculation_exact_is_none_without_reserve_units() {
+        let pathfinder = TwoDexPathfinder::new(vec![]);
+
+        let pair = TokenPair {
+            token_in: "A".to_string(),
+            token_out: "B".to_string(),
+            price: 1.1,
+            liquidity: 1_000_000.0,
+            reserve_in: 1_000_000.0,
+            reserve_out: 1_200_000.0,
+            reserve_in_units: None,
+            reserve_out_units: None,
+            pool_kind: PoolKind::ConstantProduct,
+        };
+
+        let initial_amount = TokenAmount::from_f64(1000.0, 18).unwrap();
+        let result = pathfinder.calculate_route_profit_exact(
+            initial_amount,
+            "uniswap",
+            "sushiswap",
+            "A",
+            "B",
+            "C",
+            &pair,
+            &pair,
+            &pair,
+            30,
+            25,
+        );
+
+        assert!(result.is_none());
+    }
 }
 
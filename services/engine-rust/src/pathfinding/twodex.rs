@@ -121,10 +121,10 @@ pub async fn find_arbitrage_opportunities_twodex(
         }
     }
     
-    // 4. Ordenar por profit neto descendente
-    opportunities.sort_by(|a, b| {
-        b.net_profit_usd.partial_cmp(&a.net_profit_usd).unwrap()
-    });
+    // 4. Ordenar por profit neto descendente. `total_cmp` en vez de
+    // `partial_cmp(...).unwrap()`: un `net_profit_usd` corrupto a `NaN` no
+    // debe hacer panic acá, solo ordenar de forma determinística.
+    opportunities.sort_by(|a, b| b.net_profit_usd.total_cmp(&a.net_profit_usd));
     
     log::info!("✅ Encontradas {} oportunidades de arbitraje", opportunities.len());
     log::info!("📈 Cache hit rate: {:.2}%", dp_memo.get_cache_hit_rate() * 100.0);
@@ -10,6 +10,11 @@ use std::collections::HashMap;
 use chrono::Utc;
 use uuid::Uuid;
 
+use crate::connectors::sheets::SystemConfig;
+use crate::pathfinding::amm;
+use crate::utils::amounts::TokenAmount;
+use crate::utils::money::Money;
+
 /// Encuentra oportunidades de arbitraje entre dos DEXes usando programación dinámica
 ///
 /// # Argumentos
@@ -24,22 +29,24 @@ pub async fn find_arbitrage_opportunities_twodex(
     dexes: &[Dex],
     assets: &[Asset],
     pools: &[Pool],
+    blockchains: &[Blockchain],
+    system_config: &SystemConfig,
     dp_memo: &mut DPMemoState,
 ) -> Result<Vec<ArbitrageOpportunity>, ArbitrageError> {
-    
+
     println!("🔍 Iniciando búsqueda de arbitraje two-DEX con programación dinámica...");
     println!("   DEXes activos: {}", dexes.len());
     println!("   Assets activos: {}", assets.len());
     println!("   Pools activos: {}", pools.len());
-    
+
     let mut opportunities = Vec::new();
-    
+
     // Crear índices para acceso rápido
     let asset_map: HashMap<String, &Asset> = assets
         .iter()
         .map(|a| (a.asset_id.clone(), a))
         .collect();
-    
+
     let pool_by_dex: HashMap<String, Vec<&Pool>> = pools
         .iter()
         .fold(HashMap::new(), |mut acc, pool| {
@@ -48,21 +55,34 @@ pub async fn find_arbitrage_opportunities_twodex(
                 .push(pool);
             acc
         });
-    
+
+    let (blockchain_map, native_asset_by_chain) = blockchain_fee_sources(blockchains, assets);
+
     // Programación dinámica: iterar sobre pares de DEXes
     for i in 0..dexes.len() {
         for j in (i + 1)..dexes.len() {
             let dex_a = &dexes[i];
             let dex_b = &dexes[j];
-            
+
             // Verificar que ambos DEXes estén en la misma blockchain
             if dex_a.blockchain_id != dex_b.blockchain_id {
                 continue;
             }
-            
+
+            // Sin el `Blockchain`/`Asset` nativo de esta chain no se puede
+            // armar un `FeeModel` preciso; saltarla en vez de fabricar un
+            // gas price/precio nativo inventado.
+            let fee_model = match (
+                blockchain_map.get(dex_a.blockchain_id.as_str()),
+                native_asset_by_chain.get(dex_a.blockchain_id.as_str()),
+            ) {
+                (Some(chain), Some(native_asset)) => FeeModel::new(chain, native_asset, system_config),
+                _ => continue,
+            };
+
             // Crear clave para memoización
             let dex_pair_key = format!("{}_{}", dex_a.dex_id, dex_b.dex_id);
-            
+
             // Verificar cache de memoización
             if let Some(cached_profit) = dp_memo.get_cached_profit(&dex_pair_key) {
                 // Ya calculamos este par, usar resultado cacheado
@@ -73,13 +93,14 @@ pub async fn find_arbitrage_opportunities_twodex(
                 }
                 continue;
             }
-            
+
             // Calcular oportunidades para este par de DEXes
             let pair_opportunities = calculate_pair_opportunities(
                 dex_a,
                 dex_b,
                 &asset_map,
                 &pool_by_dex,
+                &fee_model,
             ).await?;
             
             // Memoizar resultados
@@ -120,6 +141,7 @@ async fn calculate_pair_opportunities(
     dex_b: &Dex,
     asset_map: &HashMap<String, &Asset>,
     pool_by_dex: &HashMap<String, Vec<&Pool>>,
+    fee_model: &FeeModel,
 ) -> Result<Vec<ArbitrageOpportunity>, ArbitrageError> {
     
     let mut opportunities = Vec::new();
@@ -156,6 +178,7 @@ async fn calculate_pair_opportunities(
                 pool_a,
                 pool_b,
                 asset_map,
+                fee_model,
             ).await {
                 opportunities.push(opportunity);
             }
@@ -172,6 +195,7 @@ async fn calculate_direct_arbitrage(
     pool_a: &Pool,
     pool_b: &Pool,
     asset_map: &HashMap<String, &Asset>,
+    fee_model: &FeeModel,
 ) -> Option<ArbitrageOpportunity> {
     
     // Obtener assets
@@ -186,10 +210,12 @@ async fn calculate_direct_arbitrage(
     // Calcular precios en ambos pools
     let price_a = pool_a.price_token0; // Token0 en términos de Token1
     let price_b = pool_b.price_token0;
-    
-    // Verificar si hay diferencia de precio significativa
-    let price_diff_bps = ((price_b - price_a).abs() / price_a * 10000.0) as u32;
-    
+
+    // Verificar si hay diferencia de precio significativa. `checked_ratio`
+    // rinde `None` (en vez de `Inf`/`NaN`) si `price_a` es cero, que antes
+    // se colaba silenciosamente a través del `as u32` truncation.
+    let price_diff_bps = (checked_ratio((price_b - price_a).abs(), price_a)? * 10000.0) as u32;
+
     if price_diff_bps < 10 {
         // Diferencia menor a 0.1% - no vale la pena
         return None;
@@ -202,35 +228,79 @@ async fn calculate_direct_arbitrage(
         (pool_b, pool_a, dex_b, dex_a)
     };
     
-    // Calcular tamaño óptimo de trade
-    let optimal_amount_usd = calculate_optimal_trade_size(
+    // Calcular costos fijos (independientes del monto) primero: el tamaño
+    // óptimo de trade los necesita para maximizar el profit neto, no el
+    // profit bruto.
+    let gas_cost_usd = estimate_gas_cost(buy_dex, sell_dex, fee_model);
+    if gas_cost_usd <= 0.0 {
+        // Un gas_estimate_swap en cero en algún Dex haría que `gas_efficiency`
+        // dividiera por cero más abajo; tratarlo como dato faltante en vez
+        // de fabricar una eficiencia infinita.
+        return None;
+    }
+
+    // Tamaño óptimo de trade vía la curva real de ambos pools (ver
+    // `calculate_optimal_trade_size`), en vez del heurístico anterior
+    // `min(5% liquidez, 10x profit mínimo, $10k)`.
+    let trade = calculate_optimal_trade_size(
         buy_pool,
         sell_pool,
-        token_in_asset,
-    );
-    
-    // Calcular costos
-    let gas_cost_usd = estimate_gas_cost(buy_dex, sell_dex);
-    let swap_fees_usd = calculate_swap_fees(buy_pool, sell_pool, optimal_amount_usd);
-    let flash_loan_required = optimal_amount_usd > 1000.0; // Umbral arbitrario
-    let flash_loan_fees_usd = if flash_loan_required {
-        optimal_amount_usd * 0.0009 // 0.09% fee típico de Aave
-    } else {
-        0.0
-    };
-    
-    let total_costs_usd = gas_cost_usd + swap_fees_usd + flash_loan_fees_usd;
-    
-    // Calcular profit esperado
-    let price_diff_ratio = (sell_pool.price_token0 - buy_pool.price_token0) / buy_pool.price_token0;
-    let gross_profit_usd = optimal_amount_usd * price_diff_ratio;
-    let net_profit_usd = gross_profit_usd - total_costs_usd;
-    
+        &token_in_asset.asset_id,
+        &token_out_asset.asset_id,
+        gas_cost_usd,
+        fee_model,
+    )?;
+    let optimal_amount_usd = trade.amount_in_usd;
+
+    let swap_fees_usd = calculate_swap_fees(buy_pool, sell_pool, optimal_amount_usd, fee_model);
+    let protocol_fees_usd = fee_model.protocol_fee_usd(optimal_amount_usd);
+    let flash_loan_required = fee_model.flash_loan_required(optimal_amount_usd);
+    let flash_loan_fees_usd = fee_model.flash_loan_fee_usd(optimal_amount_usd);
+
+    // Cota de fees compuestos: si swap (que ya incluye el fee de protocolo)
+    // + flash loan + el equivalente de gas superan `max_total_fee_bps` del
+    // trade, la ruta solo "rinde" porque alguna fuente de fee está
+    // subestimada, no porque sea en verdad rentable.
+    let total_fee_bps = checked_ratio(swap_fees_usd + flash_loan_fees_usd + gas_cost_usd, optimal_amount_usd)? * 10000.0;
+    if total_fee_bps > fee_model.max_total_fee_bps as f64 {
+        return None;
+    }
+
+    // Todo el encadenado de costos/profit en `Money` (punto fijo de 128 bits,
+    // ver `utils::money`) en vez de sumar/restar `f64` directamente: un
+    // overflow o un monto no representable (`NaN`/`inf` colado desde Sheets)
+    // aborta la oportunidad en vez de propagar basura silenciosa.
+    let gas_cost_money = Money::from_f64(gas_cost_usd).ok()?;
+    let swap_fees_money = Money::from_f64(swap_fees_usd).ok()?;
+    let flash_loan_fees_money = Money::from_f64(flash_loan_fees_usd).ok()?;
+    let total_costs_money = gas_cost_money
+        .checked_add(swap_fees_money)
+        .ok()?
+        .checked_add(flash_loan_fees_money)
+        .ok()?;
+    let total_costs_usd = total_costs_money.to_f64();
+
+    // Profit real del round-trip completo (compra en `buy_pool`, venta en
+    // `sell_pool`, ya simulado contra ambas curvas por
+    // `calculate_optimal_trade_size`), no el spread de precio lineal
+    // `optimal_amount_usd * price_diff_ratio` que usaba antes.
+    let gross_profit_money = Money::from_f64(trade.sell_leg_out_usd)
+        .ok()?
+        .checked_sub(Money::from_f64(optimal_amount_usd).ok()?)
+        .ok()?;
+    let net_profit_money = gross_profit_money
+        .checked_sub(gas_cost_money)
+        .ok()?
+        .checked_sub(flash_loan_fees_money)
+        .ok()?;
+    let gross_profit_usd = gross_profit_money.to_f64();
+    let net_profit_usd = net_profit_money.to_f64();
+
     // Verificar rentabilidad mínima
     if net_profit_usd < token_in_asset.min_arbitrage_profit_usd {
         return None;
     }
-    
+
     // Calcular métricas de riesgo
     let risk_score = calculate_risk_score(buy_pool, sell_pool, token_in_asset, token_out_asset);
     let confidence_score = calculate_confidence_score(buy_pool, sell_pool);
@@ -238,7 +308,24 @@ async fn calculate_direct_arbitrage(
     // Crear oportunidad
     let now = Utc::now();
     let route_id = Uuid::new_v4().to_string();
-    
+
+    // Verificación exacta del hop de entrada (swap real contra `buy_pool`),
+    // cuando trae `reserve*_units`: `amount_in`/`amount_out` de arriba se
+    // cotizan contra la curva del pool sobre sus reservas en USD
+    // (`reserve0_usd`/`reserve1_usd`), no sobre sus reservas exactas en
+    // unidades de token, así que pueden divergir del monto que en verdad se
+    // recibiría on-chain. `None` si `buy_pool` todavía no trae reservas exactas.
+    let amount_in_units = TokenAmount::from_f64(optimal_amount_usd / token_in_asset.price_usd, token_in_asset.decimals);
+    let amount_out_units = amount_in_units
+        .and_then(|amount_in| exact_swap_output(buy_pool, &token_in_asset.asset_id, amount_in));
+
+    // Ratios vía `checked_ratio` en vez de `/` crudo: `None` si algún
+    // denominador es cero o el resultado no es representable, en vez de
+    // colar un `Inf`/`NaN` a los campos de la oportunidad.
+    let profit_ratio = checked_ratio(net_profit_usd, optimal_amount_usd)?;
+    let gas_efficiency = checked_ratio(net_profit_usd, gas_cost_usd)?;
+    let liquidity_utilization = checked_ratio(optimal_amount_usd, buy_pool.total_liquidity_usd)?;
+
     Some(ArbitrageOpportunity {
         route_id: route_id.clone(),
         status: "READY".to_string(),
@@ -262,37 +349,42 @@ async fn calculate_direct_arbitrage(
         token_out_id: token_out_asset.asset_id.clone(),
         token_intermediate_1: None,
         
-        // Cantidades
+        // Cantidades. `amount_out`/`amount_out_usd` son lo que rinde el leg
+        // de compra (token_out recibido en `buy_pool`), no el round-trip
+        // completo: `gross_profit_usd` ya captura el resultado final tras
+        // vender de vuelta en `sell_pool`.
         amount_in: optimal_amount_usd / token_in_asset.price_usd,
-        amount_out: (optimal_amount_usd + gross_profit_usd) / token_out_asset.price_usd,
+        amount_out: trade.buy_leg_out_usd / token_out_asset.price_usd,
         amount_in_usd: optimal_amount_usd,
-        amount_out_usd: optimal_amount_usd + gross_profit_usd,
+        amount_out_usd: trade.buy_leg_out_usd,
+        amount_in_units,
+        amount_out_units,
         price_in: token_in_asset.price_usd,
         price_out: token_out_asset.price_usd,
         price_impact_bps: buy_pool.price_impact_1k as u32,
         slippage_bps: buy_dex.default_slippage_bps,
-        expected_price: (optimal_amount_usd + gross_profit_usd) / optimal_amount_usd,
+        expected_price: trade.buy_leg_out_usd / optimal_amount_usd,
         
         // Profit
         expected_profit_usd: net_profit_usd,
-        expected_profit_bps: ((net_profit_usd / optimal_amount_usd) * 10000.0) as u32,
-        expected_profit_percentage: (net_profit_usd / optimal_amount_usd) * 100.0,
+        expected_profit_bps: (profit_ratio * 10000.0) as u32,
+        expected_profit_percentage: profit_ratio * 100.0,
         min_profit_usd: net_profit_usd * 0.8, // 80% del esperado
         max_profit_usd: net_profit_usd * 1.2, // 120% del esperado
         gas_cost_usd,
         gas_cost_gwei: gas_cost_usd / 0.000001, // Aproximación
         gas_limit: buy_dex.gas_estimate_swap + sell_dex.gas_estimate_swap,
-        protocol_fees_usd: 0.0,
+        protocol_fees_usd,
         swap_fees_usd,
         flash_loan_fees_usd,
         total_costs_usd,
         net_profit_usd,
-        roi_percentage: (net_profit_usd / optimal_amount_usd) * 100.0,
-        
+        roi_percentage: profit_ratio * 100.0,
+
         // Liquidez
         required_liquidity_usd: optimal_amount_usd,
         available_liquidity_usd: buy_pool.total_liquidity_usd.min(sell_pool.total_liquidity_usd),
-        liquidity_utilization: optimal_amount_usd / buy_pool.total_liquidity_usd,
+        liquidity_utilization,
         max_trade_size_usd: buy_pool.total_liquidity_usd * 0.1, // 10% de liquidez
         optimal_trade_size_usd: optimal_amount_usd,
         min_trade_size_usd: token_in_asset.min_arbitrage_profit_usd,
@@ -312,7 +404,7 @@ async fn calculate_direct_arbitrage(
             None
         },
         flash_loan_amount_usd: if flash_loan_required { optimal_amount_usd } else { 0.0 },
-        flash_loan_fee_bps: if flash_loan_required { 9 } else { 0 },
+        flash_loan_fee_bps: if flash_loan_required { fee_model.flash_loan_fee_bps } else { 0 },
         flash_loan_fee_usd: flash_loan_fees_usd,
         
         // Riesgo
@@ -327,9 +419,9 @@ async fn calculate_direct_arbitrage(
         
         // Optimización
         optimization_score: (confidence_score + (1.0 - risk_score)) / 2.0,
-        route_efficiency: net_profit_usd / optimal_amount_usd,
-        gas_efficiency: net_profit_usd / gas_cost_usd,
-        capital_efficiency: net_profit_usd / optimal_amount_usd,
+        route_efficiency: profit_ratio,
+        gas_efficiency,
+        capital_efficiency: profit_ratio,
         is_optimal_route: true,
         
         // Priorización
@@ -346,42 +438,190 @@ async fn calculate_direct_arbitrage(
     })
 }
 
-/// Calcula el tamaño óptimo de trade
+/// Reserva en USD de `token_id` dentro de `pool` (`reserve0_usd` o
+/// `reserve1_usd` según de qué lado del pool esté ese token). `None` si
+/// `token_id` no es ninguno de los dos tokens del pool.
+fn pool_reserve_usd(pool: &Pool, token_id: &str) -> Option<f64> {
+    if pool.token0_id == token_id {
+        Some(pool.reserve0_usd)
+    } else if pool.token1_id == token_id {
+        Some(pool.reserve1_usd)
+    } else {
+        None
+    }
+}
+
+/// Resultado de `calculate_optimal_trade_size`: el monto de entrada que
+/// maximiza el profit neto del round-trip, junto con los outputs
+/// intermedio (leg de compra) y final (leg de venta, de vuelta en
+/// `token_in`) que produce ese monto contra la curva real de cada pool.
+struct OptimalTrade {
+    amount_in_usd: f64,
+    buy_leg_out_usd: f64,
+    sell_leg_out_usd: f64,
+}
+
+/// Encuentra el tamaño de trade que maximiza el profit neto del round-trip
+/// `token_in -> (buy_pool) -> token_out -> (sell_pool) -> token_in`, en vez
+/// de la heurística anterior (`min(5% liquidez, 10x profit mínimo, $10k)`),
+/// que ignoraba por completo la curva de precio real de los pools.
+///
+/// Modela cada pool con sus reservas en USD (`reserve0_usd`/`reserve1_usd`,
+/// ya presentes en `Pool`) para no tener que reconvertir entre las unidades
+/// de token distintas de `buy_pool` y `sell_pool`. El profit neto
+/// `out(x) - x - gas_cost_usd - flash_loan_fee(x)` es unimodal en `x` (sube
+/// mientras el spread entre pools domina, baja una vez que el slippage de
+/// la curva se lo come), así que se maximiza con
+/// `amm::ternary_search_optimal_amount` en vez de un solver genérico.
+/// Devuelve `None` si algún pool no trae la reserva de ambos tokens, si la
+/// liquidez disponible no supera 100 USD, o si ningún monto en
+/// `[100, liquidez disponible]` rinde una cotización válida. El monto
+/// devuelto es el de mayor profit neto aunque ese profit sea negativo: el
+/// filtro de rentabilidad (`min_arbitrage_profit_usd`) lo aplica
+/// `calculate_direct_arbitrage`.
 fn calculate_optimal_trade_size(
     buy_pool: &Pool,
     sell_pool: &Pool,
-    token_asset: &Asset,
-) -> f64 {
-    // Usar el mínimo entre:
-    // 1. 5% de la liquidez del pool más pequeño
-    // 2. 10x el profit mínimo requerido
-    // 3. $10,000 USD (límite superior arbitrario)
-    
-    let min_liquidity = buy_pool.total_liquidity_usd.min(sell_pool.total_liquidity_usd);
-    let size_by_liquidity = min_liquidity * 0.05;
-    let size_by_min_profit = token_asset.min_arbitrage_profit_usd * 10.0;
-    let max_size = 10000.0;
-    
-    size_by_liquidity.min(size_by_min_profit).min(max_size).max(100.0)
+    token_in_id: &str,
+    token_out_id: &str,
+    gas_cost_usd: f64,
+    fee_model: &FeeModel,
+) -> Option<OptimalTrade> {
+    let reserve_in1 = pool_reserve_usd(buy_pool, token_in_id)?;
+    let reserve_out1 = pool_reserve_usd(buy_pool, token_out_id)?;
+    let reserve_in2 = pool_reserve_usd(sell_pool, token_out_id)?;
+    let reserve_out2 = pool_reserve_usd(sell_pool, token_in_id)?;
+
+    let buy_fee = (buy_pool.fee_bps as f64) / 10000.0;
+    let sell_fee = (sell_pool.fee_bps as f64) / 10000.0;
+
+    // Cotiza cada leg con la curva real del pool (`PoolKind`) en vez de
+    // asumir siempre constant-product: un pool `Stable` (Curve-style) rinde
+    // mucho menos slippage cerca del peg, y tratarlo como XYK sobreestimaría
+    // el costo de un leg o subestimaría el profit real del round-trip.
+    let buy_math = amm::pool_math(buy_pool.pool_kind);
+    let sell_math = amm::pool_math(sell_pool.pool_kind);
+
+    let available_liquidity_usd = buy_pool.total_liquidity_usd.min(sell_pool.total_liquidity_usd);
+    if available_liquidity_usd <= 100.0 {
+        return None;
+    }
+
+    let net_profit_fn = |amount_in: f64| {
+        let buy_leg_out = buy_math.amount_out(reserve_in1, reserve_out1, amount_in, buy_fee)?;
+        let sell_leg_out = sell_math.amount_out(reserve_in2, reserve_out2, buy_leg_out, sell_fee)?;
+        if sell_leg_out <= 0.0 {
+            return None;
+        }
+        let flash_loan_fee_usd = fee_model.flash_loan_fee_usd(amount_in);
+        Some(sell_leg_out - amount_in - gas_cost_usd - flash_loan_fee_usd)
+    };
+
+    let (amount_in_usd, _net_profit) =
+        amm::ternary_search_optimal_amount(100.0, available_liquidity_usd, 1.0, net_profit_fn)?;
+
+    let buy_leg_out_usd = buy_math.amount_out(reserve_in1, reserve_out1, amount_in_usd, buy_fee)?;
+    let sell_leg_out_usd = sell_math.amount_out(reserve_in2, reserve_out2, buy_leg_out_usd, sell_fee)?;
+
+    Some(OptimalTrade { amount_in_usd, buy_leg_out_usd, sell_leg_out_usd })
 }
 
-/// Estima el costo de gas
-fn estimate_gas_cost(dex_a: &Dex, dex_b: &Dex) -> f64 {
-    // Gas estimado = suma de gas de ambos swaps
-    // Precio en USD aproximado (simplificado)
-    let total_gas = dex_a.gas_estimate_swap + dex_b.gas_estimate_swap;
-    let gas_price_gwei = 20.0; // Simplificado
-    let eth_price_usd = 2000.0; // Simplificado
-    
-    (total_gas as f64) * gas_price_gwei * 0.000000001 * eth_price_usd
+/// Divide dos montos USD vía `Money` (punto fijo) en vez de `f64` crudo.
+/// `None` si el denominador es cero o alguno de los dos no es representable
+/// (`NaN`/`inf`/fuera de rango), en vez de propagar un `Inf`/`NaN` silencioso
+/// a campos como `gas_efficiency` o `roi_percentage`.
+fn checked_ratio(numerator: f64, denominator: f64) -> Option<f64> {
+    let numerator = Money::from_f64(numerator).ok()?;
+    let denominator = Money::from_f64(denominator).ok()?;
+    numerator.checked_div(denominator).ok().map(|m| m.to_f64())
+}
+
+/// Parámetros de gas/fee sourceados dinámicamente en vez de las constantes
+/// fijas que este archivo usaba antes (`gas_price_gwei = 20.0`,
+/// `eth_price_usd = 2000.0`, 0.09% de flash loan, umbral de $1000) — la
+/// misma violación del banner "CERO HARDCODING" de arriba. `gas_price_gwei`
+/// y `native_token_usd` varían por chain (de `Blockchain`/su `Asset`
+/// nativo, ver `blockchain_fee_sources`); `flash_loan_fee_bps`,
+/// `min_flash_loan_usd`, `protocol_fee_bps` y `max_total_fee_bps` son
+/// globales desde `SystemConfig`.
+#[derive(Debug, Clone, Copy)]
+pub struct FeeModel {
+    pub gas_price_gwei: f64,
+    pub native_token_usd: f64,
+    pub flash_loan_fee_bps: u32,
+    pub min_flash_loan_usd: f64,
+    pub protocol_fee_bps: u32,
+    pub max_total_fee_bps: u32,
+}
+
+impl FeeModel {
+    /// `blockchain` y `native_asset` deben ser de la misma chain; ver
+    /// `blockchain_fee_sources` para cómo se resuelven ambos por
+    /// `blockchain_id`.
+    pub fn new(blockchain: &Blockchain, native_asset: &Asset, system_config: &SystemConfig) -> Self {
+        Self {
+            gas_price_gwei: blockchain.gas_price_gwei,
+            native_token_usd: native_asset.price_usd,
+            flash_loan_fee_bps: system_config.flash_loan_fee_bps,
+            min_flash_loan_usd: system_config.min_flash_loan_usd,
+            protocol_fee_bps: system_config.protocol_fee_bps,
+            max_total_fee_bps: system_config.max_total_fee_bps,
+        }
+    }
+
+    fn gas_cost_usd_for_total(&self, total_gas: u64) -> f64 {
+        (total_gas as f64) * self.gas_price_gwei * 0.000000001 * self.native_token_usd
+    }
+
+    fn flash_loan_required(&self, amount_usd: f64) -> bool {
+        amount_usd > self.min_flash_loan_usd
+    }
+
+    fn flash_loan_fee_usd(&self, amount_usd: f64) -> f64 {
+        if self.flash_loan_required(amount_usd) {
+            amount_usd * (self.flash_loan_fee_bps as f64) / 10000.0
+        } else {
+            0.0
+        }
+    }
+
+    fn protocol_fee_usd(&self, amount_usd: f64) -> f64 {
+        amount_usd * (self.protocol_fee_bps as f64) / 10000.0
+    }
+}
+
+/// Resuelve, por `blockchain_id`, el registro de `Blockchain` (gas price) y
+/// el `Asset` nativo de esa chain (para su precio en USD) — los dos
+/// insumos por-chain que le faltan a un `FeeModel::new`. Compartido por
+/// `find_arbitrage_opportunities_twodex` y `find_arbitrage_opportunities_multihop`.
+fn blockchain_fee_sources<'a>(
+    blockchains: &'a [Blockchain],
+    assets: &'a [Asset],
+) -> (HashMap<String, &'a Blockchain>, HashMap<String, &'a Asset>) {
+    let blockchain_map: HashMap<String, &Blockchain> = blockchains
+        .iter()
+        .map(|b| (b.blockchain_id.clone(), b))
+        .collect();
+    let native_asset_by_chain: HashMap<String, &Asset> = assets
+        .iter()
+        .filter(|a| a.is_native)
+        .map(|a| (a.blockchain_id.clone(), a))
+        .collect();
+    (blockchain_map, native_asset_by_chain)
+}
+
+/// Estima el costo de gas para un par de swaps (2 hops) vía `FeeModel`.
+fn estimate_gas_cost(dex_a: &Dex, dex_b: &Dex, fee_model: &FeeModel) -> f64 {
+    fee_model.gas_cost_usd_for_total(dex_a.gas_estimate_swap + dex_b.gas_estimate_swap)
 }
 
-/// Calcula fees de swap
-fn calculate_swap_fees(buy_pool: &Pool, sell_pool: &Pool, amount_usd: f64) -> f64 {
+/// Calcula fees de swap: la comisión de cada pool (AMM) más el fee de
+/// protocolo de `FeeModel` (antes fijo en `protocol_fees_usd: 0.0`).
+fn calculate_swap_fees(buy_pool: &Pool, sell_pool: &Pool, amount_usd: f64, fee_model: &FeeModel) -> f64 {
     let buy_fee = (buy_pool.fee_bps as f64) / 10000.0;
     let sell_fee = (sell_pool.fee_bps as f64) / 10000.0;
-    
-    amount_usd * (buy_fee + sell_fee)
+
+    amount_usd * (buy_fee + sell_fee) + fee_model.protocol_fee_usd(amount_usd)
 }
 
 /// Calcula score de riesgo
@@ -399,9 +639,810 @@ fn calculate_risk_score(
     ((pool_risk + liquidity_risk + volatility_risk) / 3.0).min(1.0)
 }
 
+/// Cotiza `amount_in` de `token_in_id` contra las reservas exactas de
+/// `pool` vía `amm::constant_product_output_exact`. `None` si el pool no
+/// trae `reserve0_units`/`reserve1_units`, si `token_in_id` no coincide con
+/// ninguno de los dos tokens del pool, o si sus `decimals` no concuerdan con
+/// los de `amount_in`.
+fn exact_swap_output(pool: &Pool, token_in_id: &str, amount_in: TokenAmount) -> Option<TokenAmount> {
+    let (reserve_in, reserve_out) = if token_in_id == pool.token0_id {
+        (pool.reserve0_units?, pool.reserve1_units?)
+    } else if token_in_id == pool.token1_id {
+        (pool.reserve1_units?, pool.reserve0_units?)
+    } else {
+        return None;
+    };
+
+    amm::constant_product_output_exact(amount_in, reserve_in, reserve_out, pool.fee_bps)
+}
+
 /// Calcula score de confianza
 fn calculate_confidence_score(buy_pool: &Pool, sell_pool: &Pool) -> f64 {
     // Basado en health scores de los pools
     ((buy_pool.health_score + sell_pool.health_score) / 2.0).min(1.0)
 }
 
+/// Máximo de hops (pools) en un ciclo que
+/// `find_arbitrage_opportunities_multihop` intenta materializar: suficiente
+/// para rutas triangulares (3) y cuádruples (4). `ArbitrageOpportunity` solo
+/// nombra 3 slots de dex/pool (`dex_1/2/3_id`, `pool_1/2/3_id`), así que un
+/// 4to hop se reporta en `extra_fields`; ciclos más largos que esto los
+/// descarta en vez de inventar una ruta que el schema no puede describir.
+const MAX_MULTIHOP_HOPS: usize = 4;
+
+/// Arista del grafo token -> token usado para detectar ciclos de arbitraje.
+/// Misma convención que `cycle_finder::CycleArbitrageFinder` (peso
+/// `-ln(effective_rate)`), pero cargando también el `pool_id` porque acá sí
+/// necesitamos simular el swap real contra la curva del pool, no solo
+/// reportar el spread.
+struct CycleGraphEdge {
+    from: usize,
+    to: usize,
+    weight: f64,
+    dex_id: String,
+    pool_id: String,
+}
+
+/// Encuentra oportunidades de arbitraje cíclico (3-4 hops) dentro de cada
+/// blockchain, vía un grafo dirigido token -> token y detección de ciclos de
+/// peso negativo con Bellman-Ford. A diferencia de
+/// `find_arbitrage_opportunities_twodex`, que solo compara pares de DEXes
+/// con el mismo par de tokens, esto encuentra rutas triangulares/cuádruples
+/// que cruzan tokens intermedios (`token_in -> ... -> token_in`), dejando de
+/// desperdiciar `dex_3_id`/`pool_3_id`/`token_intermediate_1`.
+pub async fn find_arbitrage_opportunities_multihop(
+    dexes: &[Dex],
+    assets: &[Asset],
+    pools: &[Pool],
+    blockchains: &[Blockchain],
+    system_config: &SystemConfig,
+) -> Result<Vec<ArbitrageOpportunity>, ArbitrageError> {
+    println!("🔍 Iniciando búsqueda de arbitraje multi-hop (ciclos vía Bellman-Ford)...");
+    println!("   DEXes activos: {}", dexes.len());
+    println!("   Pools activos: {}", pools.len());
+
+    let asset_map: HashMap<String, &Asset> = assets.iter().map(|a| (a.asset_id.clone(), a)).collect();
+    let dex_map: HashMap<String, &Dex> = dexes.iter().map(|d| (d.dex_id.clone(), d)).collect();
+    let pool_map: HashMap<String, &Pool> = pools.iter().map(|p| (p.pool_id.clone(), p)).collect();
+    let (blockchain_map, native_asset_by_chain) = blockchain_fee_sources(blockchains, assets);
+
+    let mut blockchain_ids: Vec<&str> = pools.iter().map(|p| p.blockchain_id.as_str()).collect();
+    blockchain_ids.sort_unstable();
+    blockchain_ids.dedup();
+
+    let mut opportunities = Vec::new();
+    for blockchain_id in blockchain_ids {
+        // Sin el `Blockchain`/`Asset` nativo de esta chain no se puede armar
+        // un `FeeModel` preciso; saltarla en vez de fabricar uno inventado.
+        let fee_model = match (blockchain_map.get(blockchain_id), native_asset_by_chain.get(blockchain_id)) {
+            (Some(chain), Some(native_asset)) => FeeModel::new(chain, native_asset, system_config),
+            _ => continue,
+        };
+
+        let chain_pools: Vec<&Pool> = pools
+            .iter()
+            .filter(|p| p.blockchain_id == blockchain_id && p.arbitrage_enabled)
+            .collect();
+
+        opportunities.extend(find_cycles_for_blockchain(
+            &chain_pools,
+            &asset_map,
+            &dex_map,
+            &pool_map,
+            &fee_model,
+        ));
+    }
+
+    opportunities.sort_by(|a, b| {
+        b.expected_profit_usd
+            .partial_cmp(&a.expected_profit_usd)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    for (index, opp) in opportunities.iter_mut().enumerate() {
+        opp.rank = (index + 1) as u32;
+    }
+
+    println!("✅ Arbitraje multi-hop completado: {} oportunidades encontradas", opportunities.len());
+
+    Ok(opportunities)
+}
+
+/// Corre Bellman-Ford sobre el subgrafo de una única blockchain y recupera
+/// todos los ciclos negativos distintos de longitud 3-4 como
+/// `ArbitrageOpportunity`. Estructura idéntica a
+/// `cycle_finder::CycleArbitrageFinder::find_cycles_for_chain`: arrancar
+/// todas las distancias en 0 equivale a una fuente virtual conectada a cada
+/// token, así que se detecta un ciclo negativo alcanzable desde cualquier
+/// token, no solo desde uno fijo.
+///
+/// Detector de ciclos negativos canónico de este árbol: es el único de los
+/// tres (este, `cycle_finder::CycleArbitrageFinder`,
+/// `twodex_dp_v2::find_cyclic_arbitrage`) con `FeeModel`/`PoolMath` detrás, así
+/// que trabajo nuevo de arbitraje cíclico va acá, no en los otros dos.
+/// `cycle_finder` queda congelado para su propio caller (wireado a
+/// `ArbitragePathfinder::find_best_routes`); `twodex_dp_v2::find_cyclic_arbitrage`
+/// nunca tuvo bridge a producción y está marcado `#[deprecated]` por eso, no
+/// solo documentado como frozen.
+fn find_cycles_for_blockchain(
+    pools: &[&Pool],
+    asset_map: &HashMap<String, &Asset>,
+    dex_map: &HashMap<String, &Dex>,
+    pool_map: &HashMap<String, &Pool>,
+    fee_model: &FeeModel,
+) -> Vec<ArbitrageOpportunity> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut token_index: HashMap<&str, usize> = HashMap::new();
+    for pool in pools {
+        for token_id in [pool.token0_id.as_str(), pool.token1_id.as_str()] {
+            if !token_index.contains_key(token_id) {
+                token_index.insert(token_id, tokens.len());
+                tokens.push(token_id.to_string());
+            }
+        }
+    }
+
+    let num_tokens = tokens.len();
+    if num_tokens == 0 {
+        return Vec::new();
+    }
+
+    let mut edges: Vec<CycleGraphEdge> = Vec::new();
+    for pool in pools {
+        let g = 1.0 - (pool.fee_bps as f64) / 10000.0;
+        let rate_0_to_1 = pool.price_token0 * g;
+        let rate_1_to_0 = pool.price_token1 * g;
+        let idx0 = token_index[pool.token0_id.as_str()];
+        let idx1 = token_index[pool.token1_id.as_str()];
+
+        if rate_0_to_1 > 0.0 {
+            edges.push(CycleGraphEdge {
+                from: idx0,
+                to: idx1,
+                weight: -rate_0_to_1.ln(),
+                dex_id: pool.dex_id.clone(),
+                pool_id: pool.pool_id.clone(),
+            });
+        }
+        if rate_1_to_0 > 0.0 {
+            edges.push(CycleGraphEdge {
+                from: idx1,
+                to: idx0,
+                weight: -rate_1_to_0.ln(),
+                dex_id: pool.dex_id.clone(),
+                pool_id: pool.pool_id.clone(),
+            });
+        }
+    }
+
+    let mut dist = vec![0.0f64; num_tokens];
+    let mut predecessor: Vec<Option<usize>> = vec![None; num_tokens];
+
+    for _ in 0..num_tokens.saturating_sub(1) {
+        for edge in &edges {
+            if dist[edge.from] + edge.weight < dist[edge.to] {
+                dist[edge.to] = dist[edge.from] + edge.weight;
+                predecessor[edge.to] = Some(edge.from);
+            }
+        }
+    }
+
+    // V-ésima pasada: toda arista que todavía relaja está en, o lleva a, un
+    // ciclo de peso negativo.
+    let mut seen_nodes = vec![false; num_tokens];
+    let mut opportunities = Vec::new();
+
+    for edge in &edges {
+        if dist[edge.from] + edge.weight >= dist[edge.to] {
+            continue;
+        }
+
+        // Caminar `num_tokens` veces por los predecesores garantiza terminar
+        // dentro del ciclo, no solo en el camino que lleva a él.
+        let mut node = edge.to;
+        for _ in 0..num_tokens {
+            node = predecessor[node].unwrap_or(node);
+        }
+
+        if seen_nodes[node] {
+            continue;
+        }
+
+        let mut cycle_nodes = vec![node];
+        let mut current = predecessor[node];
+        while let Some(prev) = current {
+            if prev == node {
+                break;
+            }
+            cycle_nodes.push(prev);
+            current = predecessor[prev];
+        }
+        cycle_nodes.push(node);
+        cycle_nodes.reverse();
+
+        for &n in &cycle_nodes {
+            seen_nodes[n] = true;
+        }
+
+        let hop_count = cycle_nodes.len() - 1;
+        if hop_count < 3 || hop_count > MAX_MULTIHOP_HOPS {
+            continue;
+        }
+
+        let mut cycle_edges: Vec<&CycleGraphEdge> = Vec::with_capacity(hop_count);
+        let mut complete = true;
+        for pair in cycle_nodes.windows(2) {
+            match edges.iter().find(|e| e.from == pair[0] && e.to == pair[1]) {
+                Some(e) => cycle_edges.push(e),
+                None => {
+                    complete = false;
+                    break;
+                }
+            }
+        }
+        if !complete {
+            continue;
+        }
+
+        if let Some(opportunity) = build_cycle_opportunity(
+            &cycle_nodes,
+            &cycle_edges,
+            &tokens,
+            asset_map,
+            dex_map,
+            pool_map,
+            fee_model,
+        ) {
+            opportunities.push(opportunity);
+        }
+    }
+
+    opportunities
+}
+
+/// Una pata del ciclo ya resuelta a su pool/dex concretos y sus reservas en
+/// USD, en el orden en que se recorre el ciclo.
+struct CycleLeg<'a> {
+    pool: &'a Pool,
+    dex: &'a Dex,
+    reserve_in: f64,
+    reserve_out: f64,
+    fee: f64,
+    /// Curva de cotización real del pool (`amm::pool_math(pool.pool_kind)`),
+    /// para que una pata sobre un pool `Stable` no se cotice como si fuera
+    /// constant-product.
+    math: Box<dyn amm::PoolMath>,
+}
+
+/// Encadena el swap de cada pata con la curva real de su pool
+/// (`CycleLeg::math`), devolviendo el monto en USD a la salida de cada pata
+/// (incluyendo el monto de entrada en la posición 0), igual idea que
+/// `calculate_optimal_trade_size` pero generalizada a N patas en vez de
+/// exactamente dos. Una pata cuya curva no converge (p.ej. `StablePool`
+/// fuera de rango) corta la cadena en `0.0`, igual que un output de
+/// constant-product agotado.
+fn simulate_cycle_amounts(amount_in: f64, legs: &[CycleLeg]) -> Vec<f64> {
+    let mut amounts = Vec::with_capacity(legs.len() + 1);
+    amounts.push(amount_in);
+    for leg in legs {
+        let prev = *amounts.last().unwrap();
+        let out = leg.math.amount_out(leg.reserve_in, leg.reserve_out, prev, leg.fee).unwrap_or(0.0);
+        amounts.push(out);
+    }
+    amounts
+}
+
+/// Construye la `ArbitrageOpportunity` de un ciclo ya detectado, simulando
+/// su round-trip completo contra la curva real de cada pool (igual filosofía
+/// que `calculate_optimal_trade_size`, generalizada a N patas) para
+/// encontrar el monto de entrada que maximiza el profit neto. `None` si
+/// algún pool no trae reservas/dex resolubles, si la liquidez disponible es
+/// insuficiente, o si el ciclo no limpia el profit mínimo del token de
+/// entrada.
+fn build_cycle_opportunity(
+    cycle_nodes: &[usize],
+    cycle_edges: &[&CycleGraphEdge],
+    tokens: &[String],
+    asset_map: &HashMap<String, &Asset>,
+    dex_map: &HashMap<String, &Dex>,
+    pool_map: &HashMap<String, &Pool>,
+    fee_model: &FeeModel,
+) -> Option<ArbitrageOpportunity> {
+    let hop_count = cycle_edges.len();
+    let token_in_id = tokens[cycle_nodes[0]].clone();
+    let token_in_asset = *asset_map.get(&token_in_id)?;
+    if !token_in_asset.arbitrage_enabled {
+        return None;
+    }
+
+    let mut legs = Vec::with_capacity(hop_count);
+    for (i, edge) in cycle_edges.iter().enumerate() {
+        let pool = *pool_map.get(&edge.pool_id)?;
+        let dex = *dex_map.get(&edge.dex_id)?;
+        let from_token = &tokens[cycle_nodes[i]];
+        let to_token = &tokens[cycle_nodes[i + 1]];
+        legs.push(CycleLeg {
+            pool,
+            dex,
+            reserve_in: pool_reserve_usd(pool, from_token)?,
+            reserve_out: pool_reserve_usd(pool, to_token)?,
+            fee: (pool.fee_bps as f64) / 10000.0,
+            math: amm::pool_math(pool.pool_kind),
+        });
+    }
+
+    let available_liquidity_usd = legs
+        .iter()
+        .map(|leg| leg.pool.total_liquidity_usd)
+        .fold(f64::INFINITY, f64::min);
+    if !available_liquidity_usd.is_finite() || available_liquidity_usd <= 100.0 {
+        return None;
+    }
+
+    let gas_cost_usd = estimate_gas_cost_multi(&legs.iter().map(|leg| leg.dex).collect::<Vec<_>>(), fee_model);
+    if gas_cost_usd <= 0.0 {
+        // Gas agregado en cero dividiría por cero en `gas_efficiency` más
+        // abajo; tratarlo como dato faltante en vez de fabricar un infinito.
+        return None;
+    }
+
+    let net_profit_fn = |amount_in: f64| {
+        let amounts = simulate_cycle_amounts(amount_in, &legs);
+        let out_usd = *amounts.last().unwrap();
+        if out_usd <= 0.0 {
+            return None;
+        }
+        let flash_loan_fee_usd = fee_model.flash_loan_fee_usd(amount_in);
+        Some(out_usd - amount_in - gas_cost_usd - flash_loan_fee_usd)
+    };
+
+    let (amount_in_usd, _net_profit) =
+        amm::ternary_search_optimal_amount(100.0, available_liquidity_usd, 1.0, net_profit_fn)?;
+
+    let amounts = simulate_cycle_amounts(amount_in_usd, &legs);
+    let out_usd = *amounts.last().unwrap();
+    let pool_fees_usd: f64 = legs.iter().zip(amounts.windows(2)).map(|(leg, w)| w[0] * leg.fee).sum();
+    let protocol_fees_usd = fee_model.protocol_fee_usd(amount_in_usd);
+    let swap_fees_usd = pool_fees_usd + protocol_fees_usd;
+
+    let flash_loan_required = fee_model.flash_loan_required(amount_in_usd);
+    let flash_loan_fees_usd = fee_model.flash_loan_fee_usd(amount_in_usd);
+
+    // Cota de fees compuestos, misma convención que `calculate_direct_arbitrage`.
+    let total_fee_bps = checked_ratio(swap_fees_usd + flash_loan_fees_usd + gas_cost_usd, amount_in_usd)? * 10000.0;
+    if total_fee_bps > fee_model.max_total_fee_bps as f64 {
+        return None;
+    }
+
+    // Encadenado de costos/profit en `Money` (ver `calculate_direct_arbitrage`
+    // para la misma convención en el caso de 2 hops).
+    let gas_cost_money = Money::from_f64(gas_cost_usd).ok()?;
+    let swap_fees_money = Money::from_f64(swap_fees_usd).ok()?;
+    let flash_loan_fees_money = Money::from_f64(flash_loan_fees_usd).ok()?;
+    let total_costs_money = gas_cost_money
+        .checked_add(swap_fees_money)
+        .ok()?
+        .checked_add(flash_loan_fees_money)
+        .ok()?;
+    let total_costs_usd = total_costs_money.to_f64();
+
+    let gross_profit_money = Money::from_f64(out_usd)
+        .ok()?
+        .checked_sub(Money::from_f64(amount_in_usd).ok()?)
+        .ok()?;
+    let net_profit_money = gross_profit_money
+        .checked_sub(gas_cost_money)
+        .ok()?
+        .checked_sub(flash_loan_fees_money)
+        .ok()?;
+    let net_profit_usd = net_profit_money.to_f64();
+
+    if net_profit_usd < token_in_asset.min_arbitrage_profit_usd {
+        return None;
+    }
+
+    let profit_ratio = checked_ratio(net_profit_usd, amount_in_usd)?;
+    let gas_efficiency = checked_ratio(net_profit_usd, gas_cost_usd)?;
+    let liquidity_utilization = checked_ratio(amount_in_usd, legs[0].pool.total_liquidity_usd)?;
+
+    let token_assets: Vec<&Asset> = cycle_nodes
+        .iter()
+        .filter_map(|&i| asset_map.get(&tokens[i]).copied())
+        .collect();
+    let risk_score = calculate_risk_score_multi(
+        &legs.iter().map(|leg| leg.pool).collect::<Vec<_>>(),
+        &token_assets,
+    );
+    let confidence_score = calculate_confidence_score_multi(&legs.iter().map(|leg| leg.pool).collect::<Vec<_>>());
+
+    let now = Utc::now();
+    let route_id = Uuid::new_v4().to_string();
+
+    let mut extra_fields = HashMap::new();
+    extra_fields.insert(
+        "cycle_dex_ids".to_string(),
+        serde_json::json!(legs.iter().map(|leg| leg.dex.dex_id.clone()).collect::<Vec<_>>()),
+    );
+    extra_fields.insert(
+        "cycle_pool_ids".to_string(),
+        serde_json::json!(legs.iter().map(|leg| leg.pool.pool_id.clone()).collect::<Vec<_>>()),
+    );
+    extra_fields.insert(
+        "cycle_token_ids".to_string(),
+        serde_json::json!(cycle_nodes.iter().map(|&i| tokens[i].clone()).collect::<Vec<_>>()),
+    );
+    if hop_count > 3 {
+        // El schema solo nombra 3 slots de dex/pool; el 4to hop (y su token
+        // intermedio) se reporta en `extra_fields` en vez de perderse.
+        extra_fields.insert("dex_4_id".to_string(), serde_json::json!(legs[3].dex.dex_id));
+        extra_fields.insert("pool_4_id".to_string(), serde_json::json!(legs[3].pool.pool_id));
+        extra_fields.insert("token_intermediate_2".to_string(), serde_json::json!(tokens[cycle_nodes[2]]));
+    }
+
+    Some(ArbitrageOpportunity {
+        route_id,
+        status: "READY".to_string(),
+        is_active: true,
+        is_profitable: true,
+        route_type: if hop_count == 3 { "THREE_DEX".to_string() } else { "N_HOP".to_string() },
+        strategy: "CYCLIC_ARBITRAGE".to_string(),
+        complexity: (hop_count - 1) as u32,
+        hop_count: hop_count as u32,
+        dex_count: {
+            let mut dex_ids: Vec<&str> = legs.iter().map(|leg| leg.dex.dex_id.as_str()).collect();
+            dex_ids.sort_unstable();
+            dex_ids.dedup();
+            dex_ids.len() as u32
+        },
+        blockchain_id: legs[0].dex.blockchain_id.clone(),
+
+        // Ruta
+        dex_1_id: legs[0].dex.dex_id.clone(),
+        dex_2_id: legs.get(1).map(|leg| leg.dex.dex_id.clone()),
+        dex_3_id: legs.get(2).map(|leg| leg.dex.dex_id.clone()),
+        pool_1_id: legs[0].pool.pool_id.clone(),
+        pool_2_id: legs.get(1).map(|leg| leg.pool.pool_id.clone()),
+        pool_3_id: legs.get(2).map(|leg| leg.pool.pool_id.clone()),
+        token_in_id: token_in_id.clone(),
+        token_out_id: token_in_id.clone(), // el ciclo cierra sobre el mismo token
+        token_intermediate_1: Some(tokens[cycle_nodes[1]].clone()),
+
+        // Cantidades (round-trip completo: vuelve a `token_in`)
+        amount_in: amount_in_usd / token_in_asset.price_usd,
+        amount_out: out_usd / token_in_asset.price_usd,
+        amount_in_usd,
+        amount_out_usd: out_usd,
+        amount_in_units: None,
+        amount_out_units: None,
+        price_in: token_in_asset.price_usd,
+        price_out: token_in_asset.price_usd,
+        price_impact_bps: legs[0].pool.price_impact_1k as u32,
+        slippage_bps: legs[0].dex.default_slippage_bps,
+        expected_price: out_usd / amount_in_usd,
+
+        // Profit
+        expected_profit_usd: net_profit_usd,
+        expected_profit_bps: (profit_ratio * 10000.0) as u32,
+        expected_profit_percentage: profit_ratio * 100.0,
+        min_profit_usd: net_profit_usd * 0.8,
+        max_profit_usd: net_profit_usd * 1.2,
+        gas_cost_usd,
+        gas_cost_gwei: gas_cost_usd / 0.000001,
+        gas_limit: legs.iter().map(|leg| leg.dex.gas_estimate_swap).sum(),
+        protocol_fees_usd,
+        swap_fees_usd,
+        flash_loan_fees_usd,
+        total_costs_usd,
+        net_profit_usd,
+        roi_percentage: profit_ratio * 100.0,
+
+        // Liquidez
+        required_liquidity_usd: amount_in_usd,
+        available_liquidity_usd,
+        liquidity_utilization,
+        max_trade_size_usd: legs[0].pool.total_liquidity_usd * 0.1,
+        optimal_trade_size_usd: amount_in_usd,
+        min_trade_size_usd: token_in_asset.min_arbitrage_profit_usd,
+
+        // Timing
+        discovery_timestamp: now.timestamp(),
+        expiry_timestamp: now.timestamp() + 60,
+        execution_deadline: now.timestamp() + 45,
+        time_to_expiry_ms: 60000,
+        estimated_execution_time_ms: 5000 * hop_count as i64,
+
+        // Flash loan
+        flash_loan_required,
+        flash_loan_provider: if flash_loan_required { Some("AAVE_V3".to_string()) } else { None },
+        flash_loan_amount_usd: if flash_loan_required { amount_in_usd } else { 0.0 },
+        flash_loan_fee_bps: if flash_loan_required { fee_model.flash_loan_fee_bps } else { 0 },
+        flash_loan_fee_usd: flash_loan_fees_usd,
+
+        // Riesgo
+        risk_score,
+        confidence_score,
+        stability_score: legs.iter().map(|leg| leg.pool.health_score).sum::<f64>() / legs.len() as f64,
+        execution_probability: confidence_score * 0.9,
+        slippage_risk: legs.iter().map(|leg| leg.pool.slippage_bps as f64).sum::<f64>() / legs.len() as f64 / 10000.0,
+        liquidity_risk: 1.0 - (available_liquidity_usd / 1000000.0).min(1.0),
+        timing_risk: 0.1 * hop_count as f64 / 2.0, // más patas, más ventana de que el precio se mueva
+        mev_risk: 0.2,
+
+        // Optimización
+        optimization_score: (confidence_score + (1.0 - risk_score)) / 2.0,
+        route_efficiency: profit_ratio,
+        gas_efficiency,
+        capital_efficiency: profit_ratio,
+        is_optimal_route: true,
+
+        // Priorización
+        priority: if net_profit_usd > 100.0 { 1 } else if net_profit_usd > 50.0 { 2 } else { 3 },
+        weight: net_profit_usd / (risk_score + 0.1),
+        rank: 0,
+
+        // Timestamps
+        created_at: now.to_rfc3339(),
+        updated_at: now.to_rfc3339(),
+
+        extra_fields,
+    })
+}
+
+/// Igual que `estimate_gas_cost` pero para una ruta de N hops en vez de
+/// asumir siempre exactamente dos swaps, usada por
+/// `find_arbitrage_opportunities_multihop`.
+fn estimate_gas_cost_multi(dexes: &[&Dex], fee_model: &FeeModel) -> f64 {
+    fee_model.gas_cost_usd_for_total(dexes.iter().map(|d| d.gas_estimate_swap).sum())
+}
+
+/// Igual que `calculate_risk_score` pero promediando sobre los N pools y N
+/// tokens de un ciclo en vez de asumir exactamente dos de cada uno.
+fn calculate_risk_score_multi(pools: &[&Pool], tokens_in_cycle: &[&Asset]) -> f64 {
+    let pool_risk = pools.iter().map(|p| p.risk_score).sum::<f64>() / pools.len() as f64;
+    let min_liquidity = pools.iter().map(|p| p.total_liquidity_usd).fold(f64::INFINITY, f64::min);
+    let liquidity_risk = if min_liquidity < 100000.0 { 0.3 } else { 0.1 };
+    let volatility_risk =
+        tokens_in_cycle.iter().map(|a| a.volatility_24h).sum::<f64>() / tokens_in_cycle.len() as f64;
+
+    ((pool_risk + liquidity_risk + volatility_risk) / 3.0).min(1.0)
+}
+
+/// Igual que `calculate_confidence_score` pero promediando sobre los N pools
+/// de un ciclo en vez de asumir exactamente dos.
+fn calculate_confidence_score_multi(pools: &[&Pool]) -> f64 {
+    (pools.iter().map(|p| p.health_score).sum::<f64>() / pools.len() as f64).min(1.0)
+}
+
+/// Subconjunto de oportunidades elegido para ejecución real, separado de la
+/// lista completa rankeada por profit que devuelve
+/// `find_arbitrage_opportunities_twodex`: esa lista puede tener decenas de
+/// rutas compitiendo por las mismas pools, de las que solo un subconjunto
+/// sin conflictos y dentro de presupuesto es en verdad ejecutable a la vez.
+pub struct ExecutionBatch {
+    pub selected: Vec<ArbitrageOpportunity>,
+    pub total_capital_usd: f64,
+    pub total_gas_usd: f64,
+    pub total_profit_usd: f64,
+}
+
+impl ExecutionBatch {
+    fn empty() -> Self {
+        Self {
+            selected: Vec::new(),
+            total_capital_usd: 0.0,
+            total_gas_usd: 0.0,
+            total_profit_usd: 0.0,
+        }
+    }
+}
+
+/// Por encima de este tamaño de candidatos no se corre el refinamiento DP
+/// (ver `refine_with_capital_knapsack`): la tabla `n * capital_cents`
+/// crecería demasiado para un pase que de todos modos es solo opcional.
+const DP_REFINEMENT_MAX_CANDIDATES: usize = 40;
+
+/// IDs de pool que una oportunidad ocupa, incluyendo el 4to hop de un ciclo
+/// multihop (que vive en `extra_fields["pool_4_id"]` por la limitación de
+/// schema descrita en `build_cycle_opportunity`).
+fn opportunity_pool_ids(opp: &ArbitrageOpportunity) -> Vec<String> {
+    let mut ids = vec![opp.pool_1_id.clone()];
+    if let Some(pool_id) = &opp.pool_2_id {
+        ids.push(pool_id.clone());
+    }
+    if let Some(pool_id) = &opp.pool_3_id {
+        ids.push(pool_id.clone());
+    }
+    if let Some(pool_id) = opp.extra_fields.get("pool_4_id").and_then(|v| v.as_str()) {
+        ids.push(pool_id.to_string());
+    }
+    ids
+}
+
+/// Profit por USD de capital requerido, para ordenar candidatos de mayor a
+/// menor densidad en vez de por profit absoluto (una ruta de $10 de profit
+/// sobre $50 de capital es mejor uso del presupuesto que una de $11 sobre
+/// $10000).
+fn profit_density(opp: &ArbitrageOpportunity) -> f64 {
+    checked_ratio(opp.expected_profit_usd, opp.required_liquidity_usd).unwrap_or(0.0)
+}
+
+/// Pase greedy: recorre `candidates` (ya ordenados por densidad descendente)
+/// y toma cada uno que quepa en ambos presupuestos y no reutilice una pool
+/// ya ocupada por una ruta ya elegida. Devuelve los índices elegidos (sobre
+/// `candidates`) junto con los totales acumulados.
+fn select_greedy_batch(
+    candidates: &[&ArbitrageOpportunity],
+    capital_budget_usd: f64,
+    gas_budget_usd: f64,
+) -> (Vec<usize>, f64, f64, f64) {
+    let mut used_pools: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut selected = Vec::new();
+    let mut capital_used = 0.0;
+    let mut gas_used = 0.0;
+    let mut profit_total = 0.0;
+
+    for (idx, opp) in candidates.iter().enumerate() {
+        if opp.required_liquidity_usd <= 0.0 {
+            continue;
+        }
+        let pools = opportunity_pool_ids(opp);
+        if pools.iter().any(|pool_id| used_pools.contains(pool_id)) {
+            continue;
+        }
+        if capital_used + opp.required_liquidity_usd > capital_budget_usd {
+            continue;
+        }
+        if gas_used + opp.gas_cost_usd > gas_budget_usd {
+            continue;
+        }
+
+        capital_used += opp.required_liquidity_usd;
+        gas_used += opp.gas_cost_usd;
+        profit_total += opp.expected_profit_usd;
+        used_pools.extend(pools);
+        selected.push(idx);
+    }
+
+    (selected, capital_used, gas_used, profit_total)
+}
+
+/// Refina el pase greedy con un knapsack 0/1 exacto sobre el presupuesto de
+/// capital, solo cuando `candidates` es chico (ver `DP_REFINEMENT_MAX_CANDIDATES`).
+/// Reusa la discretización vía `Money::to_cents` de
+/// `RouteRanker::optimize_route_selection`. El DP en sí ignora conflictos de
+/// pool y presupuesto de gas al llenar la tabla —agregarlos inflaría el
+/// estado a `pools * gas * capital`—, así que la selección resultante se
+/// repara después recorriéndola por densidad descendente y descartando
+/// cualquier ruta que reutilice una pool ya tomada o rompa el presupuesto de
+/// gas acumulado. Por eso es solo un refinamiento opcional, no un solver
+/// garantizado óptimo bajo los tres constraints a la vez.
+fn refine_with_capital_knapsack(
+    candidates: &[&ArbitrageOpportunity],
+    capital_budget_usd: f64,
+    gas_budget_usd: f64,
+) -> Option<(Vec<usize>, f64, f64, f64)> {
+    let n = candidates.len();
+    if n == 0 || n > DP_REFINEMENT_MAX_CANDIDATES {
+        return None;
+    }
+
+    let capital_units = Money::from_f64(capital_budget_usd)
+        .map(Money::to_cents)
+        .unwrap_or(0)
+        .max(0) as usize;
+    if capital_units == 0 {
+        return None;
+    }
+
+    let mut dp: Vec<Vec<Money>> = vec![vec![Money::ZERO; capital_units + 1]; n + 1];
+    let mut chosen: Vec<Vec<bool>> = vec![vec![false; capital_units + 1]; n + 1];
+
+    for i in 1..=n {
+        let opp = candidates[i - 1];
+        let cost = Money::from_f64(opp.required_liquidity_usd)
+            .map(Money::to_cents)
+            .unwrap_or(0)
+            .max(0) as usize;
+        let profit = Money::from_f64(opp.expected_profit_usd).unwrap_or(Money::ZERO);
+
+        for c in 0..=capital_units {
+            dp[i][c] = dp[i - 1][c];
+            if cost <= c {
+                let with_route = dp[i - 1][c - cost].checked_add(profit).unwrap_or(dp[i - 1][c - cost]);
+                if with_route > dp[i][c] {
+                    dp[i][c] = with_route;
+                    chosen[i][c] = true;
+                }
+            }
+        }
+    }
+
+    let mut raw_indices = Vec::new();
+    let mut c = capital_units;
+    for i in (1..=n).rev() {
+        if chosen[i][c] {
+            raw_indices.push(i - 1);
+            let cost = Money::from_f64(candidates[i - 1].required_liquidity_usd)
+                .map(Money::to_cents)
+                .unwrap_or(0)
+                .max(0) as usize;
+            c = c.saturating_sub(cost);
+        }
+    }
+    raw_indices.sort_by(|&a, &b| {
+        profit_density(candidates[b])
+            .partial_cmp(&profit_density(candidates[a]))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut used_pools: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut selected = Vec::new();
+    let mut capital_used = 0.0;
+    let mut gas_used = 0.0;
+    let mut profit_total = 0.0;
+
+    for idx in raw_indices {
+        let opp = candidates[idx];
+        let pools = opportunity_pool_ids(opp);
+        if pools.iter().any(|pool_id| used_pools.contains(pool_id)) {
+            continue;
+        }
+        if gas_used + opp.gas_cost_usd > gas_budget_usd {
+            continue;
+        }
+
+        capital_used += opp.required_liquidity_usd;
+        gas_used += opp.gas_cost_usd;
+        profit_total += opp.expected_profit_usd;
+        used_pools.extend(pools);
+        selected.push(idx);
+    }
+
+    Some((selected, capital_used, gas_used, profit_total))
+}
+
+/// Elige el subconjunto de `opportunities` a ejecutar de verdad, acotado por
+/// `capital_budget_usd` (suma de `required_liquidity_usd`) y
+/// `gas_budget_usd` (suma de `gas_cost_usd`), sin que dos rutas elegidas
+/// reutilicen la misma pool (ejecutarlas a la vez se pisarían el precio la
+/// una a la otra). Corre un pase greedy por densidad de profit
+/// (`profit_density`) siempre, y lo refina con
+/// `refine_with_capital_knapsack` cuando el candidate set es chico,
+/// quedándose con el que rinda más profit total. Devuelve el batch elegido
+/// por separado de la lista completa rankeada — un conjunto liquidable, no
+/// más cotizaciones crudas.
+pub fn select_executable_batch(
+    opportunities: &[ArbitrageOpportunity],
+    capital_budget_usd: f64,
+    gas_budget_usd: f64,
+) -> ExecutionBatch {
+    if opportunities.is_empty() || capital_budget_usd <= 0.0 || gas_budget_usd <= 0.0 {
+        return ExecutionBatch::empty();
+    }
+
+    let mut ranked: Vec<&ArbitrageOpportunity> = opportunities.iter().collect();
+    ranked.sort_by(|a, b| {
+        profit_density(b)
+            .partial_cmp(&profit_density(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let (greedy_idx, greedy_capital, greedy_gas, greedy_profit) =
+        select_greedy_batch(&ranked, capital_budget_usd, gas_budget_usd);
+
+    let (best_idx, total_capital_usd, total_gas_usd, total_profit_usd) =
+        match refine_with_capital_knapsack(&ranked, capital_budget_usd, gas_budget_usd) {
+            Some((dp_idx, dp_capital, dp_gas, dp_profit)) if dp_profit > greedy_profit => {
+                (dp_idx, dp_capital, dp_gas, dp_profit)
+            }
+            _ => (greedy_idx, greedy_capital, greedy_gas, greedy_profit),
+        };
+
+    ExecutionBatch {
+        selected: best_idx.into_iter().map(|idx| ranked[idx].clone()).collect(),
+        total_capital_usd,
+        total_gas_usd,
+        total_profit_usd,
+    }
+}
+
@@ -6,6 +6,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::pathfinding::amm::PoolKind;
+use crate::utils::amounts::TokenAmount;
+
 /// Blockchain con todos los campos dinámicos (49 campos)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Blockchain {
@@ -125,6 +128,16 @@ pub struct Pool {
     pub reserve1: String,
     pub reserve0_usd: f64,
     pub reserve1_usd: f64,
+    /// Mismas reservas que `reserve0`/`reserve1`, pero como [`TokenAmount`]
+    /// exacto (unidades enteras en la escala de `token0_decimals`/
+    /// `token1_decimals`, no USD), para que `calculate_direct_arbitrage`
+    /// pueda cotizar el swap real vía `amm::constant_product_output_exact`
+    /// en vez de solo diferenciar `price_token0`/`price_token1`. `None`
+    /// para pools que todavía no traen este dato desde Sheets.
+    #[serde(default)]
+    pub reserve0_units: Option<TokenAmount>,
+    #[serde(default)]
+    pub reserve1_units: Option<TokenAmount>,
     pub total_liquidity_usd: f64,
     pub fee_tier: u32,
     pub fee_bps: u32,
@@ -139,6 +152,13 @@ pub struct Pool {
     pub flash_loan_enabled: bool,
     pub health_score: f64,
     pub risk_score: f64,
+    /// Curva de pricing del pool (`amm::PoolKind`). `ConstantProduct` por
+    /// default para pools que todavía no traen este dato desde Sheets, que
+    /// es la curva asumida implícitamente antes de que este campo existiera.
+    /// `amm::pool_math` usa esto para cotizar swaps con la fórmula correcta
+    /// en vez de asumir siempre constant-product.
+    #[serde(default)]
+    pub pool_kind: PoolKind,
     // Metadata adicional
     #[serde(flatten)]
     pub extra_fields: HashMap<String, serde_json::Value>,
@@ -174,6 +194,16 @@ pub struct ArbitrageOpportunity {
     pub amount_out: f64,
     pub amount_in_usd: f64,
     pub amount_out_usd: f64,
+    /// `amount_in`/`amount_out` como [`TokenAmount`] exacto: `amount_out_units`
+    /// es el output del hop de compra cotizado contra las
+    /// `reserve0_units`/`reserve1_units` de `buy_pool`, sin el redondeo `f64`
+    /// de 18 decimales. `amount_out_units` es `None` cuando `buy_pool` no trae
+    /// reservas exactas; las oportunidades de ciclos multihop dejan ambos en
+    /// `None`.
+    #[serde(default)]
+    pub amount_in_units: Option<TokenAmount>,
+    #[serde(default)]
+    pub amount_out_units: Option<TokenAmount>,
     pub price_in: f64,
     pub price_out: f64,
     pub price_impact_bps: u32,
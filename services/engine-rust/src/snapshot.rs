@@ -0,0 +1,336 @@
+/*
+ * ============================================================================
+ * ARCHIVO: ./services/engine-rust/src/snapshot.rs
+ * MÓDULO: Rust Engine
+ * ============================================================================
+ *
+ * 📥 ENTRADA:
+ *   - Conjunto serializable de oportunidades detectadas en un tick (id + item)
+ *
+ * 🔄 TRANSFORMACIÓN:
+ *   FUNCIONES: commit_snapshot, membership_proof, verify_proof
+ *   ALGORITMO: Árbol de Merkle binario insertion-only (Keccak-256)
+ *
+ * 📤 SALIDA:
+ *   RETORNA: SnapshotRoot (32 bytes) + Vec<ProofNode> para pruebas de membership
+ *
+ * 🔗 DEPENDENCIAS: (ninguna externa al motor)
+ *
+ * ============================================================================
+ */
+
+//! Store de snapshots Merklizado, insertion-only, para auditoría verificable.
+//!
+//! Cada tick de detección produce un conjunto de oportunidades; este módulo
+//! las serializa canónicamente, las hashea como hojas, y construye un árbol
+//! de Merkle binario sobre ellas (nodos impares se promueven sin cambios, en
+//! vez de duplicarse como en Bitcoin). El root de 32 bytes resultante
+//! se publica y queda persistido keyed por `snapshot_key` (timestamp o número
+//! de bloque), de forma que un servicio downstream (API server, executor)
+//! pueda luego pedir una prueba de membership para una oportunidad concreta y
+//! verificarla contra el root publicado sin tener que confiar en una base de
+//! datos mutable.
+//!
+//! `commit_snapshot` es genérico sobre `T: Serialize` en vez de acoplarse a
+//! un tipo `ArbitrageOpportunity` concreto: hoy conviven varias definiciones
+//! de ese struct en el árbol de módulos (`engine::arbitrage`,
+//! `pathfinding::types`, `pathfinding::twodex` y `pathfinding::twodex_dp_v2`),
+//! así que atarse a una sola sería arbitrario. El
+//! llamador aporta el `id` estable de cada item junto con el item mismo.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+
+/// Root de 32 bytes de un snapshot Merklizado.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotRoot(pub [u8; 32]);
+
+impl SnapshotRoot {
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+}
+
+/// Lado que ocupa el hermano de un nodo de la prueba respecto al hash que se
+/// está verificando en ese nivel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofSide {
+    Left,
+    Right,
+}
+
+/// Un paso de una prueba de membership: el hash del hermano y el lado que
+/// ocupa, necesarios para reconstruir el camino hoja -> root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofNode {
+    pub sibling: [u8; 32],
+    pub side: ProofSide,
+}
+
+/// Hash de hoja de un item serializable, usado tanto al construir el árbol
+/// como por un verificador externo que solo tiene el item y quiere llamar a
+/// `verify_proof` sin reconstruir el árbol entero.
+///
+/// Domain-separated con un byte de prefijo (`0x00`) para que una hoja nunca
+/// pueda colisionar con un nodo interno (`hash_internal_node`, prefijo
+/// `0x01`) aunque coincidan en longitud de bytes.
+pub fn hash_leaf<T: Serialize>(item: &T) -> [u8; 32] {
+    let canonical = serde_json::to_vec(item).unwrap_or_default();
+    let mut hasher = Keccak256::new();
+    hasher.update([0x00]);
+    hasher.update(&canonical);
+    hasher.finalize().into()
+}
+
+fn hash_internal_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Reconstruye el camino hoja -> root aplicando cada paso de la prueba, y
+/// compara el resultado contra `root`. No necesita acceso al árbol original.
+pub fn verify_proof(root: SnapshotRoot, leaf: [u8; 32], proof: &[ProofNode]) -> bool {
+    let mut current = leaf;
+    for node in proof {
+        current = match node.side {
+            ProofSide::Left => hash_internal_node(&node.sibling, &current),
+            ProofSide::Right => hash_internal_node(&current, &node.sibling),
+        };
+    }
+    current == root.0
+}
+
+/// Árbol de Merkle binario completo, conservado en memoria para poder
+/// derivar pruebas de membership sin recalcular nada.
+///
+/// `levels[0]` son las hojas y `levels.last()` es `[root]`. Un nivel de
+/// tamaño impar promueve su último nodo sin cambios al nivel siguiente, en
+/// vez de duplicarlo (evita la ambigüedad de segunda preimagen del duplicado
+/// clásico de Bitcoin).
+struct MerkleTree {
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    fn build(leaves: Vec<[u8; 32]>) -> Self {
+        let mut levels = vec![leaves];
+
+        while levels.last().expect("levels siempre tiene al menos un elemento").len() > 1 {
+            let prev = levels.last().unwrap();
+            let mut next = Vec::with_capacity(prev.len().div_ceil(2));
+            let mut i = 0;
+            while i < prev.len() {
+                if i + 1 < prev.len() {
+                    next.push(hash_internal_node(&prev[i], &prev[i + 1]));
+                } else {
+                    next.push(prev[i]);
+                }
+                i += 2;
+            }
+            levels.push(next);
+        }
+
+        MerkleTree { levels }
+    }
+
+    fn root(&self) -> [u8; 32] {
+        self.levels.last().unwrap()[0]
+    }
+
+    fn proof_for(&self, mut index: usize) -> Vec<ProofNode> {
+        let mut proof = Vec::new();
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            let is_right = index % 2 == 1;
+            let sibling_index = if is_right { index - 1 } else { index + 1 };
+
+            if let Some(&sibling) = level.get(sibling_index) {
+                proof.push(ProofNode {
+                    sibling,
+                    side: if is_right { ProofSide::Left } else { ProofSide::Right },
+                });
+            }
+            // Si no hay hermano (nodo impar promovido), este hash pasa sin
+            // cambios al siguiente nivel y la prueba no gana un paso aquí.
+
+            index /= 2;
+        }
+
+        proof
+    }
+}
+
+struct CommittedSnapshot {
+    tree: MerkleTree,
+    leaf_index_by_id: HashMap<String, usize>,
+}
+
+/// Store insertion-only de snapshots Merklizados, keyed por `snapshot_key`
+/// (timestamp o número de bloque), para que un tick pueda re-derivarse y
+/// verificarse independientemente sin confiar en una base de datos mutable.
+#[derive(Default)]
+pub struct SnapshotStore {
+    snapshots: Mutex<HashMap<u64, CommittedSnapshot>>,
+}
+
+impl SnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construye el árbol de Merkle sobre `items` (hoja = `hash_leaf`), lo
+    /// persiste bajo `snapshot_key`, y devuelve el root publicable. Un
+    /// `snapshot_key` repetido sobreescribe el snapshot anterior: el store es
+    /// insertion-only a nivel de cada árbol individual (no se puede alterar
+    /// una hoja ya comprometida sin cambiar el root), no a nivel de keys.
+    pub fn commit_snapshot<T: Serialize>(
+        &self,
+        snapshot_key: u64,
+        items: &[(String, T)],
+    ) -> SnapshotRoot {
+        let leaves: Vec<[u8; 32]> = items.iter().map(|(_, item)| hash_leaf(item)).collect();
+        let leaf_index_by_id = items
+            .iter()
+            .enumerate()
+            .map(|(index, (id, _))| (id.clone(), index))
+            .collect();
+
+        let tree = if leaves.is_empty() {
+            MerkleTree { levels: vec![vec![[0u8; 32]]] }
+        } else {
+            MerkleTree::build(leaves)
+        };
+        let root = SnapshotRoot(tree.root());
+
+        self.snapshots.lock().unwrap().insert(
+            snapshot_key,
+            CommittedSnapshot { tree, leaf_index_by_id },
+        );
+
+        root
+    }
+
+    /// Prueba de membership para el item `id` dentro del snapshot
+    /// `snapshot_key`, o `None` si el snapshot o el id no existen.
+    pub fn membership_proof(&self, snapshot_key: u64, id: &str) -> Option<Vec<ProofNode>> {
+        let snapshots = self.snapshots.lock().unwrap();
+        let snapshot = snapshots.get(&snapshot_key)?;
+        let index = *snapshot.leaf_index_by_id.get(id)?;
+        Some(snapshot.tree.proof_for(index))
+    }
+
+    /// Root publicado para `snapshot_key`, si ese snapshot fue comprometido.
+    pub fn root_for(&self, snapshot_key: u64) -> Option<SnapshotRoot> {
+        self.snapshots
+            .lock()
+            .unwrap()
+            .get(&snapshot_key)
+            .map(|snapshot| SnapshotRoot(snapshot.tree.root()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Opp {
+        id: String,
+        net_profit: f64,
+    }
+
+    fn sample_items(n: usize) -> Vec<(String, Opp)> {
+        (0..n)
+            .map(|i| {
+                let id = format!("opp_{i}");
+                (id.clone(), Opp { id, net_profit: i as f64 })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_commit_snapshot_is_deterministic_for_the_same_items() {
+        let store_a = SnapshotStore::new();
+        let store_b = SnapshotStore::new();
+
+        let root_a = store_a.commit_snapshot(100, &sample_items(5));
+        let root_b = store_b.commit_snapshot(100, &sample_items(5));
+
+        assert_eq!(root_a, root_b);
+    }
+
+    #[test]
+    fn test_commit_snapshot_changes_root_when_an_item_changes() {
+        let store = SnapshotStore::new();
+        let mut items = sample_items(5);
+        let root_before = store.commit_snapshot(1, &items);
+
+        items[2].1.net_profit += 1.0;
+        let root_after = store.commit_snapshot(2, &items);
+
+        assert_ne!(root_before, root_after);
+    }
+
+    #[test]
+    fn test_membership_proof_verifies_against_the_published_root() {
+        let store = SnapshotStore::new();
+        let items = sample_items(7);
+        let root = store.commit_snapshot(42, &items);
+
+        for (id, item) in &items {
+            let proof = store.membership_proof(42, id).expect("id presente en el snapshot");
+            let leaf = hash_leaf(item);
+            assert!(verify_proof(root, leaf, &proof));
+        }
+    }
+
+    #[test]
+    fn test_membership_proof_fails_for_unknown_id_or_snapshot() {
+        let store = SnapshotStore::new();
+        store.commit_snapshot(1, &sample_items(3));
+
+        assert!(store.membership_proof(1, "nonexistent").is_none());
+        assert!(store.membership_proof(999, "opp_0").is_none());
+    }
+
+    #[test]
+    fn test_verify_proof_rejects_a_tampered_leaf() {
+        let store = SnapshotStore::new();
+        let items = sample_items(4);
+        let root = store.commit_snapshot(1, &items);
+
+        let proof = store.membership_proof(1, "opp_1").unwrap();
+        let tampered_leaf = hash_leaf(&Opp { id: "opp_1".to_string(), net_profit: 999.0 });
+
+        assert!(!verify_proof(root, tampered_leaf, &proof));
+    }
+
+    #[test]
+    fn test_single_item_snapshot_has_no_proof_steps() {
+        let store = SnapshotStore::new();
+        let items = sample_items(1);
+        let root = store.commit_snapshot(1, &items);
+
+        let proof = store.membership_proof(1, "opp_0").unwrap();
+        assert!(proof.is_empty());
+        assert!(verify_proof(root, hash_leaf(&items[0].1), &proof));
+    }
+
+    #[test]
+    fn test_odd_leaf_count_promotes_last_node_and_still_verifies() {
+        let store = SnapshotStore::new();
+        let items = sample_items(5); // niveles de 5 y 3 nodos: impares
+        let root = store.commit_snapshot(1, &items);
+
+        for (id, item) in &items {
+            let proof = store.membership_proof(1, id).unwrap();
+            assert!(verify_proof(root, hash_leaf(item), &proof));
+        }
+    }
+}
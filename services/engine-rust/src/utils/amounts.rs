@@ -0,0 +1,183 @@
+// ARBITRAXPLUS2025 - Montos on-chain de precisión completa
+//
+// `Money` (en `utils::money`) ya cubre USD/precios agregados con 9 decimales
+// de punto fijo, suficiente para ranking y reporting. Pero un monto de token
+// crudo (p.ej. `amount_in` en wei de un token de 18 decimales) puede superar
+// lo que `Money`/`f64` distinguen a nivel de sub-wei, y las APIs de DEXes y
+// Pyth devuelven esos montos en cualquiera de dos formatos JSON (`"0x..."`
+// hex o un string decimal). `TokenAmount` envuelve un `primitive_types::U256`
+// crudo (sin dividir por `10^decimals`) junto con `decimals`, siguiendo el
+// patrón de los sistemas de settlement de producción: el valor nunca pasa
+// por `f64` salvo explícitamente, vía `as_f64_lossy`, para display/métricas.
+
+use std::fmt;
+
+use primitive_types::U256;
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::utils::money::Money;
+
+/// Precio fijo en USD (p.ej. `token0_price_usd`). Reutiliza la
+/// representación de [`Money`] en vez de definir otro tipo de punto fijo
+/// desde cero: un precio y un profit en USD tienen exactamente las mismas
+/// necesidades de precisión y manejo de `NaN`/overflow.
+pub type Price = Money;
+
+/// Profit o pérdida en USD tras fees y gas. Alias de [`Money`] nombrado
+/// aparte de [`Price`] para que las firmas de función documenten la
+/// intención (un resultado, no una cotización) aunque el tipo subyacente
+/// sea el mismo.
+pub type ProfitUsd = Money;
+
+/// Wrapper serde para un `U256` que acepta tanto `"0x…"` hex como un string
+/// decimal puro en JSON (las APIs de DEXes/Pyth/Sheets usan ambos formatos
+/// indistintamente), y siempre serializa como hex para un round-trip sin
+/// pérdida y sin ambigüedad de formato.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexOrDecimalU256(pub U256);
+
+impl Serialize for HexOrDecimalU256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for HexOrDecimalU256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        let value = match raw.strip_prefix("0x").or_else(|| raw.strip_prefix("0X")) {
+            Some(hex) => U256::from_str_radix(hex, 16).map_err(DeError::custom)?,
+            None => U256::from_dec_str(&raw).map_err(DeError::custom)?,
+        };
+        Ok(HexOrDecimalU256(value))
+    }
+}
+
+/// Monto crudo de un token on-chain (unidades enteras, antes de dividir por
+/// `10^decimals`), respaldado por [`U256`] para no perder precisión con
+/// tokens de 18 decimales donde un `f64` ya no distingue diferencias de
+/// sub-wei. `decimals` viaja junto al monto porque un `U256` desnudo no dice
+/// nada sobre su escala.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenAmount {
+    raw: HexOrDecimalU256,
+    decimals: u8,
+}
+
+impl TokenAmount {
+    pub const fn from_raw(raw: U256, decimals: u8) -> Self {
+        TokenAmount {
+            raw: HexOrDecimalU256(raw),
+            decimals,
+        }
+    }
+
+    /// Construye desde un monto "humano" (p.ej. `1.5` ETH), redondeando al
+    /// entero de unidades más cercano en la escala de `decimals`. Solo para
+    /// el borde de ingesta (Sheets) donde el dato ya llega como `f64`; el
+    /// resto del pipeline debe operar sobre el `U256` crudo para no
+    /// reintroducir el error de redondeo que este tipo existe para evitar.
+    pub fn from_f64(value: f64, decimals: u8) -> Option<Self> {
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        let scaled = value * 10f64.powi(decimals as i32);
+        if !scaled.is_finite() || scaled > u128::MAX as f64 {
+            return None;
+        }
+        Some(TokenAmount::from_raw(U256::from(scaled.round() as u128), decimals))
+    }
+
+    pub fn raw(&self) -> U256 {
+        self.raw.0
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Solo para display/métricas: pasa por `f64` y por lo tanto puede
+    /// perder precisión sub-wei, igual que [`Money::to_f64`].
+    pub fn as_f64_lossy(&self) -> f64 {
+        let scale = 10f64.powi(self.decimals as i32);
+        self.raw.0.to_string().parse::<f64>().unwrap_or(f64::INFINITY) / scale
+    }
+
+    pub fn checked_add(self, other: TokenAmount) -> Option<TokenAmount> {
+        if self.decimals != other.decimals {
+            return None;
+        }
+        self.raw.0.checked_add(other.raw.0).map(|r| TokenAmount::from_raw(r, self.decimals))
+    }
+
+    pub fn checked_sub(self, other: TokenAmount) -> Option<TokenAmount> {
+        if self.decimals != other.decimals {
+            return None;
+        }
+        self.raw.0.checked_sub(other.raw.0).map(|r| TokenAmount::from_raw(r, self.decimals))
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.6} (decimals={})", self.as_f64_lossy(), self.decimals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_f64_round_trips_through_as_f64_lossy() {
+        let amount = TokenAmount::from_f64(1.5, 18).unwrap();
+        assert!((amount.as_f64_lossy() - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_f64_rejects_negative_and_non_finite() {
+        assert!(TokenAmount::from_f64(-1.0, 18).is_none());
+        assert!(TokenAmount::from_f64(f64::NAN, 18).is_none());
+        assert!(TokenAmount::from_f64(f64::INFINITY, 18).is_none());
+    }
+
+    #[test]
+    fn test_checked_add_and_sub_require_matching_decimals() {
+        let a = TokenAmount::from_f64(1.0, 18).unwrap();
+        let b = TokenAmount::from_raw(U256::from(500_000u64), 6);
+        assert!(a.checked_add(b).is_none());
+        assert!(a.checked_sub(b).is_none());
+
+        let c = TokenAmount::from_f64(0.5, 18).unwrap();
+        let sum = a.checked_add(c).unwrap();
+        assert!((sum.as_f64_lossy() - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hex_or_decimal_u256_round_trips_via_json() {
+        let value = HexOrDecimalU256(U256::from(123456789u64));
+        let json = serde_json::to_string(&value).unwrap();
+        assert!(json.starts_with("\"0x"));
+        let parsed: HexOrDecimalU256 = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.0, value.0);
+    }
+
+    #[test]
+    fn test_hex_or_decimal_u256_accepts_decimal_string_input() {
+        let parsed: HexOrDecimalU256 = serde_json::from_str("\"123456789\"").unwrap();
+        assert_eq!(parsed.0, U256::from(123456789u64));
+    }
+
+    #[test]
+    fn test_token_amount_json_round_trip_preserves_precision_past_f64() {
+        // 1 token de 18 decimales más 1 wei: una diferencia que un `f64`
+        // normalizado a unidades humanas ya no distingue, pero el `U256`
+        // crudo sí conserva byte a byte a través de serde.
+        let raw = U256::from(1_000_000_000_000_000_001u128);
+        let amount = TokenAmount::from_raw(raw, 18);
+        let json = serde_json::to_string(&amount).unwrap();
+        let parsed: TokenAmount = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.raw(), raw);
+    }
+}
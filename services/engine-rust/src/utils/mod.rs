@@ -8,16 +8,19 @@
  * 
  * 🔄 TRANSFORMACIÓN:
  *   FUNCIONES: format_currency, calculate_roi
- * 
+ *
  * 📤 SALIDA:
- * 
+ *
  * 🔗 DEPENDENCIAS:
- * 
+ *
  * ============================================================================
  */
 
 //! Utility functions
 
+pub mod amounts;
+pub mod money;
+
 pub fn format_currency(amount: f64) -> String {
     format!("${:.2}", amount)
 }
@@ -0,0 +1,175 @@
+// ARBITRAGEXPLUS2025 - Tipo de dinero de punto fijo
+//
+// Toda cantidad financiera del engine (`expected_profit_usd`, `amount_in`,
+// `price_diff_percentage`, fees...) vive hoy en `f64`, que acumula error de
+// redondeo y puede producir silenciosamente `inf`/`NaN` ante un dato de
+// Sheets corrupto (p.ej. un precio 0 que termina dividiendo). Un `NaN` es
+// especialmente dañino porque `sort_by`'s `partial_cmp` lo trata como
+// `Ordering::Equal` y desordena el ranking de oportunidades sin avisar.
+//
+// `Money` representa una cantidad como entero de 128 bits escalado por
+// `SCALE` (9 decimales). `checked_div` necesita multiplicar por `SCALE` una
+// vez más sobre un valor ya escalado (`a.0 * SCALE`) para conservar
+// precisión en el cociente, así que 18 decimales ("wei") desbordaría el
+// i128 para montos de negocio normales (`1e6 * 1e18 * 1e18` ya no entra en
+// 128 bits); 9 decimales deja margen de sobra para USD y aun así detecta
+// overflow real en vez de solo el artificial de la propia escala.
+//
+// Operaciones `checked_*` que devuelven `Result` en vez de `NaN`/overflow
+// silencioso.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+const SCALE: i128 = 1_000_000_000; // 1e9
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Money(i128);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoneyError {
+    /// El resultado no entra en el rango representable (overflow/underflow
+    /// del entero de 128 bits escalado).
+    Overflow,
+    /// División por un `Money` igual a cero.
+    DivisionByZero,
+    /// El `f64` de entrada no es representable como dinero: `NaN` o `inf`.
+    NotFinite,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoneyError::Overflow => write!(f, "money overflow"),
+            MoneyError::DivisionByZero => write!(f, "division by zero"),
+            MoneyError::NotFinite => write!(f, "value is not finite (NaN or inf)"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    /// Convierte un `f64` (p.ej. un campo recién parseado de un sheet) a
+    /// `Money`, rechazando `NaN`/`inf` y valores fuera de rango en el
+    /// borde de ingesta en vez de dejarlos propagarse como "veneno" por el
+    /// resto del pipeline.
+    pub fn from_f64(value: f64) -> Result<Self, MoneyError> {
+        if !value.is_finite() {
+            return Err(MoneyError::NotFinite);
+        }
+        let scaled = value * SCALE as f64;
+        if !scaled.is_finite() || scaled > i128::MAX as f64 || scaled < i128::MIN as f64 {
+            return Err(MoneyError::Overflow);
+        }
+        Ok(Money(scaled.round() as i128))
+    }
+
+    /// Vuelta a `f64` para interoperar con el resto del engine (serialización
+    /// a Sheets, cálculos que todavía no migraron a `Money`).
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Trunca a centavos como entero, para discretizar presupuestos en un
+    /// DP (p.ej. el knapsack de gas de `RouteRanker::optimize_route_selection`)
+    /// directamente desde la representación de punto fijo en vez de volver a
+    /// pasar por un `f64 * 100.0 as usize`, que reintroducía el error de
+    /// redondeo que `Money` existe para evitar.
+    pub fn to_cents(self) -> i64 {
+        let cents = self.0 / (SCALE / 100);
+        cents.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        self.0.checked_add(other.0).map(Money).ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, MoneyError> {
+        self.0.checked_sub(other.0).map(Money).ok_or(MoneyError::Overflow)
+    }
+
+    /// `(a*SCALE) * (b*SCALE) / SCALE = a*b*SCALE`, con el producto
+    /// intermedio en una escala más ancha para no perder precisión.
+    pub fn checked_mul(self, other: Money) -> Result<Money, MoneyError> {
+        let product = self.0.checked_mul(other.0).ok_or(MoneyError::Overflow)?;
+        Ok(Money(product / SCALE))
+    }
+
+    /// `(a*SCALE) * SCALE / (b*SCALE) = (a/b)*SCALE`.
+    pub fn checked_div(self, other: Money) -> Result<Money, MoneyError> {
+        if other.is_zero() {
+            return Err(MoneyError::DivisionByZero);
+        }
+        let numerator = self.0.checked_mul(SCALE).ok_or(MoneyError::Overflow)?;
+        Ok(Money(numerator / other.0))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2}", self.to_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_f64_round_trips_through_to_f64() {
+        let m = Money::from_f64(1234.56).unwrap();
+        assert!((m.to_f64() - 1234.56).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_f64_rejects_nan_and_inf() {
+        assert_eq!(Money::from_f64(f64::NAN), Err(MoneyError::NotFinite));
+        assert_eq!(Money::from_f64(f64::INFINITY), Err(MoneyError::NotFinite));
+        assert_eq!(Money::from_f64(f64::NEG_INFINITY), Err(MoneyError::NotFinite));
+    }
+
+    #[test]
+    fn test_checked_div_by_zero_is_an_error_not_nan() {
+        let a = Money::from_f64(100.0).unwrap();
+        let result = a.checked_div(Money::ZERO);
+        assert_eq!(result, Err(MoneyError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_checked_mul_and_div_are_consistent() {
+        let a = Money::from_f64(10.0).unwrap();
+        let b = Money::from_f64(4.0).unwrap();
+        let product = a.checked_mul(b).unwrap();
+        assert!((product.to_f64() - 40.0).abs() < 1e-9);
+        let quotient = product.checked_div(b).unwrap();
+        assert!((quotient.to_f64() - a.to_f64()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_checked_add_detects_overflow() {
+        let huge = Money(i128::MAX);
+        assert_eq!(huge.checked_add(Money::from_f64(1.0).unwrap()), Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn test_checked_sub_basic() {
+        let a = Money::from_f64(10.0).unwrap();
+        let b = Money::from_f64(3.0).unwrap();
+        let diff = a.checked_sub(b).unwrap();
+        assert!((diff.to_f64() - 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_to_cents_truncates_sub_cent_precision() {
+        assert_eq!(Money::from_f64(12.3456).unwrap().to_cents(), 1234);
+        assert_eq!(Money::from_f64(12.349).unwrap().to_cents(), 1234);
+    }
+}
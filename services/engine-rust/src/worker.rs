@@ -0,0 +1,186 @@
+/**
+ * ============================================================================
+ * ARCHIVO: ./services/engine-rust/src/worker.rs
+ * MÓDULO: Rust Engine
+ * ============================================================================
+ *
+ * 📥 ENTRADA:
+ *   - Implementaciones de `Worker` (una por job de larga duración del motor)
+ *
+ * 🔄 TRANSFORMACIÓN:
+ *   FUNCIONES: spawn, list_workers, pause, resume, cancel, shutdown
+ *
+ * 📤 SALIDA:
+ *   - `WorkerStatus` por worker: estado, progreso y último error
+ *
+ * 🔗 DEPENDENCIAS: (ninguna externa al motor)
+ *
+ * ============================================================================
+ */
+
+//! Reemplaza el patrón de un único `is_running: Mutex<bool>` compartido por
+//! todos los loops del motor (route generation, pattern analysis, sheet
+//! writing) por workers individualmente inspeccionables y controlables, al
+//! estilo de un task manager: cada uno reporta su propio estado y se puede
+//! pausar/reanudar/cancelar sin afectar a los demás.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Estado resultante de ejecutar una unidad de trabajo de un worker.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkerState {
+    /// Hizo trabajo real en esta unidad.
+    Active,
+    /// No había nada que hacer en esta unidad (p.ej. esperando el próximo intervalo).
+    Idle,
+    /// Terminó y no va a volver a ejecutarse.
+    Done,
+    /// Terminó por un error irrecuperable; el mensaje queda disponible en `WorkerStatus`.
+    Dead(String),
+}
+
+/// Snapshot inspeccionable del estado de un worker, expuesto vía
+/// `BackgroundRunner::list_workers` / `RustArbitrageEngine::list_workers`.
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub name: String,
+    pub state: WorkerState,
+    pub progress: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// Comando de control enviado a un worker en ejecución.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorkerCommand {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+/// Un job de larga duración individualmente inspeccionable y controlable.
+/// Cada implementación envuelve un loop existente del motor (ciclo de
+/// arbitraje, recarga de configuración, servicio de optimización) como una
+/// sola unidad de trabajo por llamada a `work()`, dejando que `BackgroundRunner`
+/// decida cuándo y cuántas veces correrla.
+#[async_trait]
+pub trait Worker: Send {
+    /// Nombre estable del worker, usado como key en `BackgroundRunner`.
+    fn name(&self) -> &str;
+
+    /// Ejecuta una unidad de trabajo y devuelve el estado resultante.
+    async fn work(&mut self) -> WorkerState;
+
+    /// Snapshot del estado actual (incluye progreso y último error conocido).
+    fn status(&self) -> WorkerStatus;
+}
+
+struct WorkerHandle {
+    status: Arc<Mutex<WorkerStatus>>,
+    command_tx: mpsc::UnboundedSender<WorkerCommand>,
+    join_handle: JoinHandle<()>,
+}
+
+/// Dueño de un conjunto de workers en background. Cada uno corre en su
+/// propia tarea de tokio con su propio canal de comandos y estado
+/// inspeccionable, en vez de compartir un único flag global.
+#[derive(Default)]
+pub struct BackgroundRunner {
+    workers: Mutex<HashMap<String, WorkerHandle>>,
+}
+
+impl BackgroundRunner {
+    pub fn new() -> Self {
+        Self {
+            workers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Arranca un worker en su propia tarea: llama a `work()` en loop hasta
+    /// que devuelva `Done`/`Dead`, o hasta recibir el comando `Cancel`.
+    pub fn spawn(&self, mut worker: Box<dyn Worker>) {
+        let name = worker.name().to_string();
+        let (command_tx, mut command_rx) = mpsc::unbounded_channel();
+        let status = Arc::new(Mutex::new(worker.status()));
+        let status_clone = Arc::clone(&status);
+
+        let join_handle = tokio::spawn(async move {
+            let mut paused = false;
+            loop {
+                match command_rx.try_recv() {
+                    Ok(WorkerCommand::Pause) => paused = true,
+                    Ok(WorkerCommand::Resume) => paused = false,
+                    Ok(WorkerCommand::Cancel) => break,
+                    Err(_) => {}
+                }
+
+                if paused {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    continue;
+                }
+
+                let state = worker.work().await;
+                *status_clone.lock().unwrap() = worker.status();
+
+                if matches!(state, WorkerState::Done | WorkerState::Dead(_)) {
+                    break;
+                }
+            }
+        });
+
+        self.workers.lock().unwrap().insert(
+            name,
+            WorkerHandle {
+                status,
+                command_tx,
+                join_handle,
+            },
+        );
+    }
+
+    /// Estado actual de todos los workers registrados.
+    pub fn list_workers(&self) -> Vec<WorkerStatus> {
+        self.workers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|handle| handle.status.lock().unwrap().clone())
+            .collect()
+    }
+
+    fn send_command(&self, name: &str, command: WorkerCommand) {
+        if let Some(handle) = self.workers.lock().unwrap().get(name) {
+            let _ = handle.command_tx.send(command);
+        }
+    }
+
+    pub fn pause(&self, name: &str) {
+        self.send_command(name, WorkerCommand::Pause);
+    }
+
+    pub fn resume(&self, name: &str) {
+        self.send_command(name, WorkerCommand::Resume);
+    }
+
+    pub fn cancel(&self, name: &str) {
+        self.send_command(name, WorkerCommand::Cancel);
+    }
+
+    /// Cancela todos los workers y espera a que terminen limpiamente. Reemplaza
+    /// el `sleep(Duration::from_secs(2))` a ciegas que usaba `stop()` antes.
+    pub async fn shutdown(&self) {
+        let handles: Vec<WorkerHandle> = self.workers.lock().unwrap().drain().map(|(_, h)| h).collect();
+
+        for handle in &handles {
+            let _ = handle.command_tx.send(WorkerCommand::Cancel);
+        }
+        for handle in handles {
+            let _ = handle.join_handle.await;
+        }
+    }
+}